@@ -1,15 +1,85 @@
+use crate::commands::disk::DiskDiagnostics;
 use crate::services::benchmark;
 pub use crate::services::benchmark::BenchmarkResult;
-use crate::Result;
+use crate::services::benchmark_history::{
+    self, BenchmarkComparison, BenchmarkDisk, BenchmarkHistory, BenchmarkHost, BenchmarkRecord,
+    BenchmarkRunSummary,
+};
+use crate::{AppError, Result};
+use tauri::Manager;
+use tracing::warn;
+
+fn history(app_handle: &tauri::AppHandle) -> Result<BenchmarkHistory> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::SystemError(format!("Failed to resolve app data dir: {}", e)))?;
+    Ok(BenchmarkHistory::new(dir.join("benchmark_history")))
+}
+
+impl From<&DiskDiagnostics> for BenchmarkDisk {
+    fn from(diag: &DiskDiagnostics) -> Self {
+        Self {
+            id: diag.id.clone(),
+            model: diag.model.trim().to_string(),
+            serial_number: diag.serial_number.trim().to_string(),
+            firmware_version: diag.firmware_version.trim().to_string(),
+            size_bytes: diag.size_bytes,
+            bus_type: diag.bus_type.clone(),
+            transport_type: diag.transport_type.clone(),
+        }
+    }
+}
+
+/// Model, serial and firmware of the benchmarked disk, if diagnostics can find it.
+async fn benchmark_disk(disk_id: &str) -> Option<BenchmarkDisk> {
+    let diagnostics = match crate::commands::disk::list_disk_diagnostics().await {
+        Ok(diagnostics) => diagnostics,
+        Err(e) => {
+            warn!("Disk diagnostics unavailable for benchmark history: {}", e);
+            return None;
+        }
+    };
+    diagnostics
+        .iter()
+        .find(|d| d.id == disk_id || format!("disk{}", d.disk_number) == disk_id)
+        .map(BenchmarkDisk::from)
+}
 
 /// Run disk benchmark on target path (e.g., "E:\\")
+///
+/// Finished runs are saved to the benchmark history; `disk_id` ties the run to the
+/// disk's model, serial and firmware.
 #[tauri::command]
-pub async fn run_benchmark(target_path: String, mode: Option<String>) -> Result<BenchmarkResult> {
+pub async fn run_benchmark(
+    app_handle: tauri::AppHandle,
+    target_path: String,
+    mode: Option<String>,
+    stall_threshold_ms: Option<u64>,
+    disk_id: Option<String>,
+) -> Result<BenchmarkResult> {
     let config = benchmark::BenchmarkConfig {
         target_path,
         mode: mode.unwrap_or_else(|| "quick".to_string()),
+        stall_threshold_ms,
+    };
+    let result = benchmark::run_benchmark(&config).await?;
+
+    let disk = match disk_id.as_deref() {
+        Some(id) if !id.is_empty() => benchmark_disk(id).await,
+        _ => None,
     };
-    benchmark::run_benchmark(&config).await
+    let record = BenchmarkRecord::new(
+        &config.target_path,
+        disk,
+        BenchmarkHost::current(),
+        result.clone(),
+    );
+    // A full data dir should not cost the user the result they just waited for.
+    if let Err(e) = history(&app_handle).and_then(|h| h.save(&record)) {
+        warn!("Failed to save benchmark run to history: {}", e);
+    }
+    Ok(result)
 }
 
 #[tauri::command]
@@ -17,3 +87,32 @@ pub fn cancel_benchmark() -> Result<()> {
     benchmark::request_cancel();
     Ok(())
 }
+
+/// Saved benchmark runs, newest first
+#[tauri::command]
+pub fn list_benchmark_history(app_handle: tauri::AppHandle) -> Result<Vec<BenchmarkRunSummary>> {
+    history(&app_handle)?.list()
+}
+
+#[tauri::command]
+pub fn get_benchmark_run(app_handle: tauri::AppHandle, id: String) -> Result<BenchmarkRecord> {
+    history(&app_handle)?.get(&id)
+}
+
+#[tauri::command]
+pub fn delete_benchmark_run(app_handle: tauri::AppHandle, id: String) -> Result<()> {
+    history(&app_handle)?.delete(&id)
+}
+
+/// Compare two saved runs; deltas are `other - base`
+#[tauri::command]
+pub fn compare_benchmark_runs(
+    app_handle: tauri::AppHandle,
+    base_id: String,
+    other_id: String,
+) -> Result<BenchmarkComparison> {
+    let history = history(&app_handle)?;
+    let base = history.get(&base_id)?;
+    let other = history.get(&other_id)?;
+    Ok(benchmark_history::compare_runs(&base, &other))
+}
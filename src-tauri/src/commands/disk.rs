@@ -147,8 +147,6 @@ pub async fn list_disk_diagnostics() -> Result<Vec<DiskDiagnostics>> {
     }
     #[cfg(target_os = "linux")]
     {
-        Err(crate::AppError::Unsupported(
-            "Disk diagnostics is not implemented on Linux yet".to_string(),
-        ))
+        crate::platform::linux::list_disk_diagnostics().await
     }
 }
@@ -1,4 +1,6 @@
-use crate::{AppError, Result};
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+use crate::AppError;
+use crate::Result;
 use serde::{Deserialize, Serialize};
 #[cfg(target_os = "macos")]
 use serde_json::Value;
@@ -219,7 +221,7 @@ fn parse_media_type_macos(info: &Value) -> String {
     "Unknown".to_string()
 }
 
-/// List mounted partitions with drive letters (Windows only)
+/// List mounted partitions (drive letters on Windows, mount paths on macOS/Linux)
 #[tauri::command]
 pub async fn list_partitions() -> Result<Vec<PartitionInfo>> {
     #[cfg(target_os = "windows")]
@@ -495,10 +497,15 @@ Write-Output "__WTGA_JSON__$__json"
         Ok(result)
     }
 
-    #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+    #[cfg(target_os = "linux")]
+    {
+        crate::platform::linux::list_partitions().await
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
         Err(AppError::Unsupported(
-            "Partition listing only implemented on Windows and macOS".into(),
+            "Partition listing only implemented on Windows, macOS and Linux".into(),
         ))
     }
 }
@@ -1,4 +1,4 @@
-use crate::models::FirmwareType;
+use crate::models::{BcdStoreInfo, FirmwareType};
 #[cfg(target_os = "windows")]
 use crate::services::{boot, diskpart};
 use crate::utils::command::CommandExecutor;
@@ -124,7 +124,7 @@ struct Win32NetworkAdapter {
     net_enabled: Option<bool>,
 }
 
-fn dedup_keep_order(items: Vec<String>) -> Vec<String> {
+pub(crate) fn dedup_keep_order(items: Vec<String>) -> Vec<String> {
     let mut seen = HashSet::new();
     let mut out = Vec::new();
     for item in items {
@@ -163,11 +163,11 @@ fn parse_u64_text(value: Option<&str>) -> Option<u64> {
     }
 }
 
-fn format_gb_from_bytes(bytes: u64) -> u64 {
+pub(crate) fn format_gb_from_bytes(bytes: u64) -> u64 {
     ((bytes as f64) / 1024_f64.powi(3)).round() as u64
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 fn format_mb_from_bytes(bytes: u64) -> u64 {
     ((bytes as f64) / 1024_f64.powi(2)).round() as u64
 }
@@ -244,8 +244,8 @@ fn utf16z_to_string(buf: &[u16]) -> String {
     String::from_utf16_lossy(&buf[..end]).trim().to_string()
 }
 
-#[cfg(target_os = "windows")]
-fn format_gpu_vram(bytes: u64) -> String {
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub(crate) fn format_gpu_vram(bytes: u64) -> String {
     if bytes == 0 {
         return String::new();
     }
@@ -1039,6 +1039,77 @@ pub async fn repair_boot(target_disk: String, firmware: String) -> Result<String
     }
 }
 
+/// Read the BCD stores of a target drive offline and check every boot entry against
+/// the partition table of the disk it is on.
+#[tauri::command]
+pub async fn inspect_boot_configuration(target_disk: String) -> Result<Vec<BcdStoreInfo>> {
+    #[cfg(target_os = "windows")]
+    {
+        let target_root = normalize_drive_root(&target_disk)?;
+        let disk_no = resolve_disk_number_from_drive(&target_root)?;
+
+        tokio::task::spawn_blocking(move || -> Result<Vec<BcdStoreInfo>> {
+            let mut roots = vec![target_root.clone()];
+            let mut mounted_efi: Option<String> = None;
+            match diskpart::mount_efi_partition(&disk_no) {
+                Ok((esp_letter, temporary)) => {
+                    roots.push(format!("{}\\", esp_letter));
+                    if temporary {
+                        mounted_efi = Some(esp_letter);
+                    }
+                }
+                Err(e) => tracing::info!("No EFI partition mounted for disk {}: {}", disk_no, e),
+            }
+
+            let stores: Vec<std::path::PathBuf> = roots
+                .iter()
+                .flat_map(|root| {
+                    crate::utils::bcd::BCD_STORE_PATHS
+                        .iter()
+                        .map(move |relative| std::path::Path::new(root).join(relative))
+                })
+                .filter(|path| path.is_file())
+                .collect();
+            let disk = std::path::PathBuf::from(format!("\\\\.\\PhysicalDrive{}", disk_no));
+            let result = crate::utils::bcd::inspect_stores(&disk, &stores);
+
+            if let Some(esp) = mounted_efi {
+                let _ = diskpart::remove_drive_letter(&esp);
+            }
+            Ok(result)
+        })
+        .await
+        .map_err(|e| AppError::SystemError(e.to_string()))?
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        tokio::task::spawn_blocking(move || {
+            crate::services::write_macos::inspect_bcd_stores_for_target(&target_disk)
+        })
+        .await
+        .map_err(|e| AppError::SystemError(e.to_string()))?
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        tokio::task::spawn_blocking(move || -> Result<Vec<BcdStoreInfo>> {
+            let (disk, stores) = crate::platform::linux::locate_bcd_stores(&target_disk)?;
+            Ok(crate::utils::bcd::inspect_stores(&disk, &stores))
+        })
+        .await
+        .map_err(|e| AppError::SystemError(e.to_string()))?
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = target_disk;
+        Err(AppError::Unsupported(
+            "Boot configuration inspection is not implemented on this platform".to_string(),
+        ))
+    }
+}
+
 #[tauri::command]
 pub async fn get_hardware_overview() -> Result<HardwareOverview> {
     #[cfg(target_os = "windows")]
@@ -1055,10 +1126,17 @@ pub async fn get_hardware_overview() -> Result<HardwareOverview> {
             .map_err(|e| AppError::SystemError(e.to_string()))?)
     }
 
-    #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+    #[cfg(target_os = "linux")]
+    {
+        tokio::task::spawn_blocking(crate::platform::linux::gather_hardware_overview)
+            .await
+            .map_err(|e| AppError::SystemError(e.to_string()))
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
         Err(AppError::Unsupported(
-            "Hardware overview is currently implemented on Windows only".to_string(),
+            "Hardware overview is not implemented on this platform".to_string(),
         ))
     }
 }
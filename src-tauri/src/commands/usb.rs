@@ -3,6 +3,9 @@
 use crate::Result;
 use serde::{Deserialize, Serialize};
 
+/// Event emitted to the frontend when a USB disk is attached or removed
+pub const EVENT_USB_DEVICE: &str = "usb-event";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsbDevice {
     pub id: String,
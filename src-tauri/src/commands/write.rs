@@ -1,9 +1,9 @@
 //! Write commands - Tauri command handlers for write operations
 
-use crate::models::{Disk, ImageInfo, WriteProgress, WtgConfig};
-#[cfg(target_os = "macos")]
-use crate::models::ApplyMode;
 #[cfg(any(target_os = "windows", target_os = "macos"))]
+use crate::models::TargetKind;
+use crate::models::{Disk, ImageInfo, VirtualDiskInfo, WriteProgress, WtgConfig};
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
 use crate::services;
 #[cfg(target_os = "macos")]
 use crate::utils::macos_admin;
@@ -73,15 +73,37 @@ pub async fn get_image_info(image_path: String) -> Result<Vec<ImageInfo>> {
         return Ok(info);
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[cfg(target_os = "linux")]
+    {
+        let info =
+            tokio::task::spawn_blocking(move || services::write_linux::get_image_info(&image_path))
+                .await
+                .map_err(|e| crate::AppError::SystemError(e.to_string()))??;
+
+        Ok(info)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
         let _ = image_path;
         Err(AppError::Unsupported(
-            "Image parsing is currently implemented on Windows/macOS only".to_string(),
+            "Image parsing is currently implemented on Windows/macOS/Linux only".to_string(),
         ))
     }
 }
 
+/// Inspect a VHD/VHDX file (type, virtual/allocated size, parent) without attaching it
+#[tauri::command]
+pub async fn inspect_virtual_disk(image_path: String) -> Result<VirtualDiskInfo> {
+    info!("Inspecting virtual disk: {}", image_path);
+
+    tokio::task::spawn_blocking(move || {
+        crate::utils::vhd::validate_import(std::path::Path::new(&image_path))
+    })
+    .await
+    .map_err(|e| crate::AppError::SystemError(e.to_string()))?
+}
+
 /// Start a WTG write operation
 #[tauri::command]
 pub async fn start_write(config: WtgConfig, app_handle: tauri::AppHandle) -> Result<WriteProgress> {
@@ -92,6 +114,12 @@ pub async fn start_write(config: WtgConfig, app_handle: tauri::AppHandle) -> Res
 
     #[cfg(target_os = "windows")]
     {
+        if config.target_kind == TargetKind::ImageFile {
+            return Err(AppError::Unsupported(
+                "Writing to an image file is not supported on Windows yet".to_string(),
+            ));
+        }
+
         // Set app handle for progress reporting
         PROGRESS_REPORTER.set_app_handle(app_handle);
 
@@ -113,9 +141,9 @@ pub async fn start_write(config: WtgConfig, app_handle: tauri::AppHandle) -> Res
 
     #[cfg(target_os = "macos")]
     {
-        if matches!(config.apply_mode, ApplyMode::VHD | ApplyMode::VHDX) {
+        if config.target_kind == TargetKind::ImageFile {
             return Err(AppError::Unsupported(
-                "VHD/VHDX apply mode is not supported on macOS yet".to_string(),
+                "Writing to an image file is not supported on macOS yet".to_string(),
             ));
         }
 
@@ -136,11 +164,30 @@ pub async fn start_write(config: WtgConfig, app_handle: tauri::AppHandle) -> Res
         return Ok(progress);
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[cfg(target_os = "linux")]
+    {
+        PROGRESS_REPORTER.set_app_handle(app_handle);
+
+        let app_files_path = std::env::temp_dir()
+            .join("WTGA")
+            .to_string_lossy()
+            .to_string();
+        let _ = std::fs::create_dir_all(&app_files_path);
+
+        let progress = tokio::task::spawn_blocking(move || {
+            services::write_linux::execute_write(&config, &app_files_path)
+        })
+        .await
+        .map_err(|e| crate::AppError::SystemError(e.to_string()))??;
+
+        Ok(progress)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
         let _ = (config, app_handle);
         Err(AppError::Unsupported(
-            "Write operation is currently implemented on Windows/macOS only".to_string(),
+            "Write operation is currently implemented on Windows/macOS/Linux only".to_string(),
         ))
     }
 }
@@ -224,11 +271,36 @@ pub async fn cancel_write(task_id: String) -> Result<()> {
         return Ok(());
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[cfg(target_os = "linux")]
+    {
+        let cancelled = if task_id.trim().is_empty() {
+            task_manager::TaskManager::cancel_all_tasks() > 0
+        } else {
+            task_manager::TaskManager::cancel_task(&task_id)
+        };
+
+        // The write thread kills its own wimlib-imagex child; this catches the short-lived tools.
+        for pattern in ["wimlib-imagex apply", "mkntfs", "mkfs.ntfs"] {
+            let _ = crate::utils::command::CommandExecutor::kill_process(pattern);
+        }
+
+        if cancelled {
+            info!("Linux cancellation signal set for task: {}", task_id);
+        } else {
+            info!(
+                "Linux cancellation requested; no tracked task matched: {}",
+                task_id
+            );
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
         let _ = task_id;
         Err(AppError::Unsupported(
-            "Write cancellation is currently implemented on Windows/macOS only".to_string(),
+            "Write cancellation is currently implemented on Windows/macOS/Linux only".to_string(),
         ))
     }
 }
@@ -255,10 +327,11 @@ pub async fn check_macos_target_writable(target_disk: Disk) -> Result<MacosTarge
     #[cfg(target_os = "macos")]
     {
         let disk_for_task = target_disk.clone();
-        let check =
-            tokio::task::spawn_blocking(move || services::write_macos::check_target_writable(&disk_for_task))
-                .await
-                .map_err(|e| crate::AppError::SystemError(e.to_string()))??;
+        let check = tokio::task::spawn_blocking(move || {
+            services::write_macos::check_target_writable(&disk_for_task)
+        })
+        .await
+        .map_err(|e| crate::AppError::SystemError(e.to_string()))??;
         return Ok(map_writable_check(check));
     }
 
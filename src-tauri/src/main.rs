@@ -46,6 +46,7 @@ fn main() {
             commands::system::authorize_macos_admin_session,
             commands::system::exit_app,
             commands::write::get_image_info,
+            commands::write::inspect_virtual_disk,
             commands::write::start_write,
             commands::write::cancel_write,
             commands::write::verify_system_files,
@@ -53,8 +54,13 @@ fn main() {
             commands::write::remount_macos_target_ntfs_writable,
             commands::benchmark::run_benchmark,
             commands::benchmark::cancel_benchmark,
+            commands::benchmark::list_benchmark_history,
+            commands::benchmark::get_benchmark_run,
+            commands::benchmark::delete_benchmark_run,
+            commands::benchmark::compare_benchmark_runs,
             commands::partition::list_partitions,
             commands::tools::repair_boot,
+            commands::tools::inspect_boot_configuration,
             commands::tools::get_hardware_overview,
             commands::macos_plugins::list_macos_plugins,
             commands::macos_plugins::get_macos_plugin_install_status,
@@ -56,12 +56,24 @@ pub enum VhdType {
     Expandable,
 }
 
+/// What `WtgConfig::target_disk` refers to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    /// A physical drive
+    #[default]
+    Disk,
+    /// A disk image file created for the write: raw `.img`/`.raw`, `.vhd` or `.vhdx`
+    ImageFile,
+}
+
 /// Image file type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ImageType {
     Wim,
     Esd,
+    Swm,
     Iso,
     Vhd,
     Vhdx,
@@ -136,6 +148,31 @@ fn default_vhd_name() -> String {
     "win8".to_string()
 }
 
+/// Image file target options. The file is `target_disk.device`, its size
+/// `target_disk.size`, and `target_disk.removable` picks the USB drive layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageTargetConfig {
+    /// Fixed or expandable, for `.vhd`/`.vhdx` files
+    #[serde(default = "default_image_vhd_type")]
+    pub vhd_type: VhdType,
+    /// Replace an existing file instead of refusing to write
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+impl Default for ImageTargetConfig {
+    fn default() -> Self {
+        Self {
+            vhd_type: default_image_vhd_type(),
+            overwrite: false,
+        }
+    }
+}
+
+fn default_image_vhd_type() -> VhdType {
+    VhdType::Fixed
+}
+
 /// Extra features configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ExtraFeatures {
@@ -181,6 +218,12 @@ pub struct WtgConfig {
     pub wim_index: String,
     /// Target disk
     pub target_disk: Disk,
+    /// Whether `target_disk` is a physical drive or an image file to create
+    #[serde(default)]
+    pub target_kind: TargetKind,
+    /// Image file options (used when target_kind is ImageFile)
+    #[serde(default)]
+    pub image_target: Option<ImageTargetConfig>,
     /// Boot mode
     pub boot_mode: BootMode,
     /// Apply mode
@@ -219,7 +262,7 @@ pub struct WriteProgress {
 }
 
 /// Image information from DISM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImageInfo {
     pub index: u32,
     pub name: String,
@@ -227,6 +270,114 @@ pub struct ImageInfo {
     pub description: String,
     #[serde(default)]
     pub size: u64,
+    /// "x86", "x64", "arm64", ... (empty when unknown)
+    #[serde(default)]
+    pub architecture: String,
+    /// Windows build number, 0 when unknown
+    #[serde(default)]
+    pub build: u32,
+    #[serde(default)]
+    pub edition_id: String,
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+/// Summary of a VHD/VHDX container, read from its own metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VirtualDiskInfo {
+    pub path: String,
+    /// "vhd" or "vhdx"
+    pub format: String,
+    /// "fixed", "dynamic" or "differencing"
+    pub disk_type: String,
+    pub virtual_size: u64,
+    /// Bytes of guest data actually backed by the file
+    pub allocated_size: u64,
+    pub file_size: u64,
+    /// Allocation unit of dynamic/differencing disks, 0 for fixed VHD
+    #[serde(default)]
+    pub block_size: u32,
+    #[serde(default)]
+    pub logical_sector_size: u32,
+    #[serde(default)]
+    pub physical_sector_size: u32,
+    /// Parent image of a differencing disk, as recorded by its locators
+    #[serde(default)]
+    pub parent_path: Option<String>,
+}
+
+/// Decoded `device`/`osdevice` element of a BCD entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BcdDeviceInfo {
+    /// "boot", "locate", "partition", "vhd" or "unknown"
+    pub kind: String,
+    /// "mbr" or "gpt" for partition and VHD host references
+    #[serde(default)]
+    pub partition_style: Option<String>,
+    /// MBR disk signature (hex) or GPT disk GUID
+    #[serde(default)]
+    pub disk_id: Option<String>,
+    /// GPT partition GUID
+    #[serde(default)]
+    pub partition_id: Option<String>,
+    /// MBR partition start in bytes
+    #[serde(default)]
+    pub partition_offset: Option<u64>,
+    /// Number of the referenced partition on the inspected disk, when it exists there
+    #[serde(default)]
+    pub partition_number: Option<u32>,
+    #[serde(default)]
+    pub vhd_path: Option<String>,
+    /// bcdedit-like one-line rendering
+    pub display: String,
+}
+
+/// One boot application (boot manager, OS loader, ...) of a BCD store
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BcdEntryInfo {
+    pub id: String,
+    /// Well-known name such as "{bootmgr}"
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// "bootmgr", "fwbootmgr", "osloader", "resume", "memdiag" or "application"
+    pub kind: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub device: Option<BcdDeviceInfo>,
+    #[serde(default)]
+    pub os_device: Option<BcdDeviceInfo>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub system_root: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    /// Problems found for this entry, e.g. a device on another disk
+    #[serde(default)]
+    pub issues: Vec<String>,
+}
+
+/// A BCD store read offline and checked against the disk it lives on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BcdStoreInfo {
+    pub path: String,
+    /// Partition style of the inspected disk, empty when it could not be read
+    #[serde(default)]
+    pub partition_style: String,
+    #[serde(default)]
+    pub default_entry: Option<String>,
+    #[serde(default)]
+    pub display_order: Vec<String>,
+    /// Boot menu timeout in seconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    pub entries: Vec<BcdEntryInfo>,
+    /// Store-level problems (missing default entry, unreadable partition table, ...)
+    #[serde(default)]
+    pub issues: Vec<String>,
 }
 
 /// Backward-compatible simple WriteConfig for the frontend
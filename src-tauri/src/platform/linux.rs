@@ -1,31 +1,1104 @@
-use crate::commands::disk::DiskInfo;
+mod aio;
+mod ata;
+mod hardware;
+mod nvme;
+mod partition;
+
+pub(crate) use aio::{AioCompletion, AioOp, AioQueue};
+pub use hardware::gather_hardware_overview;
+pub use partition::{list_partitions, locate_bcd_stores};
+
+use crate::commands::disk::{DiskDiagnostics, DiskInfo, SmartAttribute};
+use crate::commands::usb::{UsbDevice, UsbEvent, UsbEventType, EVENT_USB_DEVICE};
+use crate::platform::smart::ata::smart_attribute_name;
+use crate::platform::smart::nvme::apply_native_nvme_data;
+use crate::platform::smartctl::{
+    self, add_note_unique, derive_endurance_used_from_attrs, is_masked_serial, is_nvme_diag,
+    merge_smart_source,
+};
+use crate::utils::partition_plan::DiskGeometry;
 use crate::{AppError, Result};
+use nix::poll::{poll, PollFd, PollFlags};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tracing::{info, warn};
+
+const SYS_ROOT: &str = "/sys";
+const SYS_BLOCK: &str = "/sys/block";
+const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+const PROC_MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// Kernel block device name prefixes that never represent a physical target disk.
+const IGNORED_BLOCK_PREFIXES: [&str; 9] =
+    ["loop", "ram", "zram", "dm-", "md", "sr", "fd", "nbd", "zd"];
+
+/// One line of `/proc/self/mountinfo`, reduced to what disk enumeration needs.
+#[derive(Debug, Clone)]
+pub(crate) struct MountEntry {
+    /// Kernel block device name backing the mount, e.g. "sdb1" (empty for virtual fs).
+    pub device_name: String,
+    pub mount_point: String,
+    /// Directory of the filesystem mounted here; anything but "/" is a bind mount.
+    pub root: String,
+    pub fs_type: String,
+}
+
+#[derive(Debug, Default)]
+struct UdevDiskProps {
+    bus: String,
+    vendor: String,
+    model: String,
+    serial: String,
+    revision: String,
+    wwn: String,
+    vendor_id: String,
+    model_id: String,
+}
+
+fn read_sysfs_string(path: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn read_sysfs_u64(path: &Path) -> Option<u64> {
+    read_sysfs_string(path)?.parse::<u64>().ok()
+}
+
+/// Decode the octal escapes (`\040` for space, etc.) used by the kernel in mount tables.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let digits = &bytes[i + 1..i + 4];
+            if digits.iter().all(|c| (b'0'..=b'7').contains(c)) {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |acc, c| acc * 8 + (c - b'0') as u32);
+                if let Ok(v) = u8::try_from(value) {
+                    out.push(v);
+                    i += 4;
+                    continue;
+                }
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Resolve a `/dev/...` path (including by-uuid and mapper symlinks) to its kernel name.
+fn block_name_from_dev_path(source: &str) -> Option<String> {
+    if !source.starts_with("/dev/") {
+        return None;
+    }
+    let resolved = std::fs::canonicalize(source).unwrap_or_else(|_| PathBuf::from(source));
+    let name = resolved.file_name()?.to_string_lossy().to_string();
+    if Path::new(SYS_CLASS_BLOCK).join(&name).exists() {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn block_name_from_dev_numbers(major_minor: &str) -> Option<String> {
+    let link = Path::new("/sys/dev/block").join(major_minor);
+    let resolved = std::fs::canonicalize(link).ok()?;
+    Some(resolved.file_name()?.to_string_lossy().to_string())
+}
+
+fn parse_mountinfo_line(line: &str) -> Option<MountEntry> {
+    // <id> <parent> <major:minor> <root> <mount point> <options> [optional...] - <fstype> <source> <super options>
+    let (pre, post) = line.split_once(" - ")?;
+    let pre_fields: Vec<&str> = pre.split_whitespace().collect();
+    let post_fields: Vec<&str> = post.split_whitespace().collect();
+    if pre_fields.len() < 5 || post_fields.len() < 2 {
+        return None;
+    }
+
+    let mount_point = unescape_mount_field(pre_fields[4]);
+    let source = unescape_mount_field(post_fields[1]);
+    let device_name = block_name_from_dev_path(&source)
+        .or_else(|| {
+            if source.starts_with("/dev/") {
+                block_name_from_dev_numbers(pre_fields[2])
+            } else {
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    Some(MountEntry {
+        device_name,
+        mount_point,
+        root: unescape_mount_field(pre_fields[3]),
+        fs_type: post_fields[0].to_string(),
+    })
+}
+
+/// Read the current mount table of this process.
+pub(crate) fn read_mount_entries() -> Vec<MountEntry> {
+    match std::fs::read_to_string(PROC_MOUNTINFO) {
+        Ok(text) => text.lines().filter_map(parse_mountinfo_line).collect(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", PROC_MOUNTINFO, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Size and logical sector size of a whole disk, from sysfs.
+pub(crate) fn read_disk_geometry(disk_name: &str, removable: bool) -> Option<DiskGeometry> {
+    let sys_dir = Path::new(SYS_BLOCK).join(disk_name);
+    // sysfs reports the size in 512-byte units whatever the logical sector size.
+    let size = read_sysfs_u64(&sys_dir.join("size"))? * 512;
+    let sector_size = read_sysfs_u64(&sys_dir.join("queue/logical_block_size")).unwrap_or(512);
+    Some(DiskGeometry {
+        size,
+        sector_size: sector_size as u32,
+        removable,
+    })
+}
+
+// BLKRRPART from <linux/fs.h>
+nix::ioctl_none!(blkrrpart, 0x12, 95);
+
+/// Ask the kernel to re-read the partition table of a whole disk after it was rewritten.
+/// udev may still hold the device open for a moment, so EBUSY is retried briefly.
+pub(crate) fn reread_partition_table(disk_node: &Path) -> Result<()> {
+    let file = std::fs::File::open(disk_node).map_err(AppError::io)?;
+    let mut attempts = 0;
+    loop {
+        match unsafe { blkrrpart(std::os::fd::AsRawFd::as_raw_fd(&file)) } {
+            Ok(_) => return Ok(()),
+            Err(nix::errno::Errno::EBUSY) if attempts < 10 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(300));
+            }
+            Err(e) => {
+                return Err(AppError::DiskError(format!(
+                    "ioctl(BLKRRPART) on {} failed: {}",
+                    disk_node.display(),
+                    e
+                )))
+            }
+        }
+    }
+}
+
+/// Walk from any block device (partition, dm/md holder) down to the whole disks under it.
+pub(crate) fn parent_disks_of(block_name: &str) -> Vec<String> {
+    parent_disks_in(Path::new(SYS_ROOT), block_name)
+}
+
+/// [`parent_disks_of`] against the sysfs tree mounted at `sys`.
+fn parent_disks_in(sys: &Path, block_name: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut visited = HashSet::new();
+    collect_parent_disks(sys, block_name, &mut result, &mut visited);
+    result
+}
+
+fn collect_parent_disks(
+    sys: &Path,
+    block_name: &str,
+    out: &mut Vec<String>,
+    visited: &mut HashSet<String>,
+) {
+    if block_name.is_empty() || !visited.insert(block_name.to_string()) {
+        return;
+    }
+
+    let class_path = sys.join("class/block").join(block_name);
+    let Ok(real_path) = std::fs::canonicalize(&class_path) else {
+        return;
+    };
+
+    if real_path.join("partition").exists() {
+        if let Some(parent) = real_path.parent().and_then(|p| p.file_name()) {
+            let parent = parent.to_string_lossy().to_string();
+            if !out.contains(&parent) {
+                out.push(parent);
+            }
+        }
+        return;
+    }
+
+    let slaves: Vec<String> = std::fs::read_dir(real_path.join("slaves"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if slaves.is_empty() {
+        if sys.join("block").join(block_name).exists() && !out.iter().any(|d| d == block_name) {
+            out.push(block_name.to_string());
+        }
+        return;
+    }
+
+    for slave in slaves {
+        collect_parent_disks(sys, &slave, out, visited);
+    }
+}
+
+fn is_candidate_disk(sys_block: &Path, name: &str) -> bool {
+    if IGNORED_BLOCK_PREFIXES.iter().any(|p| name.starts_with(p)) {
+        return false;
+    }
+    // mmcblk0boot0 / mmcblk0rpmb are hardware boot areas, not user-visible disks.
+    if name.starts_with("mmcblk") && (name.contains("boot") || name.contains("rpmb")) {
+        return false;
+    }
+    let size_sectors = read_sysfs_u64(&sys_block.join(name).join("size")).unwrap_or(0);
+    size_sectors > 0
+}
+
+/// Sorted kernel names of all whole disks; the position in this list is the disk index.
+fn enumerate_disk_names() -> Result<Vec<String>> {
+    let entries = std::fs::read_dir(SYS_BLOCK).map_err(AppError::io)?;
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| is_candidate_disk(Path::new(SYS_BLOCK), name))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn read_udev_props(name: &str) -> UdevDiskProps {
+    let device = match udev::Device::from_subsystem_sysname("block".to_string(), name.to_string()) {
+        Ok(d) => d,
+        Err(e) => {
+            warn!("udev lookup failed for {}: {}", name, e);
+            return UdevDiskProps::default();
+        }
+    };
+
+    let raw = |key: &str| -> String {
+        device
+            .property_value(key)
+            .map(|v| v.to_string_lossy().trim().to_string())
+            .unwrap_or_default()
+    };
+    // udev replaces spaces with underscores in ID_VENDOR / ID_MODEL.
+    let text = |key: &str| -> String { raw(key).replace('_', " ").trim().to_string() };
+
+    UdevDiskProps {
+        bus: raw("ID_BUS"),
+        vendor: text("ID_VENDOR"),
+        model: text("ID_MODEL"),
+        serial: raw("ID_SERIAL_SHORT"),
+        revision: raw("ID_REVISION"),
+        wwn: raw("ID_WWN"),
+        vendor_id: raw("ID_VENDOR_ID"),
+        model_id: raw("ID_MODEL_ID"),
+    }
+}
+
+fn is_usb_attached(sys_dir: &Path) -> bool {
+    std::fs::canonicalize(sys_dir)
+        .map(|p| p.to_string_lossy().contains("/usb"))
+        .unwrap_or(false)
+}
+
+/// Transport label in the same vocabulary the frontend uses for Windows bus types.
+fn detect_transport(name: &str, sys_dir: &Path, udev_bus: &str) -> String {
+    if name.starts_with("nvme") {
+        return "NVMe".to_string();
+    }
+    if is_usb_attached(sys_dir) || udev_bus.eq_ignore_ascii_case("usb") {
+        return "USB".to_string();
+    }
+    if name.starts_with("mmcblk") {
+        return read_sysfs_string(&sys_dir.join("device/type"))
+            .filter(|t| t.eq_ignore_ascii_case("MMC"))
+            .map(|_| "MMC".to_string())
+            .unwrap_or_else(|| "SD".to_string());
+    }
+    if name.starts_with("vd") {
+        return "Virtio".to_string();
+    }
+    if name.starts_with("xvd") {
+        return "Xen".to_string();
+    }
+    if udev_bus.eq_ignore_ascii_case("ata") {
+        return "SATA".to_string();
+    }
+    let device_path = std::fs::canonicalize(sys_dir.join("device"))
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if device_path.contains("/ata") {
+        return "SATA".to_string();
+    }
+    if udev_bus.eq_ignore_ascii_case("scsi") || name.starts_with("sd") {
+        return "SCSI".to_string();
+    }
+    "Unknown".to_string()
+}
+
+fn read_disk_model(name: &str, sys_dir: &Path, udev: &UdevDiskProps) -> String {
+    let model = read_sysfs_string(&sys_dir.join("device/model"))
+        .or_else(|| read_sysfs_string(&sys_dir.join("device/name")))
+        .unwrap_or_else(|| udev.model.clone());
+    let vendor =
+        read_sysfs_string(&sys_dir.join("device/vendor")).unwrap_or_else(|| udev.vendor.clone());
+
+    // SATA disks behind libata report the placeholder vendor "ATA".
+    let vendor = if vendor.eq_ignore_ascii_case("ATA") || vendor.starts_with("0x") {
+        String::new()
+    } else {
+        vendor
+    };
+
+    let combined = if !vendor.is_empty() && !model.is_empty() && !model.starts_with(&vendor) {
+        format!("{} {}", vendor, model)
+    } else if !model.is_empty() {
+        model
+    } else {
+        vendor
+    };
+
+    if combined.is_empty() {
+        name.to_string()
+    } else {
+        combined
+    }
+}
+
+fn parse_media_type(name: &str, sys_dir: &Path, transport: &str) -> String {
+    if name.starts_with("nvme") {
+        return "SSD".to_string();
+    }
+    match read_sysfs_string(&sys_dir.join("queue/rotational")).as_deref() {
+        Some("0") => "SSD".to_string(),
+        // usb-storage marks almost every device rotational, so the flag means nothing there.
+        Some("1") if transport != "USB" => "HDD".to_string(),
+        _ => "Unknown".to_string(),
+    }
+}
+
+/// Map whole disk name -> mount points of the filesystems living on it.
+fn build_volume_map(mounts: &[MountEntry]) -> HashMap<String, Vec<String>> {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in mounts {
+        if entry.device_name.is_empty() {
+            continue;
+        }
+        for disk in parent_disks_of(&entry.device_name) {
+            let points = map.entry(disk).or_default();
+            if !points.contains(&entry.mount_point) {
+                points.push(entry.mount_point.clone());
+            }
+        }
+    }
+    map
+}
+
+fn system_disk_names(mounts: &[MountEntry]) -> HashSet<String> {
+    mounts
+        .iter()
+        .filter(|m| m.mount_point == "/" && !m.device_name.is_empty())
+        .flat_map(|m| parent_disks_of(&m.device_name))
+        .collect()
+}
+
+fn pick_volume(points: Option<&Vec<String>>) -> String {
+    let Some(points) = points else {
+        return String::new();
+    };
+    if points.iter().any(|p| p == "/") {
+        return "/".to_string();
+    }
+    points.first().cloned().unwrap_or_default()
+}
+
+fn build_disk_info(
+    name: &str,
+    index: usize,
+    volume_map: &HashMap<String, Vec<String>>,
+    system_disks: &HashSet<String>,
+) -> DiskInfo {
+    let sys_dir = Path::new(SYS_BLOCK).join(name);
+    let udev = read_udev_props(name);
+    let transport = detect_transport(name, &sys_dir, &udev.bus);
+
+    // /sys/block/*/size is always expressed in 512-byte units.
+    let size = read_sysfs_u64(&sys_dir.join("size")).unwrap_or(0) * 512;
+    let removable = read_sysfs_string(&sys_dir.join("removable")).as_deref() == Some("1")
+        || transport == "USB"
+        || transport == "SD";
+
+    DiskInfo {
+        id: name.to_string(),
+        name: read_disk_model(name, &sys_dir, &udev),
+        size,
+        removable,
+        device: format!("/dev/{}", name),
+        media_type: parse_media_type(name, &sys_dir, &transport),
+        drive_type: transport,
+        index: index.to_string(),
+        volume: pick_volume(volume_map.get(name)),
+        is_system: system_disks.contains(name),
+    }
+}
+
+/// Turn "/dev/sdb", "sdb" or a partition like "sdb1" into the whole disk kernel name.
+fn normalize_disk_id(input: &str) -> Option<String> {
+    normalize_disk_id_in(Path::new(SYS_ROOT), input)
+}
+
+fn normalize_disk_id_in(sys: &Path, input: &str) -> Option<String> {
+    let trimmed = input.trim();
+    let candidate = trimmed.strip_prefix("/dev/").unwrap_or(trimmed);
+    if candidate.is_empty() || candidate.contains('/') {
+        return None;
+    }
+    if sys.join("block").join(candidate).exists() {
+        return Some(candidate.to_string());
+    }
+    parent_disks_in(sys, candidate).into_iter().next()
+}
 
 /// List all disks on Linux
 pub async fn list_disks() -> Result<Vec<DiskInfo>> {
-    Err(AppError::Unsupported(
-        "Linux disk enumeration is not implemented yet".to_string(),
-    ))
+    info!("Listing disks on Linux");
+
+    let names = enumerate_disk_names()?;
+    let mounts = read_mount_entries();
+    let volume_map = build_volume_map(&mounts);
+    let system_disks = system_disk_names(&mounts);
+
+    Ok(names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| build_disk_info(name, index, &volume_map, &system_disks))
+        .collect())
 }
 
 /// Get disk info on Linux
 pub async fn get_disk_info(disk_id: &str) -> Result<DiskInfo> {
-    let _ = disk_id;
-    Err(AppError::Unsupported(
-        "Linux disk info retrieval is not implemented yet".to_string(),
-    ))
+    let name = normalize_disk_id(disk_id).ok_or_else(|| {
+        AppError::InvalidParameter(format!("Invalid disk identifier: {}", disk_id))
+    })?;
+
+    let names = enumerate_disk_names()?;
+    let index = names
+        .iter()
+        .position(|n| *n == name)
+        .ok_or_else(|| AppError::DeviceNotFound(disk_id.to_string()))?;
+
+    let mounts = read_mount_entries();
+    let volume_map = build_volume_map(&mounts);
+    let system_disks = system_disk_names(&mounts);
+    Ok(build_disk_info(&name, index, &volume_map, &system_disks))
+}
+
+fn interface_type_for_transport(transport: &str) -> String {
+    match transport {
+        "NVMe" => "NVMExpress".to_string(),
+        "" => "Unknown".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn build_disk_diagnostics(
+    name: &str,
+    index: usize,
+    system_disks: &HashSet<String>,
+) -> DiskDiagnostics {
+    let sys_dir = Path::new(SYS_BLOCK).join(name);
+    let udev = read_udev_props(name);
+    let transport = detect_transport(name, &sys_dir, &udev.bus);
+    let is_usb = transport == "USB";
+    let model = read_disk_model(name, &sys_dir, &udev);
+
+    let serial_number =
+        read_sysfs_string(&sys_dir.join("device/serial")).unwrap_or_else(|| udev.serial.clone());
+    let firmware_version = read_sysfs_string(&sys_dir.join("device/firmware_rev"))
+        .or_else(|| read_sysfs_string(&sys_dir.join("device/rev")))
+        .unwrap_or_else(|| udev.revision.clone());
+    let unique_id = read_sysfs_string(&sys_dir.join("wwid"))
+        .or_else(|| read_sysfs_string(&sys_dir.join("device/wwid")))
+        .unwrap_or_else(|| udev.wwn.clone());
+    let pnp_device_id = std::fs::canonicalize(sys_dir.join("device"))
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let (usb_vendor_id, usb_product_id) = if is_usb {
+        (udev.vendor_id.clone(), udev.model_id.clone())
+    } else {
+        (String::new(), String::new())
+    };
+
+    let mut reliability = Map::new();
+    if !udev.bus.is_empty() {
+        reliability.insert("Udev.Bus".to_string(), Value::String(udev.bus.clone()));
+    }
+
+    DiskDiagnostics {
+        id: name.to_string(),
+        disk_number: index as u32,
+        model: model.clone(),
+        friendly_name: model,
+        serial_number,
+        firmware_version,
+        interface_type: interface_type_for_transport(&transport),
+        pnp_device_id,
+        usb_vendor_id,
+        usb_product_id,
+        is_usb,
+        bus_type: if udev.bus.is_empty() {
+            transport.clone()
+        } else {
+            udev.bus.to_ascii_uppercase()
+        },
+        unique_id,
+        media_type: parse_media_type(name, &sys_dir, &transport),
+        transport_type: transport,
+        size_bytes: read_sysfs_u64(&sys_dir.join("size")).unwrap_or(0) * 512,
+        is_system: system_disks.contains(name),
+        health_status: "Unknown".to_string(),
+        smart_supported: false,
+        smart_enabled: false,
+        smart_data_source: "NONE".to_string(),
+        ata_smart_available: false,
+        reliability_available: !reliability.is_empty(),
+        temperature_c: None,
+        power_on_hours: None,
+        power_cycle_count: None,
+        percentage_used: None,
+        read_errors_total: None,
+        write_errors_total: None,
+        host_reads_total: None,
+        host_writes_total: None,
+        smart_attributes: Vec::new(),
+        reliability: Value::Object(reliability),
+        notes: Vec::new(),
+    }
+}
+
+fn enrich_with_native_smart(diagnostics: &mut [DiskDiagnostics]) {
+    for diag in diagnostics.iter_mut() {
+        let looks_nvme = is_nvme_diag(diag);
+        if looks_nvme && try_enrich_native_nvme(diag) {
+            continue;
+        }
+
+        let ata_ok = try_enrich_native_ata(diag);
+
+        // Same lightweight fallback as Windows: a misreported bridge may still answer NVMe.
+        if !looks_nvme && !ata_ok && diag.id.starts_with("nvme") {
+            let _ = try_enrich_native_nvme(diag);
+        }
+    }
+}
+
+fn try_enrich_native_ata(diag: &mut DiskDiagnostics) -> bool {
+    // NVMe namespaces and eMMC/SD blocks do not speak SCSI, so SG_IO would only fail.
+    if diag.id.starts_with("nvme") || diag.id.starts_with("mmcblk") {
+        return false;
+    }
+
+    let node = format!("/dev/{}", diag.id);
+    let handle = match ata::AtaHandle::open(&node) {
+        Ok(handle) => handle,
+        Err(e) => {
+            warn!("Failed to open {} for ATA SMART reading: {}", node, e);
+            return false;
+        }
+    };
+
+    let smart_data = match handle.read_smart_data(diag.is_usb) {
+        Ok(smart_data) => smart_data,
+        Err(e) => {
+            warn!("Failed to read ATA SMART data from {}: {}", node, e);
+            return false;
+        }
+    };
+    info!("Successfully read ATA SMART data from {}", node);
+
+    if diag.temperature_c.is_none() {
+        diag.temperature_c = smart_data.temperature.map(|t| t as f64);
+    }
+    if diag.power_on_hours.is_none() {
+        diag.power_on_hours = smart_data.power_on_hours;
+    }
+    if diag.power_cycle_count.is_none() {
+        diag.power_cycle_count = smart_data.power_cycle_count;
+    }
+
+    let attrs: Vec<SmartAttribute> = smart_data
+        .attributes
+        .iter()
+        .map(|attr| SmartAttribute {
+            id: attr.id as u32,
+            name: smart_attribute_name(attr.id),
+            current: Some(attr.current as u32),
+            worst: Some(attr.worst as u32),
+            threshold: if smart_data.thresholds_available {
+                Some(attr.threshold as u32)
+            } else {
+                None
+            },
+            raw: Some(attr.raw),
+            raw_hex: format!("0x{:012X}", attr.raw),
+        })
+        .collect();
+
+    if !attrs.is_empty() && attrs.len() >= diag.smart_attributes.len() {
+        diag.smart_attributes = attrs;
+        diag.ata_smart_available = true;
+        diag.smart_supported = true;
+        diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "ATA_NATIVE_SGIO");
+        add_note_unique(
+            diag,
+            "ATA SMART data read directly via Linux SG_IO (SAT ATA PASS-THROUGH).",
+        );
+        if !smart_data.thresholds_available {
+            add_note_unique(
+                diag,
+                "SMART threshold table was not returned by device/bridge; threshold values are unavailable.",
+            );
+        }
+    }
+    if let Some(used) = derive_endurance_used_from_attrs(&diag.smart_attributes) {
+        if diag.percentage_used.is_none() || diag.percentage_used.unwrap_or(0.0) <= 0.0 {
+            diag.percentage_used = Some(used);
+        }
+    }
+    true
+}
+
+fn try_enrich_native_nvme(diag: &mut DiskDiagnostics) -> bool {
+    let mut nodes = Vec::new();
+    if let Some(controller) = smartctl::nvme_controller_name(&diag.id) {
+        nodes.push(format!("/dev/{}", controller));
+    }
+    nodes.push(format!("/dev/{}", diag.id));
+
+    nodes
+        .iter()
+        .any(|node| try_enrich_native_nvme_node(diag, node))
+}
+
+fn try_enrich_native_nvme_node(diag: &mut DiskDiagnostics, node: &str) -> bool {
+    match nvme::NVMeHandle::open(node) {
+        Ok(handle) => {
+            if let Ok(id_ctrl) = handle.read_identify_controller() {
+                if diag.model.trim().is_empty() && !id_ctrl.model.is_empty() {
+                    diag.model = id_ctrl.model;
+                }
+                if (diag.serial_number.trim().is_empty() || is_masked_serial(&diag.serial_number))
+                    && !id_ctrl.serial_number.is_empty()
+                {
+                    diag.serial_number = id_ctrl.serial_number;
+                }
+                if diag.firmware_version.trim().is_empty() && !id_ctrl.firmware_version.is_empty() {
+                    diag.firmware_version = id_ctrl.firmware_version;
+                }
+            }
+
+            match handle.read_smart_data() {
+                Ok(nvme_data) => {
+                    info!("Successfully read NVMe SMART data from {}", node);
+                    apply_native_nvme_data(diag, &nvme_data);
+                    add_note_unique(
+                        diag,
+                        "NVMe SMART data read directly via Linux NVMe admin ioctl.",
+                    );
+                    true
+                }
+                Err(e) => {
+                    warn!("Failed to read NVMe SMART data from {}: {}", node, e);
+                    false
+                }
+            }
+        }
+        Err(e) => {
+            warn!("Failed to open {} for NVMe SMART reading: {}", node, e);
+            false
+        }
+    }
+}
+
+/// List detailed disk diagnostics on Linux
+pub async fn list_disk_diagnostics() -> Result<Vec<DiskDiagnostics>> {
+    let names = enumerate_disk_names()?;
+    let mounts = read_mount_entries();
+    let system_disks = system_disk_names(&mounts);
+
+    let mut diagnostics: Vec<DiskDiagnostics> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| build_disk_diagnostics(name, index, &system_disks))
+        .collect();
+
+    smartctl::enrich_with_smartctl(&mut diagnostics);
+    enrich_with_native_smart(&mut diagnostics);
+    smartctl::normalize_endurance_percentage(&mut diagnostics);
+    Ok(diagnostics)
+}
+
+/// Quiet period after the last udev event for a disk before its state is reported.
+const USB_EVENT_DEBOUNCE: Duration = Duration::from_millis(750);
+/// How often the monitor thread wakes up to check for stop requests.
+const USB_MONITOR_POLL_MS: i32 = 200;
+
+lazy_static::lazy_static! {
+    static ref USB_MONITORS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+struct PendingUsbEvent {
+    event_type: UsbEventType,
+    device: UsbDevice,
+    last_seen: Instant,
+}
+
+fn is_usb_udev_device(device: &udev::Device) -> bool {
+    device
+        .property_value("ID_BUS")
+        .map(|v| v.eq_ignore_ascii_case("usb"))
+        .unwrap_or(false)
+        || device.syspath().to_string_lossy().contains("/usb")
+}
+
+/// Whole disk name an event refers to; partition events are folded into their disk.
+fn usb_event_disk_name(device: &udev::Device) -> Option<String> {
+    let name = device.sysname().to_string_lossy().to_string();
+    let devtype = device
+        .devtype()
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if devtype == "partition" {
+        // On removal the partition is already gone from sysfs, so use the syspath layout.
+        return device
+            .syspath()
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|p| p.to_string_lossy().to_string());
+    }
+
+    if devtype == "disk" && is_candidate_name(&name) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn is_candidate_name(name: &str) -> bool {
+    !IGNORED_BLOCK_PREFIXES.iter().any(|p| name.starts_with(p))
+}
+
+fn build_usb_device(name: &str, device: Option<&udev::Device>) -> UsbDevice {
+    let prop = |key: &str| -> String {
+        device
+            .and_then(|d| d.property_value(key))
+            .map(|v| v.to_string_lossy().replace('_', " ").trim().to_string())
+            .unwrap_or_default()
+    };
+    let vendor = prop("ID_VENDOR");
+    let product = prop("ID_MODEL");
+
+    let sys_dir = Path::new(SYS_BLOCK).join(name);
+    let size = read_sysfs_u64(&sys_dir.join("size")).unwrap_or(0) * 512;
+    let display_name = if sys_dir.exists() {
+        read_disk_model(
+            name,
+            &sys_dir,
+            &UdevDiskProps {
+                vendor: vendor.clone(),
+                model: product.clone(),
+                ..Default::default()
+            },
+        )
+    } else if !product.is_empty() {
+        product.clone()
+    } else {
+        name.to_string()
+    };
+
+    UsbDevice {
+        id: name.to_string(),
+        name: display_name,
+        vendor,
+        product,
+        size,
+    }
+}
+
+/// USB disks that are already attached when monitoring starts.
+fn attached_usb_disks() -> HashMap<String, UsbDevice> {
+    let mut result = HashMap::new();
+    for name in enumerate_disk_names().unwrap_or_default() {
+        let sys_dir = Path::new(SYS_BLOCK).join(&name);
+        if !is_usb_attached(&sys_dir) {
+            continue;
+        }
+        let device = udev::Device::from_subsystem_sysname("block".to_string(), name.clone()).ok();
+        let usb = build_usb_device(&name, device.as_ref());
+        result.insert(name, usb);
+    }
+    result
+}
+
+fn emit_usb_event(app_handle: &tauri::AppHandle, event_type: UsbEventType, device: UsbDevice) {
+    info!("USB {:?}: {} ({})", event_type, device.id, device.name);
+    let payload = UsbEvent { event_type, device };
+    if let Err(e) = app_handle.emit(EVENT_USB_DEVICE, payload) {
+        warn!("Failed to emit USB event: {}", e);
+    }
+}
+
+/// Fold a settled event into the reported state and return what the frontend should see.
+///
+/// Card readers stay attached while their medium comes and goes: the kernel only sends a
+/// `change` uevent (DISK_MEDIA_CHANGE=1) and the disk size drops to or rises from zero. So
+/// for the frontend, a medium appearing is a connect and a medium disappearing is a
+/// disconnect, even though the reader itself never left.
+fn settle_usb_event(
+    reported: &mut HashMap<String, UsbDevice>,
+    disk: String,
+    event: PendingUsbEvent,
+) -> Option<(UsbEventType, UsbDevice)> {
+    match event.event_type {
+        UsbEventType::Connected => {
+            let has_medium = event.device.size > 0;
+            let previous = reported.insert(disk, event.device.clone());
+            match previous {
+                Some(previous) if previous.size > 0 && !has_medium => {
+                    Some((UsbEventType::Disconnected, previous))
+                }
+                Some(previous) if previous.size == 0 && has_medium => {
+                    Some((UsbEventType::Connected, event.device))
+                }
+                None if has_medium => Some((UsbEventType::Connected, event.device)),
+                _ => None,
+            }
+        }
+        UsbEventType::Disconnected => match reported.remove(&disk) {
+            // An empty reader was never reported as connected.
+            Some(previous) if previous.size > 0 => Some((UsbEventType::Disconnected, previous)),
+            _ => None,
+        },
+    }
+}
+
+fn run_usb_monitor(app_handle: tauri::AppHandle, stop_flag: Arc<AtomicBool>) -> Result<()> {
+    let socket = udev::MonitorBuilder::new()
+        .and_then(|b| b.match_subsystem("block"))
+        .and_then(|b| b.listen())
+        .map_err(|e| AppError::UsbError(format!("Failed to open udev monitor: {}", e)))?;
+
+    // Last state reported per disk; seeded so already-present sticks don't fire on startup.
+    let mut reported: HashMap<String, UsbDevice> = attached_usb_disks();
+    let mut pending: HashMap<String, PendingUsbEvent> = HashMap::new();
+
+    while !stop_flag.load(Ordering::SeqCst) {
+        let mut fds = [PollFd::new(&socket, PollFlags::POLLIN)];
+        match poll(&mut fds, USB_MONITOR_POLL_MS) {
+            Ok(_) => {}
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(AppError::UsbError(format!("udev poll failed: {}", e))),
+        }
+
+        for event in socket.iter() {
+            let event_type = match event.event_type() {
+                udev::EventType::Add | udev::EventType::Change => UsbEventType::Connected,
+                udev::EventType::Remove => UsbEventType::Disconnected,
+                _ => continue,
+            };
+            let device = event.device();
+            if !is_usb_udev_device(&device) {
+                continue;
+            }
+            let Some(disk) = usb_event_disk_name(&device) else {
+                continue;
+            };
+
+            // A partition vanishing is not the disk vanishing; only the disk's own
+            // remove event means the stick was pulled.
+            let is_partition = device.devtype().map(|v| v == "partition").unwrap_or(false);
+            if is_partition && matches!(event_type, UsbEventType::Disconnected) {
+                continue;
+            }
+
+            let usb = match event_type {
+                UsbEventType::Connected => {
+                    let disk_device = if is_partition {
+                        udev::Device::from_subsystem_sysname("block".to_string(), disk.clone()).ok()
+                    } else {
+                        None
+                    };
+                    build_usb_device(&disk, disk_device.as_ref().or(Some(&device)))
+                }
+                UsbEventType::Disconnected => reported
+                    .get(&disk)
+                    .cloned()
+                    .unwrap_or_else(|| build_usb_device(&disk, Some(&device))),
+            };
+
+            pending.insert(
+                disk,
+                PendingUsbEvent {
+                    event_type,
+                    device: usb,
+                    last_seen: Instant::now(),
+                },
+            );
+        }
+
+        let settled: Vec<String> = pending
+            .iter()
+            .filter(|(_, p)| p.last_seen.elapsed() >= USB_EVENT_DEBOUNCE)
+            .map(|(disk, _)| disk.clone())
+            .collect();
+
+        for disk in settled {
+            let Some(event) = pending.remove(&disk) else {
+                continue;
+            };
+            if let Some((event_type, device)) = settle_usb_event(&mut reported, disk, event) {
+                emit_usb_event(&app_handle, event_type, device);
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// Start USB monitoring on Linux
-pub async fn start_usb_monitoring(_app_handle: tauri::AppHandle) -> Result<String> {
-    Err(AppError::Unsupported(
-        "Linux USB monitoring is not implemented yet".to_string(),
-    ))
+pub async fn start_usb_monitoring(app_handle: tauri::AppHandle) -> Result<String> {
+    let monitor_id = format!("monitor-linux-{}", uuid::Uuid::new_v4());
+    let stop_flag = Arc::new(AtomicBool::new(false));
+
+    if let Ok(mut monitors) = USB_MONITORS.lock() {
+        monitors.insert(monitor_id.clone(), stop_flag.clone());
+    }
+
+    let thread_id = monitor_id.clone();
+    std::thread::Builder::new()
+        .name("usb-monitor".to_string())
+        .spawn(move || {
+            info!("USB monitor {} started", thread_id);
+            if let Err(e) = run_usb_monitor(app_handle, stop_flag) {
+                warn!("USB monitor {} stopped with error: {}", thread_id, e);
+            } else {
+                info!("USB monitor {} stopped", thread_id);
+            }
+            if let Ok(mut monitors) = USB_MONITORS.lock() {
+                monitors.remove(&thread_id);
+            }
+        })
+        .map_err(AppError::io)?;
+
+    Ok(monitor_id)
 }
 
 /// Stop USB monitoring on Linux
-pub async fn stop_usb_monitoring(_monitor_id: &str) -> Result<()> {
-    Err(AppError::Unsupported(
-        "Linux USB monitoring is not implemented yet".to_string(),
-    ))
+pub async fn stop_usb_monitoring(monitor_id: &str) -> Result<()> {
+    let flag = USB_MONITORS
+        .lock()
+        .ok()
+        .and_then(|mut monitors| monitors.remove(monitor_id));
+
+    match flag {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => {
+            warn!("USB monitor {} is not running", monitor_id);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// A sysfs tree with one USB disk `sdb` holding partition `sdb1`, linked the way the
+    /// kernel links `/sys/block` and `/sys/class/block`.
+    fn fake_sysfs() -> PathBuf {
+        let sys = std::env::temp_dir().join(format!("wtg-sysfs-{}", uuid::Uuid::new_v4()));
+        let disk = "devices/pci0000:00/usb1/host6/block/sdb";
+        std::fs::create_dir_all(sys.join(disk).join("sdb1")).unwrap();
+        std::fs::write(sys.join(disk).join("sdb1/partition"), "1\n").unwrap();
+        std::fs::create_dir_all(sys.join("block")).unwrap();
+        std::fs::create_dir_all(sys.join("class/block")).unwrap();
+        symlink(Path::new("..").join(disk), sys.join("block/sdb")).unwrap();
+        symlink(Path::new("../..").join(disk), sys.join("class/block/sdb")).unwrap();
+        symlink(
+            Path::new("../..").join(disk).join("sdb1"),
+            sys.join("class/block/sdb1"),
+        )
+        .unwrap();
+        sys
+    }
+
+    #[test]
+    fn decodes_octal_escapes_in_mount_fields() {
+        assert_eq!(
+            unescape_mount_field("/media/USB\\040Disk"),
+            "/media/USB Disk"
+        );
+        assert_eq!(unescape_mount_field("a\\011b\\012c"), "a\tb\nc");
+        assert_eq!(unescape_mount_field("back\\134slash"), "back\\slash");
+        assert_eq!(
+            unescape_mount_field("/media/caf\\303\\251"),
+            "/media/caf\u{e9}"
+        );
+        // Anything that is not a complete three-digit octal escape is kept as is.
+        assert_eq!(unescape_mount_field("x\\09y"), "x\\09y");
+        assert_eq!(unescape_mount_field("tail\\04"), "tail\\04");
+        assert_eq!(unescape_mount_field("big\\777"), "big\\777");
+    }
+
+    #[test]
+    fn parses_escaped_mountinfo_lines() {
+        let entry = parse_mountinfo_line(
+            "36 25 0:32 /sub /mnt/My\\040Share rw,relatime shared:1 - tmpfs tmpfs rw,size=8g",
+        )
+        .unwrap();
+        assert_eq!(entry.mount_point, "/mnt/My Share");
+        assert_eq!(entry.root, "/sub");
+        assert_eq!(entry.fs_type, "tmpfs");
+        assert_eq!(entry.device_name, "");
+        assert!(parse_mountinfo_line("36 25 0:32 / /mnt rw").is_none());
+    }
+
+    #[test]
+    fn normalizes_disk_ids_to_whole_disks() {
+        let sys = fake_sysfs();
+        for input in ["sdb", "/dev/sdb", " sdb ", "sdb1", "/dev/sdb1"] {
+            assert_eq!(
+                normalize_disk_id_in(&sys, input).as_deref(),
+                Some("sdb"),
+                "{}",
+                input
+            );
+        }
+        for input in ["", "/dev/", "../sdb", "/dev/disk/by-id/usb-x", "sdz"] {
+            assert_eq!(normalize_disk_id_in(&sys, input), None, "{}", input);
+        }
+        let _ = std::fs::remove_dir_all(&sys);
+    }
 }
@@ -0,0 +1,292 @@
+// ATA SMART access through SCSI generic SG_IO and SAT ATA PASS-THROUGH (12/16)
+// Same CDB patterns as the Windows SCSI pass-through path; parsing is shared in platform::smart::ata.
+
+use crate::platform::smart::ata::{
+    build_sat_cdb, parse_ata_smart_tables, sat_pattern_cdb_length, SatPattern, SmartData,
+    READ_ATTRIBUTES, READ_ATTRIBUTE_BUFFER_SIZE, READ_THRESHOLDS, SAT_PATTERN_ATTEMPTS,
+};
+use nix::libc;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+
+const SG_INTERFACE_ID: i32 = b'S' as i32;
+const SG_DXFER_FROM_DEV: i32 = -3;
+const SG_TIMEOUT_MS: u32 = 4_000;
+const SENSE_BUFFER_SIZE: usize = 32;
+
+const SCSI_STATUS_GOOD: u8 = 0x00;
+const SCSI_STATUS_CHECK_CONDITION: u8 = 0x02;
+const SENSE_KEY_NO_SENSE: u8 = 0x00;
+const SENSE_KEY_RECOVERED_ERROR: u8 = 0x01;
+/// Low bits of `driver_status`; DRIVER_SENSE (0x08) alone just means sense data is present.
+const DRIVER_STATUS_MASK: u16 = 0x07;
+const ATA_STATUS_RETURN_DESCRIPTOR: u8 = 0x09;
+const ATA_STATUS_ERR: u8 = 0x01;
+
+/// `struct sg_io_hdr` from <scsi/sg.h>
+#[repr(C)]
+struct SgIoHdr {
+    interface_id: i32,
+    dxfer_direction: i32,
+    cmd_len: u8,
+    mx_sb_len: u8,
+    iovec_count: u16,
+    dxfer_len: u32,
+    dxferp: *mut libc::c_void,
+    cmdp: *mut u8,
+    sbp: *mut u8,
+    timeout: u32,
+    flags: u32,
+    pack_id: i32,
+    usr_ptr: *mut libc::c_void,
+    status: u8,
+    masked_status: u8,
+    msg_status: u8,
+    sb_len_wr: u8,
+    host_status: u16,
+    driver_status: u16,
+    resid: i32,
+    duration: u32,
+    info: u32,
+}
+
+// SG_IO = 0x2285, not encoded with _IOWR.
+nix::ioctl_readwrite_bad!(sg_io, 0x2285, SgIoHdr);
+
+pub struct AtaHandle {
+    file: File,
+    path: String,
+}
+
+impl AtaHandle {
+    /// Open a SCSI disk node (`/dev/sda`) for SG_IO; libata and USB bridges both accept SAT CDBs.
+    pub fn open(device_path: &str) -> Result<Self, String> {
+        let mut last_err = String::new();
+        for write in [true, false] {
+            match OpenOptions::new()
+                .read(true)
+                .write(write)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(device_path)
+            {
+                Ok(file) => {
+                    return Ok(AtaHandle {
+                        file,
+                        path: device_path.to_string(),
+                    })
+                }
+                Err(e) => last_err = e.to_string(),
+            }
+        }
+
+        Err(format!(
+            "Failed to open disk {} for SG_IO: {}",
+            device_path, last_err
+        ))
+    }
+
+    /// Read the SMART attribute table and, when available, the threshold table.
+    /// Vendor bridge CDBs are only tried when `allow_vendor_patterns` is set (USB enclosures).
+    pub fn read_smart_data(&self, allow_vendor_patterns: bool) -> Result<SmartData, String> {
+        let (attr_data, pattern) =
+            self.send_sat_smart_command(READ_ATTRIBUTES, allow_vendor_patterns)?;
+
+        // Thresholds come from the same bridge dialect that answered the attribute read.
+        let threshold_data = self
+            .send_sat_smart_command_once(READ_THRESHOLDS, pattern)
+            .ok()
+            .filter(|data| data.iter().any(|b| *b != 0));
+
+        parse_ata_smart_tables(&attr_data, threshold_data.as_deref())
+    }
+
+    fn send_sat_smart_command(
+        &self,
+        sub_command: u8,
+        allow_vendor_patterns: bool,
+    ) -> Result<(Vec<u8>, SatPattern), String> {
+        let mut last_err = String::new();
+
+        for pattern in SAT_PATTERN_ATTEMPTS {
+            if !pattern.is_standard() && !allow_vendor_patterns {
+                continue;
+            }
+            match self.send_sat_smart_command_once(sub_command, pattern) {
+                Ok(data) if data.iter().any(|b| *b != 0) => return Ok((data, pattern)),
+                Ok(_) => {
+                    last_err = "SAT returned empty buffer".to_string();
+                }
+                Err(e) => {
+                    last_err = e;
+                }
+            }
+        }
+
+        if last_err.is_empty() {
+            Err("SAT SMART command failed".to_string())
+        } else {
+            Err(last_err)
+        }
+    }
+
+    fn send_sat_smart_command_once(
+        &self,
+        sub_command: u8,
+        pattern: SatPattern,
+    ) -> Result<Vec<u8>, String> {
+        let mut cdb = [0u8; 16];
+        build_sat_cdb(&mut cdb, sub_command, pattern);
+        let mut data = vec![0u8; READ_ATTRIBUTE_BUFFER_SIZE];
+        let mut sense = [0u8; SENSE_BUFFER_SIZE];
+
+        let mut hdr = SgIoHdr {
+            interface_id: SG_INTERFACE_ID,
+            dxfer_direction: SG_DXFER_FROM_DEV,
+            cmd_len: sat_pattern_cdb_length(pattern),
+            mx_sb_len: SENSE_BUFFER_SIZE as u8,
+            iovec_count: 0,
+            dxfer_len: data.len() as u32,
+            dxferp: data.as_mut_ptr() as *mut libc::c_void,
+            cmdp: cdb.as_mut_ptr(),
+            sbp: sense.as_mut_ptr(),
+            timeout: SG_TIMEOUT_MS,
+            flags: 0,
+            pack_id: 0,
+            usr_ptr: std::ptr::null_mut(),
+            status: 0,
+            masked_status: 0,
+            msg_status: 0,
+            sb_len_wr: 0,
+            host_status: 0,
+            driver_status: 0,
+            resid: 0,
+            duration: 0,
+            info: 0,
+        };
+
+        unsafe { sg_io(self.file.as_raw_fd(), &mut hdr) }
+            .map_err(|e| format!("ioctl(SG_IO) on {} failed: {}", self.path, e))?;
+
+        let sense_len = (hdr.sb_len_wr as usize).min(SENSE_BUFFER_SIZE);
+        check_sg_result(
+            hdr.status,
+            hdr.host_status,
+            hdr.driver_status,
+            &sense[..sense_len],
+        )
+        .map_err(|e| format!("SAT {:?} on {}: {}", pattern, self.path, e))?;
+
+        Ok(data)
+    }
+}
+
+/// Decide whether a completed SG_IO request carried valid SMART data.
+///
+/// With CK_COND set the SAT layer always answers CHECK CONDITION with a RECOVERED ERROR
+/// ("ATA pass-through information available") sense, so that is treated as success unless
+/// the returned ATA status has ERR set.
+fn check_sg_result(
+    status: u8,
+    host_status: u16,
+    driver_status: u16,
+    sense: &[u8],
+) -> Result<(), String> {
+    if host_status != 0 {
+        return Err(format!("host status 0x{:02X}", host_status));
+    }
+    if driver_status & DRIVER_STATUS_MASK != 0 {
+        return Err(format!("driver status 0x{:02X}", driver_status));
+    }
+
+    match status {
+        SCSI_STATUS_GOOD if sense.is_empty() => return Ok(()),
+        SCSI_STATUS_GOOD | SCSI_STATUS_CHECK_CONDITION => {}
+        other => return Err(format!("SCSI status 0x{:02X}", other)),
+    }
+
+    if sense.len() < 3 {
+        return if status == SCSI_STATUS_GOOD {
+            Ok(())
+        } else {
+            Err("CHECK CONDITION without sense data".to_string())
+        };
+    }
+
+    let response_code = sense[0] & 0x7F;
+    let (sense_key, ata_status) = match response_code {
+        // Descriptor format: look for the ATA Status Return descriptor.
+        0x72 | 0x73 => {
+            let mut ata_status = None;
+            let end = (8 + sense.get(7).copied().unwrap_or(0) as usize).min(sense.len());
+            let mut offset = 8;
+            while offset + 1 < end {
+                let desc_len = sense[offset + 1] as usize + 2;
+                if sense[offset] == ATA_STATUS_RETURN_DESCRIPTOR && offset + 13 < sense.len() {
+                    ata_status = Some(sense[offset + 13]);
+                }
+                offset += desc_len;
+            }
+            (sense[1] & 0x0F, ata_status)
+        }
+        // Fixed format: SAT places the ATA status in INFORMATION byte 1.
+        0x70 | 0x71 => (sense[2] & 0x0F, sense.get(4).copied()),
+        other => return Err(format!("unknown sense response code 0x{:02X}", other)),
+    };
+
+    if sense_key != SENSE_KEY_NO_SENSE && sense_key != SENSE_KEY_RECOVERED_ERROR {
+        return Err(format!("sense key 0x{:X}", sense_key));
+    }
+    if let Some(ata_status) = ata_status.filter(|s| s & ATA_STATUS_ERR != 0) {
+        return Err(format!("ATA status 0x{:02X} has ERR set", ata_status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor_sense(sense_key: u8, ata_status: u8) -> Vec<u8> {
+        let mut sense = vec![0u8; 22];
+        sense[0] = 0x72;
+        sense[1] = sense_key;
+        sense[7] = 14;
+        sense[8] = ATA_STATUS_RETURN_DESCRIPTOR;
+        sense[9] = 12;
+        sense[21] = ata_status;
+        sense
+    }
+
+    #[test]
+    fn good_status_without_sense_is_ok() {
+        assert!(check_sg_result(SCSI_STATUS_GOOD, 0, 0, &[]).is_ok());
+    }
+
+    #[test]
+    fn ck_cond_recovered_error_is_ok() {
+        let sense = descriptor_sense(SENSE_KEY_RECOVERED_ERROR, 0x50);
+        assert!(check_sg_result(SCSI_STATUS_CHECK_CONDITION, 0, 0x08, &sense).is_ok());
+    }
+
+    #[test]
+    fn ata_error_bit_is_rejected() {
+        let sense = descriptor_sense(SENSE_KEY_RECOVERED_ERROR, 0x51);
+        assert!(check_sg_result(SCSI_STATUS_CHECK_CONDITION, 0, 0x08, &sense).is_err());
+    }
+
+    #[test]
+    fn illegal_request_is_rejected() {
+        // Fixed format ILLEGAL REQUEST, typical for a bridge without SAT support.
+        let mut sense = vec![0u8; 18];
+        sense[0] = 0x70;
+        sense[2] = 0x05;
+        assert!(check_sg_result(SCSI_STATUS_CHECK_CONDITION, 0, 0x08, &sense).is_err());
+    }
+
+    #[test]
+    fn transport_failures_are_rejected() {
+        assert!(check_sg_result(SCSI_STATUS_GOOD, 0x07, 0, &[]).is_err());
+        assert!(check_sg_result(SCSI_STATUS_GOOD, 0, 0x06, &[]).is_err());
+    }
+}
@@ -0,0 +1,759 @@
+//! Hardware overview from procfs / sysfs / SMBIOS tables
+//!
+//! Every path is resolved under a caller-supplied root so the gatherer can run against a
+//! fixture tree in tests; production passes "/".
+
+use super::{is_candidate_disk, read_disk_model, read_sysfs_string, read_sysfs_u64, UdevDiskProps};
+use crate::commands::tools::{
+    dedup_keep_order, format_gb_from_bytes, format_gpu_vram, HardwareOverview,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Locations of the pci.ids database across distributions (hwdata, pciutils).
+const PCI_IDS_PATHS: [&str; 3] = [
+    "usr/share/hwdata/pci.ids",
+    "usr/share/misc/pci.ids",
+    "usr/share/pci.ids",
+];
+
+/// DMI strings firmware vendors leave behind instead of real data.
+const DMI_PLACEHOLDERS: [&str; 6] = [
+    "to be filled by o.e.m.",
+    "default string",
+    "system manufacturer",
+    "system product name",
+    "not applicable",
+    "o.e.m.",
+];
+
+const SMBIOS_TYPE_MEMORY_DEVICE: u8 = 17;
+const SMBIOS_TYPE_END_OF_TABLE: u8 = 127;
+
+pub fn gather_hardware_overview() -> HardwareOverview {
+    gather_from_root(Path::new("/"))
+}
+
+fn gather_from_root(root: &Path) -> HardwareOverview {
+    let pci_ids = PCI_IDS_PATHS
+        .iter()
+        .find_map(|p| std::fs::read_to_string(root.join(p)).ok())
+        .unwrap_or_default();
+
+    let processors = dedup_keep_order(build_processors(root));
+    let motherboard = build_motherboard(root);
+    let memory_summary = build_memory_summary(root);
+
+    HardwareOverview {
+        processors: if processors.is_empty() {
+            vec!["Unknown CPU".to_string()]
+        } else {
+            processors
+        },
+        motherboard: if motherboard.is_empty() {
+            "Unknown".to_string()
+        } else {
+            motherboard
+        },
+        memory_summary: if memory_summary.is_empty() {
+            "Unknown".to_string()
+        } else {
+            memory_summary
+        },
+        graphics: build_graphics(root, &pci_ids),
+        monitors: build_monitors(root),
+        disks: build_disks(root),
+        audio_devices: build_audio_devices(root),
+        network_adapters: build_network_adapters(root, &pci_ids),
+    }
+}
+
+fn sorted_dir_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Value of `KEY=value` in a sysfs uevent file.
+fn read_uevent_value(path: &Path, key: &str) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    text.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k == key && !v.trim().is_empty()).then(|| v.trim().to_string())
+    })
+}
+
+// ---------- CPU ----------
+
+fn build_processors(root: &Path) -> Vec<String> {
+    let Ok(text) = std::fs::read_to_string(root.join("proc/cpuinfo")) else {
+        return Vec::new();
+    };
+
+    #[derive(Default)]
+    struct Package {
+        name: String,
+        threads: u32,
+        core_ids: HashSet<String>,
+        cores_hint: u32,
+    }
+
+    let mut packages: BTreeMap<u32, Package> = BTreeMap::new();
+    for block in text.split("\n\n") {
+        let mut fields = BTreeMap::new();
+        for line in block.lines() {
+            if let Some((key, value)) = line.split_once(':') {
+                fields.insert(key.trim(), value.trim());
+            }
+        }
+        if !fields.contains_key("processor") {
+            continue;
+        }
+
+        let physical_id = fields
+            .get("physical id")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let package = packages.entry(physical_id).or_default();
+        package.threads += 1;
+        if package.name.is_empty() {
+            // "Processor" / "cpu model" cover older ARM and MIPS kernels.
+            package.name = ["model name", "Processor", "cpu model"]
+                .iter()
+                .find_map(|k| fields.get(k).filter(|v| !v.is_empty()))
+                .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default();
+        }
+        if let Some(core_id) = fields.get("core id") {
+            package.core_ids.insert(core_id.to_string());
+        }
+        if let Some(cores) = fields.get("cpu cores").and_then(|v| v.parse::<u32>().ok()) {
+            package.cores_hint = cores;
+        }
+    }
+
+    packages
+        .into_values()
+        .map(|p| {
+            let name = if p.name.is_empty() {
+                "Unknown CPU".to_string()
+            } else {
+                p.name
+            };
+            let cores = if !p.core_ids.is_empty() {
+                p.core_ids.len() as u32
+            } else if p.cores_hint > 0 {
+                p.cores_hint
+            } else {
+                p.threads
+            };
+            format!("{name} ({cores}C/{}T)", p.threads)
+        })
+        .collect()
+}
+
+// ---------- Motherboard ----------
+
+fn read_dmi_field(root: &Path, field: &str) -> String {
+    read_sysfs_string(&root.join("sys/class/dmi/id").join(field))
+        .filter(|v| !DMI_PLACEHOLDERS.contains(&v.to_ascii_lowercase().as_str()))
+        .unwrap_or_default()
+}
+
+fn join_vendor_model(vendor: String, model: String) -> String {
+    if vendor.is_empty() || model.starts_with(&vendor) {
+        model
+    } else if model.is_empty() {
+        vendor
+    } else {
+        format!("{vendor} {model}")
+    }
+}
+
+fn build_motherboard(root: &Path) -> String {
+    let board = join_vendor_model(
+        read_dmi_field(root, "board_vendor"),
+        read_dmi_field(root, "board_name"),
+    );
+    if !board.is_empty() {
+        return board;
+    }
+
+    let product = join_vendor_model(
+        read_dmi_field(root, "sys_vendor"),
+        read_dmi_field(root, "product_name"),
+    );
+    if !product.is_empty() {
+        return product;
+    }
+
+    // Device-tree machines (ARM SBCs) have no DMI at all.
+    std::fs::read_to_string(root.join("proc/device-tree/model"))
+        .map(|s| {
+            s.trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+// ---------- Memory ----------
+
+/// One populated SMBIOS type 17 (Memory Device) entry.
+#[derive(Debug, PartialEq)]
+struct MemoryModule {
+    size_bytes: u64,
+    type_code: u8,
+    speed_mhz: u16,
+}
+
+fn smbios_memory_type_name(code: u8) -> &'static str {
+    match code {
+        20 => "DDR",
+        21 => "DDR2",
+        24 => "DDR3",
+        26 => "DDR4",
+        29 => "LPDDR3",
+        30 => "LPDDR4",
+        34 => "DDR5",
+        35 => "LPDDR5",
+        _ => "Unknown",
+    }
+}
+
+fn read_le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Walk a raw SMBIOS structure table (`/sys/firmware/dmi/tables/DMI`) and return the
+/// populated memory devices.
+fn parse_smbios_memory_modules(table: &[u8]) -> Vec<MemoryModule> {
+    let mut modules = Vec::new();
+    let mut offset = 0usize;
+
+    while offset + 4 <= table.len() {
+        let kind = table[offset];
+        let length = table[offset + 1] as usize;
+        if length < 4 || offset + length > table.len() {
+            break;
+        }
+        let formatted = &table[offset..offset + length];
+
+        if kind == SMBIOS_TYPE_MEMORY_DEVICE {
+            if let Some(module) = parse_memory_device(formatted) {
+                modules.push(module);
+            }
+        }
+        if kind == SMBIOS_TYPE_END_OF_TABLE {
+            break;
+        }
+
+        // The string set follows the formatted area and ends with a double NUL.
+        let mut next = offset + length;
+        while next + 1 < table.len() && !(table[next] == 0 && table[next + 1] == 0) {
+            next += 1;
+        }
+        offset = next + 2;
+    }
+
+    modules
+}
+
+fn parse_memory_device(formatted: &[u8]) -> Option<MemoryModule> {
+    let size_field = read_le_u16(formatted, 0x0C)?;
+    let size_bytes = match size_field {
+        0 | 0xFFFF => return None,
+        0x7FFF => (read_le_u32(formatted, 0x1C)? & 0x7FFF_FFFF) as u64 * 1024 * 1024,
+        v if v & 0x8000 != 0 => (v & 0x7FFF) as u64 * 1024,
+        v => v as u64 * 1024 * 1024,
+    };
+    if size_bytes == 0 {
+        return None;
+    }
+
+    let valid_speed = |v: u16| (v != 0 && v != 0xFFFF).then_some(v);
+    let speed_mhz = read_le_u16(formatted, 0x20)
+        .and_then(valid_speed)
+        .or_else(|| read_le_u16(formatted, 0x15).and_then(valid_speed))
+        .unwrap_or(0);
+
+    Some(MemoryModule {
+        size_bytes,
+        type_code: formatted.get(0x12).copied().unwrap_or(0),
+        speed_mhz,
+    })
+}
+
+/// Sum of the "System RAM" ranges in the firmware memory map; closer to the installed
+/// amount than MemTotal, which excludes kernel reservations.
+fn read_memmap_total(root: &Path) -> Option<u64> {
+    let dir = root.join("sys/firmware/memmap");
+    let mut total = 0u64;
+    for name in sorted_dir_names(&dir) {
+        let entry = dir.join(name);
+        if read_sysfs_string(&entry.join("type")).as_deref() != Some("System RAM") {
+            continue;
+        }
+        let parse_hex = |file: &str| {
+            read_sysfs_string(&entry.join(file))
+                .and_then(|v| u64::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        };
+        if let (Some(start), Some(end)) = (parse_hex("start"), parse_hex("end")) {
+            total += end.saturating_sub(start) + 1;
+        }
+    }
+    (total > 0).then_some(total)
+}
+
+fn read_meminfo_total(root: &Path) -> Option<u64> {
+    let text = std::fs::read_to_string(root.join("proc/meminfo")).ok()?;
+    let line = text.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kb = line
+        .trim_start_matches("MemTotal:")
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+fn build_memory_summary(root: &Path) -> String {
+    let modules = std::fs::read(root.join("sys/firmware/dmi/tables/DMI"))
+        .map(|table| parse_smbios_memory_modules(&table))
+        .unwrap_or_default();
+
+    if modules.is_empty() {
+        // The raw SMBIOS table is root-only; fall back to the total alone.
+        return read_memmap_total(root)
+            .or_else(|| read_meminfo_total(root))
+            .map(format_gb_from_bytes)
+            .filter(|gb| *gb > 0)
+            .map(|gb| format!("{gb}GB RAM"))
+            .unwrap_or_default();
+    }
+
+    let module_gb: Vec<u64> = modules
+        .iter()
+        .map(|m| format_gb_from_bytes(m.size_bytes))
+        .collect();
+    let total_gb: u64 = module_gb.iter().sum();
+    let modules_text = module_gb
+        .iter()
+        .map(|gb| format!("{gb}GB"))
+        .collect::<Vec<_>>()
+        .join(" + ");
+    let mem_type = modules
+        .iter()
+        .map(|m| smbios_memory_type_name(m.type_code))
+        .find(|name| *name != "Unknown")
+        .unwrap_or("RAM");
+
+    match modules.iter().map(|m| m.speed_mhz).max().filter(|s| *s > 0) {
+        Some(mhz) => format!("{total_gb}GB {mem_type} {mhz}MHz ({modules_text})"),
+        None => format!("{total_gb}GB {mem_type} ({modules_text})"),
+    }
+}
+
+// ---------- PCI names ----------
+
+/// Vendor and device names for a PCI ID pair from pci.ids text.
+fn lookup_pci_ids(db: &str, vendor_id: u16, device_id: u16) -> Option<(String, Option<String>)> {
+    let vendor_key = format!("{vendor_id:04x}");
+    let device_key = format!("{device_id:04x}");
+    let mut vendor_name: Option<String> = None;
+
+    for line in db.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if let Some(name) = vendor_name.as_ref() {
+            if !line.starts_with('\t') {
+                // Next vendor block: the device is unknown.
+                return Some((name.clone(), None));
+            }
+            if let Some(rest) = line.strip_prefix('\t') {
+                if rest.starts_with('\t') {
+                    continue;
+                }
+                if let Some(device) = rest.strip_prefix(&device_key) {
+                    return Some((name.clone(), Some(device.trim().to_string())));
+                }
+            }
+        } else if let Some(name) = line.strip_prefix(&vendor_key) {
+            if name.starts_with(char::is_whitespace) {
+                vendor_name = Some(name.trim().to_string());
+            }
+        }
+    }
+    vendor_name.map(|name| (name, None))
+}
+
+/// Bracketed part of a pci.ids name, e.g. "GeForce RTX 3070" in "GA104 [GeForce RTX 3070]".
+fn bracket_text(name: &str) -> Option<&str> {
+    let start = name.rfind('[')?;
+    let end = name[start..].find(']')? + start;
+    Some(name[start + 1..end].trim()).filter(|s| !s.is_empty())
+}
+
+/// Short vendor label: "NVIDIA Corporation" -> "NVIDIA",
+/// "Advanced Micro Devices, Inc. [AMD/ATI]" -> "AMD".
+fn short_vendor_name(vendor: &str) -> String {
+    if let Some(bracket) = bracket_text(vendor) {
+        return bracket.split('/').next().unwrap_or(bracket).to_string();
+    }
+    vendor
+        .split_whitespace()
+        .next()
+        .unwrap_or(vendor)
+        .trim_end_matches(',')
+        .to_string()
+}
+
+/// Human name for a PCI function. GPUs carry their marketing name in brackets, while
+/// for other classes the bracket is usually a codename and is dropped.
+fn pci_display_name(db: &str, pci_dir: &Path, prefer_bracket: bool) -> Option<String> {
+    let parse_id = |file: &str| {
+        read_sysfs_string(&pci_dir.join(file))
+            .and_then(|v| u16::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+    };
+    let (vendor_id, device_id) = (parse_id("vendor")?, parse_id("device")?);
+    let (vendor, device) = lookup_pci_ids(db, vendor_id, device_id)?;
+    let vendor = short_vendor_name(&vendor);
+    let Some(device) = device else {
+        return Some(format!("{vendor} {vendor_id:04x}:{device_id:04x}"));
+    };
+
+    let model = if prefer_bracket {
+        bracket_text(&device).unwrap_or(&device).to_string()
+    } else {
+        match device.rfind(" [") {
+            Some(idx) if device.ends_with(']') => device[..idx].trim().to_string(),
+            _ => device.clone(),
+        }
+    };
+    Some(join_vendor_model(vendor, model))
+}
+
+// ---------- Graphics / monitors ----------
+
+fn is_drm_card(name: &str) -> bool {
+    name.strip_prefix("card")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn build_graphics(root: &Path, pci_ids: &str) -> Vec<String> {
+    let drm = root.join("sys/class/drm");
+    let mut out = Vec::new();
+
+    for card in sorted_dir_names(&drm)
+        .into_iter()
+        .filter(|n| is_drm_card(n))
+    {
+        let device_dir = drm.join(&card).join("device");
+        let name = pci_display_name(pci_ids, &device_dir, true)
+            .or_else(|| read_uevent_value(&device_dir.join("uevent"), "OF_NAME"))
+            .or_else(|| read_uevent_value(&device_dir.join("uevent"), "DRIVER"));
+        let Some(name) = name else {
+            continue;
+        };
+
+        // Only amdgpu (and xe/i915 on discrete parts) export VRAM size through sysfs.
+        let vram_text =
+            format_gpu_vram(read_sysfs_u64(&device_dir.join("mem_info_vram_total")).unwrap_or(0));
+        if vram_text.is_empty() {
+            out.push(name);
+        } else {
+            out.push(format!("{name} ({vram_text})"));
+        }
+    }
+    dedup_keep_order(out)
+}
+
+/// Identification and preferred mode from a 128-byte EDID base block.
+#[derive(Debug, PartialEq)]
+struct EdidInfo {
+    /// Three-letter PNP manufacturer ID, e.g. "DEL".
+    manufacturer: String,
+    /// Display product name descriptor (0xFC), absent on most laptop panels.
+    name: Option<String>,
+    resolution: Option<(u32, u32)>,
+}
+
+fn parse_edid(edid: &[u8]) -> Option<EdidInfo> {
+    const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+    if edid.len() < 128 || edid[..8] != HEADER {
+        return None;
+    }
+
+    let mfg = u16::from_be_bytes([edid[8], edid[9]]);
+    let manufacturer: String = [(mfg >> 10) & 0x1F, (mfg >> 5) & 0x1F, mfg & 0x1F]
+        .iter()
+        .filter(|v| (1..=26).contains(*v))
+        .map(|v| (b'A' + *v as u8 - 1) as char)
+        .collect();
+
+    let mut name = None;
+    let mut resolution = None;
+    for offset in [54usize, 72, 90, 108] {
+        let desc = &edid[offset..offset + 18];
+        let pixel_clock = u16::from_le_bytes([desc[0], desc[1]]);
+        if pixel_clock != 0 {
+            // Detailed timing descriptor; the first one is the preferred mode.
+            if resolution.is_none() {
+                let h = desc[2] as u32 | ((desc[4] as u32 >> 4) << 8);
+                let v = desc[5] as u32 | ((desc[7] as u32 >> 4) << 8);
+                resolution = Some((h, v));
+            }
+        } else if desc[3] == 0xFC && name.is_none() {
+            let text: String = desc[5..18]
+                .iter()
+                .take_while(|b| **b != 0x0A)
+                .map(|b| *b as char)
+                .collect();
+            let text = text.trim().to_string();
+            if !text.is_empty() {
+                name = Some(text);
+            }
+        }
+    }
+
+    Some(EdidInfo {
+        manufacturer,
+        name,
+        resolution,
+    })
+}
+
+fn build_monitors(root: &Path) -> Vec<String> {
+    let drm = root.join("sys/class/drm");
+    let mut out = Vec::new();
+
+    for entry in sorted_dir_names(&drm) {
+        // Connector directories are named "<card>-<connector>", e.g. "card0-HDMI-A-1".
+        let Some((card, connector)) = entry.split_once('-') else {
+            continue;
+        };
+        if !is_drm_card(card) {
+            continue;
+        }
+        let dir = drm.join(&entry);
+        if read_sysfs_string(&dir.join("status")).as_deref() != Some("connected") {
+            continue;
+        }
+
+        let parsed = std::fs::read(dir.join("edid"))
+            .ok()
+            .and_then(|edid| parse_edid(&edid));
+        let Some(info) = parsed else {
+            out.push(connector.to_string());
+            continue;
+        };
+        // Panels without a name descriptor only identify their manufacturer.
+        let name = info.name.unwrap_or_else(|| {
+            format!("{} {connector}", info.manufacturer)
+                .trim()
+                .to_string()
+        });
+        match info.resolution {
+            Some((w, h)) => out.push(format!("{name} ({w}x{h})")),
+            None => out.push(name),
+        }
+    }
+    dedup_keep_order(out)
+}
+
+// ---------- Disks ----------
+
+fn build_disks(root: &Path) -> Vec<String> {
+    let sys_block = root.join("sys/block");
+    let udev = UdevDiskProps::default();
+    let mut out = Vec::new();
+
+    for name in sorted_dir_names(&sys_block) {
+        if !is_candidate_disk(&sys_block, &name) {
+            continue;
+        }
+        let sys_dir = sys_block.join(&name);
+        let model = read_disk_model(&name, &sys_dir, &udev);
+        let size_bytes = read_sysfs_u64(&sys_dir.join("size")).unwrap_or(0) * 512;
+        out.push(format!("{model} ({}GB)", format_gb_from_bytes(size_bytes)));
+    }
+    dedup_keep_order(out)
+}
+
+// ---------- Audio ----------
+
+fn build_audio_devices(root: &Path) -> Vec<String> {
+    let asound = root.join("proc/asound");
+    let Ok(cards) = std::fs::read_to_string(asound.join("cards")) else {
+        return Vec::new();
+    };
+
+    let mut out = Vec::new();
+    // Card header lines look like " 0 [PCH            ]: HDA-Intel - HDA Intel PCH";
+    // the indented continuation line after each one is skipped.
+    for line in cards.lines() {
+        let trimmed = line.trim_start();
+        let Some((index, rest)) = trimmed.split_once(' ') else {
+            continue;
+        };
+        if index.parse::<u32>().is_err() || !rest.trim_start().starts_with('[') {
+            continue;
+        }
+        let Some((_, name)) = rest.split_once(" - ") else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let codec = std::fs::read_to_string(asound.join(format!("card{index}/codec#0")))
+            .ok()
+            .and_then(|text| {
+                text.lines()
+                    .find_map(|l| l.strip_prefix("Codec:"))
+                    .map(|c| c.trim().to_string())
+            })
+            .filter(|c| !c.is_empty());
+        match codec {
+            Some(codec) => out.push(format!("{name} ({codec})")),
+            None => out.push(name.to_string()),
+        }
+    }
+    dedup_keep_order(out)
+}
+
+// ---------- Network ----------
+
+fn build_network_adapters(root: &Path, pci_ids: &str) -> Vec<String> {
+    let net = root.join("sys/class/net");
+    let mut out = Vec::new();
+
+    for iface in sorted_dir_names(&net) {
+        let dir: PathBuf = net.join(&iface);
+        // Only interfaces backed by a bus device are physical; lo, bridges, veth and
+        // tunnels have no "device" link.
+        let device_dir = dir.join("device");
+        if iface == "lo" || !device_dir.exists() {
+            continue;
+        }
+
+        let is_wireless = read_uevent_value(&dir.join("uevent"), "DEVTYPE").as_deref()
+            == Some("wlan")
+            || dir.join("wireless").exists();
+        let name = pci_display_name(pci_ids, &device_dir, false)
+            .or_else(|| read_uevent_value(&device_dir.join("uevent"), "DRIVER"))
+            .unwrap_or_else(|| {
+                if is_wireless {
+                    "Wireless adapter".to_string()
+                } else {
+                    "Network adapter".to_string()
+                }
+            });
+        out.push(format!("{name} ({iface})"));
+    }
+    dedup_keep_order(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/linux_root")
+    }
+
+    #[test]
+    fn gathers_overview_from_fixture_root() {
+        let overview = gather_from_root(&fixture_root());
+
+        assert_eq!(
+            overview.processors,
+            vec!["12th Gen Intel(R) Core(TM) i5-12400 (2C/4T)"]
+        );
+        assert_eq!(
+            overview.motherboard,
+            "ASUSTeK COMPUTER INC. PRIME B660M-A D4"
+        );
+        assert_eq!(overview.memory_summary, "16GB DDR4 3200MHz (8GB + 8GB)");
+        assert_eq!(
+            overview.graphics,
+            vec![
+                "NVIDIA GeForce RTX 3070",
+                "AMD Radeon RX 6800/6800 XT / 6900 XT (16GB)"
+            ]
+        );
+        assert_eq!(
+            overview.monitors,
+            vec!["DELL U2720Q (3840x2160)", "BOE eDP-1 (1920x1080)"]
+        );
+        assert_eq!(
+            overview.disks,
+            vec![
+                "WD_BLACK SN770 500GB (466GB)",
+                "Samsung SSD 870 EVO 1TB (932GB)"
+            ]
+        );
+        assert_eq!(
+            overview.audio_devices,
+            vec![
+                "HDA Intel PCH (Realtek ALC897)",
+                "HDA NVidia (Nvidia GPU 9f HDMI/DP)"
+            ]
+        );
+        assert_eq!(
+            overview.network_adapters,
+            vec![
+                "Realtek RTL8125 2.5GbE Controller (enp3s0)",
+                "Intel Wi-Fi 6E(802.11ax) AX210/AX1675* 2x2 (wlp4s0)"
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_falls_back_to_memmap_without_smbios() {
+        let dir = std::env::temp_dir().join(format!("wtg-hw-memmap-{}", std::process::id()));
+        let entry = dir.join("sys/firmware/memmap/0");
+        std::fs::create_dir_all(&entry).unwrap();
+        std::fs::write(entry.join("start"), "0x100000000\n").unwrap();
+        std::fs::write(entry.join("end"), "0x2ffffffff\n").unwrap();
+        std::fs::write(entry.join("type"), "System RAM\n").unwrap();
+
+        let summary = build_memory_summary(&dir);
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(summary, "8GB RAM");
+    }
+
+    #[test]
+    fn smbios_size_encodings() {
+        let mut device = vec![0u8; 0x28];
+        device[0] = SMBIOS_TYPE_MEMORY_DEVICE;
+        device[1] = 0x28;
+        device[0x12] = 34;
+
+        // 0x7FFF defers to the 32-bit extended size in MB.
+        device[0x0C..0x0E].copy_from_slice(&0x7FFFu16.to_le_bytes());
+        device[0x1C..0x20].copy_from_slice(&65536u32.to_le_bytes());
+        assert_eq!(parse_memory_device(&device).unwrap().size_bytes, 64 << 30);
+
+        // Bit 15 switches the unit to KB.
+        device[0x0C..0x0E].copy_from_slice(&(0x8000u16 | 512).to_le_bytes());
+        assert_eq!(parse_memory_device(&device).unwrap().size_bytes, 512 << 10);
+
+        device[0x0C..0x0E].copy_from_slice(&0u16.to_le_bytes());
+        assert!(parse_memory_device(&device).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+// NVMe SMART access through the Linux NVMe admin passthrough ioctl
+// Same log pages as the Windows Storage Query path; parsing is shared in platform::smart::nvme.
+
+use crate::platform::smart::nvme::{
+    parse_identify_controller, parse_smart_data, NVMeIdentifyInfo, NVMeSmartData,
+    NVME_IDENTIFY_SIZE, NVME_SMART_LOG_SIZE,
+};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+const NVME_ADMIN_IDENTIFY: u8 = 0x06;
+const NVME_LOG_PAGE_HEALTH_INFO: u32 = 0x02;
+const NVME_IDENTIFY_CNS_CONTROLLER: u32 = 0x01;
+const NVME_NSID_ALL: u32 = 0xFFFF_FFFF;
+const NVME_ADMIN_TIMEOUT_MS: u32 = 5_000;
+
+/// `struct nvme_passthru_cmd` from <linux/nvme_ioctl.h>
+#[repr(C)]
+#[derive(Debug, Default)]
+struct NvmePassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+// NVME_IOCTL_ADMIN_CMD = _IOWR('N', 0x41, struct nvme_admin_cmd)
+nix::ioctl_readwrite!(nvme_ioctl_admin_cmd, b'N', 0x41, NvmePassthruCmd);
+
+pub struct NVMeHandle {
+    file: File,
+    path: String,
+}
+
+impl NVMeHandle {
+    /// Open an NVMe controller (`/dev/nvme0`) or namespace (`/dev/nvme0n1`) node.
+    pub fn open(device_path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(device_path)
+            .map_err(|e| format!("Failed to open NVMe device {}: {}", device_path, e))?;
+        Ok(NVMeHandle {
+            file,
+            path: device_path.to_string(),
+        })
+    }
+
+    pub fn read_smart_data(&self) -> Result<NVMeSmartData, String> {
+        let mut buffer = vec![0u8; NVME_SMART_LOG_SIZE];
+        let numd = (NVME_SMART_LOG_SIZE / 4) as u32 - 1;
+        let mut cmd = NvmePassthruCmd {
+            opcode: NVME_ADMIN_GET_LOG_PAGE,
+            nsid: NVME_NSID_ALL,
+            cdw10: (numd << 16) | NVME_LOG_PAGE_HEALTH_INFO,
+            ..Default::default()
+        };
+        self.run_admin_command(&mut cmd, &mut buffer)?;
+
+        if buffer.iter().all(|b| *b == 0) {
+            return Err(format!("NVMe SMART log from {} is empty", self.path));
+        }
+        parse_smart_data(&buffer)
+    }
+
+    pub fn read_identify_controller(&self) -> Result<NVMeIdentifyInfo, String> {
+        let mut buffer = vec![0u8; NVME_IDENTIFY_SIZE];
+        let mut cmd = NvmePassthruCmd {
+            opcode: NVME_ADMIN_IDENTIFY,
+            cdw10: NVME_IDENTIFY_CNS_CONTROLLER,
+            ..Default::default()
+        };
+        self.run_admin_command(&mut cmd, &mut buffer)?;
+
+        if buffer.iter().all(|b| *b == 0) {
+            return Err(format!(
+                "NVMe identify controller data from {} is empty",
+                self.path
+            ));
+        }
+        Ok(parse_identify_controller(&buffer))
+    }
+
+    fn run_admin_command(
+        &self,
+        cmd: &mut NvmePassthruCmd,
+        buffer: &mut [u8],
+    ) -> Result<(), String> {
+        cmd.addr = buffer.as_mut_ptr() as u64;
+        cmd.data_len = buffer.len() as u32;
+        cmd.timeout_ms = NVME_ADMIN_TIMEOUT_MS;
+
+        // The kernel returns the NVMe status field as a positive value on command failure.
+        let status = unsafe { nvme_ioctl_admin_cmd(self.file.as_raw_fd(), cmd) }.map_err(|e| {
+            format!(
+                "ioctl(NVME_IOCTL_ADMIN_CMD, opcode 0x{:02X}) on {} failed: {}",
+                cmd.opcode, self.path, e
+            )
+        })?;
+
+        if status != 0 {
+            return Err(format!(
+                "NVMe admin command 0x{:02X} on {} returned status 0x{:X}",
+                cmd.opcode, self.path, status
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,415 @@
+//! Mounted partition listing with offline Windows installation detection
+
+use super::{
+    detect_transport, enumerate_disk_names, parent_disks_of, parse_media_type, read_mount_entries,
+    read_udev_props, MountEntry, SYS_BLOCK,
+};
+use crate::commands::partition::PartitionInfo;
+use crate::utils::bcd::BCD_STORE_PATHS;
+use crate::utils::regf::Hive;
+use crate::{AppError, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const CURRENT_VERSION_KEY: &str = "Microsoft\\Windows NT\\CurrentVersion";
+/// First build number shipped as Windows 11; its ProductName still says "Windows 10".
+const WINDOWS_11_FIRST_BUILD: u32 = 22000;
+
+/// Filesystem details udev's blkid probe attached to a partition.
+#[derive(Debug, Default)]
+struct UdevFsProps {
+    fs_type: String,
+    fs_version: String,
+    label: String,
+}
+
+/// List mounted partitions. Linux has no drive letters, so `drive_letter` carries the
+/// mount path.
+pub async fn list_partitions() -> Result<Vec<PartitionInfo>> {
+    info!("Listing partitions on Linux");
+
+    let disk_names = enumerate_disk_names()?;
+    let mounts = read_mount_entries();
+    let mut disk_cache: HashMap<String, (String, String)> = HashMap::new();
+    let mut result = Vec::new();
+
+    for entry in select_partition_mounts(&mounts) {
+        let Some((disk_name, disk_number)) = parent_disks_of(&entry.device_name)
+            .into_iter()
+            .find_map(|d| disk_names.iter().position(|n| *n == d).map(|i| (d, i)))
+        else {
+            continue;
+        };
+
+        let (protocol, media_type) = disk_cache
+            .entry(disk_name.clone())
+            .or_insert_with(|| {
+                let sys_dir = Path::new(SYS_BLOCK).join(&disk_name);
+                let bus = read_udev_props(&disk_name).bus;
+                let transport = detect_transport(&disk_name, &sys_dir, &bus);
+                let media = parse_media_type(&disk_name, &sys_dir, &transport);
+                (transport, media)
+            })
+            .clone();
+
+        // fsblkcnt_t / c_ulong are narrower than u64 on 32-bit targets.
+        #[allow(clippy::unnecessary_cast)]
+        let (size, free) = match nix::sys::statvfs::statvfs(entry.mount_point.as_str()) {
+            Ok(st) => {
+                let fragment = st.fragment_size() as u64;
+                (
+                    st.blocks() as u64 * fragment,
+                    st.blocks_available() as u64 * fragment,
+                )
+            }
+            Err(e) => {
+                warn!("statvfs({}) failed: {}", entry.mount_point, e);
+                (0, 0)
+            }
+        };
+
+        let udev_fs = read_udev_fs_props(&entry.device_name);
+        let filesystem = normalize_filesystem(&entry.fs_type, &udev_fs);
+        let windows_name = if filesystem == "NTFS" {
+            detect_windows_install(Path::new(&entry.mount_point))
+        } else {
+            None
+        };
+
+        result.push(PartitionInfo {
+            drive_letter: entry.mount_point.clone(),
+            label: udev_fs.label,
+            filesystem,
+            size,
+            free,
+            disk_number: disk_number as u32,
+            protocol,
+            media_type,
+            has_windows: windows_name.is_some(),
+            windows_name: windows_name.unwrap_or_default(),
+        });
+    }
+
+    result.sort_by(|a, b| a.drive_letter.cmp(&b.drive_letter));
+    Ok(result)
+}
+
+/// Locate the BCD stores on the mounted partitions of the disk holding `mount_point`.
+/// Returns the device node of that disk and the stores, target volume first.
+pub fn locate_bcd_stores(mount_point: &str) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let target = match mount_point.trim().trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    let mounts = read_mount_entries();
+    let selected = select_partition_mounts(&mounts);
+    let entry = selected
+        .iter()
+        .find(|m| m.mount_point == target)
+        .ok_or_else(|| {
+            AppError::DeviceNotFound(format!("{} is not a mounted partition", target))
+        })?;
+    let disk = parent_disks_of(&entry.device_name)
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            AppError::DeviceNotFound(format!("Cannot resolve the disk of {}", entry.device_name))
+        })?;
+
+    // The ESP or BIOS system partition of the same disk is usually mounted elsewhere.
+    let siblings = selected
+        .iter()
+        .filter(|m| m.mount_point != target && parent_disks_of(&m.device_name).contains(&disk));
+    let stores = std::iter::once(entry)
+        .chain(siblings)
+        .flat_map(|m| find_bcd_stores(Path::new(&m.mount_point)))
+        .collect();
+
+    Ok((Path::new("/dev").join(disk), stores))
+}
+
+fn find_bcd_stores(volume_root: &Path) -> Vec<PathBuf> {
+    BCD_STORE_PATHS
+        .iter()
+        .filter_map(|relative| {
+            relative
+                .split('\\')
+                .try_fold(volume_root.to_path_buf(), |dir, part| {
+                    find_child_ci(&dir, part)
+                })
+        })
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// One mount per block device: skip virtual filesystems, bind mounts of subdirectories
+/// and repeated mounts of the same device (the first one in mount order wins).
+fn select_partition_mounts(mounts: &[MountEntry]) -> Vec<&MountEntry> {
+    let mut seen = HashSet::new();
+    mounts
+        .iter()
+        .filter(|m| !m.device_name.is_empty() && m.root == "/")
+        .filter(|m| seen.insert(m.device_name.clone()))
+        .collect()
+}
+
+fn read_udev_fs_props(name: &str) -> UdevFsProps {
+    let device = match udev::Device::from_subsystem_sysname("block".to_string(), name.to_string()) {
+        Ok(d) => d,
+        Err(e) => {
+            warn!("udev lookup failed for {}: {}", name, e);
+            return UdevFsProps::default();
+        }
+    };
+
+    let raw = |key: &str| -> String {
+        device
+            .property_value(key)
+            .map(|v| v.to_string_lossy().trim().to_string())
+            .unwrap_or_default()
+    };
+
+    // ID_FS_LABEL has spaces replaced by underscores; the _ENC variant keeps them as \x20.
+    let label = match raw("ID_FS_LABEL_ENC") {
+        enc if !enc.is_empty() => decode_udev_escapes(&enc),
+        _ => raw("ID_FS_LABEL"),
+    };
+
+    UdevFsProps {
+        fs_type: raw("ID_FS_TYPE"),
+        fs_version: raw("ID_FS_VERSION"),
+        label,
+    }
+}
+
+/// Decode the `\xNN` escapes udev uses in `*_ENC` properties.
+fn decode_udev_escapes(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x') && i + 3 < bytes.len() {
+            if let Ok(v) = u8::from_str_radix(&value[i + 2..i + 4], 16) {
+                out.push(v);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Filesystem name in the vocabulary Windows reports (`DriveInfo.DriveFormat`).
+fn normalize_filesystem(mount_fs_type: &str, udev: &UdevFsProps) -> String {
+    // ntfs-3g and other FUSE drivers only show up as "fuseblk" in the mount table.
+    let fs = if mount_fs_type.starts_with("fuse") && !udev.fs_type.is_empty() {
+        udev.fs_type.as_str()
+    } else {
+        mount_fs_type
+    };
+
+    match fs.to_ascii_lowercase().as_str() {
+        "ntfs" | "ntfs3" | "ntfs-3g" => "NTFS".to_string(),
+        "vfat" | "msdos" | "fat" => {
+            if udev.fs_version.eq_ignore_ascii_case("FAT32") || udev.fs_version.is_empty() {
+                "FAT32".to_string()
+            } else {
+                "FAT".to_string()
+            }
+        }
+        "exfat" => "exFAT".to_string(),
+        "refs" => "ReFS".to_string(),
+        "udf" => "UDF".to_string(),
+        _ => fs.to_string(),
+    }
+}
+
+/// Resolve `name` inside `dir` ignoring case; NTFS is case-insensitive but its Linux
+/// drivers generally are not.
+fn find_child_ci(dir: &Path, name: &str) -> Option<PathBuf> {
+    let direct = dir.join(name);
+    if direct.exists() {
+        return Some(direct);
+    }
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+        .map(|e| e.path())
+}
+
+/// Return the product name if `mount_point` holds a Windows installation.
+fn detect_windows_install(mount_point: &Path) -> Option<String> {
+    let windows_dir = find_child_ci(mount_point, "Windows")?;
+    let system32 = find_child_ci(&windows_dir, "System32")?;
+    if !system32.is_dir() {
+        return None;
+    }
+
+    Some(read_windows_product_name(&system32).unwrap_or_else(|| "Windows".to_string()))
+}
+
+fn read_windows_product_name(system32: &Path) -> Option<String> {
+    let hive_path = find_child_ci(&find_child_ci(system32, "config")?, "SOFTWARE")?;
+    let mut hive = match Hive::open(&hive_path) {
+        Ok(h) => h,
+        Err(e) => {
+            warn!("Failed to open {}: {}", hive_path.display(), e);
+            return None;
+        }
+    };
+
+    let key = hive.open_key(CURRENT_VERSION_KEY).ok()??;
+    let product = hive
+        .value(&key, "ProductName")
+        .ok()??
+        .as_string()?
+        .trim()
+        .to_string();
+    if product.is_empty() {
+        return None;
+    }
+
+    let build = ["CurrentBuildNumber", "CurrentBuild"]
+        .iter()
+        .filter_map(|name| hive.value(&key, name).ok().flatten())
+        .filter_map(|v| v.as_string())
+        .find_map(|v| v.trim().parse::<u32>().ok());
+
+    Some(windows_display_name(&product, build))
+}
+
+fn windows_display_name(product: &str, build: Option<u32>) -> String {
+    match (product.strip_prefix("Windows 10"), build) {
+        (Some(rest), Some(b)) if b >= WINDOWS_11_FIRST_BUILD => format!("Windows 11{}", rest),
+        _ => product.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOFTWARE_HIVE: &[u8] = include_bytes!("../../../tests/fixtures/regf/software_min.hiv");
+
+    fn mount(device: &str, mount_point: &str, root: &str) -> MountEntry {
+        MountEntry {
+            device_name: device.to_string(),
+            mount_point: mount_point.to_string(),
+            root: root.to_string(),
+            fs_type: "ext4".to_string(),
+        }
+    }
+
+    fn fs_props(fs_type: &str, fs_version: &str) -> UdevFsProps {
+        UdevFsProps {
+            fs_type: fs_type.to_string(),
+            fs_version: fs_version.to_string(),
+            label: String::new(),
+        }
+    }
+
+    fn scratch_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wtg-{}-{}", tag, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn skips_virtual_bind_and_duplicate_mounts() {
+        let mounts = vec![
+            mount("sda2", "/", "/"),
+            mount("", "/proc", "/"),
+            mount("sda2", "/var/lib/docker", "/"),
+            mount("sdb1", "/srv/data", "/exports"),
+            mount("sdb1", "/media/win", "/"),
+        ];
+        let selected: Vec<&str> = select_partition_mounts(&mounts)
+            .into_iter()
+            .map(|m| m.mount_point.as_str())
+            .collect();
+        assert_eq!(selected, vec!["/", "/media/win"]);
+    }
+
+    #[test]
+    fn normalizes_filesystem_names() {
+        let none = UdevFsProps::default();
+        assert_eq!(normalize_filesystem("ntfs3", &none), "NTFS");
+        assert_eq!(
+            normalize_filesystem("fuseblk", &fs_props("ntfs", "")),
+            "NTFS"
+        );
+        assert_eq!(
+            normalize_filesystem("vfat", &fs_props("vfat", "FAT32")),
+            "FAT32"
+        );
+        assert_eq!(
+            normalize_filesystem("vfat", &fs_props("vfat", "FAT16")),
+            "FAT"
+        );
+        assert_eq!(normalize_filesystem("exfat", &none), "exFAT");
+        assert_eq!(normalize_filesystem("ext4", &none), "ext4");
+    }
+
+    #[test]
+    fn decodes_udev_label_escapes() {
+        assert_eq!(
+            decode_udev_escapes("Windows\\x20To\\x20Go"),
+            "Windows To Go"
+        );
+        assert_eq!(decode_udev_escapes("plain"), "plain");
+        assert_eq!(decode_udev_escapes("bad\\xZZ"), "bad\\xZZ");
+    }
+
+    #[test]
+    fn finds_bcd_stores_ignoring_case() {
+        let root = scratch_dir("bcd");
+        std::fs::create_dir_all(root.join("efi/microsoft/BOOT")).unwrap();
+        std::fs::write(root.join("efi/microsoft/BOOT/bcd"), b"regf").unwrap();
+        std::fs::create_dir_all(root.join("Boot/BCD")).unwrap();
+
+        let stores = find_bcd_stores(&root);
+        assert_eq!(stores, vec![root.join("efi/microsoft/BOOT/bcd")]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn windows_11_builds_are_renamed() {
+        assert_eq!(
+            windows_display_name("Windows 10 Pro", Some(22631)),
+            "Windows 11 Pro"
+        );
+        assert_eq!(
+            windows_display_name("Windows 10 Pro", Some(19045)),
+            "Windows 10 Pro"
+        );
+        assert_eq!(
+            windows_display_name("Windows Server 2022 Standard", Some(20348)),
+            "Windows Server 2022 Standard"
+        );
+    }
+
+    #[test]
+    fn detects_windows_install_and_reads_product_name() {
+        let root = scratch_dir("partition");
+        let config = root.join("WINDOWS/system32/config");
+        std::fs::create_dir_all(&config).unwrap();
+        std::fs::write(config.join("SOFTWARE"), SOFTWARE_HIVE).unwrap();
+
+        assert_eq!(
+            detect_windows_install(&root).as_deref(),
+            Some("Windows 11 Pro")
+        );
+
+        std::fs::remove_file(config.join("SOFTWARE")).unwrap();
+        assert_eq!(detect_windows_install(&root).as_deref(), Some("Windows"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+        let empty = scratch_dir("partition-empty");
+        assert_eq!(detect_windows_install(&empty), None);
+        std::fs::remove_dir_all(&empty).unwrap();
+    }
+}
@@ -2,5 +2,10 @@
 pub mod linux;
 #[cfg(target_os = "macos")]
 pub mod macos;
+// macOS has no native SMART transport yet, so only the smartctl path uses it there.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+pub mod smart;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod smartctl;
 #[cfg(target_os = "windows")]
 pub mod windows;
@@ -0,0 +1,5 @@
+// Platform-independent SMART data parsing
+// Shared by the native SMART readers; only the transport differs per OS.
+
+pub mod ata;
+pub mod nvme;
@@ -0,0 +1,436 @@
+// ATA SMART attribute/threshold table parsing and SAT CDB construction
+// Migrated from CrystalDiskInfo (MIT License)
+// Original Author: hiyohiyo (https://crystalmark.info/)
+
+/// Size of the SMART READ DATA / READ THRESHOLDS sector.
+pub const READ_ATTRIBUTE_BUFFER_SIZE: usize = 512;
+
+// ATA Commands
+pub const SMART_CMD: u8 = 0xB0;
+
+// SMART Sub Commands
+pub const READ_ATTRIBUTES: u8 = 0xD0;
+pub const READ_THRESHOLDS: u8 = 0xD1;
+
+/// End of the 30 x 12-byte attribute entries in the SMART data sector.
+const SMART_TABLE_END: usize = 362;
+
+pub struct SmartData {
+    pub thresholds_available: bool,
+    pub attributes: Vec<SmartAttribute>,
+    pub temperature: Option<i32>,
+    pub power_on_hours: Option<u64>,
+    pub power_cycle_count: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmartAttribute {
+    pub id: u8,
+    pub current: u8,
+    pub worst: u8,
+    pub threshold: u8,
+    pub raw: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SatPattern {
+    Ata12(u8),
+    Ata16(u8),
+    Sunplus,
+    IoData,
+    Logitec,
+    Prolific,
+    JMicron,
+    Cypress,
+}
+
+/// CrystalDiskInfo style fallback attempts for bridge variations, in probing order.
+/// Flags 0x2E request CK_COND so the bridge returns the ATA status; 0x0E omits it
+/// for bridges that reject that bit.
+pub const SAT_PATTERN_ATTEMPTS: [SatPattern; 10] = [
+    SatPattern::Ata12(0x2E),
+    SatPattern::Ata16(0x2E),
+    SatPattern::Ata12(0x0E),
+    SatPattern::Ata16(0x0E),
+    SatPattern::JMicron,
+    SatPattern::Sunplus,
+    SatPattern::IoData,
+    SatPattern::Logitec,
+    SatPattern::Prolific,
+    SatPattern::Cypress,
+];
+
+impl SatPattern {
+    /// Standard T10 SAT ATA PASS-THROUGH (12/16) rather than a vendor bridge command.
+    pub fn is_standard(self) -> bool {
+        matches!(self, SatPattern::Ata12(_) | SatPattern::Ata16(_))
+    }
+}
+
+impl SmartData {
+    fn from_attributes(attributes: &[SmartAttribute], thresholds_available: bool) -> Self {
+        let temperature = attributes
+            .iter()
+            .find(|a| a.id == 194 || a.id == 190)
+            .map(|a| {
+                let raw = a.raw;
+                if raw <= 200 {
+                    raw as i32
+                } else {
+                    (raw & 0xFF) as i32
+                }
+            });
+
+        let power_on_hours = attributes.iter().find(|a| a.id == 9).map(|a| a.raw);
+
+        let power_cycle_count = attributes.iter().find(|a| a.id == 12).map(|a| a.raw);
+
+        SmartData {
+            thresholds_available,
+            attributes: attributes.to_vec(),
+            temperature,
+            power_on_hours,
+            power_cycle_count,
+        }
+    }
+}
+
+pub fn parse_ata_smart_tables(
+    attr_data: &[u8],
+    threshold_data: Option<&[u8]>,
+) -> Result<SmartData, String> {
+    if attr_data.len() < SMART_TABLE_END {
+        return Err("SMART attribute table payload too small".to_string());
+    }
+
+    let threshold_data = threshold_data.filter(|buf| buf.len() >= SMART_TABLE_END);
+    let thresholds_available = threshold_data.is_some();
+    let mut attributes = Vec::new();
+    for i in (2..SMART_TABLE_END).step_by(12) {
+        let id = attr_data[i];
+        if id == 0 {
+            continue;
+        }
+
+        let current = attr_data[i + 3];
+        let worst = attr_data[i + 4];
+        let raw = u64::from_le_bytes([
+            attr_data[i + 5],
+            attr_data[i + 6],
+            attr_data[i + 7],
+            attr_data[i + 8],
+            attr_data[i + 9],
+            attr_data[i + 10],
+            0,
+            0,
+        ]);
+
+        let mut threshold = 0u8;
+        if let Some(tables) = threshold_data {
+            for j in (2..SMART_TABLE_END).step_by(12) {
+                if tables[j] == id {
+                    threshold = tables[j + 1];
+                    break;
+                }
+            }
+        }
+
+        attributes.push(SmartAttribute {
+            id,
+            current,
+            worst,
+            threshold,
+            raw,
+        });
+    }
+
+    if attributes.is_empty() {
+        return Err("SMART attributes are empty".to_string());
+    }
+
+    Ok(SmartData::from_attributes(
+        &attributes,
+        thresholds_available,
+    ))
+}
+
+pub fn sat_pattern_cdb_length(pattern: SatPattern) -> u8 {
+    match pattern {
+        SatPattern::Ata16(_) | SatPattern::Prolific | SatPattern::Cypress => 16,
+        SatPattern::Logitec => 10,
+        _ => 12,
+    }
+}
+
+pub fn build_sat_cdb(cdb: &mut [u8; 16], sub_command: u8, pattern: SatPattern) {
+    let target = 0xA0_u8;
+
+    match pattern {
+        SatPattern::Ata12(flags) => {
+            cdb[0] = 0xA1;
+            cdb[1] = 0x08;
+            cdb[2] = flags;
+            cdb[3] = sub_command;
+            cdb[4] = 0x01;
+            cdb[5] = 0x01;
+            cdb[6] = 0x4F;
+            cdb[7] = 0xC2;
+            cdb[8] = target;
+            cdb[9] = SMART_CMD;
+        }
+        SatPattern::Ata16(flags) => {
+            cdb[0] = 0x85;
+            cdb[1] = 0x08;
+            cdb[2] = flags;
+            cdb[4] = sub_command;
+            cdb[6] = 0x01;
+            cdb[10] = 0x4F;
+            cdb[12] = 0xC2;
+            cdb[13] = target;
+            cdb[14] = SMART_CMD;
+        }
+        SatPattern::Sunplus => {
+            cdb[0] = 0xF8;
+            cdb[2] = 0x22;
+            cdb[3] = 0x10;
+            cdb[4] = 0x01;
+            cdb[5] = sub_command;
+            cdb[6] = 0x01;
+            cdb[8] = 0x4F;
+            cdb[9] = 0xC2;
+            cdb[10] = target;
+            cdb[11] = SMART_CMD;
+        }
+        SatPattern::IoData => {
+            cdb[0] = 0xE3;
+            cdb[2] = sub_command;
+            cdb[5] = 0x4F;
+            cdb[6] = 0xC2;
+            cdb[7] = target;
+            cdb[8] = SMART_CMD;
+        }
+        SatPattern::Logitec => {
+            cdb[0] = 0xE0;
+            cdb[2] = sub_command;
+            cdb[5] = 0x4F;
+            cdb[6] = 0xC2;
+            cdb[7] = target;
+            cdb[8] = SMART_CMD;
+            cdb[9] = 0x4C;
+        }
+        SatPattern::Prolific => {
+            cdb[0] = 0xD8;
+            cdb[1] = 0x15;
+            cdb[3] = sub_command;
+            cdb[4] = 0x06;
+            cdb[5] = 0x7B;
+            cdb[8] = 0x02;
+            cdb[10] = 0x01;
+            cdb[12] = 0x4F;
+            cdb[13] = 0xC2;
+            cdb[14] = target;
+            cdb[15] = SMART_CMD;
+        }
+        SatPattern::JMicron => {
+            cdb[0] = 0xDF;
+            cdb[1] = 0x10;
+            cdb[3] = 0x02;
+            cdb[5] = sub_command;
+            cdb[6] = 0x01;
+            cdb[7] = 0x01;
+            cdb[8] = 0x4F;
+            cdb[9] = 0xC2;
+            cdb[10] = target;
+            cdb[11] = SMART_CMD;
+        }
+        SatPattern::Cypress => {
+            cdb[0] = 0x24;
+            cdb[1] = 0x24;
+            cdb[3] = 0xBE;
+            cdb[4] = 0x01;
+            cdb[6] = sub_command;
+            cdb[9] = 0x4F;
+            cdb[10] = 0xC2;
+            cdb[11] = target;
+            cdb[12] = SMART_CMD;
+        }
+    }
+}
+
+pub fn smart_attribute_name(id: u8) -> String {
+    match id {
+        1 => "Read Error Rate".to_string(),
+        2 => "Throughput Performance".to_string(),
+        3 => "Spin-Up Time".to_string(),
+        4 => "Start/Stop Count".to_string(),
+        5 => "Reallocated Sectors Count".to_string(),
+        7 => "Seek Error Rate".to_string(),
+        8 => "Seek Time Performance".to_string(),
+        9 => "Power-On Hours".to_string(),
+        10 => "Spin Retry Count".to_string(),
+        11 => "Calibration Retry Count".to_string(),
+        12 => "Power Cycle Count".to_string(),
+        170 => "Available Reserved Space".to_string(),
+        171 => "Program Fail Count".to_string(),
+        172 => "Erase Fail Count".to_string(),
+        173 => "Wear Leveling Count".to_string(),
+        174 => "Unexpected Power Loss Count".to_string(),
+        177 => "Wear Range Delta".to_string(),
+        179 => "Used Reserved Block Count Total".to_string(),
+        180 => "Unused Reserved Block Count Total".to_string(),
+        181 => "Program Fail Count Total".to_string(),
+        182 => "Erase Fail Count".to_string(),
+        183 => "Runtime Bad Block".to_string(),
+        184 => "End-to-End Error".to_string(),
+        187 => "Reported Uncorrectable Errors".to_string(),
+        188 => "Command Timeout".to_string(),
+        190 => "Airflow Temperature".to_string(),
+        191 => "G-Sense Error Rate".to_string(),
+        192 => "Power-Off Retract Count".to_string(),
+        193 => "Load/Unload Cycle Count".to_string(),
+        194 => "Temperature".to_string(),
+        195 => "Hardware ECC Recovered".to_string(),
+        196 => "Reallocation Event Count".to_string(),
+        197 => "Current Pending Sector Count".to_string(),
+        198 => "Offline Uncorrectable Sector Count".to_string(),
+        199 => "UltraDMA CRC Error Count".to_string(),
+        200 => "Write Error Rate".to_string(),
+        201 => "Soft Read Error Rate".to_string(),
+        202 => "Data Address Mark Errors".to_string(),
+        206 => "Flying Height".to_string(),
+        210 => "Vibration During Write".to_string(),
+        211 => "Vibration During Write Time".to_string(),
+        212 => "Shock During Write".to_string(),
+        220 => "Disk Shift".to_string(),
+        222 => "Loaded Hours".to_string(),
+        223 => "Load/Unload Retry Count".to_string(),
+        224 => "Load Friction".to_string(),
+        225 => "Load/Unload Cycle Count".to_string(),
+        226 => "Load-in Time".to_string(),
+        227 => "Torque Amplification Count".to_string(),
+        228 => "Power-Off Retract Cycle".to_string(),
+        230 => "Drive Life Protection Status".to_string(),
+        231 => "SSD Life Left".to_string(),
+        232 => "Available Reserved Space".to_string(),
+        233 => "Media Wearout Indicator".to_string(),
+        234 => "Average Erase Count".to_string(),
+        235 => "Good Block Count".to_string(),
+        241 => "Total LBAs Written".to_string(),
+        242 => "Total LBAs Read".to_string(),
+        243 => "Total LBAs Written Expanded".to_string(),
+        244 => "Total LBAs Read Expanded".to_string(),
+        245 => "NAND Writes (1GiB)".to_string(),
+        246 => "Total NAND Writes".to_string(),
+        247 => "Host Program NAND Pages Count".to_string(),
+        248 => "FTL Program NAND Pages Count".to_string(),
+        249 => "NAND Writes (1GiB)".to_string(),
+        250 => "Read Error Retry Rate".to_string(),
+        251 => "Minimum Spares Remaining".to_string(),
+        252 => "Newly Added Bad Flash Block".to_string(),
+        254 => "Free Fall Protection".to_string(),
+        _ => format!("Attribute {}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTES: &[u8] = include_bytes!("../../../tests/fixtures/ata/smart_data_mx500.bin");
+    const THRESHOLDS: &[u8] =
+        include_bytes!("../../../tests/fixtures/ata/smart_thresholds_mx500.bin");
+
+    fn attribute(data: &SmartData, id: u8) -> &SmartAttribute {
+        data.attributes.iter().find(|a| a.id == id).unwrap()
+    }
+
+    #[test]
+    fn parses_attribute_and_threshold_tables() {
+        let data = parse_ata_smart_tables(ATTRIBUTES, Some(THRESHOLDS)).unwrap();
+        assert!(data.thresholds_available);
+        assert_eq!(data.attributes.len(), 6);
+        assert_eq!(data.temperature, Some(34));
+        assert_eq!(data.power_on_hours, Some(12_345));
+        assert_eq!(data.power_cycle_count, Some(1_024));
+
+        let realloc = attribute(&data, 5);
+        assert_eq!(realloc.current, 100);
+        assert_eq!(realloc.worst, 100);
+        assert_eq!(realloc.threshold, 10);
+        assert_eq!(realloc.raw, 0);
+
+        let life_left = attribute(&data, 202);
+        assert_eq!(life_left.current, 96);
+        assert_eq!(life_left.raw, 4);
+
+        let written = attribute(&data, 246);
+        assert_eq!(written.raw, 0x0000_0012_3456_7890);
+        assert_eq!(written.threshold, 0);
+    }
+
+    #[test]
+    fn thresholds_are_optional() {
+        let data = parse_ata_smart_tables(ATTRIBUTES, None).unwrap();
+        assert!(!data.thresholds_available);
+        assert!(data.attributes.iter().all(|a| a.threshold == 0));
+
+        let truncated = &THRESHOLDS[..100];
+        let data = parse_ata_smart_tables(ATTRIBUTES, Some(truncated)).unwrap();
+        assert!(!data.thresholds_available);
+    }
+
+    #[test]
+    fn rejects_short_or_empty_tables() {
+        assert!(parse_ata_smart_tables(&ATTRIBUTES[..200], None).is_err());
+        assert!(parse_ata_smart_tables(&[0u8; 512], None).is_err());
+    }
+
+    #[test]
+    fn packed_temperature_uses_low_byte() {
+        let mut attrs = ATTRIBUTES.to_vec();
+        // Attribute 194 lives in the fifth entry: raw = 34 | (min 21 << 16) | (max 52 << 32)
+        let entry = 2 + 4 * 12;
+        assert_eq!(attrs[entry], 194);
+        attrs[entry + 7] = 21;
+        attrs[entry + 9] = 52;
+        let data = parse_ata_smart_tables(&attrs, None).unwrap();
+        assert_eq!(data.temperature, Some(34));
+    }
+
+    #[test]
+    fn builds_ata_pass_through_12_cdb() {
+        let mut cdb = [0u8; 16];
+        build_sat_cdb(&mut cdb, READ_ATTRIBUTES, SatPattern::Ata12(0x2E));
+        assert_eq!(
+            &cdb[..12],
+            &[0xA1, 0x08, 0x2E, 0xD0, 0x01, 0x01, 0x4F, 0xC2, 0xA0, 0xB0, 0x00, 0x00]
+        );
+        assert_eq!(sat_pattern_cdb_length(SatPattern::Ata12(0x2E)), 12);
+    }
+
+    #[test]
+    fn builds_ata_pass_through_16_cdb() {
+        let mut cdb = [0u8; 16];
+        build_sat_cdb(&mut cdb, READ_THRESHOLDS, SatPattern::Ata16(0x0E));
+        assert_eq!(
+            cdb,
+            [
+                0x85, 0x08, 0x0E, 0x00, 0xD1, 0x00, 0x01, 0x00, 0x00, 0x00, 0x4F, 0x00, 0xC2, 0xA0,
+                0xB0, 0x00
+            ]
+        );
+        assert_eq!(sat_pattern_cdb_length(SatPattern::Ata16(0x0E)), 16);
+    }
+
+    #[test]
+    fn standard_patterns_are_probed_first() {
+        let first_vendor = SAT_PATTERN_ATTEMPTS
+            .iter()
+            .position(|p| !p.is_standard())
+            .unwrap();
+        assert_eq!(first_vendor, 4);
+        assert!(SAT_PATTERN_ATTEMPTS[first_vendor..]
+            .iter()
+            .all(|p| !p.is_standard()));
+    }
+}
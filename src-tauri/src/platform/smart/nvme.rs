@@ -0,0 +1,321 @@
+// NVMe SMART / Health log and Identify Controller parsing
+// Migrated from CrystalDiskInfo (MIT License)
+// Original Author: hiyohiyo (https://crystalmark.info/)
+
+use crate::commands::disk::DiskDiagnostics;
+use crate::platform::smartctl::{insert_if_absent, merge_smart_source};
+use serde_json::{Map, Value};
+
+/// Size of the SMART / Health Information log page (Log Identifier 02h).
+pub const NVME_SMART_LOG_SIZE: usize = 512;
+/// Size of the Identify Controller data structure (CNS 01h).
+pub const NVME_IDENTIFY_SIZE: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct NVMeSmartData {
+    pub critical_warning: u8,
+    pub temperature: i32,
+    pub available_spare: u8,
+    pub available_spare_threshold: u8,
+    pub percentage_used: u8,
+    pub data_units_read: u128,
+    pub data_units_written: u128,
+    pub host_read_commands: u128,
+    pub host_write_commands: u128,
+    pub controller_busy_time: u128,
+    pub power_cycles: u128,
+    pub power_on_hours: u128,
+    pub unsafe_shutdowns: u128,
+    pub media_errors: u128,
+    pub num_err_log_entries: u128,
+    pub warning_temp_time: u32,
+    pub critical_temp_time: u32,
+    pub temp_sensors: [i32; 8],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NVMeIdentifyInfo {
+    pub model: String,
+    pub serial_number: String,
+    pub firmware_version: String,
+}
+
+pub fn parse_smart_data(buffer: &[u8]) -> Result<NVMeSmartData, String> {
+    if buffer.len() < NVME_SMART_LOG_SIZE {
+        return Err("NVMe SMART buffer too small".to_string());
+    }
+
+    let temp_kelvin = u16::from_le_bytes([buffer[1], buffer[2]]) as i32;
+    let temperature = if (1..0x7FFF).contains(&temp_kelvin) {
+        temp_kelvin - 273
+    } else {
+        -1000
+    };
+
+    let mut temp_sensors = [-1000_i32; 8];
+    for (i, sensor) in temp_sensors.iter_mut().enumerate() {
+        let offset = 200 + i * 2;
+        let sensor_k = u16::from_le_bytes([buffer[offset], buffer[offset + 1]]) as i32;
+        if (1..0x7FFF).contains(&sensor_k) {
+            *sensor = sensor_k - 273;
+        }
+    }
+
+    Ok(NVMeSmartData {
+        critical_warning: buffer[0],
+        temperature,
+        available_spare: buffer[3],
+        available_spare_threshold: buffer[4],
+        percentage_used: buffer[5],
+        data_units_read: parse_u128_le_at(buffer, 32),
+        data_units_written: parse_u128_le_at(buffer, 48),
+        host_read_commands: parse_u128_le_at(buffer, 64),
+        host_write_commands: parse_u128_le_at(buffer, 80),
+        controller_busy_time: parse_u128_le_at(buffer, 96),
+        power_cycles: parse_u128_le_at(buffer, 112),
+        power_on_hours: parse_u128_le_at(buffer, 128),
+        unsafe_shutdowns: parse_u128_le_at(buffer, 144),
+        media_errors: parse_u128_le_at(buffer, 160),
+        num_err_log_entries: parse_u128_le_at(buffer, 176),
+        warning_temp_time: u32::from_le_bytes([buffer[192], buffer[193], buffer[194], buffer[195]]),
+        critical_temp_time: u32::from_le_bytes([
+            buffer[196],
+            buffer[197],
+            buffer[198],
+            buffer[199],
+        ]),
+        temp_sensors,
+    })
+}
+
+pub fn parse_identify_controller(data: &[u8]) -> NVMeIdentifyInfo {
+    if data.len() < 72 {
+        return NVMeIdentifyInfo::default();
+    }
+    NVMeIdentifyInfo {
+        serial_number: parse_ascii_field(&data[4..24]),
+        model: parse_ascii_field(&data[24..64]),
+        firmware_version: parse_ascii_field(&data[64..72]),
+    }
+}
+
+fn parse_u128_le_at(buf: &[u8], offset: usize) -> u128 {
+    if offset + 16 > buf.len() {
+        return 0;
+    }
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&buf[offset..offset + 16]);
+    u128::from_le_bytes(bytes)
+}
+
+fn parse_ascii_field(data: &[u8]) -> String {
+    let s = String::from_utf8_lossy(data).into_owned();
+    s.trim_matches(char::from(0)).trim().to_string()
+}
+
+/// Fill the diagnostics fields an earlier source left empty from a native NVMe
+/// SMART / Health log, and record the raw log under `NvmeIoctl.*` reliability keys.
+pub fn apply_native_nvme_data(diag: &mut DiskDiagnostics, nvme_data: &NVMeSmartData) {
+    diag.transport_type = "NVMe".to_string();
+    diag.interface_type = "NVMExpress".to_string();
+    diag.media_type = "SSD".to_string();
+
+    if diag.temperature_c.is_none() && nvme_data.temperature > -1000 {
+        diag.temperature_c = Some(nvme_data.temperature as f64);
+    }
+    if diag.power_on_hours.is_none() {
+        diag.power_on_hours = Some(u128_to_u64(nvme_data.power_on_hours));
+    }
+    if diag.power_cycle_count.is_none() {
+        diag.power_cycle_count = Some(u128_to_u64(nvme_data.power_cycles));
+    }
+    if diag.percentage_used.is_none() {
+        diag.percentage_used = Some((nvme_data.percentage_used as f64).clamp(0.0, 100.0));
+    }
+    if diag.host_reads_total.is_none() {
+        let units = u128_to_u64(nvme_data.data_units_read);
+        let cmds = u128_to_u64(nvme_data.host_read_commands);
+        diag.host_reads_total = Some(if units > 0 { units } else { cmds });
+    }
+    if diag.host_writes_total.is_none() {
+        let units = u128_to_u64(nvme_data.data_units_written);
+        let cmds = u128_to_u64(nvme_data.host_write_commands);
+        diag.host_writes_total = Some(if units > 0 { units } else { cmds });
+    }
+    if diag.read_errors_total.is_none() {
+        diag.read_errors_total = Some(u128_to_u64(nvme_data.media_errors));
+    }
+    if diag.write_errors_total.is_none() {
+        diag.write_errors_total = Some(u128_to_u64(nvme_data.num_err_log_entries));
+    }
+
+    diag.smart_supported = true;
+    diag.smart_enabled = true;
+    diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "NVME_NATIVE_IOCTL");
+
+    let mut rel = match std::mem::take(&mut diag.reliability) {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.CriticalWarning",
+        Some(Value::from(nvme_data.critical_warning)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.AvailableSpare",
+        Some(Value::from(nvme_data.available_spare)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.AvailableSpareThreshold",
+        Some(Value::from(nvme_data.available_spare_threshold)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.PercentageUsed",
+        Some(Value::from(nvme_data.percentage_used)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.DataUnitsRead",
+        Some(u128_to_json_value(nvme_data.data_units_read)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.DataUnitsWritten",
+        Some(u128_to_json_value(nvme_data.data_units_written)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.HostReadCommands",
+        Some(u128_to_json_value(nvme_data.host_read_commands)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.HostWriteCommands",
+        Some(u128_to_json_value(nvme_data.host_write_commands)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.ControllerBusyTime",
+        Some(u128_to_json_value(nvme_data.controller_busy_time)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.PowerCycles",
+        Some(u128_to_json_value(nvme_data.power_cycles)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.PowerOnHours",
+        Some(u128_to_json_value(nvme_data.power_on_hours)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.UnsafeShutdowns",
+        Some(u128_to_json_value(nvme_data.unsafe_shutdowns)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.MediaErrors",
+        Some(u128_to_json_value(nvme_data.media_errors)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.ErrorLogEntries",
+        Some(u128_to_json_value(nvme_data.num_err_log_entries)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.WarningTempTime",
+        Some(Value::from(nvme_data.warning_temp_time)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.CriticalTempTime",
+        Some(Value::from(nvme_data.critical_temp_time)),
+    );
+    insert_if_absent(
+        &mut rel,
+        "NvmeIoctl.TemperatureSensors",
+        Some(Value::Array(
+            nvme_data
+                .temp_sensors
+                .iter()
+                .map(|v| Value::from(*v))
+                .collect(),
+        )),
+    );
+
+    diag.reliability = Value::Object(rel);
+}
+
+fn u128_to_u64(value: u128) -> u64 {
+    value.min(u64::MAX as u128) as u64
+}
+
+fn u128_to_json_value(value: u128) -> Value {
+    if value <= u64::MAX as u128 {
+        Value::from(value as u64)
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMART_LOG: &[u8] = include_bytes!("../../../tests/fixtures/nvme/smart_log_sn770.bin");
+    const IDENTIFY: &[u8] = include_bytes!("../../../tests/fixtures/nvme/identify_ctrl_sn770.bin");
+
+    #[test]
+    fn parses_smart_log_page() {
+        let data = parse_smart_data(SMART_LOG).unwrap();
+        assert_eq!(data.critical_warning, 0);
+        assert_eq!(data.temperature, 41);
+        assert_eq!(data.available_spare, 100);
+        assert_eq!(data.available_spare_threshold, 10);
+        assert_eq!(data.percentage_used, 3);
+        assert_eq!(data.data_units_read, 28_716_253);
+        assert_eq!(data.data_units_written, 35_612_078);
+        assert_eq!(data.host_write_commands, 421_347_121);
+        assert_eq!(data.power_cycles, 842);
+        assert_eq!(data.power_on_hours, 6023);
+        assert_eq!(data.unsafe_shutdowns, 57);
+        assert_eq!(data.num_err_log_entries, 2);
+        assert_eq!(data.temp_sensors[0], 41);
+        assert_eq!(data.temp_sensors[1], 47);
+        assert_eq!(data.temp_sensors[2], -1000);
+    }
+
+    #[test]
+    fn invalid_composite_temperature_is_flagged() {
+        let mut log = SMART_LOG.to_vec();
+        log[1] = 0;
+        log[2] = 0;
+        assert_eq!(parse_smart_data(&log).unwrap().temperature, -1000);
+    }
+
+    #[test]
+    fn rejects_short_smart_buffer() {
+        assert!(parse_smart_data(&SMART_LOG[..256]).is_err());
+    }
+
+    #[test]
+    fn parses_identify_controller() {
+        let info = parse_identify_controller(IDENTIFY);
+        assert_eq!(info.model, "WD_BLACK SN770 1TB");
+        assert_eq!(info.serial_number, "22345B801234");
+        assert_eq!(info.firmware_version, "731030WD");
+    }
+
+    #[test]
+    fn short_identify_buffer_yields_default() {
+        let info = parse_identify_controller(&IDENTIFY[..40]);
+        assert!(info.model.is_empty());
+    }
+}
@@ -0,0 +1,1488 @@
+//! smartctl (smartmontools) based SMART enrichment, shared by the Windows, macOS and
+//! Linux disk diagnostics. Device naming, probe order and where smartctl lives differ
+//! per OS.
+
+use crate::commands::disk::{DiskDiagnostics, SmartAttribute};
+#[cfg(target_os = "windows")]
+use crate::utils::command::CommandExecutor;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+const SMARTCTL_MISSING_NOTE: &str =
+    "smartctl not found in bundled resources or PATH; include smartmontools to enable extended SMART details.";
+#[cfg(not(target_os = "windows"))]
+const SMARTCTL_MISSING_NOTE: &str =
+    "smartctl not found in PATH; install smartmontools to enable extended SMART details.";
+
+pub(crate) fn enrich_with_smartctl(diagnostics: &mut [DiskDiagnostics]) {
+    if diagnostics.is_empty() {
+        return;
+    }
+
+    if !smartctl_installed() {
+        for diag in diagnostics.iter_mut() {
+            add_note_unique(diag, SMARTCTL_MISSING_NOTE);
+        }
+        return;
+    }
+
+    let scan_entries = smartctl_scan_entries();
+    for diag in diagnostics.iter_mut() {
+        if let Some(payload) = get_smartctl_payload_for_disk(diag, scan_entries.as_deref()) {
+            apply_smartctl_payload(diag, &payload);
+            add_note_unique(diag, "Extended SMART details were enhanced via smartctl.");
+        }
+    }
+}
+
+fn smartctl_installed() -> bool {
+    run_smartctl_allow_fail(&["--version"]).is_some()
+}
+
+#[derive(Debug, Clone)]
+struct SmartctlScanEntry {
+    name: String,
+    device_type: Option<String>,
+    info_name: String,
+}
+
+fn smartctl_scan_entries() -> Option<Vec<SmartctlScanEntry>> {
+    let scan_cmds: [&[&str]; 4] = [
+        &["--scan-open", "-j"],
+        &["--scan", "-j"],
+        &["--scan-open"],
+        &["--scan"],
+    ];
+
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for args in scan_cmds {
+        let Some(output) = run_smartctl_allow_fail(args) else {
+            continue;
+        };
+
+        let parsed = if args.contains(&"-j") {
+            extract_json_value(&output)
+                .map(|payload| parse_smartctl_scan_entries_json(&payload))
+                .unwrap_or_default()
+        } else {
+            parse_smartctl_scan_entries_text(&output)
+        };
+
+        for entry in parsed {
+            let key = format!(
+                "{}\u{1F}{}",
+                entry.name.to_ascii_lowercase(),
+                entry
+                    .device_type
+                    .clone()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase()
+            );
+            if seen.insert(key) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    if entries.is_empty() {
+        None
+    } else {
+        Some(entries)
+    }
+}
+
+fn parse_smartctl_scan_entries_json(payload: &Value) -> Vec<SmartctlScanEntry> {
+    let mut entries = Vec::new();
+    let Some(devices) = payload.get("devices").and_then(Value::as_array) else {
+        return entries;
+    };
+
+    for dev in devices {
+        let name = dev
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if name.is_empty() {
+            continue;
+        }
+        let info_name = dev
+            .get("info_name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        let device_type = dev
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(ToOwned::to_owned);
+
+        entries.push(SmartctlScanEntry {
+            name,
+            device_type,
+            info_name,
+        });
+    }
+
+    entries
+}
+
+fn parse_smartctl_scan_entries_text(output: &str) -> Vec<SmartctlScanEntry> {
+    let mut entries = Vec::new();
+
+    for raw_line in output.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (main_part, info_part) = line
+            .split_once('#')
+            .map(|(a, b)| (a.trim(), b.trim()))
+            .unwrap_or((line, ""));
+
+        let mut parts = main_part.split_whitespace();
+        let Some(name) = parts.next().map(str::trim) else {
+            continue;
+        };
+        if !name.starts_with('/') {
+            continue;
+        }
+
+        let mut device_type: Option<String> = None;
+        while let Some(token) = parts.next() {
+            if token == "-d" {
+                device_type = parts
+                    .next()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(ToOwned::to_owned);
+                break;
+            }
+        }
+
+        entries.push(SmartctlScanEntry {
+            name: name.to_string(),
+            device_type,
+            info_name: info_part.to_string(),
+        });
+    }
+
+    entries
+}
+
+#[cfg(not(target_os = "windows"))]
+fn smartctl_entry_matches_disk(entry: &SmartctlScanEntry, diag: &DiskDiagnostics) -> bool {
+    let nodes = smartctl_device_nodes(diag);
+    let name = entry.name.to_ascii_lowercase();
+    let info = entry.info_name.to_ascii_lowercase();
+
+    nodes.iter().any(|node| {
+        let node = node.to_ascii_lowercase();
+        name == node || info.split_whitespace().any(|token| token == node)
+    })
+}
+
+/// smartctl on Windows scans as /dev/sdX but also accepts /dev/pdN, so match on the number.
+#[cfg(target_os = "windows")]
+fn smartctl_entry_matches_disk(entry: &SmartctlScanEntry, diag: &DiskDiagnostics) -> bool {
+    let disk_number = diag.disk_number;
+    let key_pd = format!("pd{}", disk_number).to_ascii_lowercase();
+    let key_phy = format!("physicaldrive{}", disk_number).to_ascii_lowercase();
+    let key_disk = format!("disk{}", disk_number).to_ascii_lowercase();
+    let name = entry.name.to_ascii_lowercase();
+    let info = entry.info_name.to_ascii_lowercase();
+
+    [name, info]
+        .iter()
+        .any(|v| v.contains(&key_pd) || v.contains(&key_phy) || v.contains(&key_disk))
+}
+
+fn push_smartctl_attempt(
+    attempts: &mut Vec<Vec<String>>,
+    seen: &mut HashSet<String>,
+    device: &str,
+    device_type: Option<&str>,
+) {
+    let mut args = vec!["-x".to_string(), "-j".to_string()];
+    if let Some(dt) = device_type.map(str::trim).filter(|s| !s.is_empty()) {
+        args.push("-d".to_string());
+        args.push(dt.to_string());
+    }
+    args.push(device.to_string());
+
+    let key = args.join("\u{1F}");
+    if seen.insert(key) {
+        attempts.push(args);
+    }
+}
+
+fn normalized_eq(a: &str, b: &str) -> bool {
+    let na: String = a
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let nb: String = b
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    !na.is_empty() && !nb.is_empty() && na == nb
+}
+
+fn payload_matches_diag(payload: &Value, diag: &DiskDiagnostics) -> bool {
+    let nodes = smartctl_device_nodes(diag);
+    if get_string_path(payload, &["device", "name"])
+        .map(|n| nodes.iter().any(|node| n.eq_ignore_ascii_case(node)))
+        .unwrap_or(false)
+    {
+        return true;
+    }
+
+    let payload_serial = get_string_path(payload, &["serial_number"]).unwrap_or_default();
+    let diag_serial = diag.serial_number.trim();
+    if !diag_serial.is_empty()
+        && !is_masked_serial(diag_serial)
+        && !payload_serial.is_empty()
+        && normalized_eq(diag_serial, &payload_serial)
+    {
+        return true;
+    }
+
+    let payload_model = get_string_path(payload, &["model_name"]).unwrap_or_default();
+    let diag_model = if diag.model.trim().is_empty() {
+        diag.friendly_name.trim()
+    } else {
+        diag.model.trim()
+    };
+    let model_like = !payload_model.is_empty()
+        && !diag_model.is_empty()
+        && (payload_model
+            .to_ascii_uppercase()
+            .contains(&diag_model.to_ascii_uppercase())
+            || diag_model
+                .to_ascii_uppercase()
+                .contains(&payload_model.to_ascii_uppercase()));
+
+    if model_like {
+        if let Some(cap) = get_u64_path(payload, &["user_capacity", "bytes"]) {
+            let size = diag.size_bytes;
+            if size > 0 {
+                let diff = cap.abs_diff(size);
+                let tolerance = (size / 20).max(64 * 1024 * 1024);
+                if diff <= tolerance {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Device nodes smartctl can be pointed at for a disk: the block node, plus the
+/// controller character device for NVMe namespaces (nvme0n1 -> nvme0).
+#[cfg(target_os = "linux")]
+fn smartctl_device_nodes(diag: &DiskDiagnostics) -> Vec<String> {
+    let mut nodes = vec![format!("/dev/{}", diag.id)];
+    if let Some(controller) = nvme_controller_name(&diag.id) {
+        nodes.push(format!("/dev/{}", controller));
+    }
+    nodes
+}
+
+#[cfg(target_os = "macos")]
+fn smartctl_device_nodes(diag: &DiskDiagnostics) -> Vec<String> {
+    vec![
+        format!("/dev/disk{}", diag.disk_number),
+        format!("/dev/rdisk{}", diag.disk_number),
+    ]
+}
+
+#[cfg(target_os = "windows")]
+fn smartctl_device_nodes(diag: &DiskDiagnostics) -> Vec<String> {
+    vec![format!("/dev/pd{}", diag.disk_number)]
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn nvme_controller_name(block_name: &str) -> Option<String> {
+    let rest = block_name.strip_prefix("nvme")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() || !rest[digits.len()..].starts_with('n') {
+        return None;
+    }
+    Some(format!("nvme{}", digits))
+}
+
+/// smartctl invocations for one disk, in probe order and without repeats.
+struct SmartctlAttempts {
+    nodes: Vec<String>,
+    attempts: Vec<Vec<String>>,
+    seen: HashSet<String>,
+}
+
+impl SmartctlAttempts {
+    /// Start with a plain probe of every device node.
+    fn new(nodes: Vec<String>) -> Self {
+        let mut this = Self {
+            nodes,
+            attempts: Vec::new(),
+            seen: HashSet::new(),
+        };
+        this.push_plain();
+        this
+    }
+
+    fn push_plain(&mut self) {
+        for dev in &self.nodes {
+            push_smartctl_attempt(&mut self.attempts, &mut self.seen, dev, None);
+        }
+    }
+
+    fn push_types(&mut self, types: &[&str]) {
+        for dev in &self.nodes {
+            for dt in types {
+                push_smartctl_attempt(&mut self.attempts, &mut self.seen, dev, Some(dt));
+            }
+        }
+    }
+
+    fn push_scan_entries(
+        &mut self,
+        diag: &DiskDiagnostics,
+        scan_entries: Option<&[SmartctlScanEntry]>,
+    ) {
+        for entry in scan_entries
+            .unwrap_or_default()
+            .iter()
+            .filter(|e| smartctl_entry_matches_disk(e, diag))
+        {
+            push_smartctl_attempt(
+                &mut self.attempts,
+                &mut self.seen,
+                &entry.name,
+                entry.device_type.as_deref(),
+            );
+        }
+    }
+}
+
+/// CrystalDiskInfo has dedicated NVMe-over-USB paths for JMicron/ASMedia/Realtek.
+const USB_NVME_TYPES: [&str; 7] = [
+    "sntjmicron",
+    "sntjmicron,0",
+    "sntjmicron,1",
+    "sntasmedia",
+    "sntrealtek",
+    "sntrealtek,0",
+    "sntrealtek,1",
+];
+
+/// Bridge-specific pass-through types, picked by the USB vendor id.
+fn usb_bridge_types(vendor_id: &str) -> &'static [&'static str] {
+    match vendor_id.trim().to_ascii_uppercase().as_str() {
+        // JMicron
+        "152D" => &["usbjmicron", "sntjmicron", "sntjmicron,0", "sntjmicron,1"],
+        // ASMedia
+        "174C" => &["sntasmedia"],
+        // Realtek
+        "0BDA" => &["sntrealtek", "sntrealtek,0", "sntrealtek,1"],
+        // Cypress / Prolific / Sunplus
+        "04B4" => &["usbcypress"],
+        "067B" => &["usbprolific"],
+        "04FC" => &["usbsunplus"],
+        _ => &[],
+    }
+}
+
+/// The first useful payload wins, so each OS keeps its own probe order.
+fn build_smartctl_attempts(
+    diag: &DiskDiagnostics,
+    scan_entries: Option<&[SmartctlScanEntry]>,
+) -> Vec<Vec<String>> {
+    let mut attempts = SmartctlAttempts::new(smartctl_device_nodes(diag));
+    #[cfg(target_os = "windows")]
+    push_windows_attempts(&mut attempts, diag, scan_entries);
+    #[cfg(target_os = "macos")]
+    push_macos_attempts(&mut attempts, diag, scan_entries);
+    #[cfg(target_os = "linux")]
+    push_linux_attempts(&mut attempts, diag, scan_entries);
+    attempts.attempts
+}
+
+/// Windows tries JMicron pass-through early and the vendor bridge types before the
+/// scanned entries.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn push_windows_attempts(
+    attempts: &mut SmartctlAttempts,
+    diag: &DiskDiagnostics,
+    scan_entries: Option<&[SmartctlScanEntry]>,
+) {
+    let is_nvme = diag.transport_type.eq_ignore_ascii_case("nvme")
+        || diag.interface_type.eq_ignore_ascii_case("nvmexpress")
+        || diag.bus_type.eq_ignore_ascii_case("nvme");
+
+    if diag.is_usb {
+        attempts.push_types(&["sat,auto", "sat", "sat,12", "sat,16", "scsi"]);
+    }
+    if is_nvme {
+        attempts.push_types(&["nvme"]);
+        if diag.is_usb {
+            attempts.push_types(&USB_NVME_TYPES);
+        }
+    } else {
+        if diag.is_usb {
+            attempts.push_types(&["usbjmicron"]);
+        }
+        attempts.push_types(&["sat", "scsi"]);
+    }
+    attempts.push_types(usb_bridge_types(&diag.usb_vendor_id));
+    attempts.push_scan_entries(diag, scan_entries);
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn push_macos_attempts(
+    attempts: &mut SmartctlAttempts,
+    diag: &DiskDiagnostics,
+    scan_entries: Option<&[SmartctlScanEntry]>,
+) {
+    if diag.is_usb {
+        attempts.push_types(&["sat,auto", "sat", "sat,12", "sat,16", "scsi"]);
+    }
+    if is_nvme_diag(diag) {
+        attempts.push_types(&["nvme"]);
+        if diag.is_usb {
+            attempts.push_types(&USB_NVME_TYPES);
+        }
+    } else {
+        attempts.push_types(&["sat", "scsi"]);
+    }
+    attempts.push_scan_entries(diag, scan_entries);
+}
+
+/// Linux also lets smartctl pick the device type, and falls back to the vendor bridge
+/// types (JMicron for unknown SATA bridges) after the scanned entries.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn push_linux_attempts(
+    attempts: &mut SmartctlAttempts,
+    diag: &DiskDiagnostics,
+    scan_entries: Option<&[SmartctlScanEntry]>,
+) {
+    let is_nvme = is_nvme_diag(diag);
+    if diag.is_usb {
+        attempts.push_types(&["sat,auto", "sat", "sat,12", "sat,16", "auto", "scsi"]);
+    }
+    if is_nvme {
+        attempts.push_types(&["nvme"]);
+        if diag.is_usb {
+            attempts.push_types(&USB_NVME_TYPES);
+        }
+    } else {
+        attempts.push_types(&["sat", "auto", "scsi"]);
+    }
+    attempts.push_scan_entries(diag, scan_entries);
+    if diag.is_usb {
+        match usb_bridge_types(&diag.usb_vendor_id) {
+            [] if !is_nvme => attempts.push_types(&["usbjmicron"]),
+            types => attempts.push_types(types),
+        }
+    }
+}
+
+fn get_smartctl_payload_for_disk(
+    diag: &DiskDiagnostics,
+    scan_entries: Option<&[SmartctlScanEntry]>,
+) -> Option<Value> {
+    for args in build_smartctl_attempts(diag, scan_entries) {
+        if let Some(payload) = run_smartctl_json(&args) {
+            if is_useful_smartctl_payload(&payload) {
+                return Some(payload);
+            }
+        }
+    }
+
+    if let Some(entries) = scan_entries {
+        for entry in entries {
+            let mut fallback = vec!["-x".to_string(), "-j".to_string()];
+            if let Some(dt) = entry
+                .device_type
+                .as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+            {
+                fallback.push("-d".to_string());
+                fallback.push(dt.to_string());
+            }
+            fallback.push(entry.name.clone());
+
+            if let Some(payload) = run_smartctl_json(&fallback) {
+                if is_useful_smartctl_payload(&payload) && payload_matches_diag(&payload, diag) {
+                    return Some(payload);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn run_smartctl_json(args: &[String]) -> Option<Value> {
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run_smartctl_allow_fail(&arg_refs)?;
+    extract_json_value(&output)
+}
+
+#[cfg(target_os = "windows")]
+fn run_smartctl_allow_fail(args: &[&str]) -> Option<String> {
+    for cmd in smartctl_candidates() {
+        if let Ok(output) = CommandExecutor::execute_allow_fail(&cmd, args) {
+            return Some(output);
+        }
+    }
+    None
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run_smartctl_allow_fail(args: &[&str]) -> Option<String> {
+    for cmd in smartctl_candidates() {
+        let output = match Command::new(&cmd).args(args).output() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let merged = if stdout.trim().is_empty() {
+            stderr
+        } else if stderr.trim().is_empty() {
+            stdout
+        } else {
+            format!("{stdout}\n{stderr}")
+        };
+
+        if !merged.trim().is_empty() {
+            return Some(merged);
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "windows")]
+fn smartctl_candidates() -> Vec<String> {
+    let mut candidates = Vec::new();
+
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            // Prefer bundled smartmontools shipped with the app.
+            push_candidate_path(
+                &mut candidates,
+                dir.join("smartmontools").join("bin").join("smartctl.exe"),
+            );
+            push_candidate_path(
+                &mut candidates,
+                dir.join("resources")
+                    .join("smartmontools")
+                    .join("bin")
+                    .join("smartctl.exe"),
+            );
+            push_candidate_path(
+                &mut candidates,
+                dir.join("..")
+                    .join("resources")
+                    .join("smartmontools")
+                    .join("bin")
+                    .join("smartctl.exe"),
+            );
+
+            // Backward compatibility for old bundled layout.
+            push_candidate_path(
+                &mut candidates,
+                dir.join("resources").join("smartctl").join("smartctl.exe"),
+            );
+            push_candidate_path(
+                &mut candidates,
+                dir.join("..")
+                    .join("resources")
+                    .join("smartctl")
+                    .join("smartctl.exe"),
+            );
+            push_candidate_path(&mut candidates, dir.join("smartctl.exe"));
+        }
+    }
+
+    if let Ok(cwd) = std::env::current_dir() {
+        // Repository/dev layout convenience.
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("src-tauri")
+                .join("resources")
+                .join("smartmontools")
+                .join("bin")
+                .join("smartctl.exe"),
+        );
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("smartmontools").join("bin").join("smartctl.exe"),
+        );
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("useable_software")
+                .join("smartmontools")
+                .join("bin")
+                .join("smartctl.exe"),
+        );
+    }
+
+    // PATH and global installs as fallback.
+    candidates.extend([
+        "smartctl".to_string(),
+        "smartctl.exe".to_string(),
+        r"C:\Program Files\smartmontools\bin\smartctl.exe".to_string(),
+        r"C:\Program Files (x86)\smartmontools\bin\smartctl.exe".to_string(),
+    ]);
+
+    // Keep only existing absolute/relative paths; retain command names.
+    let mut filtered = Vec::new();
+    for c in candidates {
+        if c.contains('\\') || c.contains('/') {
+            if std::path::Path::new(&c).exists() {
+                filtered.push(c);
+            }
+        } else {
+            filtered.push(c);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    filtered
+        .into_iter()
+        .filter(|s| seen.insert(s.to_ascii_lowercase()))
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn smartctl_candidates() -> Vec<String> {
+    let mut candidates = Vec::new();
+
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            push_candidate_path(
+                &mut candidates,
+                dir.join("smartmontools").join("bin").join("smartctl"),
+            );
+            push_candidate_path(&mut candidates, dir.join("resources").join("smartctl"));
+            push_candidate_path(
+                &mut candidates,
+                dir.join("..").join("resources").join("smartctl"),
+            );
+        }
+    }
+
+    if let Ok(cwd) = std::env::current_dir() {
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("src-tauri")
+                .join("resources")
+                .join("smartmontools")
+                .join("bin")
+                .join("smartctl"),
+        );
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("src-tauri")
+                .join("resources")
+                .join("smartctl")
+                .join("smartctl"),
+        );
+        push_candidate_path(
+            &mut candidates,
+            cwd.join("useable_software")
+                .join("smartmontools")
+                .join("bin")
+                .join("smartctl"),
+        );
+    }
+
+    candidates.extend([
+        "smartctl".to_string(),
+        "/opt/homebrew/sbin/smartctl".to_string(),
+        "/usr/local/sbin/smartctl".to_string(),
+        "/usr/sbin/smartctl".to_string(),
+        "/sbin/smartctl".to_string(),
+    ]);
+
+    let mut filtered = Vec::new();
+    for c in candidates {
+        if c.contains('/') {
+            if std::path::Path::new(&c).exists() {
+                filtered.push(c);
+            }
+        } else {
+            filtered.push(c);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    filtered
+        .into_iter()
+        .filter(|s| seen.insert(s.to_ascii_lowercase()))
+        .collect()
+}
+
+fn push_candidate_path(candidates: &mut Vec<String>, path: std::path::PathBuf) {
+    candidates.push(path.to_string_lossy().to_string());
+}
+
+fn extract_json_value(output: &str) -> Option<Value> {
+    let trimmed = output.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let start = trimmed.find('{').or_else(|| trimmed.find('['))?;
+    serde_json::from_str(&trimmed[start..]).ok()
+}
+
+fn is_useful_smartctl_payload(payload: &Value) -> bool {
+    payload.get("smartctl").is_some()
+        && (payload.get("model_name").is_some()
+            || payload.get("serial_number").is_some()
+            || payload.pointer("/ata_smart_attributes/table").is_some()
+            || payload.get("nvme_smart_health_information_log").is_some()
+            || payload.pointer("/temperature/current").is_some()
+            || payload.pointer("/power_on_time/hours").is_some())
+}
+
+fn apply_smartctl_payload(diag: &mut DiskDiagnostics, payload: &Value) {
+    if let Some(model) = get_string_path(payload, &["model_name"]).filter(|s| !s.is_empty()) {
+        if diag.model.trim().is_empty() {
+            diag.model = model;
+        }
+    }
+
+    if let Some(firmware) =
+        get_string_path(payload, &["firmware_version"]).filter(|s| !s.is_empty())
+    {
+        if diag.firmware_version.trim().is_empty() {
+            diag.firmware_version = firmware;
+        }
+    }
+
+    if let Some(serial) = get_string_path(payload, &["serial_number"]).filter(|s| !s.is_empty()) {
+        if diag.serial_number.trim().is_empty() || is_masked_serial(&diag.serial_number) {
+            diag.serial_number = serial;
+        }
+    }
+
+    if let Some(protocol) = get_string_path(payload, &["device", "protocol"]) {
+        if protocol.eq_ignore_ascii_case("nvme") {
+            diag.transport_type = "NVMe".to_string();
+            diag.interface_type = "NVMExpress".to_string();
+            if diag.media_type.eq_ignore_ascii_case("unknown") || diag.media_type.is_empty() {
+                diag.media_type = "SSD".to_string();
+            }
+        }
+    }
+
+    if let Some(rotation) = get_u64_path(payload, &["rotation_rate"]) {
+        if rotation == 0 {
+            diag.media_type = "SSD".to_string();
+        } else if rotation > 0 {
+            diag.media_type = "HDD".to_string();
+        }
+    }
+
+    if diag.temperature_c.is_none() {
+        diag.temperature_c = extract_smartctl_temperature(payload);
+    }
+    if diag.power_on_hours.is_none() {
+        diag.power_on_hours = get_u64_path(payload, &["power_on_time", "hours"]).or_else(|| {
+            get_u64_path(
+                payload,
+                &["nvme_smart_health_information_log", "power_on_hours"],
+            )
+        });
+    }
+    if diag.power_cycle_count.is_none() {
+        diag.power_cycle_count = get_u64_path(payload, &["power_cycle_count"]).or_else(|| {
+            get_u64_path(
+                payload,
+                &["nvme_smart_health_information_log", "power_cycles"],
+            )
+        });
+    }
+    if diag.percentage_used.is_none() {
+        diag.percentage_used = get_f64_path(
+            payload,
+            &["nvme_smart_health_information_log", "percentage_used"],
+        );
+    }
+    if diag.host_reads_total.is_none() {
+        diag.host_reads_total = get_u64_path(
+            payload,
+            &["nvme_smart_health_information_log", "host_reads"],
+        )
+        .or_else(|| {
+            get_u64_path(
+                payload,
+                &["nvme_smart_health_information_log", "data_units_read"],
+            )
+        });
+    }
+    if diag.host_writes_total.is_none() {
+        diag.host_writes_total = get_u64_path(
+            payload,
+            &["nvme_smart_health_information_log", "host_writes"],
+        )
+        .or_else(|| {
+            get_u64_path(
+                payload,
+                &["nvme_smart_health_information_log", "data_units_written"],
+            )
+        });
+    }
+    if diag.read_errors_total.is_none() {
+        diag.read_errors_total = get_u64_path(
+            payload,
+            &["nvme_smart_health_information_log", "media_errors"],
+        );
+    }
+    if diag.write_errors_total.is_none() {
+        diag.write_errors_total = get_u64_path(
+            payload,
+            &["nvme_smart_health_information_log", "num_err_log_entries"],
+        );
+    }
+
+    if let Some(enabled) = get_bool_path(payload, &["smart_support", "enabled"]) {
+        diag.smart_enabled = enabled;
+        diag.smart_supported = true;
+    }
+    if let Some(passed) = get_bool_path(payload, &["smart_status", "passed"]) {
+        diag.smart_supported = true;
+        // Windows and macOS keep the verdict of their own health APIs, even "Unknown";
+        // Linux has no such API, so smartctl's verdict is the only one.
+        if cfg!(target_os = "linux")
+            && (diag.health_status.is_empty() || diag.health_status.eq_ignore_ascii_case("unknown"))
+        {
+            diag.health_status = if passed { "Healthy" } else { "Warning" }.to_string();
+        }
+    }
+
+    let smartctl_attrs = parse_smartctl_ata_attributes(payload);
+    if !smartctl_attrs.is_empty() {
+        if diag.smart_attributes.len() < smartctl_attrs.len() {
+            diag.smart_attributes = smartctl_attrs.clone();
+        }
+        diag.ata_smart_available = true;
+        diag.smart_supported = true;
+        diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "SMARTCTL_ATA");
+
+        if diag.temperature_c.is_none() {
+            diag.temperature_c = smartctl_attr_raw(&smartctl_attrs, 194)
+                .or_else(|| smartctl_attr_raw(&smartctl_attrs, 190))
+                .map(raw_temp_to_celsius);
+        }
+        if diag.power_on_hours.is_none() {
+            diag.power_on_hours = smartctl_attr_raw_u64(&smartctl_attrs, 9);
+        }
+        if diag.power_cycle_count.is_none() {
+            diag.power_cycle_count = smartctl_attr_raw_u64(&smartctl_attrs, 12);
+        }
+        if diag.host_writes_total.is_none() {
+            diag.host_writes_total = smartctl_attr_raw_u64(&smartctl_attrs, 241);
+        }
+        if diag.host_reads_total.is_none() {
+            diag.host_reads_total = smartctl_attr_raw_u64(&smartctl_attrs, 242);
+        }
+        if diag.read_errors_total.is_none() {
+            diag.read_errors_total = smartctl_attr_raw_u64(&smartctl_attrs, 1)
+                .or_else(|| smartctl_attr_raw_u64(&smartctl_attrs, 187));
+        }
+        if diag.write_errors_total.is_none() {
+            diag.write_errors_total = smartctl_attr_raw_u64(&smartctl_attrs, 200)
+                .or_else(|| smartctl_attr_raw_u64(&smartctl_attrs, 181));
+        }
+        if let Some(used) = derive_endurance_used_from_attrs(&smartctl_attrs) {
+            if diag.percentage_used.is_none() || diag.percentage_used.unwrap_or(0.0) <= 0.0 {
+                diag.percentage_used = Some(used);
+            }
+        }
+    }
+
+    if payload.get("nvme_smart_health_information_log").is_some() {
+        diag.smart_supported = true;
+        diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "SMARTCTL_NVME");
+    }
+
+    let mut rel = match std::mem::take(&mut diag.reliability) {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+    merge_smartctl_reliability(&mut rel, payload, diag.smart_attributes.len());
+    // Windows keeps the flag its PowerShell query reported.
+    if cfg!(not(target_os = "windows")) {
+        diag.reliability_available = !rel.is_empty();
+    }
+    diag.reliability = Value::Object(rel);
+}
+
+fn merge_smartctl_reliability(
+    reliability: &mut Map<String, Value>,
+    payload: &Value,
+    attr_count: usize,
+) {
+    insert_if_absent(
+        reliability,
+        "Smartctl.Device",
+        get_string_path(payload, &["device", "name"]).map(Value::String),
+    );
+    insert_if_absent(
+        reliability,
+        "Smartctl.DeviceType",
+        get_string_path(payload, &["device", "type"]).map(Value::String),
+    );
+    insert_if_absent(
+        reliability,
+        "Smartctl.Protocol",
+        get_string_path(payload, &["device", "protocol"]).map(Value::String),
+    );
+    insert_if_absent(
+        reliability,
+        "Smartctl.ExitStatus",
+        get_u64_path(payload, &["smartctl", "exit_status"]).map(Value::from),
+    );
+    insert_if_absent(
+        reliability,
+        "Smartctl.RotationRate",
+        get_u64_path(payload, &["rotation_rate"]).map(Value::from),
+    );
+    if let Some(capacity) = get_u64_path(payload, &["user_capacity", "bytes"]) {
+        insert_if_absent(
+            reliability,
+            "Smartctl.UserCapacityBytes",
+            Some(Value::from(capacity)),
+        );
+    }
+    if payload.get("ata_smart_attributes").is_some() {
+        insert_if_absent(
+            reliability,
+            "Smartctl.AtaAttributeCount",
+            Some(Value::from(attr_count as u64)),
+        );
+    }
+
+    let nvme_fields = [
+        ("Nvme.CriticalWarning", "critical_warning"),
+        ("Nvme.AvailableSpare", "available_spare"),
+        ("Nvme.AvailableSpareThreshold", "available_spare_threshold"),
+        ("Nvme.PercentageUsed", "percentage_used"),
+        ("Nvme.DataUnitsRead", "data_units_read"),
+        ("Nvme.DataUnitsWritten", "data_units_written"),
+        ("Nvme.HostReads", "host_reads"),
+        ("Nvme.HostWrites", "host_writes"),
+        ("Nvme.ControllerBusyTime", "controller_busy_time"),
+        ("Nvme.PowerCycles", "power_cycles"),
+        ("Nvme.PowerOnHours", "power_on_hours"),
+        ("Nvme.UnsafeShutdowns", "unsafe_shutdowns"),
+        ("Nvme.MediaErrors", "media_errors"),
+        ("Nvme.ErrorLogEntries", "num_err_log_entries"),
+    ];
+
+    for (key, field) in nvme_fields {
+        let path = ["nvme_smart_health_information_log", field];
+        if let Some(v) = get_path(payload, &path).and_then(value_to_json_scalar) {
+            insert_if_absent(reliability, key, Some(v));
+        }
+    }
+}
+
+fn parse_smartctl_ata_attributes(payload: &Value) -> Vec<SmartAttribute> {
+    let mut attrs = Vec::new();
+    let Some(table) =
+        get_path(payload, &["ata_smart_attributes", "table"]).and_then(Value::as_array)
+    else {
+        return attrs;
+    };
+
+    for item in table {
+        let Some(id) = get_path(item, &["id"]).and_then(value_to_u64) else {
+            continue;
+        };
+        let name = get_string_path(item, &["name"]).unwrap_or_else(|| format!("Attribute {}", id));
+        let current = get_path(item, &["value"])
+            .and_then(value_to_u64)
+            .map(|v| v as u32);
+        let worst = get_path(item, &["worst"])
+            .and_then(value_to_u64)
+            .map(|v| v as u32);
+        let threshold = get_path(item, &["thresh"])
+            .and_then(value_to_u64)
+            .map(|v| v as u32);
+        let raw = get_path(item, &["raw"])
+            .and_then(|v| get_path(v, &["value"]).or(Some(v)))
+            .and_then(value_to_u64);
+        let raw_hex = raw.map(|v| format!("0x{:X}", v)).unwrap_or_default();
+
+        attrs.push(SmartAttribute {
+            id: id as u32,
+            name,
+            current,
+            worst,
+            threshold,
+            raw,
+            raw_hex,
+        });
+    }
+
+    attrs
+}
+
+fn smartctl_attr_raw(attrs: &[SmartAttribute], id: u32) -> Option<f64> {
+    attrs
+        .iter()
+        .find(|a| a.id == id)
+        .and_then(|a| a.raw)
+        .map(|v| v as f64)
+}
+
+fn smartctl_attr_raw_u64(attrs: &[SmartAttribute], id: u32) -> Option<u64> {
+    attrs.iter().find(|a| a.id == id).and_then(|a| a.raw)
+}
+
+fn smartctl_attr_current(attrs: &[SmartAttribute], id: u32) -> Option<u32> {
+    attrs.iter().find(|a| a.id == id).and_then(|a| a.current)
+}
+
+pub(crate) fn derive_endurance_used_from_attrs(attrs: &[SmartAttribute]) -> Option<f64> {
+    // CDI-like priority:
+    // 1) Life-left normalized attributes (231/233/202): used = 100 - current
+    // 2) Vendor fallback: RAW 202 directly represents used% for some SSD families.
+    let life_left = smartctl_attr_current(attrs, 231)
+        .or_else(|| smartctl_attr_current(attrs, 233))
+        .or_else(|| smartctl_attr_current(attrs, 202));
+
+    if let Some(v) = life_left {
+        if v > 0 && v < 100 {
+            return Some((100.0 - v as f64).clamp(0.0, 100.0));
+        }
+    }
+
+    if let Some(raw_used) = smartctl_attr_raw_u64(attrs, 202) {
+        if raw_used > 0 && raw_used <= 100 {
+            return Some(raw_used as f64);
+        }
+    }
+
+    None
+}
+
+pub(crate) fn normalize_endurance_percentage(diagnostics: &mut [DiskDiagnostics]) {
+    for diag in diagnostics.iter_mut() {
+        if is_nvme_diag(diag) {
+            continue;
+        }
+
+        let estimated = derive_endurance_used_from_attrs(&diag.smart_attributes);
+        match (diag.percentage_used, estimated) {
+            (Some(v), Some(used)) if v <= 0.0 && used > 0.0 => {
+                diag.percentage_used = Some(used);
+            }
+            (Some(v), None) if v <= 0.0 => {
+                diag.percentage_used = None;
+            }
+            (None, Some(used)) if used > 0.0 => {
+                diag.percentage_used = Some(used);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn is_nvme_diag(diag: &DiskDiagnostics) -> bool {
+    let haystack = format!(
+        "{} {} {} {} {}",
+        diag.transport_type, diag.interface_type, diag.bus_type, diag.model, diag.pnp_device_id
+    )
+    .to_ascii_uppercase();
+
+    haystack.contains("NVME") || haystack.contains("NVMEXPRESS") || haystack.contains("OPTANE")
+}
+
+fn raw_temp_to_celsius(raw: f64) -> f64 {
+    if raw <= 200.0 {
+        raw
+    } else {
+        (raw as u64 & 0xFF) as f64
+    }
+}
+
+fn extract_smartctl_temperature(payload: &Value) -> Option<f64> {
+    if let Some(temp) = get_f64_path(payload, &["temperature", "current"]) {
+        return Some(temp);
+    }
+    if let Some(mut nvme_temp) = get_f64_path(
+        payload,
+        &["nvme_smart_health_information_log", "temperature"],
+    ) {
+        if nvme_temp > 200.0 {
+            nvme_temp -= 273.15;
+        }
+        return Some((nvme_temp * 10.0).round() / 10.0);
+    }
+    None
+}
+
+pub(crate) fn merge_smart_source(existing: &str, add: &str) -> String {
+    if existing.is_empty() || existing.eq_ignore_ascii_case("none") {
+        return add.to_string();
+    }
+    if existing.split('+').any(|x| x.eq_ignore_ascii_case(add)) {
+        return existing.to_string();
+    }
+    format!("{existing}+{add}")
+}
+
+pub(crate) fn add_note_unique(diag: &mut DiskDiagnostics, note: &str) {
+    if !diag.notes.iter().any(|n| n == note) {
+        diag.notes.push(note.to_string());
+    }
+}
+
+pub(crate) fn insert_if_absent(map: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if map.contains_key(key) {
+        return;
+    }
+    if let Some(v) = value {
+        if !v.is_null() {
+            map.insert(key.to_string(), v);
+        }
+    }
+}
+
+fn get_path<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path {
+        current = current.get(*segment)?;
+    }
+    Some(current)
+}
+
+fn get_string_path(value: &Value, path: &[&str]) -> Option<String> {
+    let v = get_path(value, path)?;
+    if let Some(s) = v.as_str() {
+        let trimmed = s.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
+        }
+    }
+    None
+}
+
+fn get_u64_path(value: &Value, path: &[&str]) -> Option<u64> {
+    get_path(value, path).and_then(value_to_u64)
+}
+
+fn get_f64_path(value: &Value, path: &[&str]) -> Option<f64> {
+    get_path(value, path).and_then(value_to_f64)
+}
+
+fn get_bool_path(value: &Value, path: &[&str]) -> Option<bool> {
+    get_path(value, path).and_then(Value::as_bool)
+}
+
+fn value_to_json_scalar(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => Some(value.clone()),
+        Value::Object(_) => {
+            if let Some(n) = value_to_u64(value) {
+                Some(Value::from(n))
+            } else {
+                value_to_f64(value).map(Value::from)
+            }
+        }
+        Value::Array(_) => None,
+    }
+}
+
+fn value_to_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().map(|f| f.max(0.0) as u64)),
+        Value::String(s) => parse_u64_string(s),
+        Value::Object(map) => map.get("value").and_then(value_to_u64),
+        _ => None,
+    }
+}
+
+fn value_to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => parse_f64_string(s),
+        Value::Object(map) => map.get("value").and_then(value_to_f64),
+        _ => None,
+    }
+}
+
+fn parse_u64_string(s: &str) -> Option<u64> {
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Ok(n) = trimmed.parse::<u64>() {
+        return Some(n);
+    }
+
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        None
+    } else {
+        digits.parse::<u64>().ok()
+    }
+}
+
+fn parse_f64_string(s: &str) -> Option<f64> {
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Ok(n) = trimmed.parse::<f64>() {
+        return Some(n);
+    }
+
+    let normalized: String = trimmed
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+    if normalized.is_empty() {
+        None
+    } else {
+        normalized.parse::<f64>().ok()
+    }
+}
+
+pub(crate) fn is_masked_serial(serial: &str) -> bool {
+    let normalized: String = serial
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if normalized.len() < 4 {
+        return true;
+    }
+    if normalized.chars().all(|c| c == '0' || c == 'D') {
+        return true;
+    }
+    if normalized.chars().all(|c| c == '0') {
+        return true;
+    }
+    if normalized.chars().all(|c| c == 'F') {
+        return true;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCAN_OPEN: &str = include_str!("../../tests/fixtures/smartctl/scan_open.json");
+    const SATA_SSD: &str = include_str!("../../tests/fixtures/smartctl/sata_ssd.json");
+    const NVME: &str = include_str!("../../tests/fixtures/smartctl/nvme.json");
+    const USB_SAT: &str = include_str!("../../tests/fixtures/smartctl/usb_sat.json");
+
+    fn fixture(raw: &str) -> Value {
+        extract_json_value(raw).expect("fixture should be valid JSON")
+    }
+
+    fn diag(id: &str, transport: &str, is_usb: bool) -> DiskDiagnostics {
+        DiskDiagnostics {
+            id: id.to_string(),
+            transport_type: transport.to_string(),
+            interface_type: transport.to_string(),
+            is_usb,
+            health_status: "Unknown".to_string(),
+            smart_data_source: "NONE".to_string(),
+            reliability: Value::Object(Map::new()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_scan_open_json() {
+        let entries = parse_smartctl_scan_entries_json(&fixture(SCAN_OPEN));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].name, "/dev/sdb");
+        assert_eq!(entries[1].device_type.as_deref(), Some("usbjmicron"));
+        assert_eq!(entries[2].name, "/dev/nvme0");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scan_entries_match_nvme_controller_node() {
+        let entries = parse_smartctl_scan_entries_json(&fixture(SCAN_OPEN));
+        let nvme = diag("nvme0n1", "NVMe", false);
+        let matched: Vec<&str> = entries
+            .iter()
+            .filter(|e| smartctl_entry_matches_disk(e, &nvme))
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(matched, vec!["/dev/nvme0"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn usb_disks_retry_with_sat_and_auto() {
+        let entries = parse_smartctl_scan_entries_json(&fixture(SCAN_OPEN));
+        let usb = diag("sdb", "USB", true);
+        let attempts = build_smartctl_attempts(&usb, Some(&entries));
+
+        assert_eq!(attempts[0], vec!["-x", "-j", "/dev/sdb"]);
+        assert!(attempts.contains(&vec![
+            "-x".to_string(),
+            "-j".to_string(),
+            "-d".to_string(),
+            "sat".to_string(),
+            "/dev/sdb".to_string()
+        ]));
+        assert!(attempts.iter().any(|a| a.contains(&"auto".to_string())));
+        assert_eq!(
+            attempts.last().map(|a| a.join(" ")),
+            Some("-x -j -d usbjmicron /dev/sdb".to_string())
+        );
+    }
+
+    #[test]
+    fn windows_probes_bridges_before_scanned_entries() {
+        let scanned = [SmartctlScanEntry {
+            name: "/dev/pd0".to_string(),
+            device_type: Some("ata".to_string()),
+            info_name: "/dev/disk0".to_string(),
+        }];
+        let probe_types = |diag: &DiskDiagnostics| {
+            let mut attempts = SmartctlAttempts::new(vec!["/dev/pd0".to_string()]);
+            push_windows_attempts(&mut attempts, diag, Some(&scanned));
+            attempts
+                .attempts
+                .iter()
+                .map(|a| {
+                    if a.len() == 5 {
+                        a[3].clone()
+                    } else {
+                        String::new()
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut sata = diag("pd0", "USB", true);
+        sata.usb_vendor_id = "174c".to_string();
+        assert_eq!(
+            probe_types(&sata),
+            [
+                "",
+                "sat,auto",
+                "sat",
+                "sat,12",
+                "sat,16",
+                "scsi",
+                "usbjmicron",
+                "sntasmedia",
+                "ata"
+            ]
+        );
+
+        let mut nvme = diag("pd0", "NVMe", true);
+        nvme.usb_vendor_id = "152D".to_string();
+        assert_eq!(
+            probe_types(&nvme),
+            [
+                "",
+                "sat,auto",
+                "sat",
+                "sat,12",
+                "sat,16",
+                "scsi",
+                "nvme",
+                "sntjmicron",
+                "sntjmicron,0",
+                "sntjmicron,1",
+                "sntasmedia",
+                "sntrealtek",
+                "sntrealtek,0",
+                "sntrealtek,1",
+                "usbjmicron",
+                "ata"
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_sata_ssd_payload() {
+        let mut d = diag("sda", "SATA", false);
+        apply_smartctl_payload(&mut d, &fixture(SATA_SSD));
+
+        assert_eq!(d.model, "Samsung SSD 870 EVO 500GB");
+        assert_eq!(d.serial_number, "S62ANJ0R123456X");
+        assert_eq!(d.media_type, "SSD");
+        assert_eq!(d.temperature_c, Some(34.0));
+        assert_eq!(d.power_on_hours, Some(12873));
+        assert_eq!(d.power_cycle_count, Some(1164));
+        assert_eq!(d.host_writes_total, Some(61408234122));
+        assert_eq!(d.smart_attributes.len(), 9);
+        assert_eq!(d.smart_data_source, "SMARTCTL_ATA");
+        let verdict = if cfg!(target_os = "linux") {
+            "Healthy"
+        } else {
+            "Unknown"
+        };
+        assert_eq!(d.health_status, verdict);
+        assert!(d.ata_smart_available);
+        assert_eq!(
+            d.reliability["Smartctl.AtaAttributeCount"],
+            Value::from(9u64)
+        );
+    }
+
+    #[test]
+    fn applies_nvme_payload() {
+        let mut d = diag("nvme0n1", "NVMe", false);
+        apply_smartctl_payload(&mut d, &fixture(NVME));
+
+        assert_eq!(d.transport_type, "NVMe");
+        assert_eq!(d.interface_type, "NVMExpress");
+        assert_eq!(d.temperature_c, Some(41.0));
+        assert_eq!(d.power_on_hours, Some(6023));
+        assert_eq!(d.percentage_used, Some(3.0));
+        assert_eq!(d.host_writes_total, Some(421347121));
+        assert_eq!(d.write_errors_total, Some(2));
+        assert_eq!(d.smart_data_source, "SMARTCTL_NVME");
+        assert_eq!(d.reliability["Nvme.UnsafeShutdowns"], Value::from(57u64));
+    }
+
+    #[test]
+    fn applies_usb_sat_payload_with_packed_temperature() {
+        let mut d = diag("sdb", "USB", true);
+        apply_smartctl_payload(&mut d, &fixture(USB_SAT));
+
+        assert_eq!(d.temperature_c, Some(33.0));
+        assert_eq!(d.power_on_hours, Some(2210));
+        assert_eq!(d.read_errors_total, Some(0));
+        assert_eq!(d.percentage_used, Some(4.0));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn usb_payload_matches_only_its_device() {
+        let payload = fixture(USB_SAT);
+        assert!(payload_matches_diag(&payload, &diag("sdb", "USB", true)));
+        assert!(!payload_matches_diag(&payload, &diag("sdc", "USB", true)));
+    }
+}
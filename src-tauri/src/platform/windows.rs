@@ -3,11 +3,14 @@ mod smart;
 use crate::commands::disk::DiskDiagnostics;
 use crate::commands::disk::DiskInfo;
 use crate::commands::disk::SmartAttribute;
+use crate::platform::smart::ata::smart_attribute_name;
+use crate::platform::smart::nvme::apply_native_nvme_data;
+use crate::platform::smartctl::{
+    add_note_unique, derive_endurance_used_from_attrs, enrich_with_smartctl, is_masked_serial,
+    is_nvme_diag, merge_smart_source, normalize_endurance_percentage,
+};
 use crate::utils::command::CommandExecutor;
 use crate::{AppError, Result};
-use regex::Regex;
-use serde_json::{Map, Value};
-use std::collections::HashSet;
 use tracing::{info, warn};
 
 /// PowerShell script to get disk info with volume letters.
@@ -659,7 +662,7 @@ fn enrich_with_native_smart(diagnostics: &mut [DiskDiagnostics]) {
 
         match smart::DiskHandle::open(diag.disk_number) {
             Ok(handle) => match handle.read_smart_data() {
-                Ok(smart_data) => {
+                Ok((smart_data, read_method)) => {
                     info!(
                         "Successfully read ATA SMART data for disk {}",
                         diag.disk_number
@@ -680,7 +683,7 @@ fn enrich_with_native_smart(diagnostics: &mut [DiskDiagnostics]) {
                         .iter()
                         .map(|attr| SmartAttribute {
                             id: attr.id as u32,
-                            name: get_smart_attribute_name(attr.id),
+                            name: smart_attribute_name(attr.id),
                             current: Some(attr.current as u32),
                             worst: Some(attr.worst as u32),
                             threshold: if smart_data.thresholds_available {
@@ -697,7 +700,7 @@ fn enrich_with_native_smart(diagnostics: &mut [DiskDiagnostics]) {
                         diag.smart_attributes = attrs;
                         diag.ata_smart_available = true;
                         diag.smart_supported = true;
-                        let (native_code, native_note) = match smart_data.read_method {
+                        let (native_code, native_note) = match read_method {
                             smart::SmartReadMethod::AtaPassThrough => (
                                 "ATA_NATIVE_IOCTL",
                                 "ATA SMART data read directly via Windows IOCTL (native API).",
@@ -753,16 +756,6 @@ fn enrich_with_native_smart(diagnostics: &mut [DiskDiagnostics]) {
     }
 }
 
-fn is_nvme_diag(diag: &DiskDiagnostics) -> bool {
-    let haystack = format!(
-        "{} {} {} {} {}",
-        diag.transport_type, diag.interface_type, diag.bus_type, diag.model, diag.pnp_device_id
-    )
-    .to_ascii_uppercase();
-
-    haystack.contains("NVME") || haystack.contains("NVMEXPRESS") || haystack.contains("OPTANE")
-}
-
 fn try_enrich_native_nvme(diag: &mut DiskDiagnostics) -> bool {
     match smart::nvme::NVMeHandle::open(diag.disk_number) {
         Ok(handle) => {
@@ -812,1255 +805,6 @@ fn try_enrich_native_nvme(diag: &mut DiskDiagnostics) -> bool {
     }
 }
 
-fn apply_native_nvme_data(diag: &mut DiskDiagnostics, nvme_data: &smart::nvme::NVMeSmartData) {
-    diag.transport_type = "NVMe".to_string();
-    diag.interface_type = "NVMExpress".to_string();
-    diag.media_type = "SSD".to_string();
-
-    if diag.temperature_c.is_none() && nvme_data.temperature > -1000 {
-        diag.temperature_c = Some(nvme_data.temperature as f64);
-    }
-    if diag.power_on_hours.is_none() {
-        diag.power_on_hours = Some(u128_to_u64(nvme_data.power_on_hours));
-    }
-    if diag.power_cycle_count.is_none() {
-        diag.power_cycle_count = Some(u128_to_u64(nvme_data.power_cycles));
-    }
-    if diag.percentage_used.is_none() {
-        diag.percentage_used = Some((nvme_data.percentage_used as f64).clamp(0.0, 100.0));
-    }
-    if diag.host_reads_total.is_none() {
-        let units = u128_to_u64(nvme_data.data_units_read);
-        let cmds = u128_to_u64(nvme_data.host_read_commands);
-        diag.host_reads_total = Some(if units > 0 { units } else { cmds });
-    }
-    if diag.host_writes_total.is_none() {
-        let units = u128_to_u64(nvme_data.data_units_written);
-        let cmds = u128_to_u64(nvme_data.host_write_commands);
-        diag.host_writes_total = Some(if units > 0 { units } else { cmds });
-    }
-    if diag.read_errors_total.is_none() {
-        diag.read_errors_total = Some(u128_to_u64(nvme_data.media_errors));
-    }
-    if diag.write_errors_total.is_none() {
-        diag.write_errors_total = Some(u128_to_u64(nvme_data.num_err_log_entries));
-    }
-
-    diag.smart_supported = true;
-    diag.smart_enabled = true;
-    diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "NVME_NATIVE_IOCTL");
-
-    let mut rel = match std::mem::take(&mut diag.reliability) {
-        Value::Object(map) => map,
-        _ => Map::new(),
-    };
-
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.CriticalWarning",
-        Some(Value::from(nvme_data.critical_warning)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.AvailableSpare",
-        Some(Value::from(nvme_data.available_spare)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.AvailableSpareThreshold",
-        Some(Value::from(nvme_data.available_spare_threshold)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.PercentageUsed",
-        Some(Value::from(nvme_data.percentage_used)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.DataUnitsRead",
-        Some(u128_to_json_value(nvme_data.data_units_read)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.DataUnitsWritten",
-        Some(u128_to_json_value(nvme_data.data_units_written)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.HostReadCommands",
-        Some(u128_to_json_value(nvme_data.host_read_commands)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.HostWriteCommands",
-        Some(u128_to_json_value(nvme_data.host_write_commands)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.ControllerBusyTime",
-        Some(u128_to_json_value(nvme_data.controller_busy_time)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.PowerCycles",
-        Some(u128_to_json_value(nvme_data.power_cycles)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.PowerOnHours",
-        Some(u128_to_json_value(nvme_data.power_on_hours)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.UnsafeShutdowns",
-        Some(u128_to_json_value(nvme_data.unsafe_shutdowns)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.MediaErrors",
-        Some(u128_to_json_value(nvme_data.media_errors)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.ErrorLogEntries",
-        Some(u128_to_json_value(nvme_data.num_err_log_entries)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.WarningTempTime",
-        Some(Value::from(nvme_data.warning_temp_time)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.CriticalTempTime",
-        Some(Value::from(nvme_data.critical_temp_time)),
-    );
-    insert_if_absent(
-        &mut rel,
-        "NvmeIoctl.TemperatureSensors",
-        Some(Value::Array(
-            nvme_data
-                .temp_sensors
-                .iter()
-                .map(|v| Value::from(*v))
-                .collect(),
-        )),
-    );
-
-    diag.reliability = Value::Object(rel);
-}
-
-fn u128_to_u64(value: u128) -> u64 {
-    value.min(u64::MAX as u128) as u64
-}
-
-fn u128_to_json_value(value: u128) -> Value {
-    if value <= u64::MAX as u128 {
-        Value::from(value as u64)
-    } else {
-        Value::String(value.to_string())
-    }
-}
-
-fn get_smart_attribute_name(id: u8) -> String {
-    match id {
-        1 => "Read Error Rate".to_string(),
-        2 => "Throughput Performance".to_string(),
-        3 => "Spin-Up Time".to_string(),
-        4 => "Start/Stop Count".to_string(),
-        5 => "Reallocated Sectors Count".to_string(),
-        7 => "Seek Error Rate".to_string(),
-        8 => "Seek Time Performance".to_string(),
-        9 => "Power-On Hours".to_string(),
-        10 => "Spin Retry Count".to_string(),
-        11 => "Calibration Retry Count".to_string(),
-        12 => "Power Cycle Count".to_string(),
-        170 => "Available Reserved Space".to_string(),
-        171 => "Program Fail Count".to_string(),
-        172 => "Erase Fail Count".to_string(),
-        173 => "Wear Leveling Count".to_string(),
-        174 => "Unexpected Power Loss Count".to_string(),
-        177 => "Wear Range Delta".to_string(),
-        179 => "Used Reserved Block Count Total".to_string(),
-        180 => "Unused Reserved Block Count Total".to_string(),
-        181 => "Program Fail Count Total".to_string(),
-        182 => "Erase Fail Count".to_string(),
-        183 => "Runtime Bad Block".to_string(),
-        184 => "End-to-End Error".to_string(),
-        187 => "Reported Uncorrectable Errors".to_string(),
-        188 => "Command Timeout".to_string(),
-        190 => "Airflow Temperature".to_string(),
-        191 => "G-Sense Error Rate".to_string(),
-        192 => "Power-Off Retract Count".to_string(),
-        193 => "Load/Unload Cycle Count".to_string(),
-        194 => "Temperature".to_string(),
-        195 => "Hardware ECC Recovered".to_string(),
-        196 => "Reallocation Event Count".to_string(),
-        197 => "Current Pending Sector Count".to_string(),
-        198 => "Offline Uncorrectable Sector Count".to_string(),
-        199 => "UltraDMA CRC Error Count".to_string(),
-        200 => "Write Error Rate".to_string(),
-        201 => "Soft Read Error Rate".to_string(),
-        202 => "Data Address Mark Errors".to_string(),
-        206 => "Flying Height".to_string(),
-        210 => "Vibration During Write".to_string(),
-        211 => "Vibration During Write Time".to_string(),
-        212 => "Shock During Write".to_string(),
-        220 => "Disk Shift".to_string(),
-        222 => "Loaded Hours".to_string(),
-        223 => "Load/Unload Retry Count".to_string(),
-        224 => "Load Friction".to_string(),
-        225 => "Load/Unload Cycle Count".to_string(),
-        226 => "Load-in Time".to_string(),
-        227 => "Torque Amplification Count".to_string(),
-        228 => "Power-Off Retract Cycle".to_string(),
-        230 => "Drive Life Protection Status".to_string(),
-        231 => "SSD Life Left".to_string(),
-        232 => "Available Reserved Space".to_string(),
-        233 => "Media Wearout Indicator".to_string(),
-        234 => "Average Erase Count".to_string(),
-        235 => "Good Block Count".to_string(),
-        241 => "Total LBAs Written".to_string(),
-        242 => "Total LBAs Read".to_string(),
-        243 => "Total LBAs Written Expanded".to_string(),
-        244 => "Total LBAs Read Expanded".to_string(),
-        245 => "NAND Writes (1GiB)".to_string(),
-        246 => "Total NAND Writes".to_string(),
-        247 => "Host Program NAND Pages Count".to_string(),
-        248 => "FTL Program NAND Pages Count".to_string(),
-        249 => "NAND Writes (1GiB)".to_string(),
-        250 => "Read Error Retry Rate".to_string(),
-        251 => "Minimum Spares Remaining".to_string(),
-        252 => "Newly Added Bad Flash Block".to_string(),
-        254 => "Free Fall Protection".to_string(),
-        _ => format!("Attribute {}", id),
-    }
-}
-
-fn enrich_with_smartctl(diagnostics: &mut [DiskDiagnostics]) {
-    if diagnostics.is_empty() {
-        return;
-    }
-
-    if !smartctl_installed() {
-        for diag in diagnostics.iter_mut() {
-            add_note_unique(
-                diag,
-                "smartctl not found in bundled resources or PATH; include smartmontools to enable extended SMART details.",
-            );
-        }
-        return;
-    }
-
-    let scan_entries = smartctl_scan_entries();
-    for diag in diagnostics.iter_mut() {
-        if let Some(payload) = get_smartctl_payload_for_disk(diag, scan_entries.as_deref()) {
-            apply_smartctl_payload(diag, &payload);
-            add_note_unique(diag, "Extended SMART details were enhanced via smartctl.");
-        }
-    }
-}
-
-fn smartctl_installed() -> bool {
-    run_smartctl_allow_fail(&["--version"]).is_some()
-}
-
-#[derive(Debug, Clone)]
-struct SmartctlScanEntry {
-    name: String,
-    device_type: Option<String>,
-    info_name: String,
-}
-
-fn smartctl_scan_entries() -> Option<Vec<SmartctlScanEntry>> {
-    let scan_cmds: [&[&str]; 4] = [
-        &["--scan-open", "-j"],
-        &["--scan", "-j"],
-        &["--scan-open"],
-        &["--scan"],
-    ];
-
-    let mut entries = Vec::new();
-    let mut seen = HashSet::new();
-    for args in scan_cmds {
-        let Some(output) = run_smartctl_allow_fail(args) else {
-            continue;
-        };
-
-        let parsed = if args.contains(&"-j") {
-            extract_json_value(&output)
-                .map(|payload| parse_smartctl_scan_entries_json(&payload))
-                .unwrap_or_default()
-        } else {
-            parse_smartctl_scan_entries_text(&output)
-        };
-
-        for entry in parsed {
-            let key = format!(
-                "{}\u{1F}{}",
-                entry.name.to_ascii_lowercase(),
-                entry
-                    .device_type
-                    .clone()
-                    .unwrap_or_default()
-                    .to_ascii_lowercase()
-            );
-            if seen.insert(key) {
-                entries.push(entry);
-            }
-        }
-    }
-
-    if entries.is_empty() {
-        None
-    } else {
-        Some(entries)
-    }
-}
-
-fn parse_smartctl_scan_entries_json(payload: &Value) -> Vec<SmartctlScanEntry> {
-    let mut entries = Vec::new();
-    let Some(devices) = payload.get("devices").and_then(Value::as_array) else {
-        return entries;
-    };
-
-    for dev in devices {
-        let name = dev
-            .get("name")
-            .and_then(Value::as_str)
-            .unwrap_or("")
-            .trim()
-            .to_string();
-        if name.is_empty() {
-            continue;
-        }
-        let info_name = dev
-            .get("info_name")
-            .and_then(Value::as_str)
-            .unwrap_or("")
-            .trim()
-            .to_string();
-        let device_type = dev
-            .get("type")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .map(ToOwned::to_owned);
-
-        entries.push(SmartctlScanEntry {
-            name,
-            device_type,
-            info_name,
-        });
-    }
-    entries
-}
-
-fn parse_smartctl_scan_entries_text(output: &str) -> Vec<SmartctlScanEntry> {
-    let mut entries = Vec::new();
-    let scan_regex = Regex::new(
-        r#"(?i)^\s*(?P<name>\S+)(?:\s+-d\s+(?P<dtype>[^\s#]+))?(?:\s+#\s*(?P<info>.*))?$"#,
-    )
-    .ok();
-
-    for raw_line in output.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        if let Some(re) = scan_regex.as_ref() {
-            if let Some(caps) = re.captures(line) {
-                let name = caps
-                    .name("name")
-                    .map(|m| m.as_str().trim().to_string())
-                    .unwrap_or_default();
-                if name.is_empty() || !name.starts_with('/') {
-                    continue;
-                }
-                let device_type = caps
-                    .name("dtype")
-                    .map(|m| m.as_str().trim().to_string())
-                    .filter(|s| !s.is_empty());
-                let info_name = caps
-                    .name("info")
-                    .map(|m| m.as_str().trim().to_string())
-                    .unwrap_or_default();
-
-                entries.push(SmartctlScanEntry {
-                    name,
-                    device_type,
-                    info_name,
-                });
-            }
-        }
-    }
-
-    entries
-}
-
-fn smartctl_entry_matches_disk(entry: &SmartctlScanEntry, disk_number: u32) -> bool {
-    let key_pd = format!("pd{}", disk_number).to_ascii_lowercase();
-    let key_phy = format!("physicaldrive{}", disk_number).to_ascii_lowercase();
-    let key_disk = format!("disk{}", disk_number).to_ascii_lowercase();
-    let name = entry.name.to_ascii_lowercase();
-    let info = entry.info_name.to_ascii_lowercase();
-
-    [name, info]
-        .iter()
-        .any(|v| v.contains(&key_pd) || v.contains(&key_phy) || v.contains(&key_disk))
-}
-
-fn push_smartctl_attempt(
-    attempts: &mut Vec<Vec<String>>,
-    seen: &mut HashSet<String>,
-    device: &str,
-    device_type: Option<&str>,
-) {
-    let mut args = vec!["-x".to_string(), "-j".to_string()];
-    if let Some(dt) = device_type.map(str::trim).filter(|dt| !dt.is_empty()) {
-        args.push("-d".to_string());
-        args.push(dt.to_string());
-    }
-    args.push(device.to_string());
-
-    let key = args.join("\u{1F}");
-    if seen.insert(key) {
-        attempts.push(args);
-    }
-}
-
-fn normalized_eq(a: &str, b: &str) -> bool {
-    let na: String = a
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .map(|c| c.to_ascii_uppercase())
-        .collect();
-    let nb: String = b
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .map(|c| c.to_ascii_uppercase())
-        .collect();
-    !na.is_empty() && !nb.is_empty() && na == nb
-}
-
-fn payload_matches_diag(payload: &Value, diag: &DiskDiagnostics) -> bool {
-    let pd_name = format!("/dev/pd{}", diag.disk_number).to_ascii_lowercase();
-    if get_string_path(payload, &["device", "name"])
-        .map(|n| n.to_ascii_lowercase().contains(&pd_name))
-        .unwrap_or(false)
-    {
-        return true;
-    }
-
-    let payload_serial = get_string_path(payload, &["serial_number"]).unwrap_or_default();
-    let diag_serial = diag.serial_number.trim();
-    if !diag_serial.is_empty()
-        && !is_masked_serial(diag_serial)
-        && !payload_serial.is_empty()
-        && normalized_eq(diag_serial, &payload_serial)
-    {
-        return true;
-    }
-
-    let payload_model = get_string_path(payload, &["model_name"]).unwrap_or_default();
-    let diag_model = if diag.model.trim().is_empty() {
-        diag.friendly_name.trim()
-    } else {
-        diag.model.trim()
-    };
-    let model_like = !payload_model.is_empty()
-        && !diag_model.is_empty()
-        && (payload_model
-            .to_ascii_uppercase()
-            .contains(&diag_model.to_ascii_uppercase())
-            || diag_model
-                .to_ascii_uppercase()
-                .contains(&payload_model.to_ascii_uppercase()));
-
-    if model_like {
-        if let Some(cap) = get_u64_path(payload, &["user_capacity", "bytes"]) {
-            let size = diag.size_bytes;
-            if size > 0 {
-                let diff = cap.abs_diff(size);
-                let tolerance = (size / 20).max(64 * 1024 * 1024);
-                if diff <= tolerance {
-                    return true;
-                }
-            }
-        }
-    }
-
-    false
-}
-
-fn get_smartctl_payload_for_disk(
-    diag: &DiskDiagnostics,
-    scan_entries: Option<&[SmartctlScanEntry]>,
-) -> Option<Value> {
-    let device = format!("/dev/pd{}", diag.disk_number);
-    let mut attempts: Vec<Vec<String>> = Vec::new();
-    let mut seen = HashSet::new();
-
-    push_smartctl_attempt(&mut attempts, &mut seen, &device, None);
-
-    let is_nvme = diag.transport_type.eq_ignore_ascii_case("nvme")
-        || diag.interface_type.eq_ignore_ascii_case("nvmexpress")
-        || diag.bus_type.eq_ignore_ascii_case("nvme");
-
-    if diag.is_usb {
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sat,auto"));
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sat"));
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sat,12"));
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sat,16"));
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("scsi"));
-    }
-
-    if is_nvme {
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("nvme"));
-        if diag.is_usb {
-            // CrystalDiskInfo has dedicated NVMe-over-USB paths for JMicron/ASMedia/Realtek.
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron,0"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron,1"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntasmedia"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek,0"));
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek,1"));
-        }
-    } else {
-        if diag.is_usb {
-            push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("usbjmicron"));
-        }
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sat"));
-        push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("scsi"));
-    }
-
-    let usb_vid = diag.usb_vendor_id.trim().to_ascii_uppercase();
-    if !usb_vid.is_empty() {
-        match usb_vid.as_str() {
-            // JMicron
-            "152D" => {
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("usbjmicron"));
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron"));
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron,0"));
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntjmicron,1"));
-            }
-            // ASMedia
-            "174C" => {
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntasmedia"));
-            }
-            // Realtek
-            "0BDA" => {
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek"));
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek,0"));
-                push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("sntrealtek,1"));
-            }
-            // Cypress / Prolific / Sunplus
-            "04B4" => push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("usbcypress")),
-            "067B" => push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("usbprolific")),
-            "04FC" => push_smartctl_attempt(&mut attempts, &mut seen, &device, Some("usbsunplus")),
-            _ => {}
-        }
-    }
-
-    if let Some(entries) = scan_entries {
-        for entry in entries
-            .iter()
-            .filter(|e| smartctl_entry_matches_disk(e, diag.disk_number))
-        {
-            push_smartctl_attempt(
-                &mut attempts,
-                &mut seen,
-                &entry.name,
-                entry.device_type.as_deref(),
-            );
-        }
-    }
-
-    for args in attempts {
-        if let Some(payload) = run_smartctl_json(&args) {
-            if is_useful_smartctl_payload(&payload) {
-                return Some(payload);
-            }
-        }
-    }
-
-    if let Some(entries) = scan_entries {
-        // Fallback: probe scanned entries and match by serial/model/size
-        for entry in entries {
-            let mut fallback = vec!["-x".to_string(), "-j".to_string()];
-            if let Some(dt) = entry
-                .device_type
-                .as_deref()
-                .map(str::trim)
-                .filter(|s| !s.is_empty())
-            {
-                fallback.push("-d".to_string());
-                fallback.push(dt.to_string());
-            }
-            fallback.push(entry.name.clone());
-
-            if let Some(payload) = run_smartctl_json(&fallback) {
-                if is_useful_smartctl_payload(&payload) && payload_matches_diag(&payload, diag) {
-                    return Some(payload);
-                }
-            }
-        }
-    }
-
-    None
-}
-
-fn run_smartctl_json(args: &[String]) -> Option<Value> {
-    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
-    let output = run_smartctl_allow_fail(&arg_refs)?;
-    extract_json_value(&output)
-}
-
-fn run_smartctl_allow_fail(args: &[&str]) -> Option<String> {
-    for cmd in smartctl_candidates() {
-        if let Ok(output) = CommandExecutor::execute_allow_fail(&cmd, args) {
-            return Some(output);
-        }
-    }
-    None
-}
-
-fn smartctl_candidates() -> Vec<String> {
-    let mut candidates = Vec::new();
-
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(dir) = exe.parent() {
-            // Prefer bundled smartmontools shipped with the app.
-            push_candidate_path(
-                &mut candidates,
-                dir.join("smartmontools").join("bin").join("smartctl.exe"),
-            );
-            push_candidate_path(
-                &mut candidates,
-                dir.join("resources")
-                    .join("smartmontools")
-                    .join("bin")
-                    .join("smartctl.exe"),
-            );
-            push_candidate_path(
-                &mut candidates,
-                dir.join("..")
-                    .join("resources")
-                    .join("smartmontools")
-                    .join("bin")
-                    .join("smartctl.exe"),
-            );
-
-            // Backward compatibility for old bundled layout.
-            push_candidate_path(
-                &mut candidates,
-                dir.join("resources").join("smartctl").join("smartctl.exe"),
-            );
-            push_candidate_path(
-                &mut candidates,
-                dir.join("..")
-                    .join("resources")
-                    .join("smartctl")
-                    .join("smartctl.exe"),
-            );
-            push_candidate_path(&mut candidates, dir.join("smartctl.exe"));
-        }
-    }
-
-    if let Ok(cwd) = std::env::current_dir() {
-        // Repository/dev layout convenience.
-        push_candidate_path(
-            &mut candidates,
-            cwd.join("src-tauri")
-                .join("resources")
-                .join("smartmontools")
-                .join("bin")
-                .join("smartctl.exe"),
-        );
-        push_candidate_path(
-            &mut candidates,
-            cwd.join("smartmontools").join("bin").join("smartctl.exe"),
-        );
-        push_candidate_path(
-            &mut candidates,
-            cwd.join("useable_software")
-                .join("smartmontools")
-                .join("bin")
-                .join("smartctl.exe"),
-        );
-    }
-
-    // PATH and global installs as fallback.
-    candidates.extend([
-        "smartctl".to_string(),
-        "smartctl.exe".to_string(),
-        r"C:\Program Files\smartmontools\bin\smartctl.exe".to_string(),
-        r"C:\Program Files (x86)\smartmontools\bin\smartctl.exe".to_string(),
-    ]);
-
-    // Keep only existing absolute/relative paths; retain command names.
-    let mut filtered = Vec::new();
-    for c in candidates {
-        if c.contains('\\') || c.contains('/') {
-            if std::path::Path::new(&c).exists() {
-                filtered.push(c);
-            }
-        } else {
-            filtered.push(c);
-        }
-    }
-
-    let mut seen = std::collections::HashSet::new();
-    filtered
-        .into_iter()
-        .filter(|s| seen.insert(s.to_ascii_lowercase()))
-        .collect()
-}
-
-fn push_candidate_path(candidates: &mut Vec<String>, path: std::path::PathBuf) {
-    candidates.push(path.to_string_lossy().to_string());
-}
-
-fn extract_json_value(output: &str) -> Option<Value> {
-    let trimmed = output.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-    let start = trimmed.find('{').or_else(|| trimmed.find('['))?;
-    serde_json::from_str(&trimmed[start..]).ok()
-}
-
-fn is_useful_smartctl_payload(payload: &Value) -> bool {
-    payload.get("smartctl").is_some()
-        && (payload.get("model_name").is_some()
-            || payload.get("serial_number").is_some()
-            || payload.pointer("/ata_smart_attributes/table").is_some()
-            || payload.get("nvme_smart_health_information_log").is_some()
-            || payload.pointer("/temperature/current").is_some()
-            || payload.pointer("/power_on_time/hours").is_some())
-}
-
-fn apply_smartctl_payload(diag: &mut DiskDiagnostics, payload: &Value) {
-    if let Some(model) = get_string_path(payload, &["model_name"]).filter(|s| !s.is_empty()) {
-        if diag.model.trim().is_empty() {
-            diag.model = model;
-        }
-    }
-
-    if let Some(firmware) =
-        get_string_path(payload, &["firmware_version"]).filter(|s| !s.is_empty())
-    {
-        if diag.firmware_version.trim().is_empty() {
-            diag.firmware_version = firmware;
-        }
-    }
-
-    if let Some(serial) = get_string_path(payload, &["serial_number"]).filter(|s| !s.is_empty()) {
-        if diag.serial_number.trim().is_empty() || is_masked_serial(&diag.serial_number) {
-            diag.serial_number = serial;
-        }
-    }
-
-    if let Some(protocol) = get_string_path(payload, &["device", "protocol"]) {
-        if protocol.eq_ignore_ascii_case("nvme") {
-            diag.transport_type = "NVMe".to_string();
-            diag.interface_type = "NVMExpress".to_string();
-            if diag.media_type.eq_ignore_ascii_case("unknown") || diag.media_type.is_empty() {
-                diag.media_type = "SSD".to_string();
-            }
-        }
-    }
-
-    if let Some(rotation) = get_u64_path(payload, &["rotation_rate"]) {
-        if rotation == 0 {
-            diag.media_type = "SSD".to_string();
-        } else if rotation > 0 {
-            diag.media_type = "HDD".to_string();
-        }
-    }
-
-    if diag.temperature_c.is_none() {
-        diag.temperature_c = extract_smartctl_temperature(payload);
-    }
-    if diag.power_on_hours.is_none() {
-        diag.power_on_hours = get_u64_path(payload, &["power_on_time", "hours"]).or_else(|| {
-            get_u64_path(
-                payload,
-                &["nvme_smart_health_information_log", "power_on_hours"],
-            )
-        });
-    }
-    if diag.power_cycle_count.is_none() {
-        diag.power_cycle_count = get_u64_path(payload, &["power_cycle_count"]).or_else(|| {
-            get_u64_path(
-                payload,
-                &["nvme_smart_health_information_log", "power_cycles"],
-            )
-        });
-    }
-    if diag.percentage_used.is_none() {
-        diag.percentage_used = get_f64_path(
-            payload,
-            &["nvme_smart_health_information_log", "percentage_used"],
-        );
-    }
-    if diag.host_reads_total.is_none() {
-        diag.host_reads_total = get_u64_path(
-            payload,
-            &["nvme_smart_health_information_log", "host_reads"],
-        )
-        .or_else(|| {
-            get_u64_path(
-                payload,
-                &["nvme_smart_health_information_log", "data_units_read"],
-            )
-        });
-    }
-    if diag.host_writes_total.is_none() {
-        diag.host_writes_total = get_u64_path(
-            payload,
-            &["nvme_smart_health_information_log", "host_writes"],
-        )
-        .or_else(|| {
-            get_u64_path(
-                payload,
-                &["nvme_smart_health_information_log", "data_units_written"],
-            )
-        });
-    }
-    if diag.read_errors_total.is_none() {
-        diag.read_errors_total = get_u64_path(
-            payload,
-            &["nvme_smart_health_information_log", "media_errors"],
-        );
-    }
-
-    if diag.write_errors_total.is_none() {
-        diag.write_errors_total = get_u64_path(
-            payload,
-            &["nvme_smart_health_information_log", "num_err_log_entries"],
-        );
-    }
-
-    if let Some(enabled) = get_bool_path(payload, &["smart_support", "enabled"]) {
-        diag.smart_enabled = enabled;
-        diag.smart_supported = true;
-    }
-    if let Some(_passed) = get_bool_path(payload, &["smart_status", "passed"]) {
-        diag.smart_supported = true;
-    }
-
-    let smartctl_attrs = parse_smartctl_ata_attributes(payload);
-    if !smartctl_attrs.is_empty() {
-        if diag.smart_attributes.len() < smartctl_attrs.len() {
-            diag.smart_attributes = smartctl_attrs.clone();
-        }
-        diag.ata_smart_available = true;
-        diag.smart_supported = true;
-        diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "SMARTCTL_ATA");
-
-        if diag.temperature_c.is_none() {
-            diag.temperature_c = smartctl_attr_raw(&smartctl_attrs, 194)
-                .or_else(|| smartctl_attr_raw(&smartctl_attrs, 190))
-                .map(raw_temp_to_celsius);
-        }
-        if diag.power_on_hours.is_none() {
-            diag.power_on_hours = smartctl_attr_raw_u64(&smartctl_attrs, 9);
-        }
-        if diag.power_cycle_count.is_none() {
-            diag.power_cycle_count = smartctl_attr_raw_u64(&smartctl_attrs, 12);
-        }
-        if diag.host_writes_total.is_none() {
-            diag.host_writes_total = smartctl_attr_raw_u64(&smartctl_attrs, 241);
-        }
-        if diag.host_reads_total.is_none() {
-            diag.host_reads_total = smartctl_attr_raw_u64(&smartctl_attrs, 242);
-        }
-        if diag.read_errors_total.is_none() {
-            diag.read_errors_total = smartctl_attr_raw_u64(&smartctl_attrs, 1)
-                .or_else(|| smartctl_attr_raw_u64(&smartctl_attrs, 187));
-        }
-        if diag.write_errors_total.is_none() {
-            diag.write_errors_total = smartctl_attr_raw_u64(&smartctl_attrs, 200)
-                .or_else(|| smartctl_attr_raw_u64(&smartctl_attrs, 181));
-        }
-        if let Some(used) = derive_endurance_used_from_attrs(&smartctl_attrs) {
-            if diag.percentage_used.is_none() || diag.percentage_used.unwrap_or(0.0) <= 0.0 {
-                diag.percentage_used = Some(used);
-            }
-        }
-    }
-
-    if payload.get("nvme_smart_health_information_log").is_some() {
-        diag.smart_supported = true;
-        diag.smart_data_source = merge_smart_source(&diag.smart_data_source, "SMARTCTL_NVME");
-    }
-
-    let mut rel = match std::mem::take(&mut diag.reliability) {
-        Value::Object(map) => map,
-        _ => Map::new(),
-    };
-    merge_smartctl_reliability(&mut rel, payload, diag.smart_attributes.len());
-    diag.reliability = Value::Object(rel);
-}
-
-fn merge_smartctl_reliability(
-    reliability: &mut Map<String, Value>,
-    payload: &Value,
-    attr_count: usize,
-) {
-    insert_if_absent(
-        reliability,
-        "Smartctl.Device",
-        get_string_path(payload, &["device", "name"]).map(Value::String),
-    );
-    insert_if_absent(
-        reliability,
-        "Smartctl.DeviceType",
-        get_string_path(payload, &["device", "type"]).map(Value::String),
-    );
-    insert_if_absent(
-        reliability,
-        "Smartctl.Protocol",
-        get_string_path(payload, &["device", "protocol"]).map(Value::String),
-    );
-    insert_if_absent(
-        reliability,
-        "Smartctl.ExitStatus",
-        get_u64_path(payload, &["smartctl", "exit_status"]).map(Value::from),
-    );
-    insert_if_absent(
-        reliability,
-        "Smartctl.RotationRate",
-        get_u64_path(payload, &["rotation_rate"]).map(Value::from),
-    );
-
-    if let Some(capacity) = get_u64_path(payload, &["user_capacity", "bytes"]) {
-        insert_if_absent(
-            reliability,
-            "Smartctl.UserCapacityBytes",
-            Some(Value::from(capacity)),
-        );
-    }
-
-    if payload.get("ata_smart_attributes").is_some() {
-        insert_if_absent(
-            reliability,
-            "Smartctl.AtaAttributeCount",
-            Some(Value::from(attr_count as u64)),
-        );
-    }
-
-    let nvme_fields = [
-        ("Nvme.CriticalWarning", "critical_warning"),
-        ("Nvme.AvailableSpare", "available_spare"),
-        ("Nvme.AvailableSpareThreshold", "available_spare_threshold"),
-        ("Nvme.PercentageUsed", "percentage_used"),
-        ("Nvme.DataUnitsRead", "data_units_read"),
-        ("Nvme.DataUnitsWritten", "data_units_written"),
-        ("Nvme.HostReads", "host_reads"),
-        ("Nvme.HostWrites", "host_writes"),
-        ("Nvme.ControllerBusyTime", "controller_busy_time"),
-        ("Nvme.PowerCycles", "power_cycles"),
-        ("Nvme.PowerOnHours", "power_on_hours"),
-        ("Nvme.UnsafeShutdowns", "unsafe_shutdowns"),
-        ("Nvme.MediaErrors", "media_errors"),
-        ("Nvme.ErrorLogEntries", "num_err_log_entries"),
-    ];
-
-    for (key, field) in nvme_fields {
-        let path = ["nvme_smart_health_information_log", field];
-        if let Some(v) = get_path(payload, &path).and_then(value_to_json_scalar) {
-            insert_if_absent(reliability, key, Some(v));
-        }
-    }
-}
-
-fn parse_smartctl_ata_attributes(payload: &Value) -> Vec<SmartAttribute> {
-    let mut attrs = Vec::new();
-    let Some(table) =
-        get_path(payload, &["ata_smart_attributes", "table"]).and_then(Value::as_array)
-    else {
-        return attrs;
-    };
-
-    for item in table {
-        let Some(id) = get_path(item, &["id"]).and_then(value_to_u64) else {
-            continue;
-        };
-        let name = get_string_path(item, &["name"]).unwrap_or_else(|| format!("Attribute {}", id));
-        let current = get_path(item, &["value"])
-            .and_then(value_to_u64)
-            .map(|v| v as u32);
-        let worst = get_path(item, &["worst"])
-            .and_then(value_to_u64)
-            .map(|v| v as u32);
-        let threshold = get_path(item, &["thresh"])
-            .and_then(value_to_u64)
-            .map(|v| v as u32);
-        let raw = get_path(item, &["raw"])
-            .and_then(|v| get_path(v, &["value"]).or(Some(v)))
-            .and_then(value_to_u64);
-        let raw_hex = raw
-            .map(|v| format!("0x{:X}", v))
-            .unwrap_or_else(String::new);
-
-        attrs.push(SmartAttribute {
-            id: id as u32,
-            name,
-            current,
-            worst,
-            threshold,
-            raw,
-            raw_hex,
-        });
-    }
-    attrs
-}
-
-fn smartctl_attr_raw(attrs: &[SmartAttribute], id: u32) -> Option<f64> {
-    attrs
-        .iter()
-        .find(|a| a.id == id)
-        .and_then(|a| a.raw)
-        .map(|v| v as f64)
-}
-
-fn smartctl_attr_raw_u64(attrs: &[SmartAttribute], id: u32) -> Option<u64> {
-    attrs.iter().find(|a| a.id == id).and_then(|a| a.raw)
-}
-
-fn smartctl_attr_current(attrs: &[SmartAttribute], id: u32) -> Option<u32> {
-    attrs.iter().find(|a| a.id == id).and_then(|a| a.current)
-}
-
-fn derive_endurance_used_from_attrs(attrs: &[SmartAttribute]) -> Option<f64> {
-    // CDI-like priority:
-    // 1) Life-left normalized attributes (231/233/202): used = 100 - current
-    // 2) Vendor fallback: RAW 202 directly represents used% for some SSD families.
-    let life_left = smartctl_attr_current(attrs, 231)
-        .or_else(|| smartctl_attr_current(attrs, 233))
-        .or_else(|| smartctl_attr_current(attrs, 202));
-
-    if let Some(v) = life_left {
-        if v > 0 && v < 100 {
-            return Some((100.0 - v as f64).clamp(0.0, 100.0));
-        }
-    }
-
-    if let Some(raw_used) = smartctl_attr_raw_u64(attrs, 202) {
-        if raw_used > 0 && raw_used <= 100 {
-            return Some(raw_used as f64);
-        }
-    }
-
-    None
-}
-
-fn normalize_endurance_percentage(diagnostics: &mut [DiskDiagnostics]) {
-    for diag in diagnostics.iter_mut() {
-        if is_nvme_diag(diag) {
-            continue;
-        }
-
-        let estimated = derive_endurance_used_from_attrs(&diag.smart_attributes);
-
-        match (diag.percentage_used, estimated) {
-            (Some(v), Some(used)) if v <= 0.0 && used > 0.0 => {
-                diag.percentage_used = Some(used);
-            }
-            (Some(v), None) if v <= 0.0 => {
-                // Placeholder zero from some drivers is misleading for SATA/USB.
-                diag.percentage_used = None;
-            }
-            (None, Some(used)) if used > 0.0 => {
-                diag.percentage_used = Some(used);
-            }
-            _ => {}
-        }
-    }
-}
-
-fn raw_temp_to_celsius(raw: f64) -> f64 {
-    if raw <= 200.0 {
-        raw
-    } else {
-        (raw as u64 & 0xFF) as f64
-    }
-}
-
-fn extract_smartctl_temperature(payload: &Value) -> Option<f64> {
-    if let Some(temp) = get_f64_path(payload, &["temperature", "current"]) {
-        return Some(temp);
-    }
-
-    if let Some(mut nvme_temp) = get_f64_path(
-        payload,
-        &["nvme_smart_health_information_log", "temperature"],
-    ) {
-        if nvme_temp > 200.0 {
-            nvme_temp -= 273.15;
-        }
-        return Some((nvme_temp * 10.0).round() / 10.0);
-    }
-
-    None
-}
-
-fn merge_smart_source(existing: &str, add: &str) -> String {
-    if existing.is_empty() || existing.eq_ignore_ascii_case("none") {
-        return add.to_string();
-    }
-    if existing.split('+').any(|x| x.eq_ignore_ascii_case(add)) {
-        return existing.to_string();
-    }
-    format!("{existing}+{add}")
-}
-
-fn add_note_unique(diag: &mut DiskDiagnostics, note: &str) {
-    if !diag.notes.iter().any(|n| n == note) {
-        diag.notes.push(note.to_string());
-    }
-}
-
-fn insert_if_absent(map: &mut Map<String, Value>, key: &str, value: Option<Value>) {
-    if map.contains_key(key) {
-        return;
-    }
-    if let Some(v) = value {
-        if !v.is_null() {
-            map.insert(key.to_string(), v);
-        }
-    }
-}
-
-fn get_path<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
-    let mut current = value;
-    for segment in path {
-        current = current.get(*segment)?;
-    }
-    Some(current)
-}
-
-fn get_string_path(value: &Value, path: &[&str]) -> Option<String> {
-    let v = get_path(value, path)?;
-    if let Some(s) = v.as_str() {
-        let trimmed = s.trim();
-        if !trimmed.is_empty() {
-            return Some(trimmed.to_string());
-        }
-    }
-    None
-}
-
-fn get_u64_path(value: &Value, path: &[&str]) -> Option<u64> {
-    get_path(value, path).and_then(value_to_u64)
-}
-
-fn get_f64_path(value: &Value, path: &[&str]) -> Option<f64> {
-    get_path(value, path).and_then(value_to_f64)
-}
-
-fn get_bool_path(value: &Value, path: &[&str]) -> Option<bool> {
-    get_path(value, path).and_then(Value::as_bool)
-}
-
-fn value_to_json_scalar(value: &Value) -> Option<Value> {
-    match value {
-        Value::Null => None,
-        Value::Bool(_) | Value::Number(_) | Value::String(_) => Some(value.clone()),
-        Value::Object(_) => {
-            if let Some(n) = value_to_u64(value) {
-                Some(Value::from(n))
-            } else if let Some(f) = value_to_f64(value) {
-                Some(Value::from(f))
-            } else {
-                None
-            }
-        }
-        Value::Array(_) => None,
-    }
-}
-
-fn value_to_u64(value: &Value) -> Option<u64> {
-    match value {
-        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().map(|f| f.max(0.0) as u64)),
-        Value::String(s) => parse_u64_string(s),
-        Value::Object(map) => map.get("value").and_then(value_to_u64),
-        _ => None,
-    }
-}
-
-fn value_to_f64(value: &Value) -> Option<f64> {
-    match value {
-        Value::Number(n) => n.as_f64(),
-        Value::String(s) => parse_f64_string(s),
-        Value::Object(map) => map.get("value").and_then(value_to_f64),
-        _ => None,
-    }
-}
-
-fn parse_u64_string(s: &str) -> Option<u64> {
-    let trimmed = s.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-    if let Ok(n) = trimmed.parse::<u64>() {
-        return Some(n);
-    }
-
-    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
-    if digits.is_empty() {
-        None
-    } else {
-        digits.parse::<u64>().ok()
-    }
-}
-
-fn parse_f64_string(s: &str) -> Option<f64> {
-    let trimmed = s.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-    if let Ok(n) = trimmed.parse::<f64>() {
-        return Some(n);
-    }
-
-    let normalized: String = trimmed
-        .chars()
-        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
-        .collect();
-    if normalized.is_empty() {
-        None
-    } else {
-        normalized.parse::<f64>().ok()
-    }
-}
-
-fn is_masked_serial(serial: &str) -> bool {
-    let normalized: String = serial
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .map(|c| c.to_ascii_uppercase())
-        .collect();
-
-    if normalized.len() < 4 {
-        return true;
-    }
-    if normalized.chars().all(|c| c == '0' || c == 'D') {
-        return true;
-    }
-    if normalized.chars().all(|c| c == '0') {
-        return true;
-    }
-    if normalized.chars().all(|c| c == 'F') {
-        return true;
-    }
-    false
-}
-
 fn parse_disk(v: &serde_json::Value) -> DiskInfo {
     let number = v["Number"].as_u64().unwrap_or(0);
     let name = v["FriendlyName"].as_str().unwrap_or("Unknown").to_string();
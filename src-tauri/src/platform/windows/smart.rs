@@ -4,6 +4,11 @@
 
 pub mod nvme;
 
+use crate::platform::smart::ata::{
+    build_sat_cdb, parse_ata_smart_tables, sat_pattern_cdb_length, SatPattern, READ_ATTRIBUTES,
+    READ_ATTRIBUTE_BUFFER_SIZE, READ_THRESHOLDS, SAT_PATTERN_ATTEMPTS, SMART_CMD,
+};
+pub use crate::platform::smart::ata::{SmartAttribute, SmartData};
 use std::mem;
 use windows::core::PCSTR;
 use windows::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
@@ -12,15 +17,6 @@ use windows::Win32::Storage::FileSystem::{
 };
 use windows::Win32::System::IO::DeviceIoControl;
 
-const READ_ATTRIBUTE_BUFFER_SIZE: usize = 512;
-
-// ATA Commands
-const SMART_CMD: u8 = 0xB0;
-
-// SMART Sub Commands
-const READ_ATTRIBUTES: u8 = 0xD0;
-const READ_THRESHOLDS: u8 = 0xD1;
-
 // IOCTL codes
 const DFP_RECEIVE_DRIVE_DATA: u32 = 0x0007C088;
 const IOCTL_ATA_PASS_THROUGH: u32 = 0x0004D02C;
@@ -33,6 +29,14 @@ const GENERIC_READ: u32 = 0x8000_0000;
 const GENERIC_WRITE: u32 = 0x4000_0000;
 const SCSI_IOCTL_DATA_IN: u8 = 1;
 
+/// Which Windows path answered the SMART read.
+#[derive(Debug, Clone, Copy)]
+pub enum SmartReadMethod {
+    AtaPassThrough,
+    PhysicalDrive,
+    SatBridge,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 struct IdeRegs {
@@ -119,47 +123,10 @@ struct ScsiPassThroughWithBuffers {
     data_buf: [u8; 512],
 }
 
-pub struct SmartData {
-    pub read_method: SmartReadMethod,
-    pub thresholds_available: bool,
-    pub attributes: Vec<SmartAttribute>,
-    pub temperature: Option<i32>,
-    pub power_on_hours: Option<u64>,
-    pub power_cycle_count: Option<u64>,
-}
-
-#[derive(Debug, Clone)]
-pub struct SmartAttribute {
-    pub id: u8,
-    pub current: u8,
-    pub worst: u8,
-    pub threshold: u8,
-    pub raw: u64,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum SmartReadMethod {
-    AtaPassThrough,
-    PhysicalDrive,
-    SatBridge,
-}
-
 pub struct DiskHandle {
     handle: HANDLE,
 }
 
-#[derive(Clone, Copy)]
-enum SatPattern {
-    Ata12(u8),
-    Ata16(u8),
-    Sunplus,
-    IoData,
-    Logitec,
-    Prolific,
-    JMicron,
-    Cypress,
-}
-
 impl DiskHandle {
     pub fn open(physical_drive_id: u32) -> Result<Self, String> {
         let path = format!("\\\\.\\PhysicalDrive{}\0", physical_drive_id);
@@ -189,19 +156,19 @@ impl DiskHandle {
         ))
     }
 
-    pub fn read_smart_data(&self) -> Result<SmartData, String> {
+    pub fn read_smart_data(&self) -> Result<(SmartData, SmartReadMethod), String> {
         // Try different methods in order of preference
         if let Ok(data) = self.read_smart_ata_pass_through() {
-            return Ok(data);
+            return Ok((data, SmartReadMethod::AtaPassThrough));
         }
 
         if let Ok(data) = self.read_smart_physical_drive() {
-            return Ok(data);
+            return Ok((data, SmartReadMethod::PhysicalDrive));
         }
 
         // SAT over SCSI bridge fallback (common for USB-SATA enclosures)
         if let Ok(data) = self.read_smart_sat() {
-            return Ok(data);
+            return Ok((data, SmartReadMethod::SatBridge));
         }
 
         Err("Failed to read SMART data using any method".to_string())
@@ -213,11 +180,7 @@ impl DiskHandle {
 
         // Threshold table is optional on some bridges/controllers.
         let threshold_data = self.send_smart_command(READ_THRESHOLDS).ok();
-        parse_ata_smart_tables(
-            &attr_data,
-            threshold_data.as_deref(),
-            SmartReadMethod::PhysicalDrive,
-        )
+        parse_ata_smart_tables(&attr_data, threshold_data.as_deref())
     }
 
     fn read_smart_ata_pass_through(&self) -> Result<SmartData, String> {
@@ -227,21 +190,13 @@ impl DiskHandle {
             .send_ata_pass_through_command(SMART_CMD, READ_THRESHOLDS)
             .ok();
 
-        parse_ata_smart_tables(
-            &attr_data,
-            threshold_data.as_deref(),
-            SmartReadMethod::AtaPassThrough,
-        )
+        parse_ata_smart_tables(&attr_data, threshold_data.as_deref())
     }
 
     fn read_smart_sat(&self) -> Result<SmartData, String> {
         let attr_data = self.send_sat_smart_command(READ_ATTRIBUTES)?;
         let threshold_data = self.send_sat_smart_command(READ_THRESHOLDS).ok();
-        parse_ata_smart_tables(
-            &attr_data,
-            threshold_data.as_deref(),
-            SmartReadMethod::SatBridge,
-        )
+        parse_ata_smart_tables(&attr_data, threshold_data.as_deref())
     }
 
     fn send_smart_command(&self, sub_command: u8) -> Result<Vec<u8>, String> {
@@ -339,22 +294,9 @@ impl DiskHandle {
     }
 
     fn send_sat_smart_command(&self, sub_command: u8) -> Result<Vec<u8>, String> {
-        // CrystalDiskInfo style fallback attempts for bridge variations.
-        let attempts = [
-            SatPattern::Ata12(0x2E),
-            SatPattern::Ata16(0x2E),
-            SatPattern::Ata12(0x0E),
-            SatPattern::Ata16(0x0E),
-            SatPattern::JMicron,
-            SatPattern::Sunplus,
-            SatPattern::IoData,
-            SatPattern::Logitec,
-            SatPattern::Prolific,
-            SatPattern::Cypress,
-        ];
         let mut last_err = String::new();
 
-        for pattern in attempts {
+        for pattern in SAT_PATTERN_ATTEMPTS {
             match self.send_sat_smart_command_once(sub_command, pattern) {
                 Ok(data) if data.iter().any(|b| *b != 0) => return Ok(data),
                 Ok(_) => {
@@ -433,207 +375,6 @@ impl Drop for DiskHandle {
     }
 }
 
-impl SmartData {
-    fn from_attributes(
-        attributes: &[SmartAttribute],
-        read_method: SmartReadMethod,
-        thresholds_available: bool,
-    ) -> Self {
-        let temperature = attributes
-            .iter()
-            .find(|a| a.id == 194 || a.id == 190)
-            .map(|a| {
-                let raw = a.raw;
-                if raw <= 200 {
-                    raw as i32
-                } else {
-                    (raw & 0xFF) as i32
-                }
-            });
-
-        let power_on_hours = attributes.iter().find(|a| a.id == 9).map(|a| a.raw);
-
-        let power_cycle_count = attributes.iter().find(|a| a.id == 12).map(|a| a.raw);
-
-        SmartData {
-            read_method,
-            thresholds_available,
-            attributes: attributes.to_vec(),
-            temperature,
-            power_on_hours,
-            power_cycle_count,
-        }
-    }
-
-    pub fn get_attribute(&self, id: u8) -> Option<&SmartAttribute> {
-        self.attributes.iter().find(|a| a.id == id)
-    }
-}
-
-fn parse_ata_smart_tables(
-    attr_data: &[u8],
-    threshold_data: Option<&[u8]>,
-    method: SmartReadMethod,
-) -> Result<SmartData, String> {
-    if attr_data.len() < 362 {
-        return Err("SMART attribute table payload too small".to_string());
-    }
-
-    let threshold_data = threshold_data.filter(|buf| buf.len() >= 362);
-    let thresholds_available = threshold_data.is_some();
-    let mut attributes = Vec::new();
-    for i in (2..362).step_by(12) {
-        let id = attr_data[i];
-        if id == 0 {
-            continue;
-        }
-
-        let current = attr_data[i + 3];
-        let worst = attr_data[i + 4];
-        let raw = u64::from_le_bytes([
-            attr_data[i + 5],
-            attr_data[i + 6],
-            attr_data[i + 7],
-            attr_data[i + 8],
-            attr_data[i + 9],
-            attr_data[i + 10],
-            0,
-            0,
-        ]);
-
-        let mut threshold = 0u8;
-        if let Some(tables) = threshold_data {
-            for j in (2..362).step_by(12) {
-                if tables[j] == id {
-                    threshold = tables[j + 1];
-                    break;
-                }
-            }
-        }
-
-        attributes.push(SmartAttribute {
-            id,
-            current,
-            worst,
-            threshold,
-            raw,
-        });
-    }
-
-    if attributes.is_empty() {
-        return Err("SMART attributes are empty".to_string());
-    }
-
-    Ok(SmartData::from_attributes(
-        &attributes,
-        method,
-        thresholds_available,
-    ))
-}
-
-fn sat_pattern_cdb_length(pattern: SatPattern) -> u8 {
-    match pattern {
-        SatPattern::Ata16(_) | SatPattern::Prolific | SatPattern::Cypress => 16,
-        SatPattern::Logitec => 10,
-        _ => 12,
-    }
-}
-
-fn build_sat_cdb(cdb: &mut [u8; 16], sub_command: u8, pattern: SatPattern) {
-    let target = 0xA0_u8;
-
-    match pattern {
-        SatPattern::Ata12(flags) => {
-            cdb[0] = 0xA1;
-            cdb[1] = 0x08;
-            cdb[2] = flags;
-            cdb[3] = sub_command;
-            cdb[4] = 0x01;
-            cdb[5] = 0x01;
-            cdb[6] = 0x4F;
-            cdb[7] = 0xC2;
-            cdb[8] = target;
-            cdb[9] = SMART_CMD;
-        }
-        SatPattern::Ata16(flags) => {
-            cdb[0] = 0x85;
-            cdb[1] = 0x08;
-            cdb[2] = flags;
-            cdb[4] = sub_command;
-            cdb[6] = 0x01;
-            cdb[10] = 0x4F;
-            cdb[12] = 0xC2;
-            cdb[13] = target;
-            cdb[14] = SMART_CMD;
-        }
-        SatPattern::Sunplus => {
-            cdb[0] = 0xF8;
-            cdb[2] = 0x22;
-            cdb[3] = 0x10;
-            cdb[4] = 0x01;
-            cdb[5] = sub_command;
-            cdb[6] = 0x01;
-            cdb[8] = 0x4F;
-            cdb[9] = 0xC2;
-            cdb[10] = target;
-            cdb[11] = SMART_CMD;
-        }
-        SatPattern::IoData => {
-            cdb[0] = 0xE3;
-            cdb[2] = sub_command;
-            cdb[5] = 0x4F;
-            cdb[6] = 0xC2;
-            cdb[7] = target;
-            cdb[8] = SMART_CMD;
-        }
-        SatPattern::Logitec => {
-            cdb[0] = 0xE0;
-            cdb[2] = sub_command;
-            cdb[5] = 0x4F;
-            cdb[6] = 0xC2;
-            cdb[7] = target;
-            cdb[8] = SMART_CMD;
-            cdb[9] = 0x4C;
-        }
-        SatPattern::Prolific => {
-            cdb[0] = 0xD8;
-            cdb[1] = 0x15;
-            cdb[3] = sub_command;
-            cdb[4] = 0x06;
-            cdb[5] = 0x7B;
-            cdb[8] = 0x02;
-            cdb[10] = 0x01;
-            cdb[12] = 0x4F;
-            cdb[13] = 0xC2;
-            cdb[14] = target;
-            cdb[15] = SMART_CMD;
-        }
-        SatPattern::JMicron => {
-            cdb[0] = 0xDF;
-            cdb[1] = 0x10;
-            cdb[3] = 0x02;
-            cdb[5] = sub_command;
-            cdb[6] = 0x01;
-            cdb[7] = 0x01;
-            cdb[8] = 0x4F;
-            cdb[9] = 0xC2;
-            cdb[10] = target;
-            cdb[11] = SMART_CMD;
-        }
-        SatPattern::Cypress => {
-            cdb[0] = 0x24;
-            cdb[1] = 0x24;
-            cdb[3] = 0xBE;
-            cdb[4] = 0x01;
-            cdb[6] = sub_command;
-            cdb[9] = 0x4F;
-            cdb[10] = 0xC2;
-            cdb[11] = target;
-            cdb[12] = SMART_CMD;
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
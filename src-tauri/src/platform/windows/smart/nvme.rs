@@ -3,6 +3,11 @@
 // Original Author: hiyohiyo (https://crystalmark.info/)
 
 use std::mem;
+
+use crate::platform::smart::nvme::{
+    parse_identify_controller, parse_smart_data, NVME_SMART_LOG_SIZE,
+};
+pub use crate::platform::smart::nvme::{NVMeIdentifyInfo, NVMeSmartData};
 use windows::core::PCSTR;
 use windows::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
 use windows::Win32::Storage::FileSystem::{
@@ -52,35 +57,6 @@ struct StorageQueryWithBuffer {
     buffer: [u8; 4096],
 }
 
-#[derive(Debug, Clone)]
-pub struct NVMeSmartData {
-    pub critical_warning: u8,
-    pub temperature: i32,
-    pub available_spare: u8,
-    pub available_spare_threshold: u8,
-    pub percentage_used: u8,
-    pub data_units_read: u128,
-    pub data_units_written: u128,
-    pub host_read_commands: u128,
-    pub host_write_commands: u128,
-    pub controller_busy_time: u128,
-    pub power_cycles: u128,
-    pub power_on_hours: u128,
-    pub unsafe_shutdowns: u128,
-    pub media_errors: u128,
-    pub num_err_log_entries: u128,
-    pub warning_temp_time: u32,
-    pub critical_temp_time: u32,
-    pub temp_sensors: [i32; 8],
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct NVMeIdentifyInfo {
-    pub model: String,
-    pub serial_number: String,
-    pub firmware_version: String,
-}
-
 pub struct NVMeHandle {
     handle: HANDLE,
 }
@@ -129,7 +105,7 @@ impl NVMeHandle {
                     4096,
                 ) {
                     Ok(data) if data.iter().take(512).any(|b| *b != 0) => {
-                        return Self::parse_smart_data(&data[..512])
+                        return parse_smart_data(&data[..NVME_SMART_LOG_SIZE])
                     }
                     Ok(_) => {}
                     Err(e) => last_err = e,
@@ -219,59 +195,6 @@ impl NVMeHandle {
             Ok(query_buffer.buffer[..copy_len].to_vec())
         }
     }
-
-    fn parse_smart_data(buffer: &[u8]) -> Result<NVMeSmartData, String> {
-        if buffer.len() < 512 {
-            return Err("NVMe SMART buffer too small".to_string());
-        }
-
-        let temp_kelvin = u16::from_le_bytes([buffer[1], buffer[2]]) as i32;
-        let temperature = if (1..0x7FFF).contains(&temp_kelvin) {
-            temp_kelvin - 273
-        } else {
-            -1000
-        };
-
-        let mut temp_sensors = [-1000_i32; 8];
-        for i in 0..8 {
-            let offset = 200 + i * 2;
-            let sensor_k = u16::from_le_bytes([buffer[offset], buffer[offset + 1]]) as i32;
-            if (1..0x7FFF).contains(&sensor_k) {
-                temp_sensors[i] = sensor_k - 273;
-            }
-        }
-
-        Ok(NVMeSmartData {
-            critical_warning: buffer[0],
-            temperature,
-            available_spare: buffer[3],
-            available_spare_threshold: buffer[4],
-            percentage_used: buffer[5],
-            data_units_read: parse_u128_le_at(buffer, 32),
-            data_units_written: parse_u128_le_at(buffer, 48),
-            host_read_commands: parse_u128_le_at(buffer, 64),
-            host_write_commands: parse_u128_le_at(buffer, 80),
-            controller_busy_time: parse_u128_le_at(buffer, 96),
-            power_cycles: parse_u128_le_at(buffer, 112),
-            power_on_hours: parse_u128_le_at(buffer, 128),
-            unsafe_shutdowns: parse_u128_le_at(buffer, 144),
-            media_errors: parse_u128_le_at(buffer, 160),
-            num_err_log_entries: parse_u128_le_at(buffer, 176),
-            warning_temp_time: u32::from_le_bytes([
-                buffer[192],
-                buffer[193],
-                buffer[194],
-                buffer[195],
-            ]),
-            critical_temp_time: u32::from_le_bytes([
-                buffer[196],
-                buffer[197],
-                buffer[198],
-                buffer[199],
-            ]),
-            temp_sensors,
-        })
-    }
 }
 
 impl Drop for NVMeHandle {
@@ -281,28 +204,3 @@ impl Drop for NVMeHandle {
         }
     }
 }
-
-fn parse_u128_le_at(buf: &[u8], offset: usize) -> u128 {
-    if offset + 16 > buf.len() {
-        return 0;
-    }
-    let mut bytes = [0u8; 16];
-    bytes.copy_from_slice(&buf[offset..offset + 16]);
-    u128::from_le_bytes(bytes)
-}
-
-fn parse_ascii_field(data: &[u8]) -> String {
-    let s = String::from_utf8_lossy(data).into_owned();
-    s.trim_matches(char::from(0)).trim().to_string()
-}
-
-fn parse_identify_controller(data: &[u8]) -> NVMeIdentifyInfo {
-    if data.len() < 72 {
-        return NVMeIdentifyInfo::default();
-    }
-    NVMeIdentifyInfo {
-        serial_number: parse_ascii_field(&data[4..24]),
-        model: parse_ascii_field(&data[24..64]),
-        firmware_version: parse_ascii_field(&data[64..72]),
-    }
-}
@@ -1,9 +1,11 @@
 //! Benchmark service aligned with WTGBench methodology.
 
+use crate::utils::latency::{LatencyHistogram, LatencySummary};
 use crate::Result;
 use rand::{Rng, RngCore};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::{
@@ -13,6 +15,16 @@ use std::sync::{
 use std::thread;
 use std::time::{Duration, Instant};
 
+#[cfg(target_os = "linux")]
+use crate::platform::linux::{AioCompletion, AioOp, AioQueue};
+#[cfg(target_os = "linux")]
+use nix::libc;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "macos")]
+use std::os::unix::io::AsRawFd;
 #[cfg(target_os = "windows")]
 use std::os::windows::fs::OpenOptionsExt;
 #[cfg(target_os = "windows")]
@@ -24,10 +36,14 @@ use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
 const FILE_FLAG_NO_BUFFERING: u32 = 0x20000000;
 #[cfg(target_os = "windows")]
 const FILE_FLAG_WRITE_THROUGH: u32 = 0x80000000;
+#[cfg(target_os = "windows")]
+const FILE_SHARE_READ: u32 = 0x00000001;
 
 const MB: u64 = 1024 * 1024;
 const GIB: f64 = 1_073_741_824.0;
 const BLOCK_SIZE: usize = 4096;
+// Buffer alignment accepted by O_DIRECT and FILE_FLAG_NO_BUFFERING on any sector size.
+const IO_ALIGN: usize = 4096;
 const RANDOM_REGION_BYTES: u64 = 512 * MB;
 const MAX_IO_BYTES: usize = 16 * 1024 * 1024;
 
@@ -56,9 +72,30 @@ const FULL_IO_BYTES: u64 = 64 * MB;
 const FULL_STEP_BYTES: u64 = 1024 * MB;
 const FULL_RESERVED_BYTES: u64 = 100 * MB;
 
+// Operations at least this slow count as stalls unless the config overrides it.
+const DEFAULT_STALL_THRESHOLD_MS: u64 = 500;
+
+// Read benchmark defaults: reads come from a prefilled region with caches bypassed.
+const READ_REGION_BYTES: u64 = RANDOM_REGION_BYTES;
+const READ_SEQ_DURATION: Duration = Duration::from_secs(10);
+const READ_QUICK_SEQ_DURATION: Duration = Duration::from_secs(5);
+const READ_MT_LEVEL_DURATION: Duration = Duration::from_secs(10);
+const READ_CACHE_DROP_EVERY_OPS: u64 = 256;
+
+// Queue-depth sweep defaults (Linux native AIO).
+#[cfg(target_os = "linux")]
+const QD_LEVELS: [u32; 4] = [1, 4, 16, 32];
+#[cfg(target_os = "linux")]
+const QD_LEVEL_DURATION: Duration = Duration::from_secs(10);
+#[cfg(target_os = "linux")]
+const QD_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
 // WTGB scenario defaults.
 const SCENARIO_LINE_DURATION: Duration = Duration::from_secs(5);
 static BENCHMARK_CANCEL_FLAG: AtomicBool = AtomicBool::new(false);
+// Cleared for a run whose target filesystem rejects O_DIRECT/F_NOCACHE.
+static BENCHMARK_DIRECT_IO: AtomicBool = AtomicBool::new(true);
+static BENCHMARK_STALL_THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_STALL_THRESHOLD_MS);
 
 pub fn request_cancel() {
     BENCHMARK_CANCEL_FLAG.store(true, Ordering::Relaxed);
@@ -72,6 +109,12 @@ fn is_cancelled() -> bool {
     BENCHMARK_CANCEL_FLAG.load(Ordering::Relaxed)
 }
 
+fn new_latency_histogram() -> LatencyHistogram {
+    LatencyHistogram::new(Duration::from_millis(
+        BENCHMARK_STALL_THRESHOLD_MS.load(Ordering::Relaxed),
+    ))
+}
+
 fn ensure_not_cancelled() -> Result<()> {
     if is_cancelled() {
         Err(crate::AppError::SystemError(
@@ -91,6 +134,35 @@ pub struct BenchmarkResult {
     pub write_4k_adjusted: Option<f64>,
     pub write_4k_samples: Vec<TrendPoint>,
     pub thread_results: Vec<ThreadResult>,
+    #[serde(default)]
+    pub read_seq: f64, // MB/s
+    #[serde(default)]
+    pub read_4k: f64, // MB/s (QD1)
+    #[serde(default)]
+    pub read_seq_samples: Vec<Sample>,
+    #[serde(default)]
+    pub read_4k_samples: Vec<TrendPoint>,
+    #[serde(default)]
+    pub read_thread_results: Vec<ThreadResult>,
+    #[serde(default)]
+    pub write_4k_latency: Option<LatencySummary>,
+    #[serde(default)]
+    pub read_4k_latency: Option<LatencySummary>,
+    #[serde(default)]
+    pub scenario_read_latency: Option<LatencySummary>,
+    #[serde(default)]
+    pub scenario_write_latency: Option<LatencySummary>,
+    /// 4K random read/write at fixed queue depths (Linux only)
+    #[serde(default)]
+    pub queue_depth_results: Vec<QueueDepthResult>,
+    /// Why the queue-depth sweep was skipped, if it was
+    #[serde(default)]
+    pub queue_depth_skip_reason: Option<String>,
+    /// Whether the run bypassed the OS cache; false when the filesystem rejected it
+    #[serde(default)]
+    pub unbuffered_io: bool,
+    #[serde(default)]
+    pub io_fallback_reason: Option<String>,
     pub full_seq_samples: Vec<Sample>,
     pub scenario_samples: Vec<TrendPoint>,
     pub scenario_total_io: Option<u64>,
@@ -107,6 +179,15 @@ pub struct ThreadResult {
     pub mb_s: f64,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct QueueDepthResult {
+    pub queue_depth: u32,
+    pub read_iops: f64,
+    pub read_latency_us: f64, // mean completion latency
+    pub write_iops: f64,
+    pub write_latency_us: f64,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct Sample {
     pub t_ms: u64,
@@ -124,6 +205,16 @@ pub struct TrendPoint {
 pub struct BenchmarkConfig {
     pub target_path: String,
     pub mode: String, // quick | multithread | fullwrite | full | scenario
+    /// Latency at which a single operation counts as a stall (default 500 ms)
+    #[serde(default)]
+    pub stall_threshold_ms: Option<u64>,
+}
+
+/// Operations and per-operation latencies of one scenario worker.
+struct ScenarioWorkerStats {
+    ops: u64,
+    read_latency: LatencyHistogram,
+    write_latency: LatencyHistogram,
 }
 
 #[derive(Debug, Clone)]
@@ -148,13 +239,140 @@ fn open_benchmark_file(path: &str) -> Result<File> {
     }
 
     #[cfg(not(target_os = "windows"))]
+    {
+        let mut options = OpenOptions::new();
+        options.create(true).write(true).read(true);
+        Ok(open_uncached(&mut options, path)?)
+    }
+}
+
+/// Read-only handle on a benchmark file; unlike the writer it can be opened by several
+/// threads at once.
+fn open_benchmark_reader(path: &str) -> Result<File> {
+    #[cfg(target_os = "windows")]
     {
         let file = OpenOptions::new()
-            .create(true)
-            .write(true)
             .read(true)
+            .share_mode(FILE_SHARE_READ)
+            .custom_flags(FILE_FLAG_NO_BUFFERING)
             .open(path)?;
-        Ok(file)
+        return Ok(file);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        Ok(open_uncached(&mut options, path)?)
+    }
+}
+
+/// Open with the page cache bypassed: O_DIRECT on Linux, F_NOCACHE on macOS. Falls
+/// back to buffered I/O when [`configure_direct_io`] found the filesystem rejects it.
+#[cfg(target_os = "linux")]
+fn open_uncached(options: &mut OpenOptions, path: &str) -> std::io::Result<File> {
+    if BENCHMARK_DIRECT_IO.load(Ordering::Relaxed) {
+        options.custom_flags(libc::O_DIRECT);
+    }
+    options.open(path)
+}
+
+#[cfg(target_os = "macos")]
+fn open_uncached(options: &mut OpenOptions, path: &str) -> std::io::Result<File> {
+    let file = options.open(path)?;
+    if BENCHMARK_DIRECT_IO.load(Ordering::Relaxed)
+        && unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } == -1
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+fn open_uncached(options: &mut OpenOptions, path: &str) -> std::io::Result<File> {
+    options.open(path)
+}
+
+/// Probe whether the target filesystem accepts cache-bypassing I/O with one aligned
+/// write and read, and fall back to buffered I/O for this run if it does not. Returns
+/// the reason for the fallback.
+fn configure_direct_io(target_dir: &Path) -> Result<Option<String>> {
+    #[cfg(target_os = "windows")]
+    {
+        // FILE_FLAG_NO_BUFFERING works on every filesystem Windows mounts.
+        let _ = target_dir;
+        return Ok(None);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        if cfg!(not(any(target_os = "linux", target_os = "macos"))) {
+            BENCHMARK_DIRECT_IO.store(false, Ordering::Relaxed);
+            return Ok(Some(
+                "Unbuffered I/O is not available on this platform".to_string(),
+            ));
+        }
+
+        BENCHMARK_DIRECT_IO.store(true, Ordering::Relaxed);
+        let probe = target_dir.join("wtg_bench.probe");
+        let outcome = (|| -> std::io::Result<()> {
+            let mut options = OpenOptions::new();
+            options.create(true).write(true).read(true);
+            let mut file = open_uncached(&mut options, &probe.to_string_lossy())?;
+            let mut block = AlignedBuf::new(BLOCK_SIZE);
+            fill_random(&mut block);
+            file.write_all(&block)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut block)?;
+            Ok(())
+        })();
+        let _ = std::fs::remove_file(&probe);
+
+        match outcome {
+            Ok(()) => Ok(None),
+            // EINVAL is how Linux filesystems such as tmpfs reject O_DIRECT.
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                BENCHMARK_DIRECT_IO.store(false, Ordering::Relaxed);
+                Ok(Some(format!(
+                    "Filesystem rejected unbuffered I/O ({}); results include OS cache effects",
+                    e
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Heap buffer aligned for O_DIRECT and FILE_FLAG_NO_BUFFERING transfers.
+struct AlignedBuf {
+    storage: Vec<u8>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuf {
+    fn new(len: usize) -> Self {
+        let storage = vec![0u8; len + IO_ALIGN];
+        let offset = storage.as_ptr().align_offset(IO_ALIGN);
+        Self {
+            storage,
+            offset,
+            len,
+        }
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.storage[self.offset..self.offset + self.len]
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.storage[self.offset..self.offset + self.len]
     }
 }
 
@@ -163,7 +381,7 @@ fn fill_random(buf: &mut [u8]) {
 }
 
 fn ensure_file_region(file: &mut File, region_bytes: u64) -> Result<()> {
-    let mut tail = [0u8; BLOCK_SIZE];
+    let mut tail = AlignedBuf::new(BLOCK_SIZE);
     fill_random(&mut tail);
     let end = region_bytes.saturating_sub(BLOCK_SIZE as u64);
     file.seek(SeekFrom::Start(end))?;
@@ -172,6 +390,45 @@ fn ensure_file_region(file: &mut File, region_bytes: u64) -> Result<()> {
     Ok(())
 }
 
+/// Evict the file's pages from the page cache so the following reads hit the device.
+/// Only matters when the filesystem rejected O_DIRECT; Windows opens benchmark files
+/// unbuffered, so there is nothing to drop.
+#[cfg(target_os = "linux")]
+fn drop_cached_pages(file: &File) {
+    use nix::fcntl::{posix_fadvise, PosixFadviseAdvice};
+    use std::os::unix::io::AsRawFd;
+    let _ = posix_fadvise(
+        file.as_raw_fd(),
+        0,
+        0,
+        PosixFadviseAdvice::POSIX_FADV_DONTNEED,
+    );
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_cached_pages(_file: &File) {}
+
+/// Make sure `[0, region)` of the benchmark file holds real data rather than holes, so
+/// reads measure the flash. `filled` bytes at the start are already written (e.g. by
+/// the sequential write phase) and are kept.
+fn prefill_read_region(path: &str, filled: u64, region: u64) -> Result<()> {
+    let mut file = open_benchmark_file(path)?;
+    let mut chunk = AlignedBuf::new(WTGB_SEQ_CHUNK_BYTES as usize);
+    fill_random(&mut chunk);
+
+    let mut offset = (filled / WTGB_SEQ_CHUNK_BYTES) * WTGB_SEQ_CHUNK_BYTES;
+    while offset < region {
+        ensure_not_cancelled()?;
+        let len = (region - offset).min(WTGB_SEQ_CHUNK_BYTES) as usize;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&chunk[..len])?;
+        offset += len as u64;
+    }
+    file.sync_data()?;
+    drop_cached_pages(&file);
+    Ok(())
+}
+
 fn round1(v: f64) -> f64 {
     (v * 10.0).round() / 10.0
 }
@@ -259,7 +516,7 @@ fn sequential_wtgb_with_ring(
     let _ = std::fs::remove_file(path);
     let mut file = open_benchmark_file(path)?;
 
-    let mut chunk = vec![0u8; WTGB_SEQ_CHUNK_BYTES as usize];
+    let mut chunk = AlignedBuf::new(WTGB_SEQ_CHUNK_BYTES as usize);
     fill_random(&mut chunk);
     let safe_ring = ring_bytes.max(WTGB_SEQ_CHUNK_BYTES);
     ensure_file_region(&mut file, safe_ring)?;
@@ -297,20 +554,23 @@ fn sequential_wtgb(path: &str, duration: Duration) -> Result<(f64, Vec<Sample>,
     sequential_wtgb_with_ring(path, duration, WTGB_SEQ_RING_BYTES)
 }
 
+/// 4K QD1 random writes; each write's latency includes the flush it triggers, as that
+/// is what the writing application waits for.
 fn random_4k_single_wtgb_with_points(
     path: &str,
     point_count: usize,
-) -> Result<(f64, f64, Vec<TrendPoint>)> {
+) -> Result<(f64, f64, Vec<TrendPoint>, LatencyHistogram)> {
     let mut file = open_benchmark_file(path)?;
     ensure_file_region(&mut file, RANDOM_REGION_BYTES)?;
     let points_target = point_count.max(1);
 
     let mut rng = rand::thread_rng();
-    let mut block = [0u8; BLOCK_SIZE];
+    let mut block = AlignedBuf::new(BLOCK_SIZE);
     fill_random(&mut block);
 
     let mut points = Vec::with_capacity(points_target);
     let mut trend = Vec::with_capacity(points_target);
+    let mut latency = new_latency_histogram();
     let mut window_ops: u64 = 0;
     let mut pending_sync_ops: u64 = 0;
     let mut window_start = Instant::now();
@@ -320,6 +580,7 @@ fn random_4k_single_wtgb_with_points(
         ensure_not_cancelled()?;
         let idx = rng.gen_range(0..(RANDOM_REGION_BYTES / BLOCK_SIZE as u64));
         let pos = idx * BLOCK_SIZE as u64;
+        let op_start = Instant::now();
         file.seek(SeekFrom::Start(pos))?;
         file.write_all(&block)?;
         window_ops += 1;
@@ -328,6 +589,7 @@ fn random_4k_single_wtgb_with_points(
             file.sync_data()?;
             pending_sync_ops = 0;
         }
+        latency.record(op_start.elapsed());
 
         if window_start.elapsed() >= WTGB_4K_SAMPLE_INTERVAL {
             if pending_sync_ops > 0 {
@@ -361,10 +623,10 @@ fn random_4k_single_wtgb_with_points(
         }
     }
 
-    Ok((avg, mean(&adjusted), trend))
+    Ok((avg, mean(&adjusted), trend, latency))
 }
 
-fn random_4k_single_wtgb(path: &str) -> Result<(f64, f64, Vec<TrendPoint>)> {
+fn random_4k_single_wtgb(path: &str) -> Result<(f64, f64, Vec<TrendPoint>, LatencyHistogram)> {
     random_4k_single_wtgb_with_points(path, WTGB_4K_POINTS)
 }
 
@@ -387,7 +649,7 @@ fn random_4k_multi_once(path: &str, threads: u32, duration: Duration) -> Result<
             let mut file = open_benchmark_file(&file_path)?;
             ensure_file_region(&mut file, RANDOM_REGION_BYTES)?;
 
-            let mut block = [0u8; BLOCK_SIZE];
+            let mut block = AlignedBuf::new(BLOCK_SIZE);
             fill_random(&mut block);
             let mut pending_sync_ops: u64 = 0;
 
@@ -482,6 +744,357 @@ fn random_4k_multithread_curve(path: &str) -> Result<Vec<ThreadResult>> {
     Ok(out)
 }
 
+fn sequential_read(path: &str, duration: Duration, region: u64) -> Result<(f64, Vec<Sample>)> {
+    let mut file = open_benchmark_reader(path)?;
+    drop_cached_pages(&file);
+    let chunk_bytes = WTGB_SEQ_CHUNK_BYTES.min(region);
+    let mut chunk = AlignedBuf::new(chunk_bytes as usize);
+    let ring = (region / chunk_bytes).max(1) * chunk_bytes;
+
+    let start = Instant::now();
+    let mut offset: u64 = 0;
+    let mut bytes_read: u64 = 0;
+    let mut speeds = Vec::new();
+    let mut samples = Vec::new();
+
+    while start.elapsed() < duration {
+        ensure_not_cancelled()?;
+        let chunk_start = Instant::now();
+        file.seek(SeekFrom::Start(offset % ring))?;
+        file.read_exact(&mut chunk)?;
+
+        let sec = chunk_start.elapsed().as_secs_f64().max(0.001);
+        let mbps = (chunk_bytes as f64 / 1024.0 / 1024.0) / sec;
+        bytes_read += chunk_bytes;
+        offset += chunk_bytes;
+        // Once the ring wraps, the chunk would otherwise come back from memory.
+        drop_cached_pages(&file);
+        speeds.push(mbps);
+        samples.push(Sample {
+            t_ms: start.elapsed().as_millis() as u64,
+            value: mbps,
+            x_gb: bytes_read as f64 / GIB,
+        });
+    }
+
+    Ok((mean(&speeds), samples))
+}
+
+fn random_4k_read_single_with_points(
+    path: &str,
+    region: u64,
+    point_count: usize,
+) -> Result<(f64, Vec<TrendPoint>, LatencyHistogram)> {
+    let mut file = open_benchmark_reader(path)?;
+    drop_cached_pages(&file);
+    let points_target = point_count.max(1);
+    let blocks = (region / BLOCK_SIZE as u64).max(1);
+
+    let mut rng = rand::thread_rng();
+    let mut block = AlignedBuf::new(BLOCK_SIZE);
+
+    let mut points = Vec::with_capacity(points_target);
+    let mut trend = Vec::with_capacity(points_target);
+    let mut latency = new_latency_histogram();
+    let mut window_ops: u64 = 0;
+    let mut window_start = Instant::now();
+    let mut elapsed_sec = 0.0;
+
+    while points.len() < points_target {
+        ensure_not_cancelled()?;
+        let pos = rng.gen_range(0..blocks) * BLOCK_SIZE as u64;
+        let op_start = Instant::now();
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+        latency.record(op_start.elapsed());
+        window_ops += 1;
+        if window_ops.is_multiple_of(READ_CACHE_DROP_EVERY_OPS) {
+            drop_cached_pages(&file);
+        }
+
+        if window_start.elapsed() >= WTGB_4K_SAMPLE_INTERVAL {
+            let sec = window_start.elapsed().as_secs_f64().max(0.001);
+            let mbps = ((window_ops * BLOCK_SIZE as u64) as f64 / 1024.0 / 1024.0) / sec;
+            points.push(mbps);
+            elapsed_sec += sec;
+            trend.push(TrendPoint {
+                x: elapsed_sec,
+                y: mbps,
+            });
+            drop_cached_pages(&file);
+            window_ops = 0;
+            window_start = Instant::now();
+        }
+    }
+
+    Ok((mean(&points), trend, latency))
+}
+
+fn random_4k_read_multi_once(
+    path: &str,
+    region: u64,
+    threads: u32,
+    duration: Duration,
+) -> Result<f64> {
+    let start_flag = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let total_bytes = Arc::new(AtomicU64::new(0));
+    let blocks = (region / BLOCK_SIZE as u64).max(1);
+    let mut handles = Vec::new();
+
+    for _ in 0..threads {
+        let f_start = start_flag.clone();
+        let f_stop = stop_flag.clone();
+        let f_total = total_bytes.clone();
+        let file_path = path.to_string();
+
+        handles.push(thread::spawn(move || -> Result<()> {
+            let mut rng = rand::thread_rng();
+            let mut file = open_benchmark_reader(&file_path)?;
+            drop_cached_pages(&file);
+            let mut block = AlignedBuf::new(BLOCK_SIZE);
+            let mut ops: u64 = 0;
+
+            while !f_start.load(Ordering::Acquire) {
+                if is_cancelled() {
+                    return Err(crate::AppError::SystemError(
+                        "Benchmark cancelled by user".to_string(),
+                    ));
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            while !f_stop.load(Ordering::Relaxed) {
+                if is_cancelled() {
+                    break;
+                }
+                let rnd = rng.gen_range(0..blocks);
+                file.seek(SeekFrom::Start(rnd * BLOCK_SIZE as u64))?;
+                file.read_exact(&mut block)?;
+                ops += 1;
+                if ops.is_multiple_of(READ_CACHE_DROP_EVERY_OPS) {
+                    drop_cached_pages(&file);
+                }
+                f_total.fetch_add(BLOCK_SIZE as u64, Ordering::Relaxed);
+            }
+            Ok(())
+        }));
+    }
+
+    start_flag.store(true, Ordering::Release);
+    let wait_start = Instant::now();
+    while wait_start.elapsed() < duration {
+        if is_cancelled() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    stop_flag.store(true, Ordering::Relaxed);
+
+    let mut worker_error: Option<crate::AppError> = None;
+    for h in handles {
+        match h.join() {
+            Ok(inner) => {
+                if let Err(e) = inner {
+                    worker_error = Some(e);
+                }
+            }
+            Err(_) => {
+                worker_error = Some(crate::AppError::SystemError(
+                    "Benchmark worker thread panicked".to_string(),
+                ));
+            }
+        }
+    }
+
+    if let Some(e) = worker_error {
+        return Err(e);
+    }
+    ensure_not_cancelled()?;
+
+    let bytes = total_bytes.load(Ordering::Relaxed);
+    Ok((bytes as f64 / 1024.0 / 1024.0) / duration.as_secs_f64().max(0.001))
+}
+
+fn random_4k_read_multithread_curve(path: &str, region: u64) -> Result<Vec<ThreadResult>> {
+    let mut out = Vec::with_capacity(WTGB_MT_LEVELS.len());
+    for t in WTGB_MT_LEVELS {
+        ensure_not_cancelled()?;
+        let mbps = random_4k_read_multi_once(path, region, t, READ_MT_LEVEL_DURATION)?;
+        out.push(ThreadResult {
+            threads: t,
+            mb_s: mbps,
+        });
+    }
+    Ok(out)
+}
+
+/// Keep `depth` 4K requests in flight against random blocks of `[0, region)` for
+/// `duration`, returning (IOPS, mean latency in µs).
+#[cfg(target_os = "linux")]
+fn random_4k_queue_depth_once(
+    path: &str,
+    region: u64,
+    depth: u32,
+    op: AioOp,
+    duration: Duration,
+) -> Result<(f64, f64)> {
+    let file = match op {
+        AioOp::Read => open_benchmark_reader(path)?,
+        AioOp::Write => open_benchmark_file(path)?,
+    };
+    let depth = depth.max(1) as usize;
+    let blocks = (region / BLOCK_SIZE as u64).max(1);
+    let mut rng = rand::thread_rng();
+    let mut buffers: Vec<AlignedBuf> = (0..depth)
+        .map(|_| {
+            let mut buf = AlignedBuf::new(BLOCK_SIZE);
+            fill_random(&mut buf);
+            buf
+        })
+        .collect();
+    let mut issued_at = vec![Instant::now(); depth];
+    // Declared after the buffers so it is dropped (and drained) before them.
+    let mut queue = AioQueue::new(depth)
+        .map_err(|e| crate::AppError::Unsupported(format!("Native AIO is unavailable: {}", e)))?;
+
+    let mut submit = |queue: &mut AioQueue, slot: usize, issued_at: &mut [Instant]| {
+        let pos = rng.gen_range(0..blocks) * BLOCK_SIZE as u64;
+        issued_at[slot] = Instant::now();
+        // The buffer lives in `buffers` until the queue is drained or dropped.
+        unsafe {
+            queue.submit(
+                file.as_raw_fd(),
+                op,
+                buffers[slot].as_mut_ptr(),
+                BLOCK_SIZE,
+                pos,
+                slot as u64,
+            )
+        }
+    };
+
+    let start = Instant::now();
+    for slot in 0..depth {
+        submit(&mut queue, slot, &mut issued_at)?;
+    }
+    let mut in_flight = depth;
+    let mut ops: u64 = 0;
+    let mut latency_total = Duration::ZERO;
+    let mut done: Vec<AioCompletion> = Vec::with_capacity(depth);
+
+    while in_flight > 0 {
+        let running = start.elapsed() < duration && !is_cancelled();
+        done.clear();
+        queue.wait(1, QD_WAIT_TIMEOUT, &mut done)?;
+        for completion in &done {
+            in_flight -= 1;
+            let slot = completion.tag as usize;
+            if completion.result < 0 {
+                return Err(crate::AppError::SystemError(format!(
+                    "Queued 4K I/O failed: {}",
+                    std::io::Error::from_raw_os_error((-completion.result) as i32)
+                )));
+            }
+            if completion.result != BLOCK_SIZE as i64 {
+                return Err(crate::AppError::SystemError(format!(
+                    "Queued 4K I/O completed short: {} of {} bytes",
+                    completion.result, BLOCK_SIZE
+                )));
+            }
+            ops += 1;
+            latency_total += issued_at[slot].elapsed();
+            if running {
+                submit(&mut queue, slot, &mut issued_at)?;
+                in_flight += 1;
+            }
+        }
+    }
+    ensure_not_cancelled()?;
+    if op == AioOp::Write {
+        file.sync_data()?;
+    }
+
+    let sec = start.elapsed().as_secs_f64().max(0.001);
+    let latency_us = if ops == 0 {
+        0.0
+    } else {
+        latency_total.as_secs_f64() * 1_000_000.0 / ops as f64
+    };
+    Ok((ops as f64 / sec, latency_us))
+}
+
+/// QD1/4/16/32 sweep with native AIO, or the reason it was skipped. Needs O_DIRECT:
+/// on a buffered fallback every request completes inside io_submit and all depths
+/// would measure QD1. Kernels without AIO (or out of AIO contexts) skip it as well.
+#[cfg(target_os = "linux")]
+fn random_4k_queue_depth_sweep(
+    path: &str,
+    region: u64,
+) -> Result<(Vec<QueueDepthResult>, Option<String>)> {
+    if !BENCHMARK_DIRECT_IO.load(Ordering::Relaxed) {
+        return Ok((
+            Vec::new(),
+            Some("The queue-depth sweep needs unbuffered I/O".to_string()),
+        ));
+    }
+    let mut out = Vec::with_capacity(QD_LEVELS.len());
+    for depth in QD_LEVELS {
+        ensure_not_cancelled()?;
+        let read = random_4k_queue_depth_once(path, region, depth, AioOp::Read, QD_LEVEL_DURATION);
+        let (read_iops, read_latency_us) = match read {
+            Err(crate::AppError::Unsupported(reason)) => return Ok((Vec::new(), Some(reason))),
+            other => other?,
+        };
+        let (write_iops, write_latency_us) =
+            random_4k_queue_depth_once(path, region, depth, AioOp::Write, QD_LEVEL_DURATION)?;
+        out.push(QueueDepthResult {
+            queue_depth: depth,
+            read_iops,
+            read_latency_us,
+            write_iops,
+            write_latency_us,
+        });
+    }
+    Ok((out, None))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn random_4k_queue_depth_sweep(
+    _path: &str,
+    _region: u64,
+) -> Result<(Vec<QueueDepthResult>, Option<String>)> {
+    Ok((Vec::new(), None))
+}
+
+/// Read phases shared by every mode: sequential and 4K QD1 random reads (plus the
+/// thread curve when requested) over `[0, region)` of the benchmark file, prefilled
+/// past the `filled` bytes already written.
+fn run_read_phases(
+    path: &str,
+    filled: u64,
+    region: u64,
+    seq_duration: Duration,
+    point_count: usize,
+    with_thread_curve: bool,
+    result: &mut BenchmarkResult,
+) -> Result<()> {
+    prefill_read_region(path, filled, region)?;
+    let (read_seq, read_seq_samples) = sequential_read(path, seq_duration, region)?;
+    let (read_4k, read_4k_samples, read_4k_latency) =
+        random_4k_read_single_with_points(path, region, point_count)?;
+
+    result.read_seq = read_seq;
+    result.read_seq_samples = read_seq_samples;
+    result.read_4k = read_4k;
+    result.read_4k_samples = read_4k_samples;
+    result.read_4k_latency = Some(read_4k_latency.summary());
+    if with_thread_curve {
+        result.read_thread_results = random_4k_read_multithread_curve(path, region)?;
+    }
+    Ok(())
+}
+
 /// WTGB-style full sequential test:
 /// - 64MB IO size
 /// - 1GB per measurement step
@@ -495,7 +1108,7 @@ fn sequential_full(path: &str, target_bytes: u64) -> Result<(f64, Vec<Sample>, u
 
     let _ = std::fs::remove_file(path);
     let mut file = open_benchmark_file(path)?;
-    let mut block = vec![0u8; FULL_IO_BYTES as usize];
+    let mut block = AlignedBuf::new(FULL_IO_BYTES as usize);
     fill_random(&mut block);
 
     let total_steps = (target_bytes / FULL_STEP_BYTES).max(1);
@@ -720,7 +1333,7 @@ fn load_scenario_lines() -> Vec<ScenarioLine> {
     built_in_normal_web_scenario()
 }
 
-fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<u64> {
+fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<ScenarioWorkerStats> {
     let mut file = open_benchmark_file(&path)?;
     ensure_file_region(&mut file, RANDOM_REGION_BYTES)?;
 
@@ -731,12 +1344,14 @@ fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<u64> {
         .max()
         .unwrap_or(&BLOCK_SIZE)
         .max(&BLOCK_SIZE);
-    let mut write_buf = vec![0u8; max_io];
+    let mut write_buf = AlignedBuf::new(max_io);
     fill_random(&mut write_buf);
-    let mut read_buf = vec![0u8; max_io];
+    let mut read_buf = AlignedBuf::new(max_io);
     let mut prev_pos: u64 = 0;
     let mut ops: u64 = 0;
     let mut pending_sync_bytes: u64 = 0;
+    let mut read_latency = new_latency_histogram();
+    let mut write_latency = new_latency_histogram();
 
     let start = Instant::now();
     while start.elapsed() < SCENARIO_LINE_DURATION {
@@ -764,6 +1379,7 @@ fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<u64> {
         } else {
             pos
         };
+        let op_start = Instant::now();
         file.seek(SeekFrom::Start(safe_pos))?;
 
         if rng.gen_bool(line.write_proportion) {
@@ -773,8 +1389,11 @@ fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<u64> {
                 file.sync_data()?;
                 pending_sync_bytes = 0;
             }
+            // Includes the flush, like the 4K QD1 write latency.
+            write_latency.record(op_start.elapsed());
         } else {
             let _ = file.read(&mut read_buf[..len])?;
+            read_latency.record(op_start.elapsed());
         }
 
         prev_pos = (safe_pos + len as u64) % RANDOM_REGION_BYTES;
@@ -785,10 +1404,16 @@ fn scenario_line_worker(path: String, line: ScenarioLine) -> Result<u64> {
         file.sync_data()?;
     }
 
-    Ok(ops)
+    Ok(ScenarioWorkerStats {
+        ops,
+        read_latency,
+        write_latency,
+    })
 }
 
-fn scenario_benchmark(path_prefix: &str) -> Result<(u64, Vec<TrendPoint>)> {
+fn scenario_benchmark(
+    path_prefix: &str,
+) -> Result<(u64, Vec<TrendPoint>, LatencyHistogram, LatencyHistogram)> {
     let lines = load_scenario_lines();
     let worker_paths: Vec<String> = (0..32)
         .map(|i| format!("{}.sce{}", path_prefix, i))
@@ -801,6 +1426,8 @@ fn scenario_benchmark(path_prefix: &str) -> Result<(u64, Vec<TrendPoint>)> {
 
     let mut total_io: u64 = 0;
     let mut trend = Vec::new();
+    let mut read_latency = new_latency_histogram();
+    let mut write_latency = new_latency_histogram();
     let global = Instant::now();
 
     for line in lines {
@@ -817,7 +1444,10 @@ fn scenario_benchmark(path_prefix: &str) -> Result<(u64, Vec<TrendPoint>)> {
         for h in handles {
             match h.join() {
                 Ok(inner) => {
-                    line_total += inner?;
+                    let stats = inner?;
+                    line_total += stats.ops;
+                    read_latency.merge(&stats.read_latency);
+                    write_latency.merge(&stats.write_latency);
                 }
                 Err(_) => {
                     return Err(crate::AppError::SystemError(
@@ -840,7 +1470,7 @@ fn scenario_benchmark(path_prefix: &str) -> Result<(u64, Vec<TrendPoint>)> {
         let _ = std::fs::remove_file(p);
     }
 
-    Ok((total_io, trend))
+    Ok((total_io, trend, read_latency, write_latency))
 }
 
 pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult> {
@@ -862,6 +1492,20 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
         )));
     }
 
+    BENCHMARK_STALL_THRESHOLD_MS.store(
+        config
+            .stall_threshold_ms
+            .filter(|ms| *ms > 0)
+            .unwrap_or(DEFAULT_STALL_THRESHOLD_MS),
+        Ordering::Relaxed,
+    );
+    let io_fallback_reason = match configure_direct_io(target_dir) {
+        Ok(reason) => reason,
+        Err(e) => {
+            clear_cancel_flag();
+            return Err(e);
+        }
+    };
     let temp_file = target_dir.join("wtg_bench.bin");
     let temp_file_str = temp_file.to_string_lossy().to_string();
     let start = Instant::now();
@@ -874,6 +1518,19 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
         write_4k_adjusted: None,
         write_4k_samples: vec![],
         thread_results: vec![],
+        read_seq: 0.0,
+        read_4k: 0.0,
+        read_seq_samples: vec![],
+        read_4k_samples: vec![],
+        read_thread_results: vec![],
+        write_4k_latency: None,
+        read_4k_latency: None,
+        scenario_read_latency: None,
+        scenario_write_latency: None,
+        queue_depth_results: vec![],
+        queue_depth_skip_reason: None,
+        unbuffered_io: io_fallback_reason.is_none(),
+        io_fallback_reason,
         full_seq_samples: vec![],
         scenario_samples: vec![],
         scenario_total_io: None,
@@ -890,6 +1547,17 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                 let seq = sequential_wtgb(&temp_file_str, WTGB_SEQ_DURATION)?;
                 let r4k = random_4k_single_wtgb(&temp_file_str)?;
                 let mt = random_4k_multithread_curve(&temp_file_str)?;
+                run_read_phases(
+                    &temp_file_str,
+                    seq.2.min(WTGB_SEQ_RING_BYTES),
+                    READ_REGION_BYTES,
+                    READ_SEQ_DURATION,
+                    WTGB_4K_POINTS,
+                    true,
+                    &mut result,
+                )?;
+                (result.queue_depth_results, result.queue_depth_skip_reason) =
+                    random_4k_queue_depth_sweep(&temp_file_str, READ_REGION_BYTES)?;
                 let (score, grade) = compute_wtgb_score(seq.0, r4k.1);
 
                 result.write_seq = seq.0;
@@ -899,6 +1567,7 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                 result.write_4k_raw = Some(r4k.0);
                 result.write_4k_adjusted = Some(r4k.1);
                 result.write_4k_samples = r4k.2;
+                result.write_4k_latency = Some(r4k.3.summary());
                 result.thread_results = mt;
                 result.score = Some(score);
                 result.grade = Some(grade);
@@ -913,6 +1582,18 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                     ));
                 }
                 let seq = sequential_full(&temp_file_str, aligned)?;
+                // The file already holds the written data; read back the start of it
+                // without growing it into the reserved space.
+                let region = seq.2.min(READ_REGION_BYTES);
+                run_read_phases(
+                    &temp_file_str,
+                    seq.2,
+                    region,
+                    READ_SEQ_DURATION,
+                    WTGB_4K_POINTS,
+                    false,
+                    &mut result,
+                )?;
                 result.write_seq = seq.0;
                 result.full_seq_samples = seq.1;
                 result.full_written_gb = seq.2 as f64 / GIB;
@@ -920,6 +1601,15 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
             "full" => {
                 let seq = sequential_wtgb(&temp_file_str, WTGB_EXTREME_DURATION)?;
                 let r4k = random_4k_single_wtgb(&temp_file_str)?;
+                run_read_phases(
+                    &temp_file_str,
+                    seq.2.min(WTGB_SEQ_RING_BYTES),
+                    READ_REGION_BYTES,
+                    READ_SEQ_DURATION,
+                    WTGB_4K_POINTS,
+                    false,
+                    &mut result,
+                )?;
                 let (score, grade) = compute_wtgb_score(seq.0, r4k.1);
 
                 result.write_seq = seq.0;
@@ -929,14 +1619,27 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                 result.write_4k_raw = Some(r4k.0);
                 result.write_4k_adjusted = Some(r4k.1);
                 result.write_4k_samples = r4k.2;
+                result.write_4k_latency = Some(r4k.3.summary());
                 result.score = Some(score);
                 result.grade = Some(grade);
             }
             "scenario" => {
-                let (total_io, trend) = scenario_benchmark(&temp_file_str)?;
+                let (total_io, trend, read_latency, write_latency) =
+                    scenario_benchmark(&temp_file_str)?;
+                result.scenario_read_latency = Some(read_latency.summary());
+                result.scenario_write_latency = Some(write_latency.summary());
                 result.scenario_total_io = Some(total_io);
                 result.scenario_score = Some(total_io as f64 / 1000.0);
                 result.scenario_samples = trend;
+                run_read_phases(
+                    &temp_file_str,
+                    0,
+                    READ_REGION_BYTES,
+                    READ_QUICK_SEQ_DURATION,
+                    WTGB_QUICK_4K_POINTS,
+                    false,
+                    &mut result,
+                )?;
             }
             _ => {
                 // quick
@@ -946,6 +1649,15 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                     WTGB_QUICK_SEQ_RING_BYTES,
                 )?;
                 let r4k = random_4k_single_wtgb_with_points(&temp_file_str, WTGB_QUICK_4K_POINTS)?;
+                run_read_phases(
+                    &temp_file_str,
+                    seq.2.min(WTGB_QUICK_SEQ_RING_BYTES),
+                    READ_REGION_BYTES,
+                    READ_QUICK_SEQ_DURATION,
+                    WTGB_QUICK_4K_POINTS,
+                    false,
+                    &mut result,
+                )?;
                 let (score, grade) = compute_wtgb_score(seq.0, r4k.1);
 
                 result.write_seq = seq.0;
@@ -955,6 +1667,7 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
                 result.write_4k_raw = Some(r4k.0);
                 result.write_4k_adjusted = Some(r4k.1);
                 result.write_4k_samples = r4k.2;
+                result.write_4k_latency = Some(r4k.3.summary());
                 result.score = Some(score);
                 result.grade = Some(grade);
             }
@@ -973,6 +1686,8 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
     result.write_4k = round1(result.write_4k);
     result.write_4k_raw = result.write_4k_raw.map(round1);
     result.write_4k_adjusted = result.write_4k_adjusted.map(round1);
+    result.read_seq = round1(result.read_seq);
+    result.read_4k = round1(result.read_4k);
     result.full_written_gb = round1(result.full_written_gb);
     result.score = result.score.map(round1);
     result.scenario_score = result.scenario_score.map(round1);
@@ -984,6 +1699,25 @@ pub async fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult>
             x
         })
         .collect();
+    result.read_thread_results = result
+        .read_thread_results
+        .into_iter()
+        .map(|mut x| {
+            x.mb_s = round1(x.mb_s);
+            x
+        })
+        .collect();
+    result.queue_depth_results = result
+        .queue_depth_results
+        .into_iter()
+        .map(|mut x| {
+            x.read_iops = x.read_iops.round();
+            x.read_latency_us = round1(x.read_latency_us);
+            x.write_iops = x.write_iops.round();
+            x.write_latency_us = round1(x.write_latency_us);
+            x
+        })
+        .collect();
     result.duration_ms = start.elapsed().as_millis() as u64;
 
     let _ = std::fs::remove_file(&temp_file_str);
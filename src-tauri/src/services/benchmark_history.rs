@@ -0,0 +1,518 @@
+//! Benchmark history: finished runs are kept as one JSON file each under the app data
+//! directory, together with the disk (model, serial, firmware) and host they ran on, so
+//! runs of the same stick or drive model can be listed and compared over time.
+
+use crate::services::benchmark::BenchmarkResult;
+use crate::utils::latency::LatencySummary;
+use crate::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+/// A metric counts as regressed when it got this much worse (in percent).
+const REGRESSION_TOLERANCE_PCT: f64 = 10.0;
+
+/// Identity of the benchmarked disk, taken from its diagnostics.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkDisk {
+    pub id: String,
+    pub model: String,
+    pub serial_number: String,
+    pub firmware_version: String,
+    pub size_bytes: u64,
+    pub bus_type: String,
+    pub transport_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkHost {
+    pub os: String,
+    pub arch: String,
+    pub os_version: String,
+    pub cpu_model: String,
+    pub total_memory: u64,
+    pub app_version: String,
+}
+
+impl BenchmarkHost {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            os_version: crate::utils::get_os_version(),
+            cpu_model: crate::utils::get_cpu_model(),
+            total_memory: crate::utils::get_total_memory(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// One saved benchmark run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkRecord {
+    pub id: String,
+    /// RFC 3339 UTC timestamp
+    pub created_at: String,
+    pub target_path: String,
+    pub mode: String,
+    pub disk: Option<BenchmarkDisk>,
+    pub host: BenchmarkHost,
+    pub result: BenchmarkResult,
+}
+
+impl BenchmarkRecord {
+    pub fn new(
+        target_path: &str,
+        disk: Option<BenchmarkDisk>,
+        host: BenchmarkHost,
+        result: BenchmarkResult,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            target_path: target_path.to_string(),
+            mode: result.mode.clone(),
+            disk,
+            host,
+            result,
+        }
+    }
+
+    pub fn summary(&self) -> BenchmarkRunSummary {
+        BenchmarkRunSummary {
+            id: self.id.clone(),
+            created_at: self.created_at.clone(),
+            target_path: self.target_path.clone(),
+            mode: self.mode.clone(),
+            disk: self.disk.clone(),
+            host_os: format!("{} {}", self.host.os_version, self.host.arch)
+                .trim()
+                .to_string(),
+            write_seq: self.result.write_seq,
+            write_4k: self.result.write_4k,
+            read_seq: self.result.read_seq,
+            read_4k: self.result.read_4k,
+            score: self.result.score,
+            grade: self.result.grade.clone(),
+            scenario_score: self.result.scenario_score,
+            duration_ms: self.result.duration_ms,
+        }
+    }
+}
+
+/// History list entry: a run without its samples and curves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkRunSummary {
+    pub id: String,
+    pub created_at: String,
+    pub target_path: String,
+    pub mode: String,
+    pub disk: Option<BenchmarkDisk>,
+    pub host_os: String,
+    pub write_seq: f64,
+    pub write_4k: f64,
+    pub read_seq: f64,
+    pub read_4k: f64,
+    pub score: Option<f64>,
+    pub grade: Option<String>,
+    pub scenario_score: Option<f64>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricDelta {
+    pub metric: String,
+    pub base: Option<f64>,
+    pub other: Option<f64>,
+    /// `other - base`, when both runs measured the metric
+    pub delta: Option<f64>,
+    pub delta_pct: Option<f64>,
+    pub higher_is_better: bool,
+    /// The metric got worse by more than the regression tolerance
+    pub regressed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub base: BenchmarkRunSummary,
+    pub other: BenchmarkRunSummary,
+    pub same_mode: bool,
+    /// Both runs were on the disk with the same serial number
+    pub same_disk: bool,
+    pub firmware_changed: bool,
+    pub metrics: Vec<MetricDelta>,
+}
+
+/// Runs saved as `<id>.json` in one directory.
+pub struct BenchmarkHistory {
+    dir: PathBuf,
+}
+
+impl BenchmarkHistory {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn record_path(&self, id: &str) -> Result<PathBuf> {
+        // Only our own ids name files, so a crafted id cannot reach outside the directory.
+        let id = Uuid::parse_str(id.trim())
+            .map_err(|_| AppError::InvalidParameter(format!("Invalid benchmark run id: {}", id)))?;
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    pub fn save(&self, record: &BenchmarkRecord) -> Result<()> {
+        let path = self.record_path(&record.id)?;
+        fs::create_dir_all(&self.dir).map_err(AppError::io)?;
+        // Write then rename, so a crash never leaves a truncated record behind.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(record)?).map_err(AppError::io)?;
+        fs::rename(&tmp, &path).map_err(AppError::io)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<BenchmarkRecord> {
+        let path = self.record_path(id)?;
+        if !path.exists() {
+            return Err(AppError::InvalidParameter(format!(
+                "Benchmark run not found: {}",
+                id
+            )));
+        }
+        read_record(&path)
+    }
+
+    /// Saved runs, newest first. Unreadable files are skipped.
+    pub fn list(&self) -> Result<Vec<BenchmarkRunSummary>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(AppError::io(e)),
+        };
+        let mut runs: Vec<BenchmarkRunSummary> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read_record(&path) {
+                Ok(record) => Some(record.summary()),
+                Err(e) => {
+                    warn!("Skipping benchmark record {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+        runs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(runs)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let path = self.record_path(id)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(AppError::InvalidParameter(
+                format!("Benchmark run not found: {}", id),
+            )),
+            Err(e) => Err(AppError::io(e)),
+        }
+    }
+}
+
+fn read_record(path: &Path) -> Result<BenchmarkRecord> {
+    let data = fs::read(path).map_err(AppError::io)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// MB/s and scores of 0 mean the mode did not measure them.
+fn measured(value: f64) -> Option<f64> {
+    (value > 0.0).then_some(value)
+}
+
+/// Comparable metrics of a run: (name, value, higher is better).
+fn run_metrics(result: &BenchmarkResult) -> Vec<(String, Option<f64>, bool)> {
+    let p99 = |summary: &Option<LatencySummary>| {
+        summary
+            .as_ref()
+            .filter(|s| s.count > 0)
+            .map(|s| s.p99_us as f64)
+    };
+    let stalls = |summary: &Option<LatencySummary>| {
+        summary
+            .as_ref()
+            .filter(|s| s.count > 0)
+            .map(|s| s.stalls as f64)
+    };
+    let mut metrics = vec![
+        (
+            "write_seq_mb_s".to_string(),
+            measured(result.write_seq),
+            true,
+        ),
+        ("write_4k_mb_s".to_string(), measured(result.write_4k), true),
+        ("read_seq_mb_s".to_string(), measured(result.read_seq), true),
+        ("read_4k_mb_s".to_string(), measured(result.read_4k), true),
+        ("score".to_string(), result.score, true),
+        ("scenario_score".to_string(), result.scenario_score, true),
+        (
+            "write_4k_p99_us".to_string(),
+            p99(&result.write_4k_latency),
+            false,
+        ),
+        (
+            "read_4k_p99_us".to_string(),
+            p99(&result.read_4k_latency),
+            false,
+        ),
+        (
+            "write_4k_stalls".to_string(),
+            stalls(&result.write_4k_latency),
+            false,
+        ),
+        (
+            "scenario_read_p99_us".to_string(),
+            p99(&result.scenario_read_latency),
+            false,
+        ),
+        (
+            "scenario_write_p99_us".to_string(),
+            p99(&result.scenario_write_latency),
+            false,
+        ),
+        (
+            "scenario_stalls".to_string(),
+            stalls(&result.scenario_read_latency)
+                .zip(stalls(&result.scenario_write_latency))
+                .map(|(read, write)| read + write),
+            false,
+        ),
+    ];
+    for qd in &result.queue_depth_results {
+        metrics.push((
+            format!("read_iops_qd{}", qd.queue_depth),
+            measured(qd.read_iops),
+            true,
+        ));
+        metrics.push((
+            format!("write_iops_qd{}", qd.queue_depth),
+            measured(qd.write_iops),
+            true,
+        ));
+    }
+    metrics
+}
+
+/// Diff two runs metric by metric; metrics neither run measured are left out.
+pub fn compare_runs(base: &BenchmarkRecord, other: &BenchmarkRecord) -> BenchmarkComparison {
+    let other_metrics = run_metrics(&other.result);
+    let mut names: Vec<(String, bool)> = run_metrics(&base.result)
+        .iter()
+        .chain(other_metrics.iter())
+        .filter(|(_, value, _)| value.is_some())
+        .map(|(name, _, higher)| (name.clone(), *higher))
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    names.retain(|(name, _)| seen.insert(name.clone()));
+
+    let base_metrics = run_metrics(&base.result);
+    let lookup = |metrics: &[(String, Option<f64>, bool)], name: &str| {
+        metrics
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .and_then(|(_, value, _)| *value)
+    };
+    let metrics = names
+        .into_iter()
+        .map(|(metric, higher_is_better)| {
+            let base_value = lookup(&base_metrics, &metric);
+            let other_value = lookup(&other_metrics, &metric);
+            let delta = base_value.zip(other_value).map(|(b, o)| o - b);
+            let delta_pct = base_value
+                .zip(delta)
+                .filter(|(b, _)| *b != 0.0)
+                .map(|(b, d)| d / b * 100.0);
+            let regressed = delta_pct.is_some_and(|pct| {
+                let worse_pct = if higher_is_better { -pct } else { pct };
+                worse_pct > REGRESSION_TOLERANCE_PCT
+            });
+            MetricDelta {
+                metric,
+                base: base_value,
+                other: other_value,
+                delta,
+                delta_pct,
+                higher_is_better,
+                regressed,
+            }
+        })
+        .collect();
+
+    let serial = |record: &BenchmarkRecord| {
+        record
+            .disk
+            .as_ref()
+            .map(|d| d.serial_number.clone())
+            .filter(|s| !s.is_empty())
+    };
+    let same_disk = serial(base).is_some() && serial(base) == serial(other);
+    let firmware = |record: &BenchmarkRecord| {
+        record
+            .disk
+            .as_ref()
+            .map(|d| d.firmware_version.clone())
+            .filter(|s| !s.is_empty())
+    };
+    let firmware_changed = match (firmware(base), firmware(other)) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    };
+
+    BenchmarkComparison {
+        base: base.summary(),
+        other: other.summary(),
+        same_mode: base.mode == other.mode,
+        same_disk,
+        firmware_changed,
+        metrics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::benchmark::QueueDepthResult;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wtg-history-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn result(write_seq: f64, read_4k: f64) -> BenchmarkResult {
+        BenchmarkResult {
+            mode: "quick".to_string(),
+            write_seq,
+            write_4k: 0.0,
+            write_4k_raw: None,
+            write_4k_adjusted: None,
+            write_4k_samples: vec![],
+            thread_results: vec![],
+            read_seq: 0.0,
+            read_4k,
+            read_seq_samples: vec![],
+            read_4k_samples: vec![],
+            read_thread_results: vec![],
+            write_4k_latency: None,
+            read_4k_latency: None,
+            scenario_read_latency: None,
+            scenario_write_latency: None,
+            queue_depth_results: vec![],
+            queue_depth_skip_reason: None,
+            unbuffered_io: true,
+            io_fallback_reason: None,
+            full_seq_samples: vec![],
+            scenario_samples: vec![],
+            scenario_total_io: None,
+            scenario_score: None,
+            score: None,
+            grade: None,
+            duration_ms: 1000,
+            full_written_gb: 0.0,
+        }
+    }
+
+    fn disk(serial: &str, firmware: &str) -> Option<BenchmarkDisk> {
+        Some(BenchmarkDisk {
+            id: "sdb".to_string(),
+            model: "SanDisk Extreme".to_string(),
+            serial_number: serial.to_string(),
+            firmware_version: firmware.to_string(),
+            size_bytes: 64_000_000_000,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn saves_lists_and_deletes_runs() {
+        let history = BenchmarkHistory::new(temp_dir("store"));
+        assert!(history.list().unwrap().is_empty());
+
+        let mut older = BenchmarkRecord::new(
+            "/media/usb",
+            disk("AA01", "1.0"),
+            BenchmarkHost::default(),
+            result(200.0, 9.5),
+        );
+        older.created_at = "2026-01-01T00:00:00Z".to_string();
+        let newer = BenchmarkRecord::new(
+            "/media/usb",
+            None,
+            BenchmarkHost::default(),
+            result(180.0, 9.0),
+        );
+        history.save(&older).unwrap();
+        history.save(&newer).unwrap();
+        fs::write(history.dir.join("broken.json"), b"{").unwrap();
+
+        let runs = history.list().unwrap();
+        assert_eq!(
+            runs.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(),
+            [newer.id.as_str(), older.id.as_str()]
+        );
+        assert_eq!(runs[1].disk.as_ref().unwrap().model, "SanDisk Extreme");
+        assert_eq!(history.get(&older.id).unwrap().result.read_4k, 9.5);
+
+        history.delete(&older.id).unwrap();
+        assert_eq!(history.list().unwrap().len(), 1);
+        assert!(history.delete(&older.id).is_err());
+        assert!(history.get("../../etc/passwd").is_err());
+        let _ = fs::remove_dir_all(&history.dir);
+    }
+
+    #[test]
+    fn compares_runs_and_flags_regressions() {
+        let mut base_result = result(200.0, 10.0);
+        base_result.queue_depth_results = vec![QueueDepthResult {
+            queue_depth: 4,
+            read_iops: 4000.0,
+            read_latency_us: 900.0,
+            write_iops: 1000.0,
+            write_latency_us: 4000.0,
+        }];
+        let base = BenchmarkRecord::new(
+            "/a",
+            disk("AA01", "1.0"),
+            BenchmarkHost::default(),
+            base_result,
+        );
+        let mut other_result = result(195.0, 7.0);
+        other_result.mode = "full".to_string();
+        let other = BenchmarkRecord::new(
+            "/a",
+            disk("AA01", "1.1"),
+            BenchmarkHost::default(),
+            other_result,
+        );
+
+        let cmp = compare_runs(&base, &other);
+        assert!(cmp.same_disk && cmp.firmware_changed && !cmp.same_mode);
+
+        let metric = |name: &str| cmp.metrics.iter().find(|m| m.metric == name).unwrap();
+        let seq = metric("write_seq_mb_s");
+        assert_eq!((seq.delta, seq.regressed), (Some(-5.0), false));
+        let read_4k = metric("read_4k_mb_s");
+        assert!(read_4k.regressed);
+        assert!((read_4k.delta_pct.unwrap() + 30.0).abs() < 1e-9);
+        let qd = metric("read_iops_qd4");
+        assert_eq!((qd.base, qd.other, qd.delta), (Some(4000.0), None, None));
+        // Neither run measured sequential reads.
+        assert!(cmp.metrics.iter().all(|m| m.metric != "read_seq_mb_s"));
+
+        let unknown = BenchmarkRecord::new(
+            "/b",
+            disk("", "1.0"),
+            BenchmarkHost::default(),
+            result(1.0, 1.0),
+        );
+        assert!(!compare_runs(&base, &unknown).same_disk);
+    }
+}
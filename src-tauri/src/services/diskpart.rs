@@ -3,8 +3,10 @@
 
 #![allow(dead_code)]
 
+use crate::models::PartitionLayout;
 use crate::utils::command::{run_diskpart_script, wait_for_path, CommandExecutor};
 use crate::utils::first_char;
+use crate::utils::partition_plan::{PartitionPlan, PartitionRole};
 use crate::AppError;
 use crate::Result;
 use tracing::info;
@@ -200,68 +202,86 @@ pub fn mount_efi_partition(disk_index: &str) -> Result<(String, bool)> {
     }
 }
 
-/// Generate and execute GPT + UEFI partition script
-/// Equivalent to DiskOperation.DiskPartGPTAndUEFI()
-pub fn diskpart_gpt_uefi(
-    efi_size: &str,
-    disk_index: &str,
-    volume_letter: &str,
-    drive_type: &str,
-    partition_sizes: &[u32],
-) -> Result<String> {
+/// `create partition` commands for every planned partition, at the planned offsets.
+/// diskpart takes sizes in MB and offsets in KB; the last partition takes the rest.
+fn create_partitions_script(plan: &PartitionPlan) -> String {
     let mut script = String::new();
-    let is_removable = drive_type.contains("Removable");
-
-    script.push_str(&format!("select disk {}\n", disk_index));
-    script.push_str("clean\n");
-    script.push_str("convert gpt NOERR\n");
-
-    // Removable devices can't create EFI partition properly
-    if is_removable {
-        script.push_str(&format!("create partition primary size {}\n", efi_size));
-    } else {
-        script.push_str(&format!("create partition efi size {}\n", efi_size));
+    let last = plan.partitions.len().saturating_sub(1);
+    for (i, part) in plan.partitions.iter().enumerate() {
+        let kind = match part.role {
+            // Removable devices can't create EFI partition properly
+            PartitionRole::Esp
+                if plan.style == PartitionLayout::GPT && !plan.geometry.removable =>
+            {
+                "efi"
+            }
+            PartitionRole::Msr => "msr",
+            _ => "primary",
+        };
+        script.push_str(&format!("create partition {}", kind));
+        if i != last {
+            script.push_str(&format!(" size={}", part.size / (1024 * 1024)));
+        }
+        script.push_str(&format!(" offset={}\n", part.start / 1024));
     }
+    script
+}
 
-    // Filter non-zero partition sizes
-    let valid_sizes: Vec<u32> = partition_sizes.iter().copied().filter(|&s| s > 0).collect();
-
-    // Create intermediate partitions (all except last)
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!(
-            "create partition primary size {}\n",
-            valid_sizes[i]
-        ));
+/// Format the system partition first so it gets the requested letter, then the extra
+/// data partitions, then the ESP.
+fn format_partitions_script(plan: &PartitionPlan, system_letter_cmd: &str) -> String {
+    let mut script = String::new();
+    let system = plan.system();
+    script.push_str(&format!("select partition {}\n", system.number));
+    if plan.style == PartitionLayout::MBR {
+        script.push_str("remove noerr\n");
     }
-
-    // Create last partition (uses remaining space)
-    script.push_str("create partition primary\n");
-
-    // Select and format the main data partition
-    if is_removable {
-        script.push_str("select partition 2\n");
-    } else {
-        script.push_str("select partition 3\n");
+    script.push_str(&format!("format fs=ntfs quick label={}\n", system.label));
+    if system.active {
+        script.push_str("active\n");
     }
-    script.push_str("format fs=ntfs quick\n");
-    script.push_str(&assign_letter_cmd(volume_letter));
+    script.push_str(system_letter_cmd);
 
-    // Format additional partitions
-    let start_partition = if is_removable { 3 } else { 4 };
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!("select partition {}\n", start_partition + i));
+    for part in plan.data_partitions() {
+        script.push_str(&format!("select partition {}\n", part.number));
         script.push_str("format fs=ntfs quick\n");
         script.push_str("assign\n");
     }
 
-    // Format EFI partition as FAT32
-    if is_removable {
-        script.push_str("select partition 1\n");
-        script.push_str("remove NOERR\n");
-    } else {
-        script.push_str("select partition 2\n");
+    if let Some(esp) = plan.esp() {
+        script.push_str(&format!("select partition {}\n", esp.number));
+        if plan.style == PartitionLayout::MBR || plan.geometry.removable {
+            script.push_str("remove NOERR\n");
+        }
+        script.push_str("format fs=fat32 quick\n");
+        if esp.active {
+            script.push_str("active\n");
+        }
     }
-    script.push_str("format fs=fat32 quick\n");
+    script
+}
+
+/// Generate and execute GPT + UEFI partition script
+/// Equivalent to DiskOperation.DiskPartGPTAndUEFI()
+pub fn diskpart_gpt_uefi(
+    plan: &PartitionPlan,
+    disk_index: &str,
+    volume_letter: &str,
+) -> Result<String> {
+    let mut script = String::new();
+
+    script.push_str(&format!("select disk {}\n", disk_index));
+    script.push_str("clean\n");
+    script.push_str("convert gpt NOERR\n");
+    // convert gpt adds an MSR of its own to fixed disks; drop it so the partitions
+    // land at the planned offsets and numbers, MSR included.
+    script.push_str("select partition 1 NOERR\n");
+    script.push_str("delete partition override NOERR\n");
+    script.push_str(&create_partitions_script(plan));
+    script.push_str(&format_partitions_script(
+        plan,
+        &assign_letter_cmd(volume_letter),
+    ));
     script.push_str("exit\n");
 
     run_diskpart_script(&script)?;
@@ -278,10 +298,9 @@ pub fn diskpart_gpt_uefi(
 /// Generate and execute MBR + UEFI partition script
 /// Equivalent to DiskOperation.DiskPartMBRAndUEFI()
 pub fn diskpart_mbr_uefi(
-    efi_size: &str,
+    plan: &PartitionPlan,
     disk_index: &str,
     volume_letter: &str,
-    partition_sizes: &[u32],
     keep_drive_letter: bool,
 ) -> Result<()> {
     let mut script = String::new();
@@ -289,40 +308,14 @@ pub fn diskpart_mbr_uefi(
     script.push_str(&format!("select disk {}\n", disk_index));
     script.push_str("clean\n");
     script.push_str("convert mbr\n");
-    script.push_str(&format!("create partition primary size {}\n", efi_size));
+    script.push_str(&create_partitions_script(plan));
 
-    let valid_sizes: Vec<u32> = partition_sizes.iter().copied().filter(|&s| s > 0).collect();
-
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!(
-            "create partition primary size {}\n",
-            valid_sizes[i]
-        ));
-    }
-
-    script.push_str("create partition primary\n");
-    script.push_str("select partition 2\n");
-    script.push_str("remove noerr\n");
-    script.push_str("format fs=ntfs quick\n");
-
-    if keep_drive_letter {
-        script.push_str(&assign_letter_cmd(volume_letter));
+    let letter_cmd = if keep_drive_letter {
+        assign_letter_cmd(volume_letter)
     } else {
-        script.push_str("assign\n");
-    }
-
-    // Format additional partitions
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!("select partition {}\n", i + 3));
-        script.push_str("format fs=ntfs quick\n");
-        script.push_str("assign\n");
-    }
-
-    // Format EFI partition as FAT32, set active
-    script.push_str("select partition 1\n");
-    script.push_str("remove noerr\n");
-    script.push_str("format fs=fat32 quick\n");
-    script.push_str("active\n");
+        "assign\n".to_string()
+    };
+    script.push_str(&format_partitions_script(plan, &letter_cmd));
     script.push_str("exit\n");
 
     run_diskpart_script(&script)?;
@@ -331,34 +324,17 @@ pub fn diskpart_mbr_uefi(
 
 /// Re-partition USB disk with MBR layout
 /// Equivalent to DiskOperation.DiskPartRePartitionUD()
-pub fn diskpart_repartition(volume_letter: &str, partition_sizes: &[u32]) -> Result<()> {
+pub fn diskpart_repartition(plan: &PartitionPlan, volume_letter: &str) -> Result<()> {
     let mut script = String::new();
 
     script.push_str(&format!("select volume {}\n", first_char(volume_letter)));
     script.push_str("clean\n");
     script.push_str("convert mbr\n");
-
-    let valid_sizes: Vec<u32> = partition_sizes.iter().copied().filter(|&s| s > 0).collect();
-
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!(
-            "create partition primary size {}\n",
-            valid_sizes[i]
-        ));
-    }
-
-    script.push_str("create partition primary\n");
-    script.push_str("select partition 1\n");
-    script.push_str("format fs=ntfs quick\n");
-    script.push_str("active\n");
-    script.push_str(&assign_letter_cmd(volume_letter));
-
-    for i in 0..valid_sizes.len().saturating_sub(1) {
-        script.push_str(&format!("select partition {}\n", i + 2));
-        script.push_str("format fs=ntfs quick\n");
-        script.push_str("assign\n");
-    }
-
+    script.push_str(&create_partitions_script(plan));
+    script.push_str(&format_partitions_script(
+        plan,
+        &assign_letter_cmd(volume_letter),
+    ));
     script.push_str("exit\n");
 
     run_diskpart_script(&script)?;
@@ -0,0 +1,303 @@
+//! Extra features for the offline (non-Windows host) write paths
+//! Nothing can be serviced offline without DISM/reg.exe, so the selected options are written
+//! into `Windows\Setup\Scripts` and executed by `SetupComplete.cmd` on first boot.
+
+use crate::models::WtgConfig;
+use crate::{AppError, Result};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default)]
+pub struct ExtraFeatureOutcome {
+    pub applied: Vec<&'static str>,
+    pub unsupported: Vec<&'static str>,
+    pub notes: Vec<String>,
+}
+
+fn write_text_file(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).map_err(AppError::io)
+}
+
+fn ensure_setup_complete_invokes_wtga_extra(scripts_dir: &Path) -> Result<()> {
+    let setup_complete_path = scripts_dir.join("SetupComplete.cmd");
+    let call_line = r#"call "%SystemRoot%\Setup\Scripts\WTGA-ExtraFeatures.cmd""#;
+    let lower_match = "wtga-extrafeatures.cmd";
+
+    let mut content = if setup_complete_path.exists() {
+        fs::read_to_string(&setup_complete_path).map_err(AppError::io)?
+    } else {
+        "@echo off\r\n".to_string()
+    };
+
+    if !content.to_ascii_lowercase().contains(lower_match) {
+        if !content.ends_with('\n') {
+            content.push_str("\r\n");
+        }
+        content.push_str(call_line);
+        content.push_str("\r\n");
+    }
+
+    if !content.to_ascii_lowercase().contains("exit /b 0") {
+        content.push_str("exit /b 0\r\n");
+    }
+
+    write_text_file(&setup_complete_path, &content)
+}
+
+fn write_skip_oobe_unattend_if_needed(system_mount: &Path) -> Result<()> {
+    let sysprep_dir = system_mount
+        .join("Windows")
+        .join("System32")
+        .join("Sysprep");
+    fs::create_dir_all(&sysprep_dir).map_err(AppError::io)?;
+    let unattend_path = sysprep_dir.join("unattend.xml");
+
+    if unattend_path.exists() {
+        return Ok(());
+    }
+
+    // Keep this minimal and architecture-agnostic by including both amd64 and arm64 blocks.
+    let unattend = r#"<?xml version="1.0" encoding="utf-8"?>
+<unattend xmlns="urn:schemas-microsoft-com:unattend">
+  <settings pass="oobeSystem">
+    <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
+      <OOBE>
+        <HideEULAPage>true</HideEULAPage>
+        <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
+        <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
+        <ProtectYourPC>3</ProtectYourPC>
+        <SkipMachineOOBE>true</SkipMachineOOBE>
+        <SkipUserOOBE>true</SkipUserOOBE>
+      </OOBE>
+    </component>
+    <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="arm64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
+      <OOBE>
+        <HideEULAPage>true</HideEULAPage>
+        <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
+        <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
+        <ProtectYourPC>3</ProtectYourPC>
+        <SkipMachineOOBE>true</SkipMachineOOBE>
+        <SkipUserOOBE>true</SkipUserOOBE>
+      </OOBE>
+    </component>
+  </settings>
+</unattend>
+"#;
+
+    write_text_file(&unattend_path, unattend)
+}
+
+/// Stage the selected extra features into the applied system so they run on first boot
+/// through `SetupComplete.cmd`. `platform` only labels the notes for the host OS.
+pub fn apply_via_setup_complete(
+    config: &WtgConfig,
+    system_mount: &Path,
+    platform: &str,
+) -> Result<ExtraFeatureOutcome> {
+    let mut outcome = ExtraFeatureOutcome::default();
+    let features = &config.extra_features;
+
+    let windows_dir = system_mount.join("Windows");
+    if !windows_dir.exists() {
+        return Err(AppError::ImageError(format!(
+            "Cannot apply extra features: Windows directory missing at {}",
+            windows_dir.display()
+        )));
+    }
+
+    let scripts_dir = windows_dir.join("Setup").join("Scripts");
+    fs::create_dir_all(&scripts_dir).map_err(AppError::io)?;
+
+    let mut script_lines = vec![
+        "@echo off".to_string(),
+        "setlocal enableextensions".to_string(),
+        "set WTGA_LOG=%SystemRoot%\\Setup\\Scripts\\WTGA-ExtraFeatures.log".to_string(),
+        "echo [WTGA] Extra features script started %DATE% %TIME%>>\"%WTGA_LOG%\"".to_string(),
+    ];
+
+    if features.block_local_disk {
+        outcome.applied.push("block_local_disk");
+        script_lines.push(
+            "echo [WTGA] Applying SAN policy (block local disks)>>\"%WTGA_LOG%\"".to_string(),
+        );
+        script_lines.push("reg add \"HKLM\\SYSTEM\\CurrentControlSet\\Services\\partmgr\\Parameters\" /v SanPolicy /t REG_DWORD /d 4 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
+        script_lines.push(
+            "(echo san policy=4) > \"%SystemRoot%\\Setup\\Scripts\\wtga-san.txt\"".to_string(),
+        );
+        script_lines.push(
+            "diskpart /s \"%SystemRoot%\\Setup\\Scripts\\wtga-san.txt\" >>\"%WTGA_LOG%\" 2>&1"
+                .to_string(),
+        );
+    }
+
+    if features.disable_winre {
+        outcome.applied.push("disable_winre");
+        script_lines.push("echo [WTGA] Disabling WinRE>>\"%WTGA_LOG%\"".to_string());
+        script_lines.push("reagentc /disable >>\"%WTGA_LOG%\" 2>&1".to_string());
+    }
+
+    if features.skip_oobe {
+        outcome.applied.push("skip_oobe");
+        write_skip_oobe_unattend_if_needed(system_mount)?;
+        script_lines
+            .push("echo [WTGA] Applying skip OOBE registry flags>>\"%WTGA_LOG%\"".to_string());
+        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v SkipMachineOOBE /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
+        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v SkipUserOOBE /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
+        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v PrivacyConsentStatus /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
+    }
+
+    if features.disable_uasp {
+        outcome.applied.push("disable_uasp");
+        script_lines.push("echo [WTGA] Disabling UASP (uaspstor)>>\"%WTGA_LOG%\"".to_string());
+        script_lines.push("reg add \"HKLM\\SYSTEM\\CurrentControlSet\\Services\\uaspstor\" /v Start /t REG_DWORD /d 4 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
+    }
+
+    if features.no_default_drive_letter {
+        outcome.applied.push("no_default_drive_letter");
+        script_lines
+            .push("echo [WTGA] Setting no default drive letter policy>>\"%WTGA_LOG%\"".to_string());
+        script_lines.push("mountvol /N >>\"%WTGA_LOG%\" 2>&1".to_string());
+        script_lines.push("(echo select volume %SystemDrive% & echo attributes volume set nodefaultdriveletter) > \"%SystemRoot%\\Setup\\Scripts\\wtga-nodefaultdriveletter.txt\"".to_string());
+        script_lines.push("diskpart /s \"%SystemRoot%\\Setup\\Scripts\\wtga-nodefaultdriveletter.txt\" >>\"%WTGA_LOG%\" 2>&1".to_string());
+    }
+
+    if features.compact_os {
+        outcome.applied.push("compact_os");
+        script_lines.push("echo [WTGA] Enabling CompactOS>>\"%WTGA_LOG%\"".to_string());
+        script_lines.push("compact.exe /CompactOS:always >>\"%WTGA_LOG%\" 2>&1".to_string());
+    }
+
+    if features.install_dotnet35 {
+        outcome.unsupported.push("install_dotnet35");
+    }
+    if features.fix_letter {
+        outcome.unsupported.push("fix_letter");
+    }
+    if features.wimboot {
+        outcome.unsupported.push("wimboot");
+    }
+    if features.ntfs_uefi_support {
+        // The offline UEFI flow already uses a dedicated FAT EFI partition; this flag is a no-op here.
+        outcome.notes.push(format!(
+            "ntfs_uefi_support is ignored on {} UEFI write path",
+            platform
+        ));
+    }
+    if features.enable_bitlocker {
+        outcome.unsupported.push("enable_bitlocker");
+    }
+    if features.driver_path.is_some() {
+        outcome.unsupported.push("driver_path");
+    }
+
+    script_lines.push(
+        "echo [WTGA] Extra features script finished %DATE% %TIME%>>\"%WTGA_LOG%\"".to_string(),
+    );
+    script_lines.push("endlocal".to_string());
+    script_lines.push("exit /b 0".to_string());
+
+    if outcome.applied.is_empty() {
+        return Ok(outcome);
+    }
+
+    let extra_script_path = scripts_dir.join("WTGA-ExtraFeatures.cmd");
+    let script_text = format!("{}\r\n", script_lines.join("\r\n"));
+    write_text_file(&extra_script_path, &script_text)?;
+    ensure_setup_complete_invokes_wtga_extra(&scripts_dir)?;
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ApplyMode, BootMode, Disk, ExtraFeatures, ImageType, PartitionConfig, PartitionLayout,
+        TargetKind,
+    };
+
+    fn temp_system_root() -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("wtga-extras-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("Windows")).unwrap();
+        root
+    }
+
+    fn config_with(extra_features: ExtraFeatures) -> WtgConfig {
+        WtgConfig {
+            image_path: "install.wim".to_string(),
+            image_type: ImageType::Wim,
+            wim_index: "1".to_string(),
+            target_disk: Disk {
+                id: "sdb".to_string(),
+                name: "USB".to_string(),
+                size: 0,
+                removable: true,
+                device: "/dev/sdb".to_string(),
+                drive_type: "USB".to_string(),
+                index: "1".to_string(),
+                volume: String::new(),
+            },
+            target_kind: TargetKind::Disk,
+            image_target: None,
+            boot_mode: BootMode::UefiGpt,
+            apply_mode: ApplyMode::Legacy,
+            partition_config: PartitionConfig {
+                boot_size: 0,
+                partition_layout: PartitionLayout::GPT,
+                extra_partition_sizes: Vec::new(),
+            },
+            vhd_config: None,
+            extra_features,
+            efi_partition_size: "300".to_string(),
+            efi_partition_path: None,
+        }
+    }
+
+    #[test]
+    fn no_script_is_written_without_supported_features() {
+        let root = temp_system_root();
+        let config = config_with(ExtraFeatures {
+            wimboot: true,
+            ..Default::default()
+        });
+
+        let outcome = apply_via_setup_complete(&config, &root, "Linux").unwrap();
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.unsupported, vec!["wimboot"]);
+        assert!(!root
+            .join("Windows/Setup/Scripts/SetupComplete.cmd")
+            .exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn setup_complete_calls_extra_script_once() {
+        let root = temp_system_root();
+        let scripts = root.join("Windows/Setup/Scripts");
+        fs::create_dir_all(&scripts).unwrap();
+        fs::write(
+            scripts.join("SetupComplete.cmd"),
+            "@echo off\r\necho oem\r\n",
+        )
+        .unwrap();
+        let config = config_with(ExtraFeatures {
+            disable_uasp: true,
+            skip_oobe: true,
+            ..Default::default()
+        });
+
+        apply_via_setup_complete(&config, &root, "Linux").unwrap();
+        let outcome = apply_via_setup_complete(&config, &root, "Linux").unwrap();
+        assert_eq!(outcome.applied, vec!["skip_oobe", "disable_uasp"]);
+
+        let setup_complete = fs::read_to_string(scripts.join("SetupComplete.cmd")).unwrap();
+        assert!(setup_complete.starts_with("@echo off\r\necho oem\r\n"));
+        assert_eq!(setup_complete.matches("WTGA-ExtraFeatures.cmd").count(), 1);
+        let extra = fs::read_to_string(scripts.join("WTGA-ExtraFeatures.cmd")).unwrap();
+        assert!(extra.contains("uaspstor"));
+        assert!(root.join("Windows/System32/Sysprep/unattend.xml").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -6,7 +6,9 @@
 use crate::models::ImageInfo;
 use crate::utils::command::CommandExecutor;
 use crate::utils::first_two_chars;
+use crate::utils::iso;
 use crate::utils::output_capture::OutputCapture;
+use crate::utils::wim;
 use crate::{AppError, Result};
 use regex::Regex;
 use tracing::info;
@@ -32,6 +34,13 @@ pub fn get_image_info(image_file: &str) -> Result<Vec<ImageInfo>> {
 
 /// Get image info directly from a WIM/ESD file
 fn get_image_info_from_wim(wim_path: &str) -> Result<Vec<ImageInfo>> {
+    // The built-in reader needs no external tools and is immune to DISM's localized output
+    match wim::read_image_info(std::path::Path::new(wim_path)) {
+        Ok(images) if !images.is_empty() => return Ok(images),
+        Ok(_) => info!("No images in WIM XML metadata, falling back to DISM"),
+        Err(e) => info!("Built-in WIM reader failed ({}), falling back to DISM", e),
+    }
+
     // Use execute_allow_fail because DISM may return non-zero even on success
     // and we need to parse stdout regardless
     let output = CommandExecutor::execute_allow_fail(
@@ -57,9 +66,25 @@ fn get_image_info_from_wim(wim_path: &str) -> Result<Vec<ImageInfo>> {
     Ok(images)
 }
 
+/// Read image info from install.wim/install.esd inside an ISO without mounting it
+fn read_iso_image_info_in_place(iso_path: &str) -> Result<Vec<ImageInfo>> {
+    let images = iso::read_install_image_info(std::path::Path::new(iso_path))?;
+    if images.is_empty() {
+        return Err(AppError::ImageError(
+            "No image indexes found in ISO install image".to_string(),
+        ));
+    }
+    Ok(images)
+}
+
 /// Mount an ISO, find install.wim/install.esd, get image info, then dismount
 #[cfg(target_os = "windows")]
 fn get_image_info_from_iso(iso_path: &str) -> Result<Vec<ImageInfo>> {
+    match read_iso_image_info_in_place(iso_path) {
+        Ok(images) => return Ok(images),
+        Err(e) => info!("Reading ISO in place failed ({}), mounting it instead", e),
+    }
+
     info!("Mounting ISO to read image info: {}", iso_path);
 
     // Mount ISO using PowerShell
@@ -100,18 +125,11 @@ fn get_image_info_from_iso(iso_path: &str) -> Result<Vec<ImageInfo>> {
 
     info!("ISO mounted at drive: {}:", drive_letter);
 
-    // Look for install.wim or install.esd
-    let wim_path = format!("{}:\\sources\\install.wim", drive_letter);
-    let esd_path = format!("{}:\\sources\\install.esd", drive_letter);
-
-    let target_path = if std::path::Path::new(&wim_path).exists() {
-        wim_path
-    } else if std::path::Path::new(&esd_path).exists() {
-        esd_path
-    } else {
+    // Look for install.wim, install.esd or install.swm
+    let Some(target_path) = find_install_image_on_drive(&drive_letter) else {
         dismount_iso(iso_path);
         return Err(AppError::ImageError(
-            "Cannot find install.wim or install.esd in ISO".to_string(),
+            "Cannot find install.wim, install.esd or install.swm in ISO".to_string(),
         ));
     };
 
@@ -125,10 +143,8 @@ fn get_image_info_from_iso(iso_path: &str) -> Result<Vec<ImageInfo>> {
 }
 
 #[cfg(not(target_os = "windows"))]
-fn get_image_info_from_iso(_iso_path: &str) -> Result<Vec<ImageInfo>> {
-    Err(AppError::ImageError(
-        "ISO mounting is only supported on Windows".to_string(),
-    ))
+fn get_image_info_from_iso(iso_path: &str) -> Result<Vec<ImageInfo>> {
+    read_iso_image_info_in_place(iso_path)
 }
 
 /// Dismount an ISO image
@@ -145,7 +161,7 @@ pub fn dismount_iso(iso_path: &str) {
     );
 }
 
-/// Mount an ISO and return the path to install.wim or install.esd inside it.
+/// Mount an ISO and return the path to install.wim, install.esd or install.swm inside it.
 /// The caller is responsible for calling dismount_iso() when done.
 #[cfg(target_os = "windows")]
 pub fn mount_iso_and_find_wim(iso_path: &str) -> Result<String> {
@@ -185,19 +201,21 @@ pub fn mount_iso_and_find_wim(iso_path: &str) -> Result<String> {
 
     info!("ISO mounted at drive: {}:", drive_letter);
 
-    let wim_path = format!("{}:\\sources\\install.wim", drive_letter);
-    let esd_path = format!("{}:\\sources\\install.esd", drive_letter);
-
-    if std::path::Path::new(&wim_path).exists() {
-        Ok(wim_path)
-    } else if std::path::Path::new(&esd_path).exists() {
-        Ok(esd_path)
-    } else {
+    find_install_image_on_drive(&drive_letter).ok_or_else(|| {
         dismount_iso(iso_path);
-        Err(AppError::ImageError(
-            "Cannot find install.wim or install.esd in mounted ISO".to_string(),
-        ))
-    }
+        AppError::ImageError(
+            "Cannot find install.wim, install.esd or install.swm in mounted ISO".to_string(),
+        )
+    })
+}
+
+/// First of install.wim / install.esd / install.swm (first split part) under `X:\sources`
+#[cfg(target_os = "windows")]
+fn find_install_image_on_drive(drive_letter: &str) -> Option<String> {
+    ["install.wim", "install.esd", "install.swm"]
+        .iter()
+        .map(|file| format!("{}:\\sources\\{}", drive_letter, file))
+        .find(|path| std::path::Path::new(path).exists())
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -240,6 +258,7 @@ fn parse_dism_output(output: &str) -> Result<Vec<ImageInfo>> {
             name,
             description,
             size,
+            ..Default::default()
         });
     }
 
@@ -325,6 +344,20 @@ fn auto_choose_esd_index(esd_path: &str) -> Result<String> {
     }
 }
 
+/// DISM source arguments for an image file. A split WIM is passed as its first part plus
+/// `/SWMFile:` with a wildcard covering the whole set.
+fn dism_image_file_args(flag: &str, image_file: &str) -> Result<Vec<String>> {
+    let path = std::path::Path::new(image_file);
+    if !wim::is_split_wim(path) {
+        return Ok(vec![format!("{}:{}", flag, image_file)]);
+    }
+    let set = wim::resolve_split_wim(path)?;
+    Ok(vec![
+        format!("{}:{}", flag, set.first_part().display()),
+        format!("/SWMFile:{}", set.ref_pattern()),
+    ])
+}
+
 /// Apply a Windows image using DISM
 /// Equivalent to ImageOperation.DismApplyImage()
 pub fn dism_apply_image(
@@ -340,12 +373,10 @@ pub fn dism_apply_image(
 
     let target = first_two_chars(target_disk); // e.g., "E:"
 
-    let mut args = vec![
-        "/Apply-Image".to_string(),
-        format!("/ImageFile:{}", image_file),
-        format!("/ApplyDir:{}", target),
-        format!("/Index:{}", wim_index),
-    ];
+    let mut args = vec!["/Apply-Image".to_string()];
+    args.extend(dism_image_file_args("/ImageFile", image_file)?);
+    args.push(format!("/ApplyDir:{}", target));
+    args.push(format!("/Index:{}", wim_index));
 
     if compact_os {
         args.push("/compact".to_string());
@@ -375,11 +406,14 @@ pub fn dism_apply_image_with_progress(
     let target = first_two_chars(target_disk); // e.g., "E:"
 
     // Create formatted strings first so they live long enough
-    let image_file_arg = format!("/ImageFile:{}", image_file);
+    let image_file_args = dism_image_file_args("/ImageFile", image_file)?;
     let apply_dir_arg = format!("/ApplyDir:{}", target);
     let index_arg = format!("/Index:{}", wim_index);
 
-    let mut args: Vec<&str> = vec!["/Apply-Image", &image_file_arg, &apply_dir_arg, &index_arg];
+    let mut args: Vec<&str> = vec!["/Apply-Image"];
+    args.extend(image_file_args.iter().map(|s| s.as_str()));
+    args.push(&apply_dir_arg);
+    args.push(&index_arg);
 
     if compact_os {
         args.push("/compact");
@@ -414,7 +448,25 @@ pub fn imagex_apply(
         image_file, target_disk, wim_index
     );
 
-    CommandExecutor::execute(imagex_path, &["/apply", image_file, wim_index, target_disk])?;
+    let path = std::path::Path::new(image_file);
+    if wim::is_split_wim(path) {
+        let set = wim::resolve_split_wim(path)?;
+        let first_part = set.first_part().to_string_lossy().to_string();
+        let ref_pattern = set.ref_pattern();
+        CommandExecutor::execute(
+            imagex_path,
+            &[
+                "/apply",
+                &first_part,
+                wim_index,
+                target_disk,
+                "/ref",
+                &ref_pattern,
+            ],
+        )?;
+    } else {
+        CommandExecutor::execute(imagex_path, &["/apply", image_file, wim_index, target_disk])?;
+    }
 
     Ok(())
 }
@@ -432,16 +484,12 @@ pub fn wimboot_apply(
     let target = first_two_chars(apply_dir);
 
     // Export image with WIMBoot
-    CommandExecutor::execute(
-        "Dism.exe",
-        &[
-            "/Export-Image",
-            "/WIMBoot",
-            &format!("/SourceImageFile:{}", source_image),
-            &format!("/SourceIndex:{}", wim_index),
-            &format!("/DestinationImageFile:{}wimboot.wim", dest_disk),
-        ],
-    )?;
+    let mut export_args = vec!["/Export-Image".to_string(), "/WIMBoot".to_string()];
+    export_args.extend(dism_image_file_args("/SourceImageFile", source_image)?);
+    export_args.push(format!("/SourceIndex:{}", wim_index));
+    export_args.push(format!("/DestinationImageFile:{}wimboot.wim", dest_disk));
+    let export_refs: Vec<&str> = export_args.iter().map(|s| s.as_str()).collect();
+    CommandExecutor::execute("Dism.exe", &export_refs)?;
 
     // Apply WIMBoot image
     CommandExecutor::execute(
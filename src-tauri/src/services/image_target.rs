@@ -0,0 +1,402 @@
+//! Disk image files as write targets
+//!
+//! For `TargetKind::ImageFile` writes, creates the raw image or VHD/VHDX file named by
+//! `target_disk.device` and reports where the virtual disk's bytes sit in it, so the
+//! platform backend can attach it as a block device and partition and fill it like a
+//! USB drive. A file created for a write is removed again unless the write succeeds.
+//!
+//! The VHD/VHDX apply modes create their container the same way, as a fixed virtual disk
+//! in the root of the target's system partition.
+
+use crate::models::{ApplyMode, ImageTargetConfig, TargetKind, VhdType, WtgConfig};
+use crate::utils::vhd::{create_virtual_disk, VhdDiskType};
+use crate::utils::vhdx::Vhdx;
+use crate::{AppError, Result};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Image sizes are rounded up to whole MiB so the last partition stays aligned.
+const SIZE_GRANULARITY: u64 = 1024 * 1024;
+
+/// Container size when `VhdConfig::size_mb` is 0, as on Windows.
+const DEFAULT_VHD_SIZE_MB: u64 = 40960;
+/// Space left free on the system partition next to a container.
+const VHD_HOST_HEADROOM: u64 = 256 * SIZE_GRANULARITY;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFileFormat {
+    Raw,
+    Vhd,
+    Vhdx,
+}
+
+impl ImageFileFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match ext.as_str() {
+            "img" | "raw" | "bin" => Ok(ImageFileFormat::Raw),
+            "vhd" => Ok(ImageFileFormat::Vhd),
+            "vhdx" => Ok(ImageFileFormat::Vhdx),
+            _ => Err(AppError::InvalidParameter(format!(
+                "Unsupported image target {}: use a .img, .raw, .vhd or .vhdx file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// The image file of a write. It is deleted when dropped unless [`ImageFile::keep`]
+/// was called.
+#[derive(Debug)]
+pub struct ImageFile {
+    pub path: PathBuf,
+    /// Virtual disk size in bytes
+    pub size: u64,
+    /// File offset of the virtual disk's bytes, `None` if they are not stored contiguously
+    pub data_offset: Option<u64>,
+    keep: bool,
+}
+
+impl ImageFile {
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for ImageFile {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Image options of an image-file write, `None` when writing to a physical drive.
+pub fn image_target_options(config: &WtgConfig) -> Option<ImageTargetConfig> {
+    (config.target_kind == TargetKind::ImageFile)
+        .then(|| config.image_target.clone().unwrap_or_default())
+}
+
+/// Create the image file a write goes into: `target_disk.device`, sized
+/// `target_disk.size` bytes. Only fixed VHD/VHDX files can be attached outside
+/// Windows, so expandable ones are rejected before anything is created.
+pub fn create_image_file(config: &WtgConfig, options: &ImageTargetConfig) -> Result<ImageFile> {
+    let path = PathBuf::from(config.target_disk.device.trim());
+    if path.as_os_str().is_empty() {
+        return Err(AppError::InvalidParameter(
+            "Image target path is empty".to_string(),
+        ));
+    }
+    let format = ImageFileFormat::from_path(&path)?;
+    if format != ImageFileFormat::Raw && options.vhd_type == VhdType::Expandable {
+        return Err(AppError::Unsupported(
+            "Expandable VHD/VHDX images can only be written on Windows; use a fixed disk or a raw .img"
+                .to_string(),
+        ));
+    }
+    let size = config.target_disk.size.div_ceil(SIZE_GRANULARITY) * SIZE_GRANULARITY;
+    if size == 0 {
+        return Err(AppError::InvalidParameter(
+            "Image target size must be greater than zero".to_string(),
+        ));
+    }
+    if path.exists() {
+        if !options.overwrite || !path.is_file() {
+            return Err(AppError::DiskError(format!(
+                "{} already exists",
+                path.display()
+            )));
+        }
+        fs::remove_file(&path).map_err(AppError::io)?;
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(AppError::io)?;
+    }
+
+    create_disk_file(path, format, size)
+}
+
+/// Create a raw image or a fixed VHD/VHDX file.
+fn create_disk_file(path: PathBuf, format: ImageFileFormat, size: u64) -> Result<ImageFile> {
+    // Own the path from here on, so a failed creation leaves nothing behind.
+    let mut image = ImageFile {
+        path,
+        size,
+        data_offset: None,
+        keep: false,
+    };
+    image.data_offset = match format {
+        ImageFileFormat::Raw => {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&image.path)
+                .and_then(|file| file.set_len(size))
+                .map_err(AppError::io)?;
+            Some(0)
+        }
+        ImageFileFormat::Vhd => {
+            create_virtual_disk(&image.path, size, VhdDiskType::Fixed)?;
+            Some(0)
+        }
+        ImageFileFormat::Vhdx => {
+            create_virtual_disk(&image.path, size, VhdDiskType::Fixed)?;
+            Vhdx::open(&image.path)?.contiguous_data_offset()?
+        }
+    };
+    Ok(image)
+}
+
+/// The container of a VHD/VHDX apply mode write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdContainer {
+    /// File name in the root of the system partition, e.g. "win8.vhdx"
+    pub filename: String,
+    /// Virtual disk size in bytes
+    pub size: u64,
+    /// Partition the virtual disk as GPT (`VhdConfig::partition_type` 1) instead of MBR
+    pub gpt: bool,
+}
+
+/// The container a VHD/VHDX apply mode write puts Windows into, `None` for a legacy
+/// apply. `host_size` is the size of the system partition it is stored on. Outside
+/// Windows only fixed containers can be attached, so expandable ones are rejected.
+pub fn vhd_container(config: &WtgConfig, host_size: u64) -> Result<Option<VhdContainer>> {
+    if config.apply_mode == ApplyMode::Legacy {
+        return Ok(None);
+    }
+    let vhd_config = config.vhd_config.as_ref().ok_or_else(|| {
+        AppError::InvalidParameter("VHD apply mode needs a VHD configuration".to_string())
+    })?;
+    if vhd_config.vhd_type == VhdType::Expandable {
+        return Err(AppError::Unsupported(
+            "Expandable VHD/VHDX containers can only be created on Windows; use a fixed container"
+                .to_string(),
+        ));
+    }
+    let name = vhd_config.filename.trim();
+    if name.is_empty() || name.contains(['/', '\\', ':']) {
+        return Err(AppError::InvalidParameter(format!(
+            "Invalid VHD file name: {}",
+            vhd_config.filename
+        )));
+    }
+    let filename = format!("{}.{}", name, vhd_config.extension.trim().to_lowercase());
+    if ImageFileFormat::from_path(Path::new(&filename))? == ImageFileFormat::Raw {
+        return Err(AppError::InvalidParameter(format!(
+            "VHD container must be a .vhd or .vhdx file: {}",
+            filename
+        )));
+    }
+
+    let available =
+        host_size.saturating_sub(VHD_HOST_HEADROOM) / SIZE_GRANULARITY * SIZE_GRANULARITY;
+    let size = match vhd_config.size_mb {
+        0 => (DEFAULT_VHD_SIZE_MB * SIZE_GRANULARITY).min(available),
+        mb => mb as u64 * SIZE_GRANULARITY,
+    };
+    if size == 0 || size > available {
+        return Err(AppError::DiskError(format!(
+            "A {} MiB VHD does not fit on the {} MiB system partition",
+            size / SIZE_GRANULARITY,
+            host_size / SIZE_GRANULARITY
+        )));
+    }
+    Ok(Some(VhdContainer {
+        filename,
+        size,
+        gpt: vhd_config.partition_type == 1,
+    }))
+}
+
+/// Create the fixed container file in `dir`, the mounted system partition.
+pub fn create_vhd_container(dir: &Path, container: &VhdContainer) -> Result<ImageFile> {
+    let path = dir.join(&container.filename);
+    let format = ImageFileFormat::from_path(&path)?;
+    create_disk_file(path, format, container.size)
+}
+
+/// The write configuration for the attached image: `target_disk` becomes the block
+/// device the image is attached as, everything else stays as requested.
+pub fn attached_config(config: &WtgConfig, image: &ImageFile, id: &str, device: &str) -> WtgConfig {
+    let mut attached = config.clone();
+    attached.target_kind = TargetKind::Disk;
+    attached.image_target = None;
+    attached.target_disk.id = id.to_string();
+    attached.target_disk.device = device.to_string();
+    attached.target_disk.size = image.size;
+    if attached.target_disk.name.trim().is_empty() {
+        attached.target_disk.name = image
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+    attached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        BootMode, Disk, ExtraFeatures, ImageType, PartitionConfig, PartitionLayout, VhdConfig,
+    };
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wtg-target-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn image_config(path: &Path, size: u64) -> WtgConfig {
+        WtgConfig {
+            image_path: "install.wim".to_string(),
+            image_type: ImageType::Wim,
+            wim_index: "1".to_string(),
+            target_disk: Disk {
+                id: String::new(),
+                name: String::new(),
+                size,
+                removable: true,
+                device: path.to_string_lossy().to_string(),
+                drive_type: String::new(),
+                index: String::new(),
+                volume: String::new(),
+            },
+            target_kind: TargetKind::ImageFile,
+            image_target: None,
+            boot_mode: BootMode::UefiGpt,
+            apply_mode: ApplyMode::Legacy,
+            partition_config: PartitionConfig {
+                boot_size: 0,
+                partition_layout: PartitionLayout::GPT,
+                extra_partition_sizes: Vec::new(),
+            },
+            vhd_config: None,
+            extra_features: ExtraFeatures::default(),
+            efi_partition_size: "300".to_string(),
+            efi_partition_path: None,
+        }
+    }
+
+    #[test]
+    fn creates_raw_images_and_respects_existing_files() {
+        let path = temp_path("golden.img");
+        let config = image_config(&path, 64 * MIB - 1000);
+        let options = image_target_options(&config).unwrap();
+        assert_eq!(options.vhd_type, VhdType::Fixed);
+
+        let mut image = create_image_file(&config, &options).unwrap();
+        assert_eq!((image.size, image.data_offset), (64 * MIB, Some(0)));
+        assert_eq!(fs::metadata(&path).unwrap().len(), 64 * MIB);
+        image.keep();
+        drop(image);
+
+        assert!(create_image_file(&config, &options).is_err());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 64 * MIB);
+
+        let overwrite = ImageTargetConfig {
+            overwrite: true,
+            ..options
+        };
+        let image = create_image_file(&image_config(&path, 32 * MIB), &overwrite).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 32 * MIB);
+        let attached = attached_config(&config, &image, "loop7", "/dev/loop7");
+        assert_eq!(attached.target_kind, TargetKind::Disk);
+        assert_eq!(attached.target_disk.device, "/dev/loop7");
+        assert_eq!(attached.target_disk.size, 32 * MIB);
+        assert_eq!(
+            attached.target_disk.name,
+            path.file_name().unwrap().to_string_lossy()
+        );
+        assert!(attached.target_disk.removable);
+
+        // Not kept: a failed write leaves no half-written image behind.
+        drop(image);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn locates_the_data_of_virtual_disks() {
+        for (name, expected) in [("fixed.vhd", 0), ("fixed.vhdx", 4 * MIB)] {
+            let path = temp_path(name);
+            let options = ImageTargetConfig::default();
+            let image = create_image_file(&image_config(&path, 64 * MIB), &options).unwrap();
+            assert_eq!(image.data_offset, Some(expected), "{}", name);
+        }
+        let expandable = ImageTargetConfig {
+            vhd_type: VhdType::Expandable,
+            overwrite: false,
+        };
+        for name in ["dynamic.vhd", "dynamic.vhdx"] {
+            let path = temp_path(name);
+            assert!(create_image_file(&image_config(&path, 64 * MIB), &expandable).is_err());
+            assert!(!path.exists(), "{}", name);
+        }
+        assert!(image_target_options(&WtgConfig {
+            target_kind: TargetKind::Disk,
+            ..image_config(Path::new("disk.img"), 0)
+        })
+        .is_none());
+        assert!(ImageFileFormat::from_path(Path::new("disk.iso")).is_err());
+    }
+
+    #[test]
+    fn sizes_vhd_containers_to_the_system_partition() {
+        let vhd = |size_mb, vhd_type, extension: &str| WtgConfig {
+            apply_mode: ApplyMode::VHDX,
+            vhd_config: Some(VhdConfig {
+                size_mb,
+                vhd_type,
+                extension: extension.to_string(),
+                filename: "win8".to_string(),
+                partition_type: 1,
+            }),
+            ..image_config(Path::new("disk.img"), 0)
+        };
+        let host = 64 * 1024 * MIB;
+
+        let auto = vhd_container(&vhd(0, VhdType::Fixed, "VHDX"), host)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            auto,
+            VhdContainer {
+                filename: "win8.vhdx".to_string(),
+                size: DEFAULT_VHD_SIZE_MB * MIB,
+                gpt: true,
+            }
+        );
+        let small = vhd_container(&vhd(0, VhdType::Fixed, "vhd"), 8 * 1024 * MIB)
+            .unwrap()
+            .unwrap();
+        assert_eq!(small.size, 8 * 1024 * MIB - VHD_HOST_HEADROOM);
+
+        assert!(vhd_container(&vhd(65536, VhdType::Fixed, "vhd"), host).is_err());
+        assert!(vhd_container(&vhd(0, VhdType::Expandable, "vhd"), host).is_err());
+        assert!(vhd_container(&vhd(0, VhdType::Fixed, "img"), host).is_err());
+        assert!(vhd_container(&image_config(Path::new("disk.img"), 0), host)
+            .unwrap()
+            .is_none());
+
+        let dir = temp_path("vhd-host");
+        fs::create_dir_all(&dir).unwrap();
+        let container = VhdContainer {
+            filename: "win8.vhd".to_string(),
+            size: 64 * MIB,
+            gpt: false,
+        };
+        let image = create_vhd_container(&dir, &container).unwrap();
+        assert_eq!(image.path, dir.join("win8.vhd"));
+        assert_eq!(image.data_offset, Some(0));
+        drop(image);
+        assert!(!dir.join("win8.vhd").exists());
+        fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -1,4 +1,6 @@
 pub mod benchmark;
+pub mod benchmark_history;
+pub mod image_target;
 
 #[cfg(target_os = "windows")]
 pub mod boot;
@@ -13,3 +15,11 @@ pub mod write;
 
 #[cfg(target_os = "macos")]
 pub mod write_macos;
+
+#[cfg(target_os = "linux")]
+pub mod write_linux;
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub mod extra_features;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub mod wimlib;
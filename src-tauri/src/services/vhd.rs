@@ -3,9 +3,11 @@
 
 #![allow(dead_code)]
 
+use crate::models::VirtualDiskInfo;
 use crate::models::{BootMode, FirmwareType};
 use crate::services::boot;
 use crate::utils::command::{run_diskpart_script, wait_for_path, CommandExecutor};
+use crate::utils::vhd::{self as vhd_file, VhdDiskType};
 use crate::{AppError, Result};
 use regex::Regex;
 use tracing::info;
@@ -131,6 +133,8 @@ pub fn clean_temp(vhd_name: &str) -> Result<()> {
 
 /// Create and attach a VHD, then apply image
 /// Equivalent to VHDOperation.CreateVHD()
+/// The container is created natively and attached, partitioned and formatted with the
+/// Storage cmdlets, so neither VHD nor VHDX goes through diskpart here.
 pub fn create_vhd(
     vhd_path: &str,
     vhd_type: &str,
@@ -138,34 +142,42 @@ pub fn create_vhd(
     image_type: &str,
     vhd_partition_type: u8,
 ) -> Result<()> {
-    let mut script = String::new();
+    let attach = format!(
+        "$ErrorActionPreference = 'Stop'; \
+         $disk = Mount-DiskImage -ImagePath '{}' -PassThru | Get-Disk",
+        vhd_path.replace('\'', "''")
+    );
 
     let image_type_lower = image_type.to_lowercase();
-    if image_type_lower == "vhd" || image_type_lower == "vhdx" {
+    let script = if image_type_lower == "vhd" || image_type_lower == "vhdx" {
         // Import existing VHD
-        script.push_str(&format!("select vdisk file=\"{}\"\n", vhd_path));
-        script.push_str("attach vdisk\n");
-        script.push_str("assign letter=v\n");
-        script.push_str("exit\n");
+        format!(
+            "{}; Get-Partition -DiskNumber $disk.Number -PartitionNumber 1 | \
+             Set-Partition -NewDriveLetter V",
+            attach
+        )
     } else {
-        // Create new VHD
-        script.push_str(&format!(
-            "create vdisk file=\"{}\" type={} maximum={}\n",
-            vhd_path, vhd_type, vhd_size
-        ));
-        script.push_str(&format!("select vdisk file=\"{}\"\n", vhd_path));
-        script.push_str("attach vdisk\n");
-        if vhd_partition_type == 1 {
-            script.push_str("convert gpt\n");
-        }
-        script.push_str("create partition primary\n");
-        script.push_str("format fs=ntfs quick\n");
-        script.push_str("assign letter=v\n");
-        script.push_str("exit\n");
-    }
+        // Create new VHD/VHDX container, then attach and format it. Initialize-Disk may
+        // add an MSR to a GPT disk; it is removed so the system stays partition 1.
+        create_vhd_file(vhd_path, vhd_type, vhd_size)?;
+        let style = if vhd_partition_type == 1 {
+            "GPT"
+        } else {
+            "MBR"
+        };
+        format!(
+            "{}; Initialize-Disk -Number $disk.Number -PartitionStyle {}; \
+             Get-Partition -DiskNumber $disk.Number -ErrorAction SilentlyContinue | \
+             Remove-Partition -Confirm:$false; \
+             New-Partition -DiskNumber $disk.Number -UseMaximumSize -DriveLetter V | \
+             Format-Volume -FileSystem NTFS -Confirm:$false | Out-Null",
+            attach, style
+        )
+    };
 
     info!("Creating VHD: {}", vhd_path);
-    run_diskpart_script(&script)?;
+    CommandExecutor::execute("powershell.exe", &["-NoProfile", "-Command", &script])?;
+    wait_for_path("V:\\", 100, 100);
 
     // Verify V: drive exists
     if !std::path::Path::new("V:\\").exists() {
@@ -177,6 +189,39 @@ pub fn create_vhd(
     Ok(())
 }
 
+/// Create an empty VHD/VHDX container without diskpart
+/// `vhd_type` is "fixed" or "expandable" and `vhd_size` is in MB, as for `create vdisk`
+fn create_vhd_file(vhd_path: &str, vhd_type: &str, vhd_size: &str) -> Result<()> {
+    let size_mb: u64 = vhd_size
+        .parse()
+        .map_err(|_| AppError::InvalidParameter(format!("Invalid VHD size: {}", vhd_size)))?;
+    let disk_type = if vhd_type == "fixed" {
+        VhdDiskType::Fixed
+    } else {
+        VhdDiskType::Dynamic
+    };
+
+    let size = size_mb
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| AppError::InvalidParameter(format!("Invalid VHD size: {}", vhd_size)))?;
+
+    info!(
+        "Creating {} VHD file: {} ({} MB)",
+        vhd_type, vhd_path, size_mb
+    );
+    vhd_file::create_virtual_disk(std::path::Path::new(vhd_path), size, disk_type)
+}
+
+/// Validate a VHD/VHDX selected for import before it is copied to the target drive
+pub fn validate_import_image(image_path: &str) -> Result<VirtualDiskInfo> {
+    let info = vhd_file::validate_import(std::path::Path::new(image_path))?;
+    info!(
+        "Import {}: type={}, virtual={} bytes, allocated={} bytes",
+        info.format, info.disk_type, info.virtual_size, info.allocated_size
+    );
+    Ok(info)
+}
+
 /// Attach an existing VHD and assign letter V:
 pub fn attach_vhd(vhd_path: &str) -> Result<()> {
     let script = format!(
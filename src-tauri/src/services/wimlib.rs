@@ -0,0 +1,276 @@
+//! wimlib-imagex helpers shared by the macOS and Linux write services
+//! - install image lookup inside a mounted ISO
+//! - image listing (built-in WIM reader, `wimlib-imagex info` as fallback), WIM
+//!   index resolution and the image's default language
+
+use crate::models::ImageInfo;
+use crate::utils::wim;
+use crate::{AppError, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::warn;
+
+fn to_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn parse_u64_digits(value: &str) -> Option<u64> {
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    digits.parse::<u64>().ok()
+}
+
+/// `wimlib-imagex apply` arguments naming the source: the image itself, or for a split
+/// WIM the first part plus `--ref` covering the rest of the set.
+pub fn wimlib_source_args(image_path: &Path) -> Result<(PathBuf, Option<String>)> {
+    if !wim::is_split_wim(image_path) {
+        return Ok((image_path.to_path_buf(), None));
+    }
+    let set = wim::resolve_split_wim(image_path)?;
+    Ok((
+        set.first_part().to_path_buf(),
+        Some(format!("--ref={}", set.ref_pattern())),
+    ))
+}
+
+/// Validate the requested WIM index, or pick the first image when it is empty/"0".
+pub fn resolve_wim_index(image_path: &Path, requested: &str) -> Result<String> {
+    let infos = read_wim_image_info(image_path)?;
+    if infos.is_empty() {
+        return Err(AppError::ImageError(format!(
+            "No image index found in {}",
+            image_path.display()
+        )));
+    }
+
+    let req = requested.trim();
+    if !req.is_empty() && req != "0" {
+        let parsed = req
+            .parse::<u32>()
+            .map_err(|_| AppError::InvalidParameter(format!("Invalid WIM index: {}", requested)))?;
+        if infos.iter().any(|i| i.index == parsed) {
+            return Ok(parsed.to_string());
+        }
+        return Err(AppError::InvalidParameter(format!(
+            "WIM index {} not found in image",
+            parsed
+        )));
+    }
+
+    Ok(infos[0].index.to_string())
+}
+
+/// Locate `sources/install.wim`, `install.esd` or the first `install.swm` part under a
+/// mounted ISO.
+pub fn find_install_image_in_mount(mount_point: &Path) -> Option<PathBuf> {
+    let sources = ["sources", "Sources", "SOURCES"];
+    let files = [
+        "install.wim",
+        "install.esd",
+        "install.swm",
+        "INSTALL.WIM",
+        "INSTALL.ESD",
+        "INSTALL.SWM",
+    ];
+
+    for src in sources {
+        for file in files {
+            let candidate = mount_point.join(src).join(file);
+            if candidate.exists() {
+                return Some(candidate);
+            }
+        }
+    }
+
+    let sources_dir = fs::read_dir(mount_point).ok()?.find_map(|entry| {
+        let entry = entry.ok()?;
+        let file_type = entry.file_type().ok()?;
+        if !file_type.is_dir() {
+            return None;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.eq_ignore_ascii_case("sources") {
+            Some(entry.path())
+        } else {
+            None
+        }
+    })?;
+
+    fs::read_dir(sources_dir).ok()?.find_map(|entry| {
+        let entry = entry.ok()?;
+        let file_type = entry.file_type().ok()?;
+        if !file_type.is_file() {
+            return None;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_install_image = ["install.wim", "install.esd", "install.swm"]
+            .iter()
+            .any(|f| name.eq_ignore_ascii_case(f));
+        if is_install_image {
+            Some(entry.path())
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse the image list printed by `wimlib-imagex info`.
+pub fn parse_wimlib_image_info(raw: &str) -> Vec<ImageInfo> {
+    let mut results = Vec::new();
+    let mut current: Option<ImageInfo> = None;
+
+    for line in raw.lines() {
+        let trimmed = line.trim();
+        if let Some(v) = trimmed.strip_prefix("Index:") {
+            if let Some(item) = current.take() {
+                results.push(item);
+            }
+            let index = v.trim().parse::<u32>().unwrap_or(0);
+            current = Some(ImageInfo {
+                index,
+                name: format!("Image {}", index),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(item) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(v) = trimmed.strip_prefix("Name:") {
+            let name = v.trim();
+            if !name.is_empty() {
+                item.name = name.to_string();
+            }
+            continue;
+        }
+
+        if let Some(v) = trimmed.strip_prefix("Description:") {
+            let desc = v.trim();
+            if !desc.is_empty() {
+                item.description = desc.to_string();
+            }
+            continue;
+        }
+
+        if let Some(v) = trimmed.strip_prefix("Total Bytes:") {
+            if let Some(bytes) = parse_u64_digits(v.trim()) {
+                item.size = bytes;
+            }
+            continue;
+        }
+    }
+
+    if let Some(item) = current {
+        results.push(item);
+    }
+
+    results.retain(|i| i.index > 0);
+    results
+}
+
+/// List the images of a WIM/ESD/SWM file from its header and XML metadata, falling back
+/// to `wimlib-imagex info` for files the built-in reader rejects.
+pub fn read_wim_image_info(image_path: &Path) -> Result<Vec<ImageInfo>> {
+    match wim::read_image_info(image_path) {
+        Ok(images) if !images.is_empty() => Ok(images),
+        Ok(_) => get_wimlib_image_info(image_path),
+        Err(e) => {
+            warn!(
+                "Built-in WIM reader failed for {}: {}; trying wimlib-imagex",
+                image_path.display(),
+                e
+            );
+            get_wimlib_image_info(image_path).map_err(|_| e)
+        }
+    }
+}
+
+/// First language recorded for the image at `index`, used as the boot menu locale.
+pub fn image_default_language(image_path: &Path, index: &str) -> Option<String> {
+    let index = index.trim().parse::<u32>().ok()?;
+    read_wim_image_info(image_path)
+        .ok()?
+        .into_iter()
+        .find(|i| i.index == index)?
+        .languages
+        .into_iter()
+        .next()
+}
+
+/// Run `wimlib-imagex info` and return the images it lists.
+pub fn get_wimlib_image_info(image_path: &Path) -> Result<Vec<ImageInfo>> {
+    let output = Command::new("wimlib-imagex")
+        .args(["info", image_path.to_string_lossy().as_ref()])
+        .output()
+        .map_err(AppError::io)?;
+    if !output.status.success() {
+        let err = to_text(&output.stderr);
+        let out = to_text(&output.stdout);
+        let detail = if err.is_empty() { out } else { err };
+        return Err(AppError::ImageError(format!(
+            "wimlib-imagex info failed: {}",
+            detail
+        )));
+    }
+
+    Ok(parse_wimlib_image_info(&to_text(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_INFO: &str = "WIM Information:
+----------------
+Path:           /media/iso/sources/install.wim
+GUID:           0x2bd2bd7b91b6a94ab4e0a93a3e8d9c2f
+Version:        68864
+Image Count:    2
+Compression:    LZX
+Chunk Size:     32768 bytes
+Part Number:    1/1
+Boot Index:     0
+Size:           4982342135 bytes
+Attributes:     Relative path junction
+
+Available Images:
+-----------------
+Index:                  1
+Name:                   Windows 11 Home
+Description:            Windows 11 Home
+Display Name:           Windows 11 Home
+Directory Count:        24012
+File Count:             98521
+Total Bytes:            17,214,337,182
+Hard Link Bytes:        6821350880
+
+Index:                  2
+Name:                   Windows 11 Pro
+Description:            
+Total Bytes:            17408115262
+";
+
+    #[test]
+    fn parses_wimlib_info_images() {
+        let images = parse_wimlib_image_info(SAMPLE_INFO);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].index, 1);
+        assert_eq!(images[0].name, "Windows 11 Home");
+        assert_eq!(images[0].description, "Windows 11 Home");
+        assert_eq!(images[0].size, 17_214_337_182);
+        assert_eq!(images[1].name, "Windows 11 Pro");
+        assert_eq!(images[1].description, "");
+        assert_eq!(images[1].size, 17_408_115_262);
+    }
+
+    #[test]
+    fn header_fields_before_first_index_are_ignored() {
+        let images = parse_wimlib_image_info("Path: x.wim\nName: bogus\nImage Count: 0\n");
+        assert!(images.is_empty());
+    }
+}
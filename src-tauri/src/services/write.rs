@@ -12,6 +12,7 @@ use crate::models::*;
 use crate::services::{boot, diskpart, image, vhd};
 use crate::utils::command::{self, wait_for_path, CommandExecutor};
 use crate::utils::first_char;
+use crate::utils::partition_plan::{self, DiskGeometry};
 use crate::utils::progress::PROGRESS_REPORTER;
 use crate::utils::task_manager;
 use crate::{AppError, Result};
@@ -182,6 +183,32 @@ fn execute_write_inner(
     result
 }
 
+/// Size and sector size of the target disk for the partition planner.
+fn target_disk_geometry(config: &WtgConfig) -> DiskGeometry {
+    let disk = &config.target_disk;
+    let reported = CommandExecutor::execute_allow_fail(
+        "powershell.exe",
+        &[
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "$d = Get-Disk -Number {} -ErrorAction SilentlyContinue; \
+               if ($d) {{ Write-Output ('' + $d.Size + ' ' + $d.LogicalSectorSize) }}",
+                disk.index
+            ),
+        ],
+    )
+    .unwrap_or_default();
+    let mut fields = reported.split_whitespace().map(|v| v.parse::<u64>().ok());
+    let size = fields.next().flatten().filter(|&v| v > 0);
+    let sector_size = fields.next().flatten().filter(|&v| v > 0);
+    DiskGeometry {
+        size: size.unwrap_or(disk.size),
+        sector_size: sector_size.unwrap_or(512) as u32,
+        removable: disk.removable || disk.drive_type.contains("Removable"),
+    }
+}
+
 /// The actual write logic, using the resolved image path (WIM/ESD, not ISO)
 fn execute_write_with_image(
     config: &WtgConfig,
@@ -192,7 +219,6 @@ fn execute_write_with_image(
 ) -> Result<()> {
     let disk_index = &config.target_disk.index;
     let volume_letter = &config.target_disk.volume;
-    let drive_type = &config.target_disk.drive_type;
 
     // Build UD path
     let ud = if !volume_letter.is_empty() {
@@ -201,7 +227,7 @@ fn execute_write_with_image(
         String::new()
     };
 
-    let partition_sizes: Vec<u32> = config.partition_config.extra_partition_sizes.clone();
+    let plan = || partition_plan::plan_for_config(config, target_disk_geometry(config));
 
     // Check for cancellation at the start of each boot mode
     match config.boot_mode {
@@ -215,13 +241,7 @@ fn execute_write_with_image(
             info!("Starting UEFI+GPT partition");
             PROGRESS_REPORTER.report_status(task_id, 8.0, "Partitioning disk", "partitioning");
 
-            let resolved_ud = diskpart::diskpart_gpt_uefi(
-                &config.efi_partition_size,
-                disk_index,
-                volume_letter,
-                drive_type,
-                &partition_sizes,
-            )?;
+            let resolved_ud = diskpart::diskpart_gpt_uefi(&plan()?, disk_index, volume_letter)?;
 
             // Use the resolved volume path (diskpart may have auto-assigned a letter)
             let ud = if !resolved_ud.is_empty() {
@@ -296,13 +316,7 @@ fn execute_write_with_image(
             info!("Starting UEFI+MBR partition");
             PROGRESS_REPORTER.report_status(task_id, 8.0, "Partitioning disk", "partitioning");
 
-            diskpart::diskpart_mbr_uefi(
-                &config.efi_partition_size,
-                disk_index,
-                volume_letter,
-                &partition_sizes,
-                false,
-            )?;
+            diskpart::diskpart_mbr_uefi(&plan()?, disk_index, volume_letter, false)?;
 
             // Resolve UD path after partitioning
             let ud = if ud.is_empty() {
@@ -379,7 +393,7 @@ fn execute_write_with_image(
             );
 
             if config.extra_features.repartition {
-                diskpart::diskpart_repartition(volume_letter, &partition_sizes)?;
+                diskpart::diskpart_repartition(&plan()?, volume_letter)?;
             } else if !config.extra_features.do_not_format {
                 diskpart::format_ntfs(volume_letter)?;
             }
@@ -783,6 +797,7 @@ fn execute_vhd_workflow(
 
     if image_type == "vhd" || image_type == "vhdx" {
         // Import existing VHD
+        vhd::validate_import_image(image_path)?;
         vhd::copy_vhd(image_path, ud, &vhd_config.extension)?;
         vhd::twice_attach_and_write_boot(
             ud,
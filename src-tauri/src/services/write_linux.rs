@@ -0,0 +1,1274 @@
+//! Linux write service (WTG migration implementation)
+//! Same pipeline as the macOS write service, on native Linux tooling:
+//! - image index listing for WIM/ESD/ISO (ISO mounted via loop or udisks)
+//! - preflight validation (root, wimlib-imagex, mkntfs; losetup for image targets)
+//! - optional raw/VHD/VHDX image file target, attached as a loop device
+//! - VHD/VHDX apply modes: a fixed container on the system partition, attached as a
+//!   loop device and booted natively
+//! - target disk unmount + GPT/MBR partitioning (native partition table writer)
+//! - FAT32 ESP + NTFS system partition (built-in FAT32 formatter / mkntfs)
+//! - WIM/ESD apply straight onto the NTFS volume (wimlib-imagex NTFS mode)
+//! - UEFI boot file + BCD staging written straight to the unmounted ESP (entries
+//!   re-pointed at the new partitions offline), SetupComplete extras
+
+use crate::models::{BootMode, Disk, ImageInfo, WriteProgress, WriteStatus, WtgConfig};
+use crate::platform::linux::{
+    parent_disks_of, read_disk_geometry, read_mount_entries, reread_partition_table,
+};
+use crate::services::extra_features;
+use crate::services::image_target::{
+    attached_config, create_image_file, create_vhd_container, image_target_options, vhd_container,
+    ImageFile, VhdContainer,
+};
+use crate::services::wimlib::{
+    find_install_image_in_mount, image_default_language, read_wim_image_info, resolve_wim_index,
+    wimlib_source_args,
+};
+use crate::utils::bcd::{fix_boot_entries, BootEntryFix};
+use crate::utils::command::wait_for_path;
+use crate::utils::fat32::{format_fat32_file, Fat32Volume};
+use crate::utils::iso;
+use crate::utils::partition_plan::{
+    plan_for_config, plan_vhd_container, FileSystem, PartitionPlan, PlannedPartition,
+};
+use crate::utils::partition_table::{read_disk_layout, write_disk_layout};
+use crate::utils::progress::PROGRESS_REPORTER;
+use crate::utils::task_manager;
+use crate::{AppError, Result};
+use nix::libc;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Extra directories searched for tools; sbin is often missing from a desktop session PATH.
+const EXTRA_TOOL_DIRS: [&str; 4] = ["/usr/local/sbin", "/usr/sbin", "/sbin", "/usr/bin"];
+
+const ESP_BOOT_DIR: &str = "EFI/Microsoft/Boot";
+const ESP_FALLBACK_DIR: &str = "EFI/Boot";
+
+const IMAGE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_MACHINE_ARM64: u16 = 0xAA64;
+const IMAGE_MACHINE_I386: u16 = 0x014C;
+
+fn to_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn find_command(cmd: &str) -> Option<PathBuf> {
+    let path_var = std::env::var("PATH").unwrap_or_default();
+    let found = path_var
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .chain(EXTRA_TOOL_DIRS)
+        .map(|dir| Path::new(dir).join(cmd))
+        .find(|candidate| candidate.is_file());
+    found
+}
+
+fn command_exists(cmd: &str) -> bool {
+    find_command(cmd).is_some()
+}
+
+fn pick_command(candidates: &[&'static str]) -> Option<&'static str> {
+    candidates.iter().copied().find(|cmd| command_exists(cmd))
+}
+
+fn new_command(cmd: &str) -> Command {
+    Command::new(find_command(cmd).unwrap_or_else(|| PathBuf::from(cmd)))
+}
+
+/// Run a tool to completion, optionally feeding `stdin`, and fail with its output on a
+/// non-zero exit.
+fn run_tool(cmd: &str, args: &[&str], stdin: Option<&str>) -> Result<String> {
+    info!("Executing: {} {}", cmd, args.join(" "));
+    let mut child = new_command(cmd)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(AppError::io)?;
+
+    if let Some(input) = stdin {
+        if let Some(mut pipe) = child.stdin.take() {
+            pipe.write_all(input.as_bytes()).map_err(AppError::io)?;
+        }
+    }
+
+    let output = child.wait_with_output().map_err(AppError::io)?;
+    if !output.status.success() {
+        let err = to_text(&output.stderr);
+        let out = to_text(&output.stdout);
+        let detail = if err.is_empty() { out } else { err };
+        return Err(AppError::CommandFailed(format!("{}: {}", cmd, detail)));
+    }
+    Ok(to_text(&output.stdout))
+}
+
+fn ensure_not_cancelled(cancel_flag: &Arc<AtomicBool>) -> Result<()> {
+    if task_manager::is_cancelled(cancel_flag) {
+        return Err(AppError::SystemError(
+            "Write operation cancelled by user".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+fn requires_root() -> Result<()> {
+    if is_root() {
+        return Ok(());
+    }
+    Err(AppError::DiskError(
+        "Writing a WTG disk on Linux needs root privileges. Restart the app with sudo or pkexec."
+            .to_string(),
+    ))
+}
+
+fn requires_wimlib() -> Result<()> {
+    if command_exists("wimlib-imagex") {
+        return Ok(());
+    }
+    Err(AppError::ImageError(
+        "Missing dependency: wimlib-imagex. Install wimlib (Debian/Ubuntu: apt install wimtools)"
+            .to_string(),
+    ))
+}
+
+fn requires_tooling() -> Result<()> {
+    requires_wimlib()?;
+    if pick_command(&["mkntfs", "mkfs.ntfs"]).is_none() {
+        return Err(AppError::DiskError(
+            "Missing dependency: mkntfs. Install ntfs-3g (Debian/Ubuntu: apt install ntfs-3g)"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn requires_losetup() -> Result<()> {
+    if command_exists("losetup") {
+        return Ok(());
+    }
+    Err(AppError::DiskError(
+        "Missing dependency: losetup. Install util-linux".to_string(),
+    ))
+}
+
+/// A temporary mount point that is unmounted and removed when dropped.
+struct MountGuard {
+    mount_point: PathBuf,
+}
+
+impl MountGuard {
+    fn mount(
+        device: &Path,
+        mount_point: PathBuf,
+        fs_type: Option<&str>,
+        options: &str,
+    ) -> Result<Self> {
+        fs::create_dir_all(&mount_point).map_err(AppError::io)?;
+        let device_text = device.to_string_lossy().to_string();
+        let mount_text = mount_point.to_string_lossy().to_string();
+        let mut args = vec!["-o", options];
+        if let Some(fs_type) = fs_type {
+            args.extend(["-t", fs_type]);
+        }
+        args.extend([device_text.as_str(), mount_text.as_str()]);
+
+        if let Err(e) = run_tool("mount", &args, None) {
+            let _ = fs::remove_dir(&mount_point);
+            return Err(e);
+        }
+        Ok(MountGuard { mount_point })
+    }
+
+    fn path(&self) -> &Path {
+        &self.mount_point
+    }
+}
+
+impl Drop for MountGuard {
+    fn drop(&mut self) {
+        let target = self.mount_point.to_string_lossy().to_string();
+        if let Err(e) = run_tool("umount", &[&target], None) {
+            warn!("Failed to unmount {}: {}", target, e);
+            let _ = run_tool("umount", &["-l", &target], None);
+        }
+        let _ = fs::remove_dir(&self.mount_point);
+    }
+}
+
+/// Loop device set up through udisks for an unprivileged read-only ISO mount.
+struct UdisksLoop {
+    loop_device: String,
+    mount_point: PathBuf,
+}
+
+impl Drop for UdisksLoop {
+    fn drop(&mut self) {
+        let _ = run_tool(
+            "udisksctl",
+            &["unmount", "-b", &self.loop_device, "--no-user-interaction"],
+            None,
+        );
+        if let Err(e) = run_tool(
+            "udisksctl",
+            &[
+                "loop-delete",
+                "-b",
+                &self.loop_device,
+                "--no-user-interaction",
+            ],
+            None,
+        ) {
+            warn!("Failed to delete loop device {}: {}", self.loop_device, e);
+        }
+    }
+}
+
+/// A read-only ISO mount: `mount -o loop` when running as root, udisks otherwise.
+enum MountedIso {
+    Mount(MountGuard),
+    Udisks(UdisksLoop),
+}
+
+impl MountedIso {
+    fn path(&self) -> &Path {
+        match self {
+            MountedIso::Mount(guard) => guard.path(),
+            MountedIso::Udisks(loop_dev) => &loop_dev.mount_point,
+        }
+    }
+}
+
+/// Loop device from `udisksctl loop-setup` output ("Mapped file x.iso as /dev/loop3.").
+fn parse_udisks_loop_device(output: &str) -> Option<String> {
+    let (_, device) = output.trim().rsplit_once(" as ")?;
+    let device = device.trim().trim_end_matches('.');
+    device.starts_with("/dev/loop").then(|| device.to_string())
+}
+
+/// Mount point from `udisksctl mount` output ("Mounted /dev/loop3 at /media/user/CCCOMA").
+fn parse_udisks_mount_point(output: &str) -> Option<PathBuf> {
+    let (_, mount_point) = output.trim().split_once(" at ")?;
+    let mount_point = mount_point.trim().trim_end_matches('.');
+    (!mount_point.is_empty()).then(|| PathBuf::from(mount_point))
+}
+
+fn udisks_mount_iso(iso_path: &Path) -> Result<UdisksLoop> {
+    let iso = iso_path.to_string_lossy().to_string();
+    let setup = run_tool(
+        "udisksctl",
+        &["loop-setup", "-r", "-f", &iso, "--no-user-interaction"],
+        None,
+    )?;
+    let loop_device = parse_udisks_loop_device(&setup).ok_or_else(|| {
+        AppError::ImageError(format!("Unexpected udisksctl loop-setup output: {}", setup))
+    })?;
+    let mut mounted = UdisksLoop {
+        loop_device,
+        mount_point: PathBuf::new(),
+    };
+
+    // udisks may auto-mount the new loop device; then `mount` fails and the mount table has it.
+    let mount_output = run_tool(
+        "udisksctl",
+        &["mount", "-b", &mounted.loop_device, "--no-user-interaction"],
+        None,
+    );
+    let loop_name = mounted.loop_device.trim_start_matches("/dev/").to_string();
+    mounted.mount_point = mount_output
+        .as_deref()
+        .ok()
+        .and_then(parse_udisks_mount_point)
+        .or_else(|| {
+            read_mount_entries()
+                .into_iter()
+                .find(|m| m.device_name == loop_name)
+                .map(|m| PathBuf::from(m.mount_point))
+        })
+        .ok_or_else(|| match mount_output {
+            Err(e) => e,
+            Ok(out) => AppError::ImageError(format!("Unexpected udisksctl mount output: {}", out)),
+        })?;
+    Ok(mounted)
+}
+
+fn mount_iso_readonly(iso_path: &Path, mount_point: PathBuf) -> Result<MountedIso> {
+    if is_root() {
+        return MountGuard::mount(iso_path, mount_point, None, "loop,ro")
+            .map(MountedIso::Mount)
+            .map_err(|e| AppError::ImageError(format!("Failed to mount ISO: {}", e)));
+    }
+    if !command_exists("udisksctl") {
+        return Err(AppError::ImageError(
+            "Mounting an ISO without root privileges needs udisksctl (udisks2)".to_string(),
+        ));
+    }
+    udisks_mount_iso(iso_path)
+        .map(MountedIso::Udisks)
+        .map_err(|e| AppError::ImageError(format!("Failed to mount ISO: {}", e)))
+}
+
+fn install_image_in_iso(mounted: &MountedIso) -> Result<PathBuf> {
+    find_install_image_in_mount(mounted.path()).ok_or_else(|| {
+        AppError::ImageError(
+            "Cannot find sources/install.wim, install.esd or install.swm in ISO".to_string(),
+        )
+    })
+}
+
+struct ResolvedApplyImage {
+    image_path: PathBuf,
+    _mounted_iso: Option<MountedIso>,
+}
+
+fn resolve_apply_image(image_path: &Path, work_dir: &Path) -> Result<ResolvedApplyImage> {
+    if !has_extension(image_path, "iso") {
+        return Ok(ResolvedApplyImage {
+            image_path: image_path.to_path_buf(),
+            _mounted_iso: None,
+        });
+    }
+
+    let mounted = mount_iso_readonly(image_path, work_dir.join("iso"))?;
+    Ok(ResolvedApplyImage {
+        image_path: install_image_in_iso(&mounted)?,
+        _mounted_iso: Some(mounted),
+    })
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .map(|v| v.eq_ignore_ascii_case(ext))
+        .unwrap_or(false)
+}
+
+/// Read the install image list straight out of the ISO; mount it only when the in-place
+/// reader cannot handle the image.
+fn get_image_info_from_iso(iso_path: &Path) -> Result<Vec<ImageInfo>> {
+    match iso::read_install_image_info(iso_path) {
+        Ok(info) if !info.is_empty() => return Ok(info),
+        Ok(_) => {}
+        Err(e) => warn!(
+            "Reading {} in place failed: {}; mounting it instead",
+            iso_path.display(),
+            e
+        ),
+    }
+
+    let mount_point = std::env::temp_dir()
+        .join("WTGA")
+        .join(format!("iso-{}", uuid::Uuid::new_v4()));
+    let mounted = mount_iso_readonly(iso_path, mount_point)?;
+    read_wim_image_info(&install_image_in_iso(&mounted)?)
+}
+
+pub fn get_image_info(image_path: &str) -> Result<Vec<ImageInfo>> {
+    let path = Path::new(image_path);
+    if !path.exists() {
+        return Err(AppError::ImageError(format!(
+            "Image file does not exist: {}",
+            image_path
+        )));
+    }
+
+    let info = if has_extension(path, "iso") {
+        get_image_info_from_iso(path)?
+    } else if ["wim", "esd", "swm"]
+        .iter()
+        .any(|ext| has_extension(path, ext))
+    {
+        read_wim_image_info(path)?
+    } else {
+        return Err(AppError::Unsupported(format!(
+            "Image info is only available for ISO/WIM/ESD/SWM on Linux: {}",
+            image_path
+        )));
+    };
+
+    if info.is_empty() {
+        return Err(AppError::ImageError(format!(
+            "No image index found in {}",
+            image_path
+        )));
+    }
+    Ok(info)
+}
+
+/// Resolve the target to a whole-disk kernel name such as "sdb" or "nvme1n1".
+fn resolve_disk_name(target_disk: &Disk) -> Result<String> {
+    for candidate in [&target_disk.device, &target_disk.id] {
+        let trimmed = candidate.trim();
+        let name = trimmed.strip_prefix("/dev/").unwrap_or(trimmed);
+        if !name.is_empty() && !name.contains('/') && Path::new("/sys/block").join(name).exists() {
+            return Ok(name.to_string());
+        }
+    }
+    Err(AppError::InvalidParameter(format!(
+        "Invalid target disk identifier: id='{}', device='{}', index='{}'",
+        target_disk.id, target_disk.device, target_disk.index
+    )))
+}
+
+/// Device node of partition `number`; kernel names ending in a digit take a "p" separator.
+fn partition_device(disk_name: &str, number: u32) -> PathBuf {
+    if disk_name.ends_with(|c: char| c.is_ascii_digit()) {
+        PathBuf::from(format!("/dev/{}p{}", disk_name, number))
+    } else {
+        PathBuf::from(format!("/dev/{}{}", disk_name, number))
+    }
+}
+
+/// Plan the target layout from the disk's sysfs geometry.
+fn plan_target_disk(config: &WtgConfig, disk_name: &str) -> Result<PartitionPlan> {
+    if config.boot_mode == BootMode::NonUefi {
+        return Err(AppError::Unsupported(
+            "Linux WTG write currently supports UEFI boot modes only".to_string(),
+        ));
+    }
+    let geometry =
+        read_disk_geometry(disk_name, config.target_disk.removable).ok_or_else(|| {
+            AppError::DeviceNotFound(format!("Cannot read the size of /dev/{}", disk_name))
+        })?;
+    plan_for_config(config, geometry)
+}
+
+/// A disk image attached as a partition-scanned loop device, detached when dropped.
+struct LoopDevice {
+    name: String,
+}
+
+impl LoopDevice {
+    fn attach(image: &ImageFile) -> Result<Self> {
+        let offset = image.data_offset.ok_or_else(|| {
+            AppError::Unsupported(format!(
+                "{} does not store its disk contiguously and cannot be attached on Linux",
+                image.path.display()
+            ))
+        })?;
+        let path = image.path.to_string_lossy();
+        let output = run_tool(
+            "losetup",
+            &[
+                "--find",
+                "--show",
+                "--partscan",
+                "--offset",
+                &offset.to_string(),
+                "--sizelimit",
+                &image.size.to_string(),
+                path.as_ref(),
+            ],
+            None,
+        )
+        .map_err(|e| AppError::DiskError(format!("Cannot attach {}: {}", path, e)))?;
+        let name = output.trim().trim_start_matches("/dev/").to_string();
+        if name.is_empty() {
+            return Err(AppError::DiskError(format!(
+                "losetup returned no loop device for {}",
+                path
+            )));
+        }
+        info!("Attached image {} as /dev/{}", path, name);
+        Ok(Self { name })
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let node = format!("/dev/{}", self.name);
+        if let Err(e) = run_tool("losetup", &["--detach", &node], None) {
+            warn!("Failed to detach {}: {}", node, e);
+        }
+    }
+}
+
+/// An image-file target attached for the write. Fields drop in order: the loop
+/// device is detached before an unfinished image file is removed.
+struct AttachedImage {
+    config: WtgConfig,
+    _loop_device: LoopDevice,
+    image: ImageFile,
+}
+
+fn attach_image_target(config: &WtgConfig) -> Result<Option<AttachedImage>> {
+    let Some(options) = image_target_options(config) else {
+        return Ok(None);
+    };
+    requires_losetup()?;
+    let image = create_image_file(config, &options)?;
+    let loop_device = LoopDevice::attach(&image)?;
+    let node = format!("/dev/{}", loop_device.name);
+    Ok(Some(AttachedImage {
+        config: attached_config(config, &image, &loop_device.name, &node),
+        _loop_device: loop_device,
+        image,
+    }))
+}
+
+/// A VHD/VHDX container on the system partition, attached for the apply. Fields drop
+/// in order: the loop device is detached, an unfinished container is removed, then the
+/// system partition is unmounted.
+struct AttachedContainer {
+    _loop_device: LoopDevice,
+    file: ImageFile,
+    _host_mount: MountGuard,
+    /// The container's NTFS partition Windows is applied to
+    partition: PathBuf,
+}
+
+/// Create the container in the root of the freshly formatted system partition, then
+/// partition and format it like Windows does.
+fn attach_vhd_container(
+    container: &VhdContainer,
+    system_partition: &Path,
+    work_dir: &Path,
+) -> Result<AttachedContainer> {
+    let plan = plan_vhd_container(container.size, container.gpt)?;
+    let host_mount = mount_system_partition(system_partition, work_dir.join("host"))?;
+    let file = create_vhd_container(host_mount.path(), container)?;
+    let loop_device = LoopDevice::attach(&file)?;
+
+    let node = PathBuf::from(format!("/dev/{}", loop_device.name));
+    write_disk_layout(&node, &plan.to_disk_layout()).map_err(|e| {
+        AppError::DiskError(format!("Partitioning {} failed: {}", container.filename, e))
+    })?;
+    reread_partition_table(&node)?;
+    let _ = run_tool("udevadm", &["settle", "--timeout=10"], None);
+
+    let system = plan.system();
+    let partition = partition_device(&loop_device.name, system.number);
+    if !wait_for_path(partition.to_string_lossy().as_ref(), 20, 500) {
+        return Err(AppError::DiskError(format!(
+            "Partition {} of {} did not appear",
+            partition.display(),
+            container.filename
+        )));
+    }
+    format_partition(system, &partition, plan.geometry.sector_size)?;
+    info!(
+        "Created {} ({} MiB) on {}",
+        container.filename,
+        container.size / (1024 * 1024),
+        system_partition.display()
+    );
+
+    Ok(AttachedContainer {
+        _loop_device: loop_device,
+        file,
+        _host_mount: host_mount,
+        partition,
+    })
+}
+
+/// Unmount every filesystem living on the target disk, deepest mount points first.
+fn unmount_disk(disk_name: &str) -> Result<()> {
+    let mut mounted: Vec<String> = read_mount_entries()
+        .into_iter()
+        .filter(|m| !m.device_name.is_empty())
+        .filter(|m| {
+            parent_disks_of(&m.device_name)
+                .iter()
+                .any(|d| d == disk_name)
+        })
+        .map(|m| m.mount_point)
+        .collect();
+    mounted.sort_by_key(|m| std::cmp::Reverse(m.len()));
+    mounted.dedup();
+
+    for mount_point in mounted {
+        info!("Unmounting {} from target /dev/{}", mount_point, disk_name);
+        run_tool("umount", &[&mount_point], None).map_err(|e| {
+            AppError::DiskError(format!(
+                "Cannot unmount {} on target disk: {}",
+                mount_point, e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+fn is_system_disk(disk_name: &str) -> bool {
+    read_mount_entries()
+        .iter()
+        .filter(|m| matches!(m.mount_point.as_str(), "/" | "/boot" | "/boot/efi" | "/usr"))
+        .filter(|m| !m.device_name.is_empty())
+        .any(|m| {
+            parent_disks_of(&m.device_name)
+                .iter()
+                .any(|d| d == disk_name)
+        })
+}
+
+#[derive(Debug, Clone)]
+struct PreparedTargetDisk {
+    efi_partition: PathBuf,
+    system_partition: PathBuf,
+    plan: PartitionPlan,
+}
+
+fn prepare_target_disk(config: &WtgConfig, disk_name: &str) -> Result<PreparedTargetDisk> {
+    let plan = plan_target_disk(config, disk_name)?;
+    let disk_node = PathBuf::from(format!("/dev/{}", disk_name));
+
+    unmount_disk(disk_name)?;
+    write_disk_layout(&disk_node, &plan.to_disk_layout()).map_err(|e| {
+        AppError::DiskError(format!(
+            "Partitioning {} failed: {}",
+            disk_node.display(),
+            e
+        ))
+    })?;
+    reread_partition_table(&disk_node)?;
+
+    let _ = run_tool("udevadm", &["settle", "--timeout=10"], None);
+
+    let esp = plan
+        .esp()
+        .ok_or_else(|| AppError::DiskError("Partition plan has no EFI partition".to_string()))?;
+    let prepared = PreparedTargetDisk {
+        efi_partition: partition_device(disk_name, esp.number),
+        system_partition: partition_device(disk_name, plan.system().number),
+        plan: plan.clone(),
+    };
+    let nodes: Vec<PathBuf> = plan
+        .partitions
+        .iter()
+        .map(|p| partition_device(disk_name, p.number))
+        .collect();
+    for node in &nodes {
+        if !wait_for_path(node.to_string_lossy().as_ref(), 20, 500) {
+            return Err(AppError::DiskError(format!(
+                "Partition {} did not appear after repartition",
+                node.display()
+            )));
+        }
+    }
+
+    // The desktop may auto-mount the fresh partitions; release them before formatting.
+    unmount_disk(disk_name)?;
+
+    for (part, node) in plan.partitions.iter().zip(&nodes) {
+        format_partition(part, node, plan.geometry.sector_size)?;
+    }
+
+    Ok(prepared)
+}
+
+fn format_partition(part: &PlannedPartition, node: &Path, sector_size: u32) -> Result<()> {
+    let failed = |e: AppError| {
+        AppError::DiskError(format!(
+            "Formatting partition {} ({:?}) failed: {}",
+            part.number, part.role, e
+        ))
+    };
+    match part.filesystem {
+        FileSystem::Fat32 => {
+            // The node starts at the partition, but the boot sector records
+            // where the volume sits on the whole disk.
+            let hidden_sectors = part.start / sector_size as u64;
+            format_fat32_file(node, 0, part.size, sector_size, hidden_sectors, &part.label)
+                .map_err(failed)?;
+        }
+        FileSystem::Ntfs => {
+            let node_arg = node.to_string_lossy();
+            let mut args = vec!["-Q"];
+            if !part.label.is_empty() {
+                args.extend(["-L", part.label.as_str()]);
+            }
+            args.push(node_arg.as_ref());
+            let tool = pick_command(&["mkntfs", "mkfs.ntfs"]).unwrap_or("mkntfs");
+            run_tool(tool, &args, None).map_err(failed)?;
+        }
+        FileSystem::None => {}
+    }
+    Ok(())
+}
+
+/// Percentage from a `wimlib-imagex apply` progress line such as
+/// "Extracting file data: 1024 MiB of 4096 MiB (25%) done".
+fn parse_apply_progress(line: &str) -> Option<f64> {
+    if !line.trim_start().starts_with("Extracting file data") {
+        return None;
+    }
+    let open = line.rfind('(')?;
+    let close = open + line[open..].find("%)")?;
+    line[open + 1..close].trim().parse::<f64>().ok()
+}
+
+/// Apply the image straight onto the unmounted NTFS partition. wimlib's NTFS mode keeps
+/// ACLs, short names and reparse points that a FUSE-mounted apply would lose.
+fn apply_windows_image(
+    source_image: &Path,
+    wim_index: &str,
+    system_partition: &Path,
+    task_id: &str,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<()> {
+    let (source, ref_arg) = wimlib_source_args(source_image)?;
+    let mut child = new_command("wimlib-imagex")
+        .args([
+            "apply",
+            source.to_string_lossy().as_ref(),
+            wim_index,
+            system_partition.to_string_lossy().as_ref(),
+        ])
+        .args(ref_arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(AppError::io)?;
+
+    let mut stderr = child.stderr.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(pipe) = stderr.as_mut() {
+            let _ = pipe.read_to_string(&mut text);
+        }
+        text
+    });
+
+    // wimlib redraws its progress line with '\r', so split on both line terminators.
+    let (tx, rx) = mpsc::channel::<String>();
+    if let Some(mut stdout) = child.stdout.take() {
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut pending = Vec::new();
+            while let Ok(n) = stdout.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                for &byte in &buf[..n] {
+                    if byte == b'\r' || byte == b'\n' {
+                        if !pending.is_empty() {
+                            let _ = tx.send(String::from_utf8_lossy(&pending).to_string());
+                            pending.clear();
+                        }
+                    } else {
+                        pending.push(byte);
+                    }
+                }
+            }
+        });
+    }
+
+    let mut last_percent = -1.0;
+    let status: ExitStatus = loop {
+        if task_manager::is_cancelled(cancel_flag) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(AppError::SystemError(
+                "Write operation cancelled by user".to_string(),
+            ));
+        }
+
+        match rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(line) => {
+                if let Some(percent) = parse_apply_progress(&line) {
+                    if percent > last_percent {
+                        last_percent = percent;
+                        PROGRESS_REPORTER.report_status(
+                            task_id,
+                            44.0 + percent * 0.26,
+                            &format!("Applying Windows image ({:.0}%)", percent),
+                            "applyingimage",
+                        );
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(status) = child.try_wait().map_err(AppError::io)? {
+                    break status;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                break child.wait().map_err(AppError::io)?;
+            }
+        }
+    };
+
+    let stderr_text = stderr_reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(AppError::ImageError(format!(
+            "wimlib-imagex apply failed: {}",
+            stderr_text.trim()
+        )));
+    }
+    Ok(())
+}
+
+fn mount_system_partition(system_partition: &Path, mount_point: PathBuf) -> Result<MountGuard> {
+    if command_exists("ntfs-3g") {
+        match MountGuard::mount(system_partition, mount_point.clone(), Some("ntfs-3g"), "rw") {
+            Ok(guard) => return Ok(guard),
+            Err(e) => warn!(
+                "ntfs-3g mount failed, trying the ntfs3 kernel driver: {}",
+                e
+            ),
+        }
+    }
+    MountGuard::mount(system_partition, mount_point, Some("ntfs3"), "rw")
+        .map_err(|e| AppError::DiskError(format!("Cannot mount NTFS system partition: {}", e)))
+}
+
+fn verify_applied_system_files(system_mount: &Path) -> Result<()> {
+    let windows_dir = system_mount.join("Windows");
+    let system32_dir = windows_dir.join("System32");
+    if !windows_dir.exists() || !system32_dir.exists() {
+        return Err(AppError::ImageError(format!(
+            "Applied system files are incomplete at {}",
+            system_mount.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Removable-media fallback loader name for the architecture of a PE image.
+fn efi_fallback_name(pe: &[u8]) -> Option<&'static str> {
+    let pe_offset = u32::from_le_bytes(pe.get(0x3C..0x40)?.try_into().ok()?) as usize;
+    if pe.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16::from_le_bytes(pe.get(pe_offset + 4..pe_offset + 6)?.try_into().ok()?);
+    match machine {
+        IMAGE_MACHINE_AMD64 => Some("bootx64.efi"),
+        IMAGE_MACHINE_ARM64 => Some("bootaa64.efi"),
+        IMAGE_MACHINE_I386 => Some("bootia32.efi"),
+        _ => None,
+    }
+}
+
+fn stage_uefi_boot_payload(system_mount: &Path, esp: &mut Fat32Volume<fs::File>) -> Result<()> {
+    let src = system_mount.join("Windows").join("Boot").join("EFI");
+    if !src.is_dir() {
+        return Err(AppError::DiskError(
+            "Windows/Boot/EFI directory not found in applied system".to_string(),
+        ));
+    }
+
+    esp.copy_dir(&src, ESP_BOOT_DIR)?;
+    let loader = esp
+        .read_file(&format!("{}/bootmgfw.efi", ESP_BOOT_DIR))
+        .map_err(|_| {
+            AppError::DiskError("bootmgfw.efi not found after payload copy".to_string())
+        })?;
+    let fallback_name = efi_fallback_name(&loader).unwrap_or("bootx64.efi");
+    esp.write_file(&format!("{}/{}", ESP_FALLBACK_DIR, fallback_name), &loader)
+}
+
+/// Device settings for the store on the freshly partitioned disk, whose partition
+/// numbers come from the plan it was partitioned with.
+fn target_boot_entry_fix(
+    disk_name: &str,
+    plan: &PartitionPlan,
+    locale: Option<String>,
+) -> Result<BootEntryFix> {
+    let layout = read_disk_layout(Path::new(&format!("/dev/{}", disk_name)))?;
+    let esp = plan
+        .esp()
+        .ok_or_else(|| AppError::DiskError("Partition plan has no EFI partition".to_string()))?;
+    Ok(BootEntryFix {
+        locale,
+        ..BootEntryFix::for_partitions(&layout, esp.number, plan.system().number)?
+    })
+}
+
+fn repair_uefi_bcd_store(
+    system_mount: &Path,
+    esp: &mut Fat32Volume<fs::File>,
+    work_dir: &Path,
+    fix: &BootEntryFix,
+) -> Result<()> {
+    let bcd_path = format!("{}/BCD", ESP_BOOT_DIR);
+    let original = if esp.exists(&bcd_path)? {
+        esp.read_file(&bcd_path)?
+    } else {
+        let candidates = [
+            system_mount.join("Windows/Boot/EFI/BCD"),
+            system_mount.join("Boot/BCD"),
+            system_mount.join("Windows/System32/config/BCD-Template"),
+        ];
+        let source = candidates
+            .iter()
+            .find(|p| p.is_file())
+            .ok_or_else(|| AppError::DiskError("No valid BCD source found".to_string()))?;
+        fs::read(source).map_err(AppError::io)?
+    };
+
+    if !original.starts_with(b"regf") {
+        return Err(AppError::DiskError(format!(
+            "Invalid BCD hive header: {}",
+            bcd_path
+        )));
+    }
+    esp.write_file(&format!("{}/BCD.wtga.bak", ESP_BOOT_DIR), &original)?;
+
+    // The hive editor works on files; edit a scratch copy and put the result on the ESP.
+    let scratch = work_dir.join("BCD");
+    fs::write(&scratch, &original).map_err(AppError::io)?;
+    let default = fix_boot_entries(&scratch, fix)?;
+    let fixed = fs::read(&scratch).map_err(AppError::io)?;
+    let _ = fs::remove_file(&scratch);
+    info!("BCD default entry {} now boots {}", default, fix.os_device);
+    esp.write_file(&bcd_path, &fixed)?;
+    esp.write_file(&format!("{}/BCD", ESP_FALLBACK_DIR), &fixed)
+}
+
+fn verify_uefi_boot_files(esp: &mut Fat32Volume<fs::File>) -> Result<()> {
+    let bcd = format!("{}/BCD", ESP_BOOT_DIR);
+    let bootmgfw = format!("{}/bootmgfw.efi", ESP_BOOT_DIR);
+    let fallback = esp
+        .read_file(&bootmgfw)
+        .ok()
+        .and_then(|pe| efi_fallback_name(&pe))
+        .unwrap_or("bootx64.efi");
+    let fallback_loader = format!("{}/{}", ESP_FALLBACK_DIR, fallback);
+
+    for path in [&bcd, &bootmgfw, &fallback_loader] {
+        if !esp.exists(path)? {
+            return Err(AppError::DiskError(format!(
+                "UEFI boot artifact missing: {}",
+                path
+            )));
+        }
+    }
+    if !esp.read_file(&bcd)?.starts_with(b"regf") {
+        return Err(AppError::DiskError(format!(
+            "Invalid BCD file header: {}",
+            bcd
+        )));
+    }
+    Ok(())
+}
+
+fn sync_filesystems() {
+    // SAFETY: sync has no preconditions.
+    unsafe { libc::sync() };
+}
+
+pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WriteProgress> {
+    let task_id = uuid::Uuid::new_v4().to_string();
+    let started = Instant::now();
+    let cancel_flag = task_manager::TaskManager::register_task(task_id.clone());
+
+    let result = execute_write_inner(config, app_files_path, &task_id, &cancel_flag);
+
+    task_manager::TaskManager::unregister_task(&task_id);
+    let elapsed = started.elapsed().as_secs();
+
+    match result {
+        Ok(message) => {
+            PROGRESS_REPORTER.report_status(&task_id, 100.0, "Write completed", "completed");
+            Ok(WriteProgress {
+                task_id,
+                status: WriteStatus::Completed,
+                progress: 100.0,
+                message,
+                speed: 0.0,
+                elapsed_seconds: elapsed,
+                estimated_remaining_seconds: 0,
+            })
+        }
+        Err(_) if task_manager::is_cancelled(&cancel_flag) => {
+            PROGRESS_REPORTER.report_status(&task_id, 0.0, "Write cancelled by user", "cancelled");
+            Ok(WriteProgress {
+                task_id,
+                status: WriteStatus::Cancelled,
+                progress: 0.0,
+                message: "Write operation cancelled".to_string(),
+                speed: 0.0,
+                elapsed_seconds: elapsed,
+                estimated_remaining_seconds: 0,
+            })
+        }
+        Err(e) => {
+            PROGRESS_REPORTER.report_status(&task_id, 0.0, &e.to_string(), "failed");
+            Err(e)
+        }
+    }
+}
+
+fn execute_write_inner(
+    config: &WtgConfig,
+    app_files_path: &str,
+    task_id: &str,
+    cancel_flag: &Arc<AtomicBool>,
+) -> Result<String> {
+    PROGRESS_REPORTER.report_status(
+        task_id,
+        5.0,
+        "Checking Linux write prerequisites",
+        "preparing",
+    );
+
+    let image_path = Path::new(&config.image_path);
+    if !image_path.exists() {
+        return Err(AppError::ImageError(format!(
+            "Image file does not exist: {}",
+            config.image_path
+        )));
+    }
+
+    if matches!(config.boot_mode, BootMode::NonUefi) {
+        return Err(AppError::Unsupported(
+            "Linux WTG write currently supports UEFI boot modes only".to_string(),
+        ));
+    }
+
+    let allow_repartition =
+        config.extra_features.repartition || !config.extra_features.do_not_format;
+    if !allow_repartition {
+        return Err(AppError::Unsupported(
+            "Linux WTG write currently requires repartition/format. Please disable 'Do not format' or enable repartition."
+                .to_string(),
+        ));
+    }
+
+    requires_root()?;
+    requires_tooling()?;
+
+    // An image-file target is created and attached here; from then on it is written
+    // like any other disk.
+    let mut attached_image = attach_image_target(config)?;
+    let config = attached_image
+        .as_ref()
+        .map(|attached| &attached.config)
+        .unwrap_or(config);
+
+    let disk_name = resolve_disk_name(&config.target_disk)?;
+    if is_system_disk(&disk_name) {
+        return Err(AppError::DiskError(format!(
+            "Refusing to write to /dev/{}: it holds the running Linux system.",
+            disk_name
+        )));
+    }
+
+    // A VHD/VHDX apply goes into a container on the system partition; make sure it
+    // fits before the disk is repartitioned.
+    let container = vhd_container(config, plan_target_disk(config, &disk_name)?.system().size)?;
+    if container.is_some() {
+        requires_losetup()?;
+    }
+
+    let work_dir = Path::new(app_files_path).join(format!("linux-{}", task_id));
+    fs::create_dir_all(&work_dir).map_err(AppError::io)?;
+
+    let resolved_image = resolve_apply_image(image_path, &work_dir)?;
+    let wim_index = resolve_wim_index(&resolved_image.image_path, &config.wim_index)?;
+    ensure_not_cancelled(cancel_flag)?;
+
+    PROGRESS_REPORTER.report_status(
+        task_id,
+        15.0,
+        "Partitioning target disk (EFI + NTFS system)",
+        "partitioning",
+    );
+    let prepared = prepare_target_disk(config, &disk_name)?;
+    ensure_not_cancelled(cancel_flag)?;
+
+    let mut attached_container = match &container {
+        Some(container) => {
+            PROGRESS_REPORTER.report_status(
+                task_id,
+                30.0,
+                &format!("Creating {}", container.filename),
+                "partitioning",
+            );
+            Some(attach_vhd_container(
+                container,
+                &prepared.system_partition,
+                &work_dir,
+            )?)
+        }
+        None => None,
+    };
+    let windows_partition = attached_container
+        .as_ref()
+        .map(|attached| attached.partition.clone())
+        .unwrap_or_else(|| prepared.system_partition.clone());
+    ensure_not_cancelled(cancel_flag)?;
+
+    PROGRESS_REPORTER.report_status(
+        task_id,
+        40.0,
+        "Applying Windows image to NTFS system partition",
+        "applyingimage",
+    );
+    apply_windows_image(
+        &resolved_image.image_path,
+        &wim_index,
+        &windows_partition,
+        task_id,
+        cancel_flag,
+    )?;
+    ensure_not_cancelled(cancel_flag)?;
+
+    PROGRESS_REPORTER.report_status(task_id, 70.0, "Mounting system partition", "applyingimage");
+    let system_mount = mount_system_partition(&windows_partition, work_dir.join("system"))?;
+    let mut esp = Fat32Volume::open_file(&prepared.efi_partition, 0)
+        .map_err(|e| AppError::DiskError(format!("Cannot open EFI partition: {}", e)))?;
+    verify_applied_system_files(system_mount.path())?;
+
+    PROGRESS_REPORTER.report_status(task_id, 74.0, "Applying extra features", "applyingextras");
+    let extra_outcome =
+        extra_features::apply_via_setup_complete(config, system_mount.path(), "Linux")?;
+    ensure_not_cancelled(cancel_flag)?;
+
+    PROGRESS_REPORTER.report_status(task_id, 82.0, "Staging UEFI boot files", "writingbootfiles");
+    stage_uefi_boot_payload(system_mount.path(), &mut esp)?;
+
+    PROGRESS_REPORTER.report_status(task_id, 88.0, "Fixing BCD for UEFI boot", "fixingbcd");
+    let locale = image_default_language(&resolved_image.image_path, &wim_index);
+    let mut boot_fix = target_boot_entry_fix(&disk_name, &prepared.plan, locale)?;
+    if let Some(container) = &container {
+        boot_fix = boot_fix.in_vhd(&container.filename);
+    }
+    repair_uefi_bcd_store(system_mount.path(), &mut esp, &work_dir, &boot_fix)?;
+
+    PROGRESS_REPORTER.report_status(task_id, 90.0, "Verifying write result", "verifying");
+    verify_uefi_boot_files(&mut esp)?;
+
+    esp.into_inner()?.sync_all().map_err(AppError::io)?;
+    sync_filesystems();
+    drop(system_mount);
+    if let Some(mut attached) = attached_container.take() {
+        attached.file.keep();
+        drop(attached);
+        sync_filesystems();
+    }
+    drop(resolved_image);
+    let _ = fs::remove_dir(&work_dir);
+
+    info!(
+        "Linux WTG deploy completed: system={} efi={}",
+        prepared.system_partition.display(),
+        prepared.efi_partition.display()
+    );
+    if !extra_outcome.applied.is_empty() {
+        info!(
+            "Linux extra features applied: {}",
+            extra_outcome.applied.join(", ")
+        );
+    }
+    if !extra_outcome.unsupported.is_empty() {
+        warn!(
+            "Linux extra features not supported yet: {}",
+            extra_outcome.unsupported.join(", ")
+        );
+    }
+    for note in &extra_outcome.notes {
+        info!("Linux extra features note: {}", note);
+    }
+
+    let mut message = format!(
+        "WTG image applied to NTFS system partition {} and UEFI boot files staged on {}.",
+        prepared.system_partition.display(),
+        prepared.efi_partition.display()
+    );
+    if let Some(container) = &container {
+        message.push_str(&format!(
+            " Windows boots from {} on the system partition.",
+            container.filename
+        ));
+    }
+    if !extra_outcome.applied.is_empty() {
+        message.push_str(&format!(
+            " Applied extra features: {}.",
+            extra_outcome.applied.join(", ")
+        ));
+    }
+    if !extra_outcome.unsupported.is_empty() {
+        message.push_str(&format!(
+            " Not yet supported on Linux: {}.",
+            extra_outcome.unsupported.join(", ")
+        ));
+    }
+    if let Some(attached) = attached_image.as_mut() {
+        attached.image.keep();
+        message.push_str(&format!(
+            " Image written to {}.",
+            attached.image.path.display()
+        ));
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_udisksctl_output() {
+        assert_eq!(
+            parse_udisks_loop_device("Mapped file /home/u/Win11.iso as /dev/loop3.\n"),
+            Some("/dev/loop3".to_string())
+        );
+        assert_eq!(
+            parse_udisks_loop_device("Error setting up loop device"),
+            None
+        );
+        assert_eq!(
+            parse_udisks_mount_point("Mounted /dev/loop3 at /media/u/CCCOMA_X64FRE_EN-US_DV9\n"),
+            Some(PathBuf::from("/media/u/CCCOMA_X64FRE_EN-US_DV9"))
+        );
+    }
+
+    #[test]
+    fn partition_device_names() {
+        assert_eq!(partition_device("sdb", 2), PathBuf::from("/dev/sdb2"));
+        assert_eq!(
+            partition_device("nvme1n1", 1),
+            PathBuf::from("/dev/nvme1n1p1")
+        );
+        assert_eq!(
+            partition_device("mmcblk0", 2),
+            PathBuf::from("/dev/mmcblk0p2")
+        );
+    }
+
+    #[test]
+    fn parses_apply_progress_lines() {
+        assert_eq!(
+            parse_apply_progress("Extracting file data: 1024 MiB of 4096 MiB (25%) done"),
+            Some(25.0)
+        );
+        assert_eq!(
+            parse_apply_progress("Creating files: 1200 of 98521 (1%) done"),
+            None
+        );
+        assert_eq!(parse_apply_progress("Applying image 1 (\"Pro\")"), None);
+    }
+
+    #[test]
+    fn efi_fallback_name_follows_pe_machine() {
+        let mut pe = vec![0u8; 0x100];
+        pe[0..2].copy_from_slice(b"MZ");
+        pe[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+
+        pe[0x84..0x86].copy_from_slice(&IMAGE_MACHINE_AMD64.to_le_bytes());
+        assert_eq!(efi_fallback_name(&pe), Some("bootx64.efi"));
+        pe[0x84..0x86].copy_from_slice(&IMAGE_MACHINE_ARM64.to_le_bytes());
+        assert_eq!(efi_fallback_name(&pe), Some("bootaa64.efi"));
+        assert_eq!(efi_fallback_name(&pe[..0x40]), None);
+    }
+}
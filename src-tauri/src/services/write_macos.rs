@@ -4,10 +4,29 @@
 //! - target disk writable check (including NTFS remount helper)
 //! - partitioning + formatting
 //! - WIM/ESD apply (wimlib-imagex)
-//! - basic UEFI boot file staging
-
-use crate::models::{BootMode, Disk, ImageInfo, WriteProgress, WriteStatus, WtgConfig};
+//! - UEFI boot file staging, BCD entries re-pointed at the new partitions offline
+//! - VHD apply mode: a fixed VHD on the system partition, attached with hdiutil
+
+use crate::models::{
+    BcdStoreInfo, BootMode, Disk, ImageInfo, PartitionLayout, WriteProgress, WriteStatus, WtgConfig,
+};
+use crate::services::extra_features;
+use crate::services::image_target::{
+    create_vhd_container, vhd_container, ImageFile, ImageFileFormat, VhdContainer,
+};
+use crate::services::wimlib::{
+    find_install_image_in_mount, image_default_language, read_wim_image_info, resolve_wim_index,
+    wimlib_source_args,
+};
+use crate::utils::bcd::{
+    fix_boot_entries, inspect_stores, BcdStore, BootEntryFix, BCD_STORE_PATHS,
+};
+use crate::utils::iso;
 use crate::utils::macos_admin;
+use crate::utils::partition_plan::{
+    plan_for_config, plan_vhd_container, DiskGeometry, FileSystem, PartitionPlan, PlannedPartition,
+};
+use crate::utils::partition_table::{read_disk_layout, write_layout, DiskLayout};
 use crate::utils::progress::PROGRESS_REPORTER;
 use crate::{AppError, Result};
 use serde::Serialize;
@@ -42,14 +61,6 @@ fn command_exists(cmd: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn parse_u64_digits(value: &str) -> Option<u64> {
-    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
-    if digits.is_empty() {
-        return None;
-    }
-    digits.parse::<u64>().ok()
-}
-
 fn requires_wimlib() -> Result<()> {
     if command_exists("wimlib-imagex") {
         return Ok(());
@@ -174,6 +185,108 @@ impl Drop for MountedIso {
     }
 }
 
+/// A fixed VHD on the system partition, attached as a raw disk image for the apply.
+/// Dropping it detaches the image before an unfinished container file is removed.
+struct AttachedVhd {
+    disk_id: String,
+    /// Writable ntfs-3g mount of the VHD's NTFS partition
+    mount: PathBuf,
+    file: ImageFile,
+}
+
+impl Drop for AttachedVhd {
+    fn drop(&mut self) {
+        // ntfs-3g mounted with privilege, so release the volume the same way first.
+        let command = format!(
+            "diskutil unmountDisk force '/dev/{}' >/dev/null 2>&1 || true",
+            shell_escape_single_quotes(&self.disk_id)
+        );
+        let _ = macos_admin::run_shell_with_auto_privilege(&command);
+        hdiutil_detach_force(&format!("/dev/{}", self.disk_id));
+    }
+}
+
+/// Attach a raw disk image without mounting it and return its disk identifier.
+fn hdiutil_attach_raw(path: &Path) -> Result<String> {
+    let output = Command::new("hdiutil")
+        .args([
+            "attach",
+            "-imagekey",
+            "diskimage-class=CRawDiskImage",
+            "-nomount",
+            "-plist",
+            path.to_string_lossy().as_ref(),
+        ])
+        .output()
+        .map_err(AppError::io)?;
+    if !output.status.success() {
+        let err = to_text(&output.stderr);
+        let out = to_text(&output.stdout);
+        let detail = if err.is_empty() { out } else { err };
+        return Err(AppError::DiskError(format!(
+            "Failed to attach {} via hdiutil: {}",
+            path.display(),
+            detail
+        )));
+    }
+
+    let value = plist_to_json(&output.stdout)?;
+    value
+        .get("system-entities")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entity| entity.get("dev-entry").and_then(Value::as_str))
+        .find_map(normalize_hdiutil_detach_target)
+        .map(|target| target.trim_start_matches("/dev/").to_string())
+        .ok_or_else(|| {
+            AppError::DiskError(format!(
+                "hdiutil attached {} but returned no device",
+                path.display()
+            ))
+        })
+}
+
+/// Create the container in the root of the mounted system partition, write its
+/// partition table straight into the file, then attach it and format its partition.
+fn attach_vhd_container(container: &VhdContainer, system_mount: &Path) -> Result<AttachedVhd> {
+    let plan = plan_vhd_container(container.size, container.gpt)?;
+    let file = create_vhd_container(system_mount, container)?;
+    // A fixed VHD stores the disk at the start of the file, followed by its footer.
+    if file.data_offset != Some(0) {
+        return Err(AppError::Unsupported(format!(
+            "{} cannot be attached on macOS; use a fixed .vhd container",
+            container.filename
+        )));
+    }
+    let mut disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&file.path)
+        .map_err(AppError::io)?;
+    write_layout(&mut disk, &plan.to_disk_layout(), file.size)?;
+    disk.sync_all().map_err(AppError::io)?;
+    drop(disk);
+
+    let disk_id = hdiutil_attach_raw(&file.path)?;
+    let mut attached = AttachedVhd {
+        disk_id,
+        mount: PathBuf::new(),
+        file,
+    };
+    let system = plan.system();
+    let partition_id = format!("{}s{}", attached.disk_id, system.number);
+    format_partition_ntfs(&partition_id, &system.label)?;
+    attached.mount = mount_ntfs_partition_writable(&partition_id, &system.label)?;
+    info!(
+        "Created {} ({} MiB) as /dev/{}",
+        container.filename,
+        container.size / (1024 * 1024),
+        attached.disk_id
+    );
+    Ok(attached)
+}
+
 struct ResolvedApplyImage {
     image_path: PathBuf,
     _mounted_iso: Option<MountedIso>,
@@ -200,7 +313,7 @@ fn resolve_apply_image(image_path: &Path) -> Result<ResolvedApplyImage> {
         .find_map(|mount| find_install_image_in_mount(mount))
         .ok_or_else(|| {
             AppError::ImageError(
-                "Cannot find sources/install.wim or sources/install.esd in ISO".to_string(),
+                "Cannot find sources/install.wim, install.esd or install.swm in ISO".to_string(),
             )
         })?;
 
@@ -210,75 +323,17 @@ fn resolve_apply_image(image_path: &Path) -> Result<ResolvedApplyImage> {
     })
 }
 
-fn resolve_wim_index(image_path: &Path, requested: &str) -> Result<String> {
-    let infos = get_wimlib_image_info(image_path)?;
-    if infos.is_empty() {
-        return Err(AppError::ImageError(format!(
-            "No image index found in {}",
-            image_path.display()
-        )));
-    }
-
-    let req = requested.trim();
-    if !req.is_empty() && req != "0" {
-        let parsed = req.parse::<u32>().map_err(|_| {
-            AppError::InvalidParameter(format!("Invalid WIM index: {}", requested))
-        })?;
-        if infos.iter().any(|i| i.index == parsed) {
-            return Ok(parsed.to_string());
-        }
-        return Err(AppError::InvalidParameter(format!(
-            "WIM index {} not found in image",
-            parsed
-        )));
-    }
-
-    Ok(infos[0].index.to_string())
-}
-
-fn find_install_image_in_mount(mount_point: &Path) -> Option<PathBuf> {
-    let sources = ["sources", "Sources", "SOURCES"];
-    let files = ["install.wim", "install.esd", "INSTALL.WIM", "INSTALL.ESD"];
-
-    for src in sources {
-        for file in files {
-            let candidate = mount_point.join(src).join(file);
-            if candidate.exists() {
-                return Some(candidate);
-            }
-        }
+fn get_image_info_from_iso(iso_path: &Path) -> Result<Vec<ImageInfo>> {
+    match iso::read_install_image_info(iso_path) {
+        Ok(info) if !info.is_empty() => return Ok(info),
+        Ok(_) => {}
+        Err(e) => warn!(
+            "Reading {} in place failed: {}; attaching it instead",
+            iso_path.display(),
+            e
+        ),
     }
 
-    let sources_dir = fs::read_dir(mount_point).ok()?.find_map(|entry| {
-        let entry = entry.ok()?;
-        let file_type = entry.file_type().ok()?;
-        if !file_type.is_dir() {
-            return None;
-        }
-        let name = entry.file_name().to_string_lossy().to_string();
-        if name.eq_ignore_ascii_case("sources") {
-            Some(entry.path())
-        } else {
-            None
-        }
-    })?;
-
-    fs::read_dir(sources_dir).ok()?.find_map(|entry| {
-        let entry = entry.ok()?;
-        let file_type = entry.file_type().ok()?;
-        if !file_type.is_file() {
-            return None;
-        }
-        let name = entry.file_name().to_string_lossy().to_string();
-        if name.eq_ignore_ascii_case("install.wim") || name.eq_ignore_ascii_case("install.esd") {
-            Some(entry.path())
-        } else {
-            None
-        }
-    })
-}
-
-fn get_image_info_from_iso(iso_path: &Path) -> Result<Vec<ImageInfo>> {
     let (mount_points, detach_targets) = hdiutil_attach_iso(iso_path)?;
     let _mounted = MountedIso { detach_targets };
 
@@ -287,11 +342,11 @@ fn get_image_info_from_iso(iso_path: &Path) -> Result<Vec<ImageInfo>> {
         .find_map(|mount| find_install_image_in_mount(mount))
         .ok_or_else(|| {
             AppError::ImageError(
-                "Cannot find sources/install.wim or install.esd in ISO".to_string(),
+                "Cannot find sources/install.wim, install.esd or install.swm in ISO".to_string(),
             )
         })?;
 
-    let info = get_wimlib_image_info(&image_in_iso)?;
+    let info = read_wim_image_info(&image_in_iso)?;
     if info.is_empty() {
         return Err(AppError::ImageError(
             "No image index found in ISO install image".to_string(),
@@ -300,80 +355,6 @@ fn get_image_info_from_iso(iso_path: &Path) -> Result<Vec<ImageInfo>> {
     Ok(info)
 }
 
-fn parse_wimlib_image_info(raw: &str) -> Vec<ImageInfo> {
-    let mut results = Vec::new();
-    let mut current: Option<ImageInfo> = None;
-
-    for line in raw.lines() {
-        let trimmed = line.trim();
-        if let Some(v) = trimmed.strip_prefix("Index:") {
-            if let Some(item) = current.take() {
-                results.push(item);
-            }
-            let index = v.trim().parse::<u32>().unwrap_or(0);
-            current = Some(ImageInfo {
-                index,
-                name: format!("Image {}", index),
-                description: String::new(),
-                size: 0,
-            });
-            continue;
-        }
-
-        let Some(item) = current.as_mut() else {
-            continue;
-        };
-
-        if let Some(v) = trimmed.strip_prefix("Name:") {
-            let name = v.trim();
-            if !name.is_empty() {
-                item.name = name.to_string();
-            }
-            continue;
-        }
-
-        if let Some(v) = trimmed.strip_prefix("Description:") {
-            let desc = v.trim();
-            if !desc.is_empty() {
-                item.description = desc.to_string();
-            }
-            continue;
-        }
-
-        if let Some(v) = trimmed.strip_prefix("Total Bytes:") {
-            if let Some(bytes) = parse_u64_digits(v.trim()) {
-                item.size = bytes;
-            }
-            continue;
-        }
-    }
-
-    if let Some(item) = current {
-        results.push(item);
-    }
-
-    results.retain(|i| i.index > 0);
-    results
-}
-
-fn get_wimlib_image_info(image_path: &Path) -> Result<Vec<ImageInfo>> {
-    let output = Command::new("wimlib-imagex")
-        .args(["info", image_path.to_string_lossy().as_ref()])
-        .output()
-        .map_err(AppError::io)?;
-    if !output.status.success() {
-        let err = to_text(&output.stderr);
-        let out = to_text(&output.stdout);
-        let detail = if err.is_empty() { out } else { err };
-        return Err(AppError::ImageError(format!(
-            "wimlib-imagex info failed: {}",
-            detail
-        )));
-    }
-
-    Ok(parse_wimlib_image_info(&to_text(&output.stdout)))
-}
-
 fn shell_escape_single_quotes(raw: &str) -> String {
     raw.replace('\'', "'\"'\"'")
 }
@@ -1082,14 +1063,19 @@ fn mount_ntfs_partition_writable(partition_id: &str, label: &str) -> Result<Path
 }
 
 fn apply_windows_image(source_image: &Path, wim_index: &str, target_mount: &Path) -> Result<()> {
-    let escaped_source = shell_escape_single_quotes(source_image.to_string_lossy().as_ref());
+    let (source, ref_arg) = wimlib_source_args(source_image)?;
+    let escaped_source = shell_escape_single_quotes(source.to_string_lossy().as_ref());
     let escaped_target = shell_escape_single_quotes(target_mount.to_string_lossy().as_ref());
     let escaped_index = shell_escape_single_quotes(wim_index);
+    let ref_part = ref_arg
+        .map(|arg| format!(" '{}'", shell_escape_single_quotes(&arg)))
+        .unwrap_or_default();
     let command = format!(
-        "wimlib-imagex apply '{source}' '{index}' '{target}'",
+        "wimlib-imagex apply '{source}' '{index}' '{target}'{refs}",
         source = escaped_source,
         index = escaped_index,
-        target = escaped_target
+        target = escaped_target,
+        refs = ref_part
     );
     macos_admin::run_shell_with_auto_privilege(&command)
 }
@@ -1105,15 +1091,70 @@ fn stage_uefi_boot_payload(system_mount: &Path, efi_mount: &Path) -> Result<()>
     macos_admin::run_shell_with_auto_privilege(&command)
 }
 
-fn repair_uefi_bcd_store(system_mount: &Path, efi_mount: &Path) -> Result<()> {
+/// Partition table of the target disk. The raw device is root-only, so its first MiB
+/// (MBR, GPT header and entries) is copied to a temp file with privilege and parsed there.
+fn read_target_disk_layout(disk_id: &str) -> Result<DiskLayout> {
+    let snapshot = std::env::temp_dir().join(format!("wtga-ptable-{}", uuid::Uuid::new_v4()));
+    let escaped_snapshot = shell_escape_single_quotes(snapshot.to_string_lossy().as_ref());
+    let command = format!(
+        "/bin/dd if=/dev/r{disk} of='{out}' bs=1m count=1 2>/dev/null && /bin/chmod 644 '{out}'",
+        disk = disk_id,
+        out = escaped_snapshot
+    );
+    let result = macos_admin::run_shell_with_auto_privilege(&command)
+        .and_then(|_| read_disk_layout(&snapshot));
+    let _ = fs::remove_file(&snapshot);
+    result
+}
+
+/// BCD device settings for Windows on `system_partition_id` booting from `efi_partition_id`.
+fn uefi_boot_entry_fix(
+    efi_partition_id: &str,
+    system_partition_id: &str,
+    locale: Option<String>,
+) -> Result<BootEntryFix> {
+    let disk_id = parent_disk_id_from_partition(system_partition_id).ok_or_else(|| {
+        AppError::DeviceNotFound(format!(
+            "Cannot determine parent disk for partition {}",
+            system_partition_id
+        ))
+    })?;
+    let layout = read_target_disk_layout(&disk_id)?;
+    Ok(BootEntryFix {
+        locale,
+        ..BootEntryFix::for_partitions(
+            &layout,
+            partition_index_from_id(efi_partition_id),
+            partition_index_from_id(system_partition_id),
+        )?
+    })
+}
+
+fn repair_uefi_bcd_store(system_mount: &Path, efi_mount: &Path, fix: &BootEntryFix) -> Result<()> {
     let escaped_system = shell_escape_single_quotes(system_mount.to_string_lossy().as_ref());
     let escaped_efi = shell_escape_single_quotes(efi_mount.to_string_lossy().as_ref());
+    let work_copy = std::env::temp_dir().join(format!("wtga-bcd-{}", uuid::Uuid::new_v4()));
+    let escaped_work = shell_escape_single_quotes(work_copy.to_string_lossy().as_ref());
     let command = format!(
-        "SYS='{sys}'; EFI='{efi}'; DST=\"$EFI/EFI/Microsoft/Boot\"; FB=\"$EFI/EFI/Boot\"; BCD=\"$DST/BCD\"; C1=\"$SYS/Windows/Boot/EFI/BCD\"; C2=\"$SYS/Boot/BCD\"; C3=\"$SYS/Windows/System32/config/BCD-Template\"; /bin/mkdir -p \"$DST\" \"$FB\"; if [ ! -f \"$BCD\" ]; then if [ -f \"$C1\" ]; then /bin/cp -f \"$C1\" \"$BCD\"; elif [ -f \"$C2\" ]; then /bin/cp -f \"$C2\" \"$BCD\"; elif [ -f \"$C3\" ]; then /bin/cp -f \"$C3\" \"$BCD\"; fi; fi; if [ ! -f \"$BCD\" ]; then echo 'No valid BCD source found'; exit 4; fi; HDR=$(/usr/bin/hexdump -n 4 -ve '1/1 \"%02x\"' \"$BCD\" 2>/dev/null || true); if [ \"$HDR\" != \"72656766\" ]; then echo \"Invalid BCD hive header: $HDR\"; exit 5; fi; /bin/cp -f \"$BCD\" \"$DST/BCD.wtga.bak\"; /bin/cp -f \"$BCD\" \"$FB/BCD\"; exit 0",
+        "SYS='{sys}'; EFI='{efi}'; DST=\"$EFI/EFI/Microsoft/Boot\"; FB=\"$EFI/EFI/Boot\"; BCD=\"$DST/BCD\"; C1=\"$SYS/Windows/Boot/EFI/BCD\"; C2=\"$SYS/Boot/BCD\"; C3=\"$SYS/Windows/System32/config/BCD-Template\"; /bin/mkdir -p \"$DST\" \"$FB\"; if [ ! -f \"$BCD\" ]; then if [ -f \"$C1\" ]; then /bin/cp -f \"$C1\" \"$BCD\"; elif [ -f \"$C2\" ]; then /bin/cp -f \"$C2\" \"$BCD\"; elif [ -f \"$C3\" ]; then /bin/cp -f \"$C3\" \"$BCD\"; fi; fi; if [ ! -f \"$BCD\" ]; then echo 'No valid BCD source found'; exit 4; fi; HDR=$(/usr/bin/hexdump -n 4 -ve '1/1 \"%02x\"' \"$BCD\" 2>/dev/null || true); if [ \"$HDR\" != \"72656766\" ]; then echo \"Invalid BCD hive header: $HDR\"; exit 5; fi; /bin/cp -f \"$BCD\" \"$DST/BCD.wtga.bak\"; /bin/cp -f \"$BCD\" '{work}'; /bin/chmod 666 '{work}'; exit 0",
         sys = escaped_system,
-        efi = escaped_efi
+        efi = escaped_efi,
+        work = escaped_work
     );
-    macos_admin::run_shell_with_auto_privilege(&command)
+    macos_admin::run_shell_with_auto_privilege(&command)?;
+
+    // Edit a user-owned copy, then put it back in both store locations.
+    let edited = fix_boot_entries(&work_copy, fix).and_then(|default| {
+        info!("BCD default entry {} now boots {}", default, fix.os_device);
+        let command = format!(
+            "EFI='{efi}'; DST=\"$EFI/EFI/Microsoft/Boot\"; FB=\"$EFI/EFI/Boot\"; /bin/cp -f '{work}' \"$DST/BCD\" && /bin/cp -f '{work}' \"$FB/BCD\" && /bin/rm -f \"$DST/BCD.LOG\" \"$DST/BCD.LOG1\" \"$DST/BCD.LOG2\"",
+            efi = escaped_efi,
+            work = escaped_work
+        );
+        macos_admin::run_shell_with_auto_privilege(&command)
+    });
+    let _ = fs::remove_file(&work_copy);
+    edited
 }
 
 fn verify_uefi_boot_files(efi_mount: &Path) -> Result<()> {
@@ -1134,24 +1175,13 @@ fn verify_uefi_boot_files(efi_mount: &Path) -> Result<()> {
         }
     }
 
-    let header = Command::new("sh")
-        .args([
-            "-lc",
-            &format!(
-                "/usr/bin/hexdump -n 4 -ve '1/1 \"%02x\"' '{}'",
-                shell_escape_single_quotes(bcd.to_string_lossy().as_ref())
-            ),
-        ])
-        .output()
-        .map_err(AppError::io)?;
-    if !header.status.success() {
-        return Err(AppError::DiskError("Failed to inspect BCD file header".to_string()));
-    }
-    let bcd_header = to_text(&header.stdout);
-    if bcd_header != "72656766" {
+    let store = BcdStore::open(&bcd).map_err(|e| {
+        AppError::DiskError(format!("Invalid BCD store {}: {}", bcd.display(), e))
+    })?;
+    if store.boot_manager().is_none() {
         return Err(AppError::DiskError(format!(
-            "Invalid BCD file header: {}",
-            bcd_header
+            "BCD store {} has no boot manager entry",
+            bcd.display()
         )));
     }
 
@@ -1321,7 +1351,8 @@ pub fn repair_boot_uefi_for_target(target_hint: &str) -> Result<String> {
     }
 
     stage_uefi_boot_payload(&system_mount, &efi_mount)?;
-    repair_uefi_bcd_store(&system_mount, &efi_mount)?;
+    let fix = uefi_boot_entry_fix(&efi_partition_id, &system_partition_id, None)?;
+    repair_uefi_bcd_store(&system_mount, &efi_mount, &fix)?;
     verify_uefi_boot_files(&efi_mount)?;
 
     Ok(format!(
@@ -1333,209 +1364,138 @@ pub fn repair_boot_uefi_for_target(target_hint: &str) -> Result<String> {
     ))
 }
 
-fn verify_applied_system_files(system_mount: &Path) -> Result<()> {
-    let windows_dir = system_mount.join("Windows");
-    let system32_dir = windows_dir.join("System32");
-    if !windows_dir.exists() || !system32_dir.exists() {
-        return Err(AppError::ImageError(format!(
-            "Applied system files are incomplete at {}",
-            system_mount.display()
-        )));
-    }
-    Ok(())
-}
-
-#[derive(Debug, Default)]
-struct MacExtraFeatureOutcome {
-    applied: Vec<&'static str>,
-    unsupported: Vec<&'static str>,
-    notes: Vec<String>,
+/// Partitions mounted for a one-off read, unmounted again when dropped. Partitions
+/// that were already mounted are left alone.
+#[derive(Default)]
+struct TemporaryMounts {
+    partition_ids: Vec<String>,
 }
 
-fn write_text_file(path: &Path, content: &str) -> Result<()> {
-    fs::write(path, content).map_err(AppError::io)
-}
-
-fn ensure_setup_complete_invokes_wtga_extra(scripts_dir: &Path) -> Result<()> {
-    let setup_complete_path = scripts_dir.join("SetupComplete.cmd");
-    let call_line = r#"call "%SystemRoot%\Setup\Scripts\WTGA-ExtraFeatures.cmd""#;
-    let lower_match = "wtga-extrafeatures.cmd";
-
-    let mut content = if setup_complete_path.exists() {
-        fs::read_to_string(&setup_complete_path).map_err(AppError::io)?
-    } else {
-        "@echo off\r\n".to_string()
-    };
-
-    if !content.to_ascii_lowercase().contains(lower_match) {
-        if !content.ends_with('\n') {
-            content.push_str("\r\n");
+impl TemporaryMounts {
+    fn mount(&mut self, partition_id: &str) -> Result<PathBuf> {
+        let was_mounted = get_partition_info_json(partition_id)
+            .map(|info| !json_str(&info, "MountPoint").is_empty())
+            .unwrap_or(false);
+        let mount_point = mount_partition_and_get_mount_point(partition_id)?;
+        if !was_mounted {
+            self.partition_ids.push(partition_id.to_string());
         }
-        content.push_str(call_line);
-        content.push_str("\r\n");
+        Ok(mount_point)
     }
+}
 
-    if !content.to_ascii_lowercase().contains("exit /b 0") {
-        content.push_str("exit /b 0\r\n");
+impl Drop for TemporaryMounts {
+    fn drop(&mut self) {
+        for partition_id in self.partition_ids.iter().rev() {
+            let command = format!(
+                "diskutil unmount '/dev/{}' >/dev/null 2>&1 || true",
+                shell_escape_single_quotes(partition_id)
+            );
+            if let Err(e) = macos_admin::run_shell_with_auto_privilege(&command) {
+                warn!("Failed to unmount {}: {}", partition_id, e);
+            }
+        }
     }
-
-    write_text_file(&setup_complete_path, &content)
 }
 
-fn write_skip_oobe_unattend_if_needed(system_mount: &Path) -> Result<()> {
-    let sysprep_dir = system_mount.join("Windows").join("System32").join("Sysprep");
-    fs::create_dir_all(&sysprep_dir).map_err(AppError::io)?;
-    let unattend_path = sysprep_dir.join("unattend.xml");
+/// Read the BCD stores on the target's system and EFI partitions and check them against
+/// the partition table of their disk. Partitions mounted for this are unmounted again.
+pub fn inspect_bcd_stores_for_target(target_hint: &str) -> Result<Vec<BcdStoreInfo>> {
+    let system_partition_id = resolve_system_partition_id_from_hint(target_hint)?;
+    let disk_id = parent_disk_id_from_partition(&system_partition_id).ok_or_else(|| {
+        AppError::DeviceNotFound(format!(
+            "Cannot determine parent disk for partition {}",
+            system_partition_id
+        ))
+    })?;
 
-    if unattend_path.exists() {
-        return Ok(());
+    let mut mounts = TemporaryMounts::default();
+    let mut roots = vec![mounts.mount(&system_partition_id)?];
+    match resolve_efi_partition_id_for_system(&system_partition_id)
+        .and_then(|efi| mounts.mount(&efi))
+    {
+        Ok(efi_mount) => roots.push(efi_mount),
+        Err(e) => warn!("EFI partition of {} not available: {}", disk_id, e),
     }
 
-    // Keep this minimal and architecture-agnostic by including both amd64 and arm64 blocks.
-    let unattend = r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend">
-  <settings pass="oobeSystem">
-    <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-      <OOBE>
-        <HideEULAPage>true</HideEULAPage>
-        <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-        <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-        <ProtectYourPC>3</ProtectYourPC>
-        <SkipMachineOOBE>true</SkipMachineOOBE>
-        <SkipUserOOBE>true</SkipUserOOBE>
-      </OOBE>
-    </component>
-    <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="arm64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-      <OOBE>
-        <HideEULAPage>true</HideEULAPage>
-        <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-        <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-        <ProtectYourPC>3</ProtectYourPC>
-        <SkipMachineOOBE>true</SkipMachineOOBE>
-        <SkipUserOOBE>true</SkipUserOOBE>
-      </OOBE>
-    </component>
-  </settings>
-</unattend>
-"#;
-
-    write_text_file(&unattend_path, unattend)
-}
-
-fn apply_macos_extra_features(config: &WtgConfig, system_mount: &Path) -> Result<MacExtraFeatureOutcome> {
-    let mut outcome = MacExtraFeatureOutcome::default();
-    let features = &config.extra_features;
+    let stores: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|root| {
+            BCD_STORE_PATHS
+                .iter()
+                .map(move |relative| relative.split('\\').fold(root.clone(), |p, c| p.join(c)))
+        })
+        .filter(|path| path.is_file())
+        .collect();
+
+    let disk = PathBuf::from(format!("/dev/r{}", disk_id));
+    Ok(inspect_stores(&disk, &stores))
+}
 
+fn verify_applied_system_files(system_mount: &Path) -> Result<()> {
     let windows_dir = system_mount.join("Windows");
-    if !windows_dir.exists() {
+    let system32_dir = windows_dir.join("System32");
+    if !windows_dir.exists() || !system32_dir.exists() {
         return Err(AppError::ImageError(format!(
-            "Cannot apply extra features: Windows directory missing at {}",
-            windows_dir.display()
+            "Applied system files are incomplete at {}",
+            system_mount.display()
         )));
     }
+    Ok(())
+}
 
-    let scripts_dir = windows_dir.join("Setup").join("Scripts");
-    fs::create_dir_all(&scripts_dir).map_err(AppError::io)?;
-
-    let mut script_lines = vec![
-        "@echo off".to_string(),
-        "setlocal enableextensions".to_string(),
-        "set WTGA_LOG=%SystemRoot%\\Setup\\Scripts\\WTGA-ExtraFeatures.log".to_string(),
-        "echo [WTGA] Extra features script started %DATE% %TIME%>>\"%WTGA_LOG%\"".to_string(),
-    ];
-
-    if features.block_local_disk {
-        outcome.applied.push("block_local_disk");
-        script_lines.push("echo [WTGA] Applying SAN policy (block local disks)>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("reg add \"HKLM\\SYSTEM\\CurrentControlSet\\Services\\partmgr\\Parameters\" /v SanPolicy /t REG_DWORD /d 4 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
-        script_lines.push("(echo san policy=4) > \"%SystemRoot%\\Setup\\Scripts\\wtga-san.txt\"".to_string());
-        script_lines.push("diskpart /s \"%SystemRoot%\\Setup\\Scripts\\wtga-san.txt\" >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.disable_winre {
-        outcome.applied.push("disable_winre");
-        script_lines.push("echo [WTGA] Disabling WinRE>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("reagentc /disable >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.skip_oobe {
-        outcome.applied.push("skip_oobe");
-        write_skip_oobe_unattend_if_needed(system_mount)?;
-        script_lines.push("echo [WTGA] Applying skip OOBE registry flags>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v SkipMachineOOBE /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
-        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v SkipUserOOBE /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
-        script_lines.push("reg add \"HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\OOBE\" /v PrivacyConsentStatus /t REG_DWORD /d 1 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.disable_uasp {
-        outcome.applied.push("disable_uasp");
-        script_lines.push("echo [WTGA] Disabling UASP (uaspstor)>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("reg add \"HKLM\\SYSTEM\\CurrentControlSet\\Services\\uaspstor\" /v Start /t REG_DWORD /d 4 /f >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.no_default_drive_letter {
-        outcome.applied.push("no_default_drive_letter");
-        script_lines.push("echo [WTGA] Setting no default drive letter policy>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("mountvol /N >>\"%WTGA_LOG%\" 2>&1".to_string());
-        script_lines.push("(echo select volume %SystemDrive% & echo attributes volume set nodefaultdriveletter) > \"%SystemRoot%\\Setup\\Scripts\\wtga-nodefaultdriveletter.txt\"".to_string());
-        script_lines.push("diskpart /s \"%SystemRoot%\\Setup\\Scripts\\wtga-nodefaultdriveletter.txt\" >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.compact_os {
-        outcome.applied.push("compact_os");
-        script_lines.push("echo [WTGA] Enabling CompactOS>>\"%WTGA_LOG%\"".to_string());
-        script_lines.push("compact.exe /CompactOS:always >>\"%WTGA_LOG%\" 2>&1".to_string());
-    }
-
-    if features.install_dotnet35 {
-        outcome.unsupported.push("install_dotnet35");
+/// Geometry of the target disk as diskutil reports it.
+fn target_disk_geometry(disk_info: &Value, target_disk: &Disk) -> DiskGeometry {
+    DiskGeometry {
+        size: json_u64(disk_info, "TotalSize")
+            .or_else(|| json_u64(disk_info, "Size"))
+            .unwrap_or(target_disk.size),
+        sector_size: json_u64(disk_info, "DeviceBlockSize").unwrap_or(512) as u32,
+        removable: target_disk.removable,
     }
-    if features.fix_letter {
-        outcome.unsupported.push("fix_letter");
-    }
-    if features.wimboot {
-        outcome.unsupported.push("wimboot");
-    }
-    if features.ntfs_uefi_support {
-        // UEFI flow on macOS already uses a dedicated FAT EFI partition; this flag is a no-op here.
-        outcome.notes.push("ntfs_uefi_support is ignored on macOS UEFI write path".to_string());
-    }
-    if features.enable_bitlocker {
-        outcome.unsupported.push("enable_bitlocker");
-    }
-    if features.driver_path.is_some() {
-        outcome.unsupported.push("driver_path");
-    }
-
-    script_lines.push("echo [WTGA] Extra features script finished %DATE% %TIME%>>\"%WTGA_LOG%\"".to_string());
-    script_lines.push("endlocal".to_string());
-    script_lines.push("exit /b 0".to_string());
+}
 
-    if outcome.applied.is_empty() {
-        return Ok(outcome);
+/// `diskutil partitionDisk` command for the plan. diskutil can create neither an MSR nor
+/// NTFS volumes: the MSR is left out and NTFS partitions start as ExFAT until mkntfs
+/// reformats them.
+fn diskutil_partition_command(node: &str, plan: &PartitionPlan) -> String {
+    let scheme = match plan.style {
+        PartitionLayout::GPT => "GPT",
+        PartitionLayout::MBR => "MBRFormat",
+    };
+    let parts: Vec<&PlannedPartition> = plan
+        .partitions
+        .iter()
+        .filter(|p| p.filesystem != FileSystem::None)
+        .collect();
+    let mut command = format!("diskutil partitionDisk {} {} {}", node, parts.len(), scheme);
+    for (i, part) in parts.iter().enumerate() {
+        let format = if part.filesystem == FileSystem::Fat32 {
+            "FAT32"
+        } else {
+            "ExFAT"
+        };
+        let name = if part.label.is_empty() {
+            "DATA"
+        } else {
+            part.label.as_str()
+        };
+        // Sizes in 512-byte blocks; the last partition takes the rest.
+        let size = if i + 1 == parts.len() {
+            "R".to_string()
+        } else {
+            format!("{}S", part.size / 512)
+        };
+        command.push_str(&format!(" {} {} {}", format, name, size));
     }
-
-    let extra_script_path = scripts_dir.join("WTGA-ExtraFeatures.cmd");
-    let script_text = format!("{}\r\n", script_lines.join("\r\n"));
-    write_text_file(&extra_script_path, &script_text)?;
-    ensure_setup_complete_invokes_wtga_extra(&scripts_dir)?;
-
-    Ok(outcome)
+    command
 }
 
 fn prepare_target_disk(config: &WtgConfig, disk_id: &str) -> Result<PreparedTargetDisk> {
     let node = format!("/dev/{}", disk_id);
-    let partition_command = match config.boot_mode {
-        BootMode::UefiGpt => format!(
-            "diskutil partitionDisk {} GPT FAT32 EFI 512m ExFAT WTGA R",
-            node
-        ),
-        BootMode::UefiMbr => format!(
-            "diskutil partitionDisk {} MBRFormat FAT32 EFI 512m ExFAT WTGA R",
-            node
-        ),
-        BootMode::NonUefi => format!("diskutil partitionDisk {} MBRFormat ExFAT WTGA R", node),
-    };
+    let disk_info = diskutil_json(&["info", "-plist", &node])?;
+    let plan = plan_for_config(config, target_disk_geometry(&disk_info, &config.target_disk))?;
+    let partition_command = diskutil_partition_command(&node, &plan);
 
     if let Err(e) = macos_admin::run_shell_with_auto_privilege(&partition_command) {
         warn!(
@@ -1553,7 +1513,7 @@ fn prepare_target_disk(config: &WtgConfig, disk_id: &str) -> Result<PreparedTarg
         Err(_) => expected,
     };
     let _ = get_partition_info_json(&prepared.system_partition_id)?;
-    format_partition_ntfs(&prepared.system_partition_id, "WTGA")?;
+    format_partition_ntfs(&prepared.system_partition_id, &plan.system().label)?;
     thread::sleep(Duration::from_secs(1));
     let system_info = get_partition_info_json(&prepared.system_partition_id)?;
     let filesystem = json_str(&system_info, "FilesystemType").to_ascii_lowercase();
@@ -1573,6 +1533,18 @@ fn prepare_target_disk(config: &WtgConfig, disk_id: &str) -> Result<PreparedTarg
         let _ = get_partition_info_json(efi_id)?;
     }
 
+    // Extra data partitions are the last ones on the disk, in plan order.
+    let data: Vec<&PlannedPartition> = plan.data_partitions().collect();
+    if !data.is_empty() {
+        let ids = list_partition_ids_for_disk(disk_id)?;
+        let first = ids.len().saturating_sub(data.len());
+        for (part, id) in data.iter().zip(&ids[first..]) {
+            if *id != prepared.system_partition_id {
+                format_partition_ntfs(id, &part.label)?;
+            }
+        }
+    }
+
     Ok(prepared)
 }
 
@@ -1591,9 +1563,8 @@ pub fn get_image_info(image_path: &str) -> Result<Vec<ImageInfo>> {
         .unwrap_or("")
         .to_ascii_lowercase();
     match ext.as_str() {
-        "wim" | "esd" => {
-            requires_wimlib()?;
-            let info = get_wimlib_image_info(path)?;
+        "wim" | "esd" | "swm" => {
+            let info = read_wim_image_info(path)?;
             if info.is_empty() {
                 return Err(AppError::ImageError(format!(
                     "No image index found in {}",
@@ -1603,7 +1574,6 @@ pub fn get_image_info(image_path: &str) -> Result<Vec<ImageInfo>> {
             return Ok(info);
         }
         "iso" => {
-            requires_wimlib()?;
             return get_image_info_from_iso(path);
         }
         _ => {}
@@ -1620,6 +1590,7 @@ pub fn get_image_info(image_path: &str) -> Result<Vec<ImageInfo>> {
         name,
         description: "macOS migration mode: single default image index".to_string(),
         size,
+        ..Default::default()
     }])
 }
 
@@ -1674,6 +1645,21 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         ));
     }
 
+    // A VHD apply goes into a container on the system partition; make sure it fits
+    // before the disk is repartitioned.
+    let host_plan = plan_for_config(
+        config,
+        target_disk_geometry(&disk_info, &config.target_disk),
+    )?;
+    let container = vhd_container(config, host_plan.system().size)?;
+    if let Some(container) = &container {
+        if ImageFileFormat::from_path(Path::new(&container.filename))? == ImageFileFormat::Vhdx {
+            return Err(AppError::Unsupported(
+                "VHDX containers cannot be attached on macOS; use the VHD apply mode".to_string(),
+            ));
+        }
+    }
+
     PROGRESS_REPORTER.report_status(
         &task_id,
         15.0,
@@ -1698,7 +1684,7 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
     );
     let efi_mount = if let Some(efi_partition_id) = &prepared.efi_partition_id {
         match mount_partition_and_get_mount_point(efi_partition_id) {
-            Ok(mount) => Some(mount),
+            Ok(mount) => Some((efi_partition_id.clone(), mount)),
             Err(primary_err) => {
                 if let Some((alt_id, alt_mount)) = try_mount_alternative_efi_partition(
                     &disk_id,
@@ -1709,7 +1695,7 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
                         "EFI mount fallback: {} failed, using {} instead",
                         efi_partition_id, alt_id
                     );
-                    Some(alt_mount)
+                    Some((alt_id, alt_mount))
                 } else {
                     return Err(primary_err);
                 }
@@ -1719,14 +1705,31 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         None
     };
 
+    let mut attached_vhd = match &container {
+        Some(container) => {
+            PROGRESS_REPORTER.report_status(
+                &task_id,
+                44.0,
+                &format!("Creating {}", container.filename),
+                "partitioning",
+            );
+            Some(attach_vhd_container(container, &system_mount)?)
+        }
+        None => None,
+    };
+    let windows_mount = attached_vhd
+        .as_ref()
+        .map(|attached| attached.mount.clone())
+        .unwrap_or_else(|| system_mount.clone());
+
     PROGRESS_REPORTER.report_status(
         &task_id,
         52.0,
         "Applying Windows image to NTFS system partition",
         "applyingimage",
     );
-    apply_windows_image(&resolved_image.image_path, &wim_index, &system_mount)?;
-    verify_applied_system_files(&system_mount)?;
+    apply_windows_image(&resolved_image.image_path, &wim_index, &windows_mount)?;
+    verify_applied_system_files(&windows_mount)?;
 
     PROGRESS_REPORTER.report_status(
         &task_id,
@@ -1734,7 +1737,7 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         "Applying extra features",
         "applyingextras",
     );
-    let extra_outcome = apply_macos_extra_features(config, &system_mount)?;
+    let extra_outcome = extra_features::apply_via_setup_complete(config, &windows_mount, "macOS")?;
 
     PROGRESS_REPORTER.report_status(
         &task_id,
@@ -1742,12 +1745,12 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         "Staging UEFI boot files",
         "writingbootfiles",
     );
-    let Some(efi_mount_path) = efi_mount.as_ref() else {
+    let Some((efi_partition_id, efi_mount_path)) = efi_mount.as_ref() else {
         return Err(AppError::DiskError(
             "EFI partition was not created/mounted; cannot stage boot files".to_string(),
         ));
     };
-    stage_uefi_boot_payload(&system_mount, efi_mount_path)?;
+    stage_uefi_boot_payload(&windows_mount, efi_mount_path)?;
 
     PROGRESS_REPORTER.report_status(
         &task_id,
@@ -1755,10 +1758,19 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         "Fixing BCD for UEFI boot",
         "fixingbcd",
     );
-    repair_uefi_bcd_store(&system_mount, efi_mount_path)?;
+    let locale = image_default_language(&resolved_image.image_path, &wim_index);
+    let mut boot_fix =
+        uefi_boot_entry_fix(efi_partition_id, &prepared.system_partition_id, locale)?;
+    if let Some(container) = &container {
+        boot_fix = boot_fix.in_vhd(&container.filename);
+    }
+    repair_uefi_bcd_store(&windows_mount, efi_mount_path, &boot_fix)?;
 
     PROGRESS_REPORTER.report_status(&task_id, 90.0, "Verifying write result", "verifying");
     verify_uefi_boot_files(efi_mount_path)?;
+    if let Some(mut attached) = attached_vhd.take() {
+        attached.file.keep();
+    }
 
     let elapsed = started.elapsed().as_secs();
     info!(
@@ -1790,6 +1802,12 @@ pub fn execute_write(config: &WtgConfig, app_files_path: &str) -> Result<WritePr
         system_mount.display(),
         efi_mount_path.display()
     );
+    if let Some(container) = &container {
+        message.push_str(&format!(
+            " Windows boots from {} on the system partition.",
+            container.filename
+        ));
+    }
     if !extra_outcome.applied.is_empty() {
         message.push_str(&format!(
             " Applied extra features: {}.",
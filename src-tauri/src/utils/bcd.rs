@@ -0,0 +1,1193 @@
+//! Offline Boot Configuration Data (BCD) store parsing and editing
+//!
+//! A BCD store is a registry hive: `Objects\{guid}\Description\Type` holds the object
+//! type and `Objects\{guid}\Elements\<element type>\Element` the element data. This
+//! module decodes the objects that matter for booting (boot manager, OS loaders) and
+//! checks their `device`/`osdevice` elements against the partition table of the disk
+//! the store lives on, without `bcdedit` or `reg load`. [`fix_boot_entries`] rewrites
+//! those elements for a new target, the offline counterpart of the bcdedit fix-ups.
+
+use crate::models::{BcdDeviceInfo, BcdEntryInfo, BcdStoreInfo};
+use crate::utils::partition_table::{self, DiskLayout, PartitionEntry, PartitionStyle};
+use crate::utils::regf::{
+    Hive, HiveFile, KeyTree, RegValue, REG_BINARY, REG_DWORD, REG_MULTI_SZ, REG_SZ,
+};
+use crate::{AppError, Result};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Store locations relative to a volume root: UEFI boot manager first, then BIOS.
+pub const BCD_STORE_PATHS: [&str; 2] = ["EFI\\Microsoft\\Boot\\BCD", "Boot\\BCD"];
+
+pub const OBJECT_FWBOOTMGR: u32 = 0x1010_0001;
+pub const OBJECT_BOOTMGR: u32 = 0x1010_0002;
+pub const OBJECT_OSLOADER: u32 = 0x1020_0003;
+pub const OBJECT_RESUME: u32 = 0x1020_0004;
+pub const OBJECT_MEMDIAG: u32 = 0x1020_0005;
+/// Top nibble of an object type: 1 = application, 2 = inheritable settings, 3 = device.
+const OBJECT_CLASS_APPLICATION: u32 = 0x1;
+
+pub const ELEMENT_DEVICE: u32 = 0x1100_0001;
+pub const ELEMENT_PATH: u32 = 0x1200_0002;
+pub const ELEMENT_DESCRIPTION: u32 = 0x1200_0004;
+pub const ELEMENT_LOCALE: u32 = 0x1200_0005;
+pub const ELEMENT_OS_DEVICE: u32 = 0x2100_0001;
+pub const ELEMENT_SYSTEM_ROOT: u32 = 0x2200_0002;
+pub const ELEMENT_DEFAULT: u32 = 0x2300_0003;
+pub const ELEMENT_DISPLAY_ORDER: u32 = 0x2400_0001;
+pub const ELEMENT_TIMEOUT: u32 = 0x2500_0004;
+pub const ELEMENT_INHERIT: u32 = 0x1400_0006;
+
+/// Boot menu timeout bcdboot gives a new store.
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 30;
+
+pub const BOOTMGR_ID: &str = "{9dea862c-5cdd-4e70-acc1-f32b344d4795}";
+const BOOTLOADERSETTINGS_ID: &str = "{6efb52bf-1766-41db-a6b3-0ee5eff72bd7}";
+
+const WELL_KNOWN_OBJECTS: [(&str, &str); 7] = [
+    (BOOTMGR_ID, "{bootmgr}"),
+    ("{a5a30fa2-3d06-4e9f-b5f4-a01df9d1fcba}", "{fwbootmgr}"),
+    ("{b2721d73-1db4-4c62-bf78-c548a880142d}", "{memdiag}"),
+    ("{7ea2e1ac-2e61-4728-aaa3-896d9d0a9f0e}", "{globalsettings}"),
+    (
+        "{6efb52bf-1766-41db-a6b3-0ee5eff72bd7}",
+        "{bootloadersettings}",
+    ),
+    ("{466f5a88-0af2-4f76-9038-095b170dc21c}", "{ntldr}"),
+    ("{ae5534e0-a924-466c-b836-758539a3ee3a}", "{ramdiskoptions}"),
+];
+
+// Device element layout: a 16-byte options GUID followed by a device descriptor.
+// Offsets below are relative to the start of a descriptor.
+pub(crate) const DEVICE_DESCRIPTOR_OFFSET: usize = 0x10;
+pub(crate) const DESCRIPTOR_TYPE: usize = 0x00;
+pub(crate) const DESCRIPTOR_SIZE: usize = 0x08;
+pub(crate) const DESCRIPTOR_PARTITION_ID: usize = 0x10;
+pub(crate) const DESCRIPTOR_LOCAL_TYPE: usize = 0x20;
+pub(crate) const DESCRIPTOR_PARTITION_STYLE: usize = 0x28;
+pub(crate) const DESCRIPTOR_DISK_ID: usize = 0x2C;
+pub(crate) const PARTITION_DESCRIPTOR_LEN: usize = 0x48;
+
+pub(crate) const DEVICE_TYPE_BOOT: u32 = 5;
+pub(crate) const DEVICE_TYPE_PARTITION: u32 = 6;
+pub(crate) const DEVICE_TYPE_LOCATE: u32 = 8;
+/// Outer descriptor of a VHD device; the local type marks it as a virtual disk.
+pub(crate) const DEVICE_TYPE_FILE: u32 = 0;
+pub(crate) const LOCAL_TYPE_VIRTUAL_DISK: u32 = 6;
+pub(crate) const PARTITION_STYLE_GPT: u32 = 0;
+pub(crate) const PARTITION_STYLE_MBR: u32 = 1;
+
+/// A partition as a BCD device element identifies it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRef {
+    Mbr {
+        signature: u32,
+        offset: u64,
+    },
+    Gpt {
+        disk_guid: Uuid,
+        partition_guid: Uuid,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BcdDevice {
+    /// The device the boot manager itself was loaded from
+    Boot,
+    /// Resolved at boot time by searching for the application path
+    Locate,
+    Partition(PartitionRef),
+    /// A VHD/VHDX file, `path` being relative to the host partition
+    Vhd {
+        host: Option<PartitionRef>,
+        path: String,
+    },
+    Unknown(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct BcdObject {
+    /// Lower-case braced GUID, e.g. `{9dea862c-5cdd-4e70-acc1-f32b344d4795}`
+    pub id: String,
+    pub object_type: u32,
+    pub elements: Vec<(u32, RegValue)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BcdStore {
+    pub objects: Vec<BcdObject>,
+}
+
+impl BcdStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_hive(&mut Hive::open(path)?)
+    }
+
+    pub fn from_hive<R: Read + Seek>(hive: &mut Hive<R>) -> Result<Self> {
+        let root = hive.root()?;
+        Self::from_tree(&hive.load_tree(&root)?)
+    }
+
+    pub fn from_tree(root: &KeyTree) -> Result<Self> {
+        let objects_key = root
+            .subkey("Objects")
+            .ok_or_else(|| invalid("Objects key not found"))?;
+
+        let objects = objects_key
+            .subkeys
+            .iter()
+            .map(|key| BcdObject {
+                id: normalize_id(&key.name),
+                object_type: key
+                    .open("Description")
+                    .and_then(|desc| desc.value("Type"))
+                    .and_then(|v| v.as_u32())
+                    .unwrap_or(0),
+                elements: key
+                    .subkey("Elements")
+                    .map(|elements| {
+                        elements
+                            .subkeys
+                            .iter()
+                            .filter_map(|element| {
+                                let element_type = u32::from_str_radix(&element.name, 16).ok()?;
+                                Some((element_type, element.value("Element")?.clone()))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Ok(BcdStore { objects })
+    }
+
+    pub fn object(&self, id: &str) -> Option<&BcdObject> {
+        let id = normalize_id(id);
+        self.objects.iter().find(|o| o.id == id)
+    }
+
+    pub fn boot_manager(&self) -> Option<&BcdObject> {
+        self.object(BOOTMGR_ID)
+    }
+
+    pub fn default_entry(&self) -> Option<String> {
+        self.boot_manager()?
+            .string(ELEMENT_DEFAULT)
+            .map(|id| normalize_id(&id))
+    }
+
+    pub fn display_order(&self) -> Vec<String> {
+        self.boot_manager()
+            .map(|b| b.object_list(ELEMENT_DISPLAY_ORDER))
+            .unwrap_or_default()
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        self.boot_manager()?.integer(ELEMENT_TIMEOUT)
+    }
+
+    /// Summarize the store for display, checking device references against `layout`
+    /// (the partition table of the disk the store was read from) when available.
+    pub fn summarize(&self, path: &Path, layout: Option<&DiskLayout>) -> BcdStoreInfo {
+        let default_entry = self.default_entry();
+        let display_order = self.display_order();
+        let mut issues = Vec::new();
+
+        if self.boot_manager().is_none() {
+            issues.push("Store has no {bootmgr} object".to_string());
+        }
+        match &default_entry {
+            Some(id) if self.object(id).is_none() => {
+                issues.push(format!("Default entry {} does not exist", id));
+            }
+            None => issues.push("No default boot entry is set".to_string()),
+            _ => {}
+        }
+        for id in &display_order {
+            if self.object(id).is_none() {
+                issues.push(format!("Display order lists missing entry {}", id));
+            }
+        }
+
+        // Boot manager first, then the menu order, then anything else bootable.
+        let mut ordered: Vec<&BcdObject> = Vec::new();
+        let candidates = [BOOTMGR_ID.to_string()]
+            .into_iter()
+            .chain(display_order.iter().cloned())
+            .filter_map(|id| self.object(&id))
+            .chain(self.objects.iter());
+        for object in candidates {
+            if object.is_application() && !ordered.iter().any(|o| o.id == object.id) {
+                ordered.push(object);
+            }
+        }
+
+        let entries = ordered
+            .into_iter()
+            .map(|object| {
+                let mut info = object.summarize(layout);
+                info.is_default = default_entry.as_deref() == Some(object.id.as_str());
+                info
+            })
+            .collect();
+
+        BcdStoreInfo {
+            path: path.to_string_lossy().to_string(),
+            partition_style: match layout.map(|l| l.style) {
+                Some(PartitionStyle::Gpt { .. }) => "gpt".to_string(),
+                Some(PartitionStyle::Mbr { .. }) => "mbr".to_string(),
+                None => String::new(),
+            },
+            default_entry,
+            display_order,
+            timeout: self.timeout(),
+            entries,
+            issues,
+        }
+    }
+}
+
+impl BcdObject {
+    pub fn element(&self, element_type: u32) -> Option<&RegValue> {
+        self.elements
+            .iter()
+            .find(|(t, _)| *t == element_type)
+            .map(|(_, v)| v)
+    }
+
+    pub fn string(&self, element_type: u32) -> Option<String> {
+        self.element(element_type)?.as_string()
+    }
+
+    /// Integer elements are stored as little-endian REG_BINARY.
+    pub fn integer(&self, element_type: u32) -> Option<u64> {
+        let value = self.element(element_type)?;
+        if value.data_type != REG_BINARY || value.data.is_empty() {
+            return value.as_u64();
+        }
+        let mut raw = [0u8; 8];
+        let len = value.data.len().min(8);
+        raw[..len].copy_from_slice(&value.data[..len]);
+        Some(u64::from_le_bytes(raw))
+    }
+
+    pub fn object_list(&self, element_type: u32) -> Vec<String> {
+        self.element(element_type)
+            .filter(|v| v.data_type == REG_MULTI_SZ)
+            .and_then(|v| v.as_multi_string())
+            .map(|ids| ids.iter().map(|id| normalize_id(id)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn device(&self, element_type: u32) -> Option<BcdDevice> {
+        self.element(element_type)
+            .filter(|v| v.data_type == REG_BINARY)
+            .map(|v| parse_device(&v.data))
+    }
+
+    pub fn is_application(&self) -> bool {
+        self.object_type >> 28 == OBJECT_CLASS_APPLICATION
+    }
+
+    fn summarize(&self, layout: Option<&DiskLayout>) -> BcdEntryInfo {
+        let kind = match self.object_type {
+            OBJECT_BOOTMGR => "bootmgr",
+            OBJECT_FWBOOTMGR => "fwbootmgr",
+            OBJECT_OSLOADER => "osloader",
+            OBJECT_RESUME => "resume",
+            OBJECT_MEMDIAG => "memdiag",
+            _ => "application",
+        };
+        let device = self.device(ELEMENT_DEVICE);
+        let os_device = self.device(ELEMENT_OS_DEVICE);
+        let mut issues = Vec::new();
+
+        if self.object_type == OBJECT_OSLOADER {
+            if device.is_none() {
+                issues.push("No device element".to_string());
+            }
+            if os_device.is_none() {
+                issues.push("No osdevice element".to_string());
+            }
+        }
+        if let Some(layout) = layout {
+            if let Some(device) = &device {
+                if let Err(issue) = check_device(device, layout) {
+                    issues.push(format!("device: {}", issue));
+                }
+            }
+            if let Some(os_device) = &os_device {
+                match check_device(os_device, layout) {
+                    Ok(Some(part)) if part.is_efi_system() => {
+                        issues.push("osdevice points at the EFI system partition".to_string())
+                    }
+                    Ok(_) => {}
+                    Err(issue) => issues.push(format!("osdevice: {}", issue)),
+                }
+            }
+        }
+
+        BcdEntryInfo {
+            id: self.id.clone(),
+            alias: well_known_alias(&self.id).map(str::to_string),
+            kind: kind.to_string(),
+            description: self.string(ELEMENT_DESCRIPTION).unwrap_or_default(),
+            device: device.as_ref().map(|d| device_info(d, layout)),
+            os_device: os_device.as_ref().map(|d| device_info(d, layout)),
+            path: self.string(ELEMENT_PATH),
+            system_root: self.string(ELEMENT_SYSTEM_ROOT),
+            locale: self.string(ELEMENT_LOCALE),
+            is_default: false,
+            issues,
+        }
+    }
+}
+
+impl BcdDevice {
+    fn host(&self) -> Option<&PartitionRef> {
+        match self {
+            BcdDevice::Partition(part) => Some(part),
+            BcdDevice::Vhd { host, .. } => host.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PartitionRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionRef::Mbr { signature, offset } => {
+                write!(f, "MBR disk 0x{:08X}, offset {}", signature, offset)
+            }
+            PartitionRef::Gpt {
+                disk_guid,
+                partition_guid,
+            } => write!(
+                f,
+                "GPT disk {{{}}}, partition {{{}}}",
+                disk_guid, partition_guid
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for BcdDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BcdDevice::Boot => write!(f, "boot"),
+            BcdDevice::Locate => write!(f, "locate"),
+            BcdDevice::Partition(part) => write!(f, "partition=[{}]", part),
+            BcdDevice::Vhd {
+                host: Some(host),
+                path,
+            } => write!(f, "vhd=[{}]{}", host, path),
+            BcdDevice::Vhd { host: None, path } => write!(f, "vhd=[locate]{}", path),
+            BcdDevice::Unknown(kind) => write!(f, "unknown device type {}", kind),
+        }
+    }
+}
+
+/// Decode a device element. VHD devices nest the host partition descriptor and the
+/// file path after the outer descriptor; both are located by scanning so that the
+/// variants written by different Windows versions are all understood.
+pub fn parse_device(data: &[u8]) -> BcdDevice {
+    let base = DEVICE_DESCRIPTOR_OFFSET;
+    if data.len() < base + DESCRIPTOR_SIZE + 4 {
+        return BcdDevice::Unknown(0);
+    }
+
+    if let Some(path) = find_vhd_path(data) {
+        let host = (base + 4..data.len().saturating_sub(PARTITION_DESCRIPTOR_LEN - 1))
+            .step_by(4)
+            .filter(|&at| {
+                read_u32(data, at + DESCRIPTOR_TYPE) == DEVICE_TYPE_PARTITION
+                    && read_u32(data, at + DESCRIPTOR_SIZE) as usize == PARTITION_DESCRIPTOR_LEN
+            })
+            .find_map(|at| parse_partition_descriptor(&data[at..]));
+        return BcdDevice::Vhd { host, path };
+    }
+
+    match read_u32(data, base + DESCRIPTOR_TYPE) {
+        DEVICE_TYPE_BOOT => BcdDevice::Boot,
+        DEVICE_TYPE_LOCATE => BcdDevice::Locate,
+        DEVICE_TYPE_PARTITION => parse_partition_descriptor(&data[base..])
+            .map(BcdDevice::Partition)
+            .unwrap_or(BcdDevice::Unknown(DEVICE_TYPE_PARTITION)),
+        other => BcdDevice::Unknown(other),
+    }
+}
+
+fn parse_partition_descriptor(desc: &[u8]) -> Option<PartitionRef> {
+    if desc.len() < DESCRIPTOR_DISK_ID + 16 {
+        return None;
+    }
+    match read_u32(desc, DESCRIPTOR_PARTITION_STYLE) {
+        PARTITION_STYLE_MBR => Some(PartitionRef::Mbr {
+            signature: read_u32(desc, DESCRIPTOR_DISK_ID),
+            offset: read_u64(desc, DESCRIPTOR_PARTITION_ID),
+        }),
+        PARTITION_STYLE_GPT => Some(PartitionRef::Gpt {
+            disk_guid: read_guid(desc, DESCRIPTOR_DISK_ID),
+            partition_guid: read_guid(desc, DESCRIPTOR_PARTITION_ID),
+        }),
+        _ => None,
+    }
+}
+
+/// First NUL-terminated UTF-16 path in the element that names a .vhd/.vhdx file.
+fn find_vhd_path(data: &[u8]) -> Option<String> {
+    (DEVICE_DESCRIPTOR_OFFSET..data.len().saturating_sub(1))
+        .step_by(2)
+        .filter(|&at| data[at] == b'\\' && data[at + 1] == 0)
+        .find_map(|at| {
+            let units: Vec<u16> = data[at..]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&u| u != 0)
+                .collect();
+            let path = String::from_utf16(&units).ok()?;
+            let lower = path.to_ascii_lowercase();
+            (lower.ends_with(".vhd") || lower.ends_with(".vhdx")).then_some(path)
+        })
+}
+
+/// `Ok(Some(partition))` when the reference resolves on this disk, `Ok(None)` for
+/// devices that are resolved at boot time, and a description of the problem otherwise.
+fn check_device<'a>(
+    device: &BcdDevice,
+    layout: &'a DiskLayout,
+) -> std::result::Result<Option<&'a PartitionEntry>, String> {
+    let Some(part) = device.host() else {
+        return Ok(None);
+    };
+    match (part, layout.style) {
+        (PartitionRef::Mbr { signature, offset }, PartitionStyle::Mbr { signature: disk }) => {
+            if *signature != disk {
+                return Err(format!(
+                    "points to MBR disk 0x{:08X}, not this disk (0x{:08X})",
+                    signature, disk
+                ));
+            }
+            layout.partition_at(*offset).map(Some).ok_or_else(|| {
+                format!("no partition starts at byte offset {} on this disk", offset)
+            })
+        }
+        (
+            PartitionRef::Gpt {
+                disk_guid,
+                partition_guid,
+            },
+            PartitionStyle::Gpt { disk_guid: disk },
+        ) => {
+            if *disk_guid != disk {
+                return Err(format!(
+                    "points to GPT disk {{{}}}, not this disk ({{{}}})",
+                    disk_guid, disk
+                ));
+            }
+            layout
+                .partition_by_guid(partition_guid)
+                .map(Some)
+                .ok_or_else(|| format!("no partition {{{}}} on this disk", partition_guid))
+        }
+        (PartitionRef::Mbr { .. }, PartitionStyle::Gpt { .. }) => {
+            Err("points to an MBR disk, but this disk is GPT".to_string())
+        }
+        (PartitionRef::Gpt { .. }, PartitionStyle::Mbr { .. }) => {
+            Err("points to a GPT disk, but this disk is MBR".to_string())
+        }
+    }
+}
+
+fn device_info(device: &BcdDevice, layout: Option<&DiskLayout>) -> BcdDeviceInfo {
+    let mut info = BcdDeviceInfo {
+        kind: match device {
+            BcdDevice::Boot => "boot",
+            BcdDevice::Locate => "locate",
+            BcdDevice::Partition(_) => "partition",
+            BcdDevice::Vhd { .. } => "vhd",
+            BcdDevice::Unknown(_) => "unknown",
+        }
+        .to_string(),
+        display: device.to_string(),
+        ..Default::default()
+    };
+    if let BcdDevice::Vhd { path, .. } = device {
+        info.vhd_path = Some(path.clone());
+    }
+    match device.host() {
+        Some(PartitionRef::Mbr { signature, offset }) => {
+            info.partition_style = Some("mbr".to_string());
+            info.disk_id = Some(format!("0x{:08X}", signature));
+            info.partition_offset = Some(*offset);
+        }
+        Some(PartitionRef::Gpt {
+            disk_guid,
+            partition_guid,
+        }) => {
+            info.partition_style = Some("gpt".to_string());
+            info.disk_id = Some(format!("{{{}}}", disk_guid));
+            info.partition_id = Some(format!("{{{}}}", partition_guid));
+        }
+        None => {}
+    }
+    info.partition_number = layout
+        .and_then(|l| check_device(device, l).ok().flatten())
+        .map(|p| p.number);
+    info
+}
+
+/// Parse every store in `stores` and check it against the partition table of `disk`
+/// (a raw device path or disk image). Stores that fail to parse are reported as an
+/// issue instead of failing the whole inspection.
+pub fn inspect_stores(disk: &Path, stores: &[PathBuf]) -> Vec<BcdStoreInfo> {
+    let layout = partition_table::read_disk_layout(disk);
+    stores
+        .iter()
+        .map(|path| {
+            let mut info = match BcdStore::open(path) {
+                Ok(store) => store.summarize(path, layout.as_ref().ok()),
+                Err(e) => BcdStoreInfo {
+                    path: path.to_string_lossy().to_string(),
+                    issues: vec![format!("Cannot parse BCD store: {}", e)],
+                    ..Default::default()
+                },
+            };
+            if let Err(e) = &layout {
+                info.issues.push(format!(
+                    "Partition table of {} could not be read, device references were not checked: {}",
+                    disk.display(),
+                    e
+                ));
+            }
+            info
+        })
+        .collect()
+}
+
+impl PartitionRef {
+    /// How a BCD device element identifies `partition` of the disk described by `layout`.
+    pub fn for_partition(layout: &DiskLayout, partition: &PartitionEntry) -> Option<Self> {
+        match layout.style {
+            PartitionStyle::Mbr { signature } => Some(PartitionRef::Mbr {
+                signature,
+                offset: partition.start,
+            }),
+            PartitionStyle::Gpt { disk_guid } => Some(PartitionRef::Gpt {
+                disk_guid,
+                partition_guid: partition.unique_guid?,
+            }),
+        }
+    }
+
+    fn encode_descriptor(&self) -> Vec<u8> {
+        let mut desc = vec![0u8; PARTITION_DESCRIPTOR_LEN];
+        write_u32(&mut desc, DESCRIPTOR_TYPE, DEVICE_TYPE_PARTITION);
+        write_u32(&mut desc, DESCRIPTOR_SIZE, PARTITION_DESCRIPTOR_LEN as u32);
+        match self {
+            PartitionRef::Mbr { signature, offset } => {
+                desc[DESCRIPTOR_PARTITION_ID..DESCRIPTOR_PARTITION_ID + 8]
+                    .copy_from_slice(&offset.to_le_bytes());
+                write_u32(&mut desc, DESCRIPTOR_PARTITION_STYLE, PARTITION_STYLE_MBR);
+                write_u32(&mut desc, DESCRIPTOR_DISK_ID, *signature);
+            }
+            PartitionRef::Gpt {
+                disk_guid,
+                partition_guid,
+            } => {
+                desc[DESCRIPTOR_PARTITION_ID..DESCRIPTOR_PARTITION_ID + 16]
+                    .copy_from_slice(&partition_guid.to_bytes_le());
+                write_u32(&mut desc, DESCRIPTOR_PARTITION_STYLE, PARTITION_STYLE_GPT);
+                desc[DESCRIPTOR_DISK_ID..DESCRIPTOR_DISK_ID + 16]
+                    .copy_from_slice(&disk_guid.to_bytes_le());
+            }
+        }
+        desc
+    }
+}
+
+/// Encode a device element, the inverse of [`parse_device`].
+pub fn encode_device(device: &BcdDevice) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; DEVICE_DESCRIPTOR_OFFSET];
+    match device {
+        BcdDevice::Boot | BcdDevice::Locate => {
+            let mut desc = vec![0u8; PARTITION_DESCRIPTOR_LEN];
+            let kind = if *device == BcdDevice::Boot {
+                DEVICE_TYPE_BOOT
+            } else {
+                DEVICE_TYPE_LOCATE
+            };
+            write_u32(&mut desc, DESCRIPTOR_TYPE, kind);
+            write_u32(&mut desc, DESCRIPTOR_SIZE, PARTITION_DESCRIPTOR_LEN as u32);
+            data.extend_from_slice(&desc);
+        }
+        BcdDevice::Partition(part) => data.extend_from_slice(&part.encode_descriptor()),
+        BcdDevice::Vhd {
+            host: Some(host),
+            path,
+        } => {
+            let mut path_bytes: Vec<u8> = path.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            path_bytes.extend_from_slice(&[0, 0]);
+            let mut outer = vec![0u8; PARTITION_DESCRIPTOR_LEN];
+            let total = 2 * PARTITION_DESCRIPTOR_LEN + path_bytes.len();
+            write_u32(&mut outer, DESCRIPTOR_TYPE, DEVICE_TYPE_FILE);
+            write_u32(&mut outer, DESCRIPTOR_SIZE, total as u32);
+            write_u32(&mut outer, DESCRIPTOR_LOCAL_TYPE, LOCAL_TYPE_VIRTUAL_DISK);
+            data.extend_from_slice(&outer);
+            data.extend_from_slice(&host.encode_descriptor());
+            data.extend_from_slice(&path_bytes);
+        }
+        BcdDevice::Vhd { host: None, .. } | BcdDevice::Unknown(_) => {
+            return Err(AppError::InvalidParameter(format!(
+                "Cannot encode BCD device: {}",
+                device
+            )));
+        }
+    }
+    Ok(data)
+}
+
+/// Edits a BCD store in memory; nothing touches the file until [`BcdEditor::save`].
+pub struct BcdEditor {
+    hive: HiveFile,
+}
+
+impl BcdEditor {
+    pub fn open(path: &Path) -> Result<Self> {
+        let hive = HiveFile::load(path)?;
+        if hive.root.subkey("Objects").is_none() {
+            return Err(invalid("Objects key not found"));
+        }
+        Ok(BcdEditor { hive })
+    }
+
+    pub fn store(&self) -> Result<BcdStore> {
+        BcdStore::from_tree(&self.hive.root)
+    }
+
+    /// Create the object if it does not exist yet.
+    pub fn ensure_object(&mut self, id: &str, object_type: u32) {
+        let object = self.object_key(id);
+        if object
+            .open("Description")
+            .and_then(|d| d.value("Type"))
+            .is_none()
+        {
+            object.create("Description").set_value(
+                "Type",
+                REG_DWORD,
+                object_type.to_le_bytes().to_vec(),
+            );
+        }
+        object.create("Elements");
+    }
+
+    pub fn set_device(&mut self, id: &str, element_type: u32, device: &BcdDevice) -> Result<()> {
+        let data = encode_device(device)?;
+        self.set_element(id, element_type, REG_BINARY, data);
+        Ok(())
+    }
+
+    pub fn set_string(&mut self, id: &str, element_type: u32, value: &str) {
+        self.set_element(id, element_type, REG_SZ, utf16z(value));
+    }
+
+    pub fn set_object_list(&mut self, id: &str, element_type: u32, ids: &[String]) {
+        let mut data: Vec<u8> = ids.iter().flat_map(|id| utf16z(id)).collect();
+        data.extend_from_slice(&[0, 0]);
+        self.set_element(id, element_type, REG_MULTI_SZ, data);
+    }
+
+    pub fn set_integer(&mut self, id: &str, element_type: u32, value: u64) {
+        self.set_element(id, element_type, REG_BINARY, value.to_le_bytes().to_vec());
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.hive.save(path)
+    }
+
+    fn set_element(&mut self, id: &str, element_type: u32, data_type: u32, data: Vec<u8>) {
+        self.object_key(id)
+            .create(&format!("Elements\\{:08X}", element_type))
+            .set_value("Element", data_type, data);
+    }
+
+    fn object_key(&mut self, id: &str) -> &mut KeyTree {
+        // Stores written by Windows use upper-case GUIDs for object key names.
+        let objects = self.hive.root.create("Objects");
+        let existing = objects
+            .subkeys
+            .iter()
+            .position(|k| normalize_id(&k.name) == normalize_id(id));
+        match existing {
+            Some(index) => &mut objects.subkeys[index],
+            None => objects.create_subkey(&id.trim().to_ascii_uppercase()),
+        }
+    }
+}
+
+/// Device and menu settings written by [`fix_boot_entries`]; the offline counterpart of
+/// `bcdedit_fix_boot_file_typical` / `bcdedit_fix_boot_file_vhd`.
+#[derive(Debug, Clone)]
+pub struct BootEntryFix {
+    /// `{bootmgr}` device: the partition holding the boot files
+    pub boot_device: BcdDevice,
+    /// `device` and `osdevice` of the default OS loader
+    pub os_device: BcdDevice,
+    /// Selects winload.efi over winload.exe when a loader has to be created
+    pub uefi: bool,
+    pub locale: Option<String>,
+    pub timeout: Option<u64>,
+}
+
+impl BootEntryFix {
+    /// UEFI boot from partition `boot_partition` into Windows on `system_partition`, both
+    /// numbered as in `layout`.
+    pub fn for_partitions(
+        layout: &DiskLayout,
+        boot_partition: u32,
+        system_partition: u32,
+    ) -> Result<Self> {
+        let reference = |number: u32| {
+            layout
+                .partitions
+                .iter()
+                .find(|p| p.number == number)
+                .and_then(|p| PartitionRef::for_partition(layout, p))
+                .map(BcdDevice::Partition)
+                .ok_or_else(|| {
+                    AppError::DiskError(format!(
+                        "Partition {} not found in the target partition table",
+                        number
+                    ))
+                })
+        };
+        Ok(BootEntryFix {
+            boot_device: reference(boot_partition)?,
+            os_device: reference(system_partition)?,
+            uefi: true,
+            locale: None,
+            timeout: Some(DEFAULT_TIMEOUT_SECONDS),
+        })
+    }
+
+    /// Boot Windows from `vhd_filename` in the root of the system partition instead, as
+    /// `bcdedit_fix_boot_file_vhd` does.
+    pub fn in_vhd(mut self, vhd_filename: &str) -> Self {
+        if let BcdDevice::Partition(host) = self.os_device {
+            self.os_device = BcdDevice::Vhd {
+                host: Some(host),
+                path: format!("\\{}", vhd_filename.trim_start_matches('\\')),
+            };
+        }
+        self
+    }
+}
+
+/// Point `{bootmgr}` and the default OS loader of the store at `path` to the given
+/// devices. A loader is created when the store (e.g. a BCD-Template copy) has none.
+/// Returns the id of the default loader.
+pub fn fix_boot_entries(path: &Path, fix: &BootEntryFix) -> Result<String> {
+    let mut editor = BcdEditor::open(path)?;
+    let store = editor.store()?;
+    let is_loader = |id: &String| {
+        store
+            .object(id)
+            .is_some_and(|o| o.object_type == OBJECT_OSLOADER)
+    };
+
+    let default = store
+        .default_entry()
+        .filter(is_loader)
+        .or_else(|| store.display_order().into_iter().find(is_loader))
+        .or_else(|| {
+            store
+                .objects
+                .iter()
+                .find(|o| o.object_type == OBJECT_OSLOADER)
+                .map(|o| o.id.clone())
+        });
+    let default = match default {
+        Some(id) => id,
+        None => {
+            let id = format!("{{{}}}", Uuid::new_v4());
+            let winload = if fix.uefi {
+                "winload.efi"
+            } else {
+                "winload.exe"
+            };
+            editor.ensure_object(&id, OBJECT_OSLOADER);
+            editor.set_string(&id, ELEMENT_DESCRIPTION, "Windows");
+            editor.set_string(
+                &id,
+                ELEMENT_PATH,
+                &format!("\\Windows\\system32\\{}", winload),
+            );
+            editor.set_string(&id, ELEMENT_SYSTEM_ROOT, "\\Windows");
+            if store.object(BOOTLOADERSETTINGS_ID).is_some() {
+                editor.set_object_list(&id, ELEMENT_INHERIT, &[BOOTLOADERSETTINGS_ID.to_string()]);
+            }
+            id
+        }
+    };
+
+    if store.boot_manager().is_none() {
+        editor.ensure_object(BOOTMGR_ID, OBJECT_BOOTMGR);
+        editor.set_string(BOOTMGR_ID, ELEMENT_DESCRIPTION, "Windows Boot Manager");
+        if fix.uefi {
+            editor.set_string(
+                BOOTMGR_ID,
+                ELEMENT_PATH,
+                "\\EFI\\Microsoft\\Boot\\bootmgfw.efi",
+            );
+        }
+    }
+    editor.set_device(BOOTMGR_ID, ELEMENT_DEVICE, &fix.boot_device)?;
+    editor.set_string(BOOTMGR_ID, ELEMENT_DEFAULT, &default);
+    let display_order: Vec<String> = std::iter::once(default.clone())
+        .chain(
+            store
+                .display_order()
+                .into_iter()
+                .filter(|id| *id != default && store.object(id).is_some()),
+        )
+        .collect();
+    editor.set_object_list(BOOTMGR_ID, ELEMENT_DISPLAY_ORDER, &display_order);
+
+    editor.set_device(&default, ELEMENT_DEVICE, &fix.os_device)?;
+    editor.set_device(&default, ELEMENT_OS_DEVICE, &fix.os_device)?;
+    if let Some(locale) = &fix.locale {
+        editor.set_string(BOOTMGR_ID, ELEMENT_LOCALE, locale);
+        editor.set_string(&default, ELEMENT_LOCALE, locale);
+    }
+    if let Some(timeout) = fix.timeout {
+        editor.set_integer(BOOTMGR_ID, ELEMENT_TIMEOUT, timeout);
+    }
+
+    editor.save(path)?;
+    Ok(default)
+}
+
+pub fn well_known_alias(id: &str) -> Option<&'static str> {
+    let id = normalize_id(id);
+    WELL_KNOWN_OBJECTS
+        .iter()
+        .find(|(guid, _)| *guid == id)
+        .map(|(_, alias)| *alias)
+}
+
+fn normalize_id(id: &str) -> String {
+    id.trim().to_ascii_lowercase()
+}
+
+fn invalid(detail: &str) -> AppError {
+    AppError::SystemError(format!("Invalid BCD store: {}", detail))
+}
+
+fn utf16z(value: &str) -> Vec<u8> {
+    value
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+fn write_u32(buf: &mut [u8], at: usize, value: u32) {
+    buf[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(raw)
+}
+
+fn read_guid(buf: &[u8], at: usize) -> Uuid {
+    let mut raw = [0u8; 16];
+    raw.copy_from_slice(&buf[at..at + 16]);
+    Uuid::from_bytes_le(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::partition_table::{BASIC_DATA_TYPE_GUID, ESP_TYPE_GUID};
+    use std::io::Cursor;
+
+    const BCD: &[u8] = include_bytes!("../../tests/fixtures/regf/bcd_min.hiv");
+    const DISK: Uuid = uuid::uuid!("5a3e6b2c-0f1d-4c9a-9b7e-1c2d3e4f5a6b");
+    const ESP: Uuid = uuid::uuid!("0c7d9f5e-3b21-4e8a-b6c4-8f1e2d3c4b5a");
+    const WIN: Uuid = uuid::uuid!("6f3c9b1e-8d2a-4b7c-a5e4-3d2c1b0a9f8e");
+    const TO_GO: &str = "{b4c2e6d8-1a3f-4c5e-9d7b-2e4f6a8c0b1d}";
+
+    fn store() -> BcdStore {
+        BcdStore::from_hive(&mut Hive::new(Cursor::new(BCD)).unwrap()).unwrap()
+    }
+
+    fn partition(number: u32, type_guid: Uuid, unique_guid: Uuid) -> PartitionEntry {
+        PartitionEntry {
+            number,
+            start: number as u64 * 1024 * 1024,
+            size: 1024 * 1024,
+            mbr_type: 0,
+            active: false,
+            type_guid: Some(type_guid),
+            unique_guid: Some(unique_guid),
+            name: String::new(),
+        }
+    }
+
+    fn layout(windows_type: Uuid) -> DiskLayout {
+        DiskLayout {
+            style: PartitionStyle::Gpt { disk_guid: DISK },
+            sector_size: 512,
+            partitions: vec![
+                partition(1, ESP_TYPE_GUID, ESP),
+                partition(2, windows_type, WIN),
+            ],
+        }
+    }
+
+    #[test]
+    fn reads_boot_manager_settings() {
+        let store = store();
+        assert_eq!(store.default_entry().as_deref(), Some(TO_GO));
+        assert_eq!(store.display_order().len(), 3);
+        assert_eq!(store.timeout(), Some(30));
+
+        let bootmgr = store.boot_manager().unwrap();
+        assert_eq!(
+            bootmgr.device(ELEMENT_DEVICE),
+            Some(BcdDevice::Partition(PartitionRef::Gpt {
+                disk_guid: DISK,
+                partition_guid: ESP,
+            }))
+        );
+        assert_eq!(
+            bootmgr.string(ELEMENT_PATH).as_deref(),
+            Some("\\EFI\\Microsoft\\Boot\\bootmgfw.efi")
+        );
+    }
+
+    #[test]
+    fn decodes_vhd_device_with_host_partition() {
+        let store = store();
+        let vhd = store
+            .object("{D6E4A8FA-3C5B-4E7A-9F9D-4A6B8C0E2D3F}")
+            .unwrap();
+        let device = vhd.device(ELEMENT_DEVICE).unwrap();
+        assert_eq!(
+            device,
+            BcdDevice::Vhd {
+                host: Some(PartitionRef::Gpt {
+                    disk_guid: DISK,
+                    partition_guid: WIN,
+                }),
+                path: "\\win8.vhdx".to_string(),
+            }
+        );
+        assert!(device.to_string().starts_with("vhd=[GPT disk"));
+    }
+
+    #[test]
+    fn decodes_mbr_partition_device() {
+        let mut data = vec![0u8; DEVICE_DESCRIPTOR_OFFSET + PARTITION_DESCRIPTOR_LEN];
+        let desc = &mut data[DEVICE_DESCRIPTOR_OFFSET..];
+        desc[..4].copy_from_slice(&DEVICE_TYPE_PARTITION.to_le_bytes());
+        desc[DESCRIPTOR_PARTITION_ID..DESCRIPTOR_PARTITION_ID + 8]
+            .copy_from_slice(&1_048_576u64.to_le_bytes());
+        desc[DESCRIPTOR_PARTITION_STYLE..DESCRIPTOR_PARTITION_STYLE + 4]
+            .copy_from_slice(&PARTITION_STYLE_MBR.to_le_bytes());
+        desc[DESCRIPTOR_DISK_ID..DESCRIPTOR_DISK_ID + 4]
+            .copy_from_slice(&0x1234_ABCDu32.to_le_bytes());
+        assert_eq!(
+            parse_device(&data),
+            BcdDevice::Partition(PartitionRef::Mbr {
+                signature: 0x1234_ABCD,
+                offset: 1_048_576,
+            })
+        );
+
+        data[DEVICE_DESCRIPTOR_OFFSET] = DEVICE_TYPE_BOOT as u8;
+        assert_eq!(parse_device(&data), BcdDevice::Boot);
+    }
+
+    #[test]
+    fn flags_entries_pointing_elsewhere() {
+        let info = store().summarize(Path::new("BCD"), Some(&layout(BASIC_DATA_TYPE_GUID)));
+        assert!(info.issues.is_empty(), "{:?}", info.issues);
+        assert_eq!(info.partition_style, "gpt");
+
+        let ids: Vec<&str> = info.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids[0], BOOTMGR_ID);
+        assert_eq!(ids[1], TO_GO);
+        assert_eq!(info.entries.len(), 4, "inheritable objects are skipped");
+
+        let bootmgr = &info.entries[0];
+        assert_eq!(bootmgr.alias.as_deref(), Some("{bootmgr}"));
+        assert!(bootmgr.issues.is_empty());
+        assert_eq!(bootmgr.device.as_ref().unwrap().partition_number, Some(1));
+
+        let to_go = &info.entries[1];
+        assert!(to_go.is_default);
+        assert!(to_go.issues.is_empty(), "{:?}", to_go.issues);
+        assert_eq!(to_go.locale.as_deref(), Some("zh-CN"));
+        assert_eq!(to_go.os_device.as_ref().unwrap().partition_number, Some(2));
+
+        let internal = &info.entries[2];
+        assert_eq!(internal.issues.len(), 2);
+        assert!(internal.issues[0].starts_with("device: points to GPT disk"));
+
+        let vhd = &info.entries[3];
+        assert_eq!(vhd.issues, vec!["No osdevice element".to_string()]);
+        assert_eq!(
+            vhd.device.as_ref().unwrap().vhd_path.as_deref(),
+            Some("\\win8.vhdx")
+        );
+    }
+
+    #[test]
+    fn flags_os_device_on_esp() {
+        let info = store().summarize(Path::new("BCD"), Some(&layout(ESP_TYPE_GUID)));
+        assert_eq!(
+            info.entries[1].issues,
+            vec!["osdevice points at the EFI system partition".to_string()]
+        );
+    }
+
+    #[test]
+    fn encoded_devices_parse_back() {
+        let gpt = PartitionRef::Gpt {
+            disk_guid: DISK,
+            partition_guid: WIN,
+        };
+        let devices = [
+            BcdDevice::Boot,
+            BcdDevice::Partition(PartitionRef::Mbr {
+                signature: 0xDEAD_BEEF,
+                offset: 0x10_0000,
+            }),
+            BcdDevice::Partition(gpt),
+            BcdDevice::Vhd {
+                host: Some(gpt),
+                path: "\\win8.vhdx".to_string(),
+            },
+        ];
+        for device in devices {
+            assert_eq!(parse_device(&encode_device(&device).unwrap()), device);
+        }
+        assert!(encode_device(&BcdDevice::Unknown(3)).is_err());
+    }
+
+    #[test]
+    fn fixes_entries_for_the_target_disk() {
+        let dir = std::env::temp_dir().join(format!("wtg-bcd-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BCD");
+        std::fs::write(&path, BCD).unwrap();
+
+        // Same store, but the partitions now live on a freshly partitioned disk.
+        let disk = uuid::uuid!("11111111-2222-4333-8444-555555555555");
+        let mut target = layout(BASIC_DATA_TYPE_GUID);
+        target.style = PartitionStyle::Gpt { disk_guid: disk };
+        let fix = BootEntryFix {
+            locale: Some("en-US".to_string()),
+            timeout: Some(5),
+            ..BootEntryFix::for_partitions(&target, 1, 2).unwrap()
+        };
+        assert_eq!(fix_boot_entries(&path, &fix).unwrap(), TO_GO);
+
+        let info = BcdStore::open(&path)
+            .unwrap()
+            .summarize(&path, Some(&target));
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(info.timeout, Some(5));
+        assert_eq!(info.default_entry.as_deref(), Some(TO_GO));
+        assert_eq!(info.display_order.len(), 3);
+        assert!(
+            info.entries[0].issues.is_empty(),
+            "{:?}",
+            info.entries[0].issues
+        );
+        let to_go = &info.entries[1];
+        assert!(to_go.issues.is_empty(), "{:?}", to_go.issues);
+        assert_eq!(to_go.locale.as_deref(), Some("en-US"));
+        assert_eq!(to_go.os_device.as_ref().unwrap().partition_number, Some(2));
+    }
+
+    #[test]
+    fn vhd_fix_keeps_host_partition() {
+        let fix = BootEntryFix::for_partitions(&layout(BASIC_DATA_TYPE_GUID), 1, 2)
+            .unwrap()
+            .in_vhd("win8.vhdx");
+        assert_eq!(
+            fix.os_device,
+            BcdDevice::Vhd {
+                host: Some(PartitionRef::Gpt {
+                    disk_guid: DISK,
+                    partition_guid: WIN,
+                }),
+                path: "\\win8.vhdx".to_string(),
+            }
+        );
+        assert!(BootEntryFix::for_partitions(&layout(BASIC_DATA_TYPE_GUID), 1, 3).is_err());
+    }
+
+    #[test]
+    fn creates_loader_when_store_has_none() {
+        let dir = std::env::temp_dir().join(format!("wtg-bcd-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BCD");
+        std::fs::write(&path, BCD).unwrap();
+
+        // Reduce the fixture to a template-like store without OS loaders.
+        let mut hive = HiveFile::load(&path).unwrap();
+        let objects = hive.root.create("Objects");
+        let loaders: Vec<String> = objects
+            .subkeys
+            .iter()
+            .filter(|k| {
+                k.open("Description")
+                    .and_then(|d| d.value("Type"))
+                    .and_then(|v| v.as_u32())
+                    == Some(OBJECT_OSLOADER)
+            })
+            .map(|k| k.name.clone())
+            .collect();
+        for name in &loaders {
+            objects.remove_subkey(name);
+        }
+        hive.save(&path).unwrap();
+
+        let partition = BcdDevice::Partition(PartitionRef::Mbr {
+            signature: 0x0BAD_F00D,
+            offset: 0x10_0000,
+        });
+        let fix = BootEntryFix {
+            boot_device: partition.clone(),
+            os_device: partition.clone(),
+            uefi: false,
+            locale: None,
+            timeout: None,
+        };
+        let id = fix_boot_entries(&path, &fix).unwrap();
+        let store = BcdStore::open(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(store.default_entry().as_deref(), Some(id.as_str()));
+        assert_eq!(store.display_order(), vec![id.clone()]);
+        let loader = store.object(&id).unwrap();
+        assert_eq!(loader.object_type, OBJECT_OSLOADER);
+        assert_eq!(
+            loader.string(ELEMENT_PATH).as_deref(),
+            Some("\\Windows\\system32\\winload.exe")
+        );
+        assert_eq!(loader.device(ELEMENT_OS_DEVICE), Some(partition));
+        assert_eq!(store.timeout(), Some(30));
+    }
+}
@@ -0,0 +1,1160 @@
+//! FAT32 formatting and a minimal file writer
+//!
+//! Formats a partition range of a disk device or image file as FAT32 (boot sector,
+//! FSInfo, both FATs, root directory with the volume label) with the cluster sizes
+//! Windows picks, and places files and directories on it with long names, so the UEFI
+//! boot payload can be staged on an ESP without mounting it. All I/O is in whole
+//! sectors or clusters so raw device handles accept it.
+
+use crate::{AppError, Result};
+use chrono::{Datelike, Local, Timelike};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Fewer clusters than this and FAT drivers take the volume for FAT16.
+pub const MIN_CLUSTERS: u64 = 65525;
+const MAX_CLUSTERS: u64 = 0x0FFF_FFF5;
+
+const RESERVED_SECTORS: u64 = 32;
+const FAT_COUNT: u64 = 2;
+const ROOT_CLUSTER: u32 = 2;
+const FSINFO_SECTOR: u64 = 1;
+const BACKUP_BOOT_SECTOR: u64 = 6;
+const MEDIA_FIXED: u8 = 0xF8;
+
+const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
+const FAT_EOC: u32 = 0x0FFF_FFFF;
+const FAT_EOC_MIN: u32 = 0x0FFF_FFF8;
+
+const FSINFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FSINFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const FSINFO_TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+
+const DIR_ENTRY_SIZE: usize = 32;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+const ATTR_LONG_NAME: u8 = 0x0F;
+const ENTRY_DELETED: u8 = 0xE5;
+const LFN_LAST: u8 = 0x40;
+const LFN_CHARS: usize = 13;
+const MAX_NAME_UNITS: usize = 255;
+/// NT case flags of short entries: lowercase base name / extension.
+const CASE_LOWER_BASE: u8 = 0x08;
+const CASE_LOWER_EXT: u8 = 0x10;
+
+const SHORT_NAME_SPECIALS: &[u8] = b"!#$%&'()-@^_`{}~";
+const INVALID_NAME_CHARS: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+
+/// On-disk geometry of a FAT32 volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fat32Params {
+    pub sector_size: u32,
+    pub sectors_per_cluster: u32,
+    pub reserved_sectors: u32,
+    pub fat_sectors: u32,
+    pub total_sectors: u32,
+    pub cluster_count: u32,
+}
+
+impl Fat32Params {
+    pub fn cluster_size(&self) -> u64 {
+        self.sector_size as u64 * self.sectors_per_cluster as u64
+    }
+
+    fn data_start(&self) -> u64 {
+        (self.reserved_sectors as u64 + FAT_COUNT * self.fat_sectors as u64)
+            * self.sector_size as u64
+    }
+}
+
+/// Cluster size Windows' `format fs=fat32` uses for a volume of `size` bytes.
+fn default_cluster_size(size: u64) -> u64 {
+    const MIB: u64 = 1024 * 1024;
+    match size {
+        s if s <= 64 * MIB => 512,
+        s if s <= 128 * MIB => 1024,
+        s if s <= 256 * MIB => 2048,
+        s if s <= 8192 * MIB => 4096,
+        s if s <= 16384 * MIB => 8192,
+        s if s <= 32768 * MIB => 16384,
+        _ => 32768,
+    }
+}
+
+/// Lay out a FAT32 volume of `size` bytes. The data area starts on a cluster boundary.
+pub fn fat32_params(size: u64, sector_size: u32) -> Result<Fat32Params> {
+    if !matches!(sector_size, 512 | 1024 | 2048 | 4096) {
+        return Err(AppError::InvalidParameter(format!(
+            "Unsupported sector size for FAT32: {}",
+            sector_size
+        )));
+    }
+    let ss = sector_size as u64;
+    let total_sectors = (size / ss).min(u32::MAX as u64);
+    let spc = (default_cluster_size(size) / ss).max(1);
+
+    let mut fat_sectors = 1u64;
+    let (reserved, clusters) = loop {
+        let reserved =
+            RESERVED_SECTORS + (spc - (RESERVED_SECTORS + FAT_COUNT * fat_sectors) % spc) % spc;
+        let data_sectors = total_sectors.saturating_sub(reserved + FAT_COUNT * fat_sectors);
+        let clusters = data_sectors / spc;
+        let needed = ((clusters + 2) * 4).div_ceil(ss);
+        if needed <= fat_sectors {
+            break (reserved, clusters);
+        }
+        fat_sectors = needed;
+    };
+    if !(MIN_CLUSTERS..=MAX_CLUSTERS).contains(&clusters) {
+        return Err(AppError::DiskError(format!(
+            "A {} MiB volume with {}-byte sectors cannot be formatted as FAT32 ({} clusters)",
+            size / (1024 * 1024),
+            sector_size,
+            clusters
+        )));
+    }
+    Ok(Fat32Params {
+        sector_size,
+        sectors_per_cluster: spc as u32,
+        reserved_sectors: reserved as u32,
+        fat_sectors: fat_sectors as u32,
+        total_sectors: total_sectors as u32,
+        cluster_count: clusters as u32,
+    })
+}
+
+/// Format `size` bytes at `offset` of a device or image file as an empty FAT32 volume.
+/// `hidden_sectors` is the volume's start sector on its disk, which can differ
+/// from `offset` when `dev` is a partition node rather than the whole disk.
+pub fn format_fat32<D: Write + Seek>(
+    dev: &mut D,
+    offset: u64,
+    size: u64,
+    sector_size: u32,
+    hidden_sectors: u64,
+    label: &str,
+) -> Result<Fat32Params> {
+    let params = fat32_params(size, sector_size)?;
+    let label = volume_label(label)?;
+    let ss = sector_size as usize;
+
+    // Clear the reserved area, both FATs and the root directory cluster.
+    let metadata_len = params.data_start() + params.cluster_size();
+    let zeros = vec![0u8; (1024 * 1024).min(metadata_len as usize)];
+    let mut cleared = 0;
+    while cleared < metadata_len {
+        let chunk = (metadata_len - cleared).min(zeros.len() as u64) as usize;
+        write_at(dev, offset + cleared, &zeros[..chunk])?;
+        cleared += chunk as u64;
+    }
+
+    let boot = boot_sector(&params, hidden_sectors, rand::random(), &label);
+    write_at(dev, offset, &boot)?;
+    write_at(dev, offset + BACKUP_BOOT_SECTOR * ss as u64, &boot)?;
+    let fsinfo = fsinfo_sector(ss, params.cluster_count - 1, ROOT_CLUSTER + 1);
+    write_at(dev, offset + FSINFO_SECTOR * ss as u64, &fsinfo)?;
+    write_at(
+        dev,
+        offset + (BACKUP_BOOT_SECTOR + FSINFO_SECTOR) * ss as u64,
+        &fsinfo,
+    )?;
+
+    let mut fat = vec![0u8; ss];
+    fat[0..4].copy_from_slice(&(0x0FFF_FF00 | MEDIA_FIXED as u32).to_le_bytes());
+    fat[4..8].copy_from_slice(&FAT_EOC.to_le_bytes());
+    fat[8..12].copy_from_slice(&FAT_EOC.to_le_bytes());
+    for copy in 0..FAT_COUNT {
+        let fat_start =
+            (params.reserved_sectors as u64 + copy * params.fat_sectors as u64) * ss as u64;
+        write_at(dev, offset + fat_start, &fat)?;
+    }
+
+    let mut root = vec![0u8; params.cluster_size() as usize];
+    root[..11].copy_from_slice(&label);
+    root[11] = ATTR_VOLUME_ID;
+    let (date, time) = dos_timestamp();
+    root[22..24].copy_from_slice(&time.to_le_bytes());
+    root[24..26].copy_from_slice(&date.to_le_bytes());
+    write_at(dev, offset + params.data_start(), &root)?;
+    dev.flush().map_err(AppError::io)?;
+    Ok(params)
+}
+
+/// Format a partition of a device or image file, addressed by byte range.
+pub fn format_fat32_file(
+    path: &Path,
+    offset: u64,
+    size: u64,
+    sector_size: u32,
+    hidden_sectors: u64,
+    label: &str,
+) -> Result<Fat32Params> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(AppError::io)?;
+    let params = format_fat32(&mut file, offset, size, sector_size, hidden_sectors, label)?;
+    file.sync_all().map_err(AppError::io)?;
+    Ok(params)
+}
+
+fn boot_sector(
+    params: &Fat32Params,
+    hidden_sectors: u64,
+    volume_id: u32,
+    label: &[u8; 11],
+) -> Vec<u8> {
+    let mut boot = vec![0u8; params.sector_size as usize];
+    boot[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+    boot[3..11].copy_from_slice(b"MSWIN4.1");
+    boot[11..13].copy_from_slice(&(params.sector_size as u16).to_le_bytes());
+    boot[13] = params.sectors_per_cluster as u8;
+    boot[14..16].copy_from_slice(&(params.reserved_sectors as u16).to_le_bytes());
+    boot[16] = FAT_COUNT as u8;
+    boot[21] = MEDIA_FIXED;
+    boot[24..26].copy_from_slice(&63u16.to_le_bytes());
+    boot[26..28].copy_from_slice(&255u16.to_le_bytes());
+    boot[28..32].copy_from_slice(&(hidden_sectors.min(u32::MAX as u64) as u32).to_le_bytes());
+    boot[32..36].copy_from_slice(&params.total_sectors.to_le_bytes());
+    boot[36..40].copy_from_slice(&params.fat_sectors.to_le_bytes());
+    boot[44..48].copy_from_slice(&ROOT_CLUSTER.to_le_bytes());
+    boot[48..50].copy_from_slice(&(FSINFO_SECTOR as u16).to_le_bytes());
+    boot[50..52].copy_from_slice(&(BACKUP_BOOT_SECTOR as u16).to_le_bytes());
+    boot[64] = 0x80;
+    boot[66] = 0x29;
+    boot[67..71].copy_from_slice(&volume_id.to_le_bytes());
+    boot[71..82].copy_from_slice(label);
+    boot[82..90].copy_from_slice(b"FAT32   ");
+    // Not bootable: `int 18h` hands control back to the BIOS.
+    boot[90..92].copy_from_slice(&[0xCD, 0x18]);
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    boot
+}
+
+fn fsinfo_sector(sector_size: usize, free_count: u32, next_free: u32) -> Vec<u8> {
+    let mut info = vec![0u8; sector_size];
+    info[0..4].copy_from_slice(&FSINFO_LEAD_SIGNATURE.to_le_bytes());
+    info[484..488].copy_from_slice(&FSINFO_STRUCT_SIGNATURE.to_le_bytes());
+    info[488..492].copy_from_slice(&free_count.to_le_bytes());
+    info[492..496].copy_from_slice(&next_free.to_le_bytes());
+    info[508..512].copy_from_slice(&FSINFO_TRAIL_SIGNATURE.to_le_bytes());
+    info
+}
+
+/// 11-byte, space-padded volume label; empty means "NO NAME".
+fn volume_label(label: &str) -> Result<[u8; 11]> {
+    let label = label.trim().to_ascii_uppercase();
+    let label = if label.is_empty() { "NO NAME" } else { &label };
+    if label.len() > 11 || !label.bytes().all(|b| b == b' ' || is_short_name_byte(b)) {
+        return Err(AppError::InvalidParameter(format!(
+            "Invalid FAT32 volume label: {}",
+            label
+        )));
+    }
+    let mut raw = [b' '; 11];
+    raw[..label.len()].copy_from_slice(label.as_bytes());
+    Ok(raw)
+}
+
+/// A directory entry as stored in its parent directory.
+#[derive(Debug, Clone)]
+struct DirEntry {
+    name: String,
+    short_name: [u8; 11],
+    attr: u8,
+    cluster: u32,
+    size: u32,
+    /// Slot index of the first long-name entry, or of the short entry without one
+    first_slot: usize,
+    /// Slot index of the short entry
+    slot: usize,
+}
+
+impl DirEntry {
+    fn is_dir(&self) -> bool {
+        self.attr & ATTR_DIRECTORY != 0
+    }
+}
+
+/// A FAT32 volume opened for adding files. The FAT is kept in memory; call
+/// [`Fat32Volume::flush`] (or [`Fat32Volume::into_inner`]) to write it back.
+pub struct Fat32Volume<D: Read + Write + Seek> {
+    dev: D,
+    offset: u64,
+    params: Fat32Params,
+    root_cluster: u32,
+    fat: Vec<u32>,
+    next_free: u32,
+    dirty: bool,
+}
+
+impl Fat32Volume<File> {
+    /// Open the FAT32 volume at `offset` of a device or image file.
+    pub fn open_file(path: &Path, offset: u64) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(AppError::io)?;
+        Self::open(file, offset)
+    }
+}
+
+impl<D: Read + Write + Seek> Fat32Volume<D> {
+    pub fn open(mut dev: D, offset: u64) -> Result<Self> {
+        // Largest supported sector, so the read stays whole-sector on any device.
+        let mut probe = vec![0u8; 4096];
+        dev.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+        dev.read_exact(&mut probe).map_err(AppError::io)?;
+        let sector_size = u16::from_le_bytes([probe[11], probe[12]]) as u32;
+        let spc = probe[13] as u32;
+        let fat_sectors = read_u32(&probe, 36);
+        if probe[510] != 0x55
+            || probe[511] != 0xAA
+            || !matches!(sector_size, 512 | 1024 | 2048 | 4096)
+            || !spc.is_power_of_two()
+            || u16::from_le_bytes([probe[17], probe[18]]) != 0
+            || u16::from_le_bytes([probe[22], probe[23]]) != 0
+            || fat_sectors == 0
+        {
+            return Err(AppError::DiskError(
+                "Not a FAT32 volume (invalid boot sector)".to_string(),
+            ));
+        }
+        let reserved_sectors = u16::from_le_bytes([probe[14], probe[15]]) as u32;
+        let fat_count = probe[16] as u64;
+        let total_sectors = read_u32(&probe, 32);
+        let data_sectors = (total_sectors as u64)
+            .checked_sub(reserved_sectors as u64 + fat_count * fat_sectors as u64)
+            .ok_or_else(|| AppError::DiskError("Corrupt FAT32 boot sector".to_string()))?;
+        let cluster_count = (data_sectors / spc as u64)
+            .min(fat_sectors as u64 * sector_size as u64 / 4 - 2)
+            .min(MAX_CLUSTERS) as u32;
+        if fat_count != FAT_COUNT {
+            return Err(AppError::DiskError(format!(
+                "Unsupported FAT32 volume with {} FATs",
+                fat_count
+            )));
+        }
+        let params = Fat32Params {
+            sector_size,
+            sectors_per_cluster: spc,
+            reserved_sectors,
+            fat_sectors,
+            total_sectors,
+            cluster_count,
+        };
+
+        let mut raw = vec![0u8; fat_sectors as usize * sector_size as usize];
+        dev.seek(SeekFrom::Start(
+            offset + reserved_sectors as u64 * sector_size as u64,
+        ))
+        .map_err(AppError::io)?;
+        dev.read_exact(&mut raw).map_err(AppError::io)?;
+        let fat = raw
+            .chunks_exact(4)
+            .take(cluster_count as usize + 2)
+            .map(|e| u32::from_le_bytes([e[0], e[1], e[2], e[3]]) & FAT_ENTRY_MASK)
+            .collect();
+
+        let volume = Self {
+            dev,
+            offset,
+            params,
+            root_cluster: read_u32(&probe, 44),
+            fat,
+            next_free: ROOT_CLUSTER,
+            dirty: false,
+        };
+        volume.chain(volume.root_cluster)?;
+        Ok(volume)
+    }
+
+    #[cfg(test)]
+    pub fn params(&self) -> Fat32Params {
+        self.params
+    }
+
+    pub fn free_clusters(&self) -> u32 {
+        self.fat[2..].iter().filter(|&&e| e == 0).count() as u32
+    }
+
+    /// Create `path` and any missing parent directories.
+    pub fn create_dir_all(&mut self, path: &str) -> Result<()> {
+        self.resolve_dir(&split_path(path), true)?;
+        Ok(())
+    }
+
+    /// Write a file, creating missing parent directories and replacing an existing file.
+    pub fn write_file(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let mut components = split_path(path);
+        let name = components
+            .pop()
+            .ok_or_else(|| AppError::InvalidParameter("Empty FAT32 file path".to_string()))?;
+        validate_name(name)?;
+        let size = u32::try_from(data.len())
+            .map_err(|_| AppError::InvalidParameter(format!("{} is too large for FAT32", path)))?;
+        let dir = self.resolve_dir(&components, true)?;
+        if let Some(existing) = self.lookup(dir, name)? {
+            if existing.is_dir() {
+                return Err(AppError::DiskError(format!("{} is a directory", path)));
+            }
+            self.remove_entry(dir, &existing)?;
+        }
+
+        let cluster_size = self.params.cluster_size() as usize;
+        let clusters = self.allocate(data.len().div_ceil(cluster_size))?;
+        self.write_chain(&clusters, data)?;
+        let first = clusters.first().copied().unwrap_or(0);
+        self.add_entry(dir, name, ATTR_ARCHIVE, first, size)
+    }
+
+    /// Read back a file written to, or already present on, the volume.
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>> {
+        let entry = self.find(path)?.filter(|e| !e.is_dir()).ok_or_else(|| {
+            AppError::DiskError(format!("File not found on FAT32 volume: {}", path))
+        })?;
+        if entry.cluster == 0 {
+            return Ok(Vec::new());
+        }
+        let mut data = self.read_chain(entry.cluster)?;
+        data.truncate(entry.size as usize);
+        Ok(data)
+    }
+
+    /// Whether a file or directory exists at `path`.
+    pub fn exists(&mut self, path: &str) -> Result<bool> {
+        Ok(split_path(path).is_empty() || self.find(path)?.is_some())
+    }
+
+    /// Copy a host directory tree into `dest`, merging with what is already there.
+    pub fn copy_dir(&mut self, src: &Path, dest: &str) -> Result<()> {
+        self.create_dir_all(dest)?;
+        let mut entries = fs::read_dir(src)
+            .map_err(AppError::io)?
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(AppError::io)?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name.to_str().ok_or_else(|| {
+                AppError::InvalidParameter(format!(
+                    "Non-UTF-8 file name: {}",
+                    entry.path().display()
+                ))
+            })?;
+            let target = format!("{}/{}", dest.trim_end_matches(['/', '\\']), name);
+            if entry.file_type().map_err(AppError::io)?.is_dir() {
+                self.copy_dir(&entry.path(), &target)?;
+            } else {
+                let data = fs::read(entry.path()).map_err(AppError::io)?;
+                self.write_file(&target, &data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the FATs and FSInfo back to the volume.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.dirty {
+            let ss = self.params.sector_size as u64;
+            let mut raw = vec![0u8; self.params.fat_sectors as usize * ss as usize];
+            for (chunk, entry) in raw.chunks_exact_mut(4).zip(&self.fat) {
+                chunk.copy_from_slice(&entry.to_le_bytes());
+            }
+            for copy in 0..FAT_COUNT {
+                let start = (self.params.reserved_sectors as u64
+                    + copy * self.params.fat_sectors as u64)
+                    * ss;
+                write_at(&mut self.dev, self.offset + start, &raw)?;
+            }
+            let fsinfo = fsinfo_sector(ss as usize, self.free_clusters(), self.next_free);
+            for sector in [FSINFO_SECTOR, BACKUP_BOOT_SECTOR + FSINFO_SECTOR] {
+                write_at(&mut self.dev, self.offset + sector * ss, &fsinfo)?;
+            }
+            self.dirty = false;
+        }
+        self.dev.flush().map_err(AppError::io)
+    }
+
+    /// Flush and hand back the underlying device.
+    pub fn into_inner(mut self) -> Result<D> {
+        self.flush()?;
+        Ok(self.dev)
+    }
+
+    fn find(&mut self, path: &str) -> Result<Option<DirEntry>> {
+        let mut components = split_path(path);
+        let Some(name) = components.pop() else {
+            return Ok(None);
+        };
+        let mut dir = self.root_cluster;
+        for component in components {
+            match self.lookup(dir, component)? {
+                Some(entry) if entry.is_dir() => dir = entry.cluster,
+                _ => return Ok(None),
+            }
+        }
+        self.lookup(dir, name)
+    }
+
+    fn resolve_dir(&mut self, components: &[&str], create: bool) -> Result<u32> {
+        let mut dir = self.root_cluster;
+        for component in components {
+            dir = match self.lookup(dir, component)? {
+                Some(entry) if entry.is_dir() => entry.cluster,
+                Some(_) => {
+                    return Err(AppError::DiskError(format!(
+                        "{} exists and is not a directory",
+                        component
+                    )))
+                }
+                None if create => self.create_dir(dir, component)?,
+                None => {
+                    return Err(AppError::DiskError(format!(
+                        "Directory not found on FAT32 volume: {}",
+                        component
+                    )))
+                }
+            };
+        }
+        Ok(dir)
+    }
+
+    fn create_dir(&mut self, parent: u32, name: &str) -> Result<u32> {
+        validate_name(name)?;
+        let cluster = self.allocate(1)?[0];
+        let mut data = vec![0u8; self.params.cluster_size() as usize];
+        let parent_ref = if parent == self.root_cluster {
+            0
+        } else {
+            parent
+        };
+        let (date, time) = dos_timestamp();
+        data[..DIR_ENTRY_SIZE].copy_from_slice(&short_entry(
+            *b".          ",
+            ATTR_DIRECTORY,
+            0,
+            cluster,
+            0,
+            date,
+            time,
+        ));
+        data[DIR_ENTRY_SIZE..2 * DIR_ENTRY_SIZE].copy_from_slice(&short_entry(
+            *b"..         ",
+            ATTR_DIRECTORY,
+            0,
+            parent_ref,
+            0,
+            date,
+            time,
+        ));
+        self.write_chain(&[cluster], &data)?;
+        self.add_entry(parent, name, ATTR_DIRECTORY, cluster, 0)?;
+        Ok(cluster)
+    }
+
+    fn lookup(&mut self, dir: u32, name: &str) -> Result<Option<DirEntry>> {
+        let data = self.read_chain(dir)?;
+        let wanted = name.to_uppercase();
+        Ok(parse_dir(&data).into_iter().find(|e| {
+            e.name.to_uppercase() == wanted || short_name_display(&e.short_name, 0) == wanted
+        }))
+    }
+
+    fn add_entry(&mut self, dir: u32, name: &str, attr: u8, cluster: u32, size: u32) -> Result<()> {
+        let mut clusters = self.chain(dir)?;
+        let mut data = self.read_chain(dir)?;
+        let existing: Vec<[u8; 11]> = parse_dir(&data).iter().map(|e| e.short_name).collect();
+
+        let (date, time) = dos_timestamp();
+        let mut slots = Vec::new();
+        let (short, case) = match exact_short_name(name) {
+            Some(exact) => exact,
+            None => {
+                let short = generate_short_name(name, &existing);
+                slots.extend(long_name_entries(name, short_name_checksum(&short)));
+                (short, 0)
+            }
+        };
+        slots.push(short_entry(short, attr, case, cluster, size, date, time));
+
+        let start = loop {
+            if let Some(start) = find_free_slots(&data, slots.len()) {
+                break start;
+            }
+            let grown = self.allocate(1)?[0];
+            let last = *clusters.last().expect("directory chain is never empty");
+            self.set_fat(last, grown);
+            clusters.push(grown);
+            data.resize(data.len() + self.params.cluster_size() as usize, 0);
+        };
+        for (i, slot) in slots.iter().enumerate() {
+            let at = (start + i) * DIR_ENTRY_SIZE;
+            data[at..at + DIR_ENTRY_SIZE].copy_from_slice(slot);
+        }
+        self.write_chain(&clusters, &data)
+    }
+
+    fn remove_entry(&mut self, dir: u32, entry: &DirEntry) -> Result<()> {
+        let clusters = self.chain(dir)?;
+        let mut data = self.read_chain(dir)?;
+        for slot in entry.first_slot..=entry.slot {
+            data[slot * DIR_ENTRY_SIZE] = ENTRY_DELETED;
+        }
+        self.write_chain(&clusters, &data)?;
+        if entry.cluster >= ROOT_CLUSTER {
+            for cluster in self.chain(entry.cluster)? {
+                self.set_fat(cluster, 0);
+            }
+        }
+        Ok(())
+    }
+
+    fn chain(&self, first: u32) -> Result<Vec<u32>> {
+        let mut clusters = Vec::new();
+        let mut cluster = first;
+        loop {
+            if cluster < ROOT_CLUSTER
+                || cluster as usize >= self.fat.len()
+                || clusters.len() > self.params.cluster_count as usize
+            {
+                return Err(AppError::DiskError(format!(
+                    "Corrupt FAT32 cluster chain at cluster {}",
+                    cluster
+                )));
+            }
+            clusters.push(cluster);
+            let next = self.fat[cluster as usize];
+            if next >= FAT_EOC_MIN {
+                return Ok(clusters);
+            }
+            cluster = next;
+        }
+    }
+
+    /// Allocate and link `count` free clusters, lowest first after the last allocation.
+    fn allocate(&mut self, count: usize) -> Result<Vec<u32>> {
+        let total = self.fat.len() as u32;
+        let mut clusters = Vec::with_capacity(count);
+        let mut cluster = self.next_free.max(ROOT_CLUSTER);
+        let mut scanned = 0;
+        while clusters.len() < count {
+            if scanned >= total - ROOT_CLUSTER {
+                for &c in &clusters {
+                    self.fat[c as usize] = 0;
+                }
+                return Err(AppError::DiskError("FAT32 volume is full".to_string()));
+            }
+            if cluster >= total {
+                cluster = ROOT_CLUSTER;
+            }
+            if self.fat[cluster as usize] == 0 {
+                // Reserve it now so the scan does not hand it out twice.
+                self.fat[cluster as usize] = FAT_EOC;
+                clusters.push(cluster);
+            }
+            cluster += 1;
+            scanned += 1;
+        }
+        for pair in clusters.windows(2) {
+            self.set_fat(pair[0], pair[1]);
+        }
+        if let Some(&last) = clusters.last() {
+            self.set_fat(last, FAT_EOC);
+            self.next_free = last + 1;
+        }
+        Ok(clusters)
+    }
+
+    fn set_fat(&mut self, cluster: u32, value: u32) {
+        self.fat[cluster as usize] = value;
+        self.dirty = true;
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.offset
+            + self.params.data_start()
+            + (cluster - ROOT_CLUSTER) as u64 * self.params.cluster_size()
+    }
+
+    fn read_chain(&mut self, first: u32) -> Result<Vec<u8>> {
+        let cluster_size = self.params.cluster_size() as usize;
+        let clusters = self.chain(first)?;
+        let mut data = vec![0u8; clusters.len() * cluster_size];
+        for (i, &cluster) in clusters.iter().enumerate() {
+            self.dev
+                .seek(SeekFrom::Start(self.cluster_offset(cluster)))
+                .map_err(AppError::io)?;
+            self.dev
+                .read_exact(&mut data[i * cluster_size..(i + 1) * cluster_size])
+                .map_err(AppError::io)?;
+        }
+        Ok(data)
+    }
+
+    /// Write `data` over `clusters`, zero-padding the last one. Runs of consecutive
+    /// clusters go out in one write.
+    fn write_chain(&mut self, clusters: &[u32], data: &[u8]) -> Result<()> {
+        let cluster_size = self.params.cluster_size() as usize;
+        let mut i = 0;
+        while i < clusters.len() {
+            let mut run = 1;
+            while i + run < clusters.len() && clusters[i + run] == clusters[i] + run as u32 {
+                run += 1;
+            }
+            let start = (i * cluster_size).min(data.len());
+            let end = ((i + run) * cluster_size).min(data.len());
+            let mut buf = data[start..end].to_vec();
+            buf.resize(run * cluster_size, 0);
+            let offset = self.cluster_offset(clusters[i]);
+            write_at(&mut self.dev, offset, &buf)?;
+            i += run;
+        }
+        Ok(())
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(['/', '\\']).filter(|c| !c.is_empty()).collect()
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name == "."
+        || name == ".."
+        || name.ends_with(['.', ' '])
+        || name.encode_utf16().count() > MAX_NAME_UNITS
+        || name
+            .chars()
+            .any(|c| c < ' ' || INVALID_NAME_CHARS.contains(&c))
+    {
+        return Err(AppError::InvalidParameter(format!(
+            "Invalid FAT32 file name: {:?}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn is_short_name_byte(b: u8) -> bool {
+    b.is_ascii_uppercase() || b.is_ascii_digit() || SHORT_NAME_SPECIALS.contains(&b)
+}
+
+/// The 8.3 entry for names that need no long name: valid short names in one case per
+/// part, with lowercase parts recorded in the NT case flags as Windows does.
+fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) => (base, ext),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 {
+        return None;
+    }
+    let mut raw = [b' '; 11];
+    let mut case = 0;
+    for (part, range, flag) in [(base, 0..8, CASE_LOWER_BASE), (ext, 8..11, CASE_LOWER_EXT)] {
+        let upper = part.to_ascii_uppercase();
+        if !upper.bytes().all(is_short_name_byte) {
+            return None;
+        }
+        if part != upper {
+            if part != part.to_ascii_lowercase() {
+                return None;
+            }
+            case |= flag;
+        }
+        raw[range.start..range.start + upper.len()].copy_from_slice(upper.as_bytes());
+    }
+    Some((raw, case))
+}
+
+/// `BASIS~N.EXT` short alias unique among `existing`.
+fn generate_short_name(name: &str, existing: &[[u8; 11]]) -> [u8; 11] {
+    let clean = |part: &str| -> Vec<u8> {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                if c.is_ascii() && is_short_name_byte(c as u8) {
+                    c as u8
+                } else {
+                    b'_'
+                }
+            })
+            .collect()
+    };
+    let (base, ext) = match name.trim_start_matches('.').rsplit_once('.') {
+        Some((base, ext)) => (clean(base), clean(ext)),
+        None => (clean(name), Vec::new()),
+    };
+    for n in 1u32.. {
+        let tail = format!("~{}", n);
+        let keep = base.len().min(8 - tail.len());
+        let mut raw = [b' '; 11];
+        raw[..keep].copy_from_slice(&base[..keep]);
+        raw[keep..keep + tail.len()].copy_from_slice(tail.as_bytes());
+        let ext_len = ext.len().min(3);
+        raw[8..8 + ext_len].copy_from_slice(&ext[..ext_len]);
+        if !existing.contains(&raw) {
+            return raw;
+        }
+    }
+    unreachable!("short name space exhausted")
+}
+
+fn short_name_checksum(short: &[u8; 11]) -> u8 {
+    short
+        .iter()
+        .fold(0u8, |sum, &b| sum.rotate_right(1).wrapping_add(b))
+}
+
+fn short_name_display(short: &[u8; 11], case: u8) -> String {
+    let mut base = String::from_utf8_lossy(&short[..8]).trim_end().to_string();
+    let mut ext = String::from_utf8_lossy(&short[8..]).trim_end().to_string();
+    if base.starts_with('\u{5}') {
+        base.replace_range(..1, "\u{E5}");
+    }
+    if case & CASE_LOWER_BASE != 0 {
+        base.make_ascii_lowercase();
+    }
+    if case & CASE_LOWER_EXT != 0 {
+        ext.make_ascii_lowercase();
+    }
+    if ext.is_empty() {
+        base
+    } else {
+        format!("{}.{}", base, ext)
+    }
+}
+
+fn short_entry(
+    short: [u8; 11],
+    attr: u8,
+    case: u8,
+    cluster: u32,
+    size: u32,
+    date: u16,
+    time: u16,
+) -> [u8; DIR_ENTRY_SIZE] {
+    let mut raw = [0u8; DIR_ENTRY_SIZE];
+    raw[..11].copy_from_slice(&short);
+    raw[11] = attr;
+    raw[12] = case;
+    raw[14..16].copy_from_slice(&time.to_le_bytes());
+    raw[16..18].copy_from_slice(&date.to_le_bytes());
+    raw[18..20].copy_from_slice(&date.to_le_bytes());
+    raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    raw[22..24].copy_from_slice(&time.to_le_bytes());
+    raw[24..26].copy_from_slice(&date.to_le_bytes());
+    raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+    raw[28..32].copy_from_slice(&size.to_le_bytes());
+    raw
+}
+
+/// Long-name entries for `name`, in on-disk order (last part first).
+fn long_name_entries(name: &str, checksum: u8) -> Vec<[u8; DIR_ENTRY_SIZE]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    if !units.len().is_multiple_of(LFN_CHARS) {
+        units.push(0);
+        units.resize(units.len().div_ceil(LFN_CHARS) * LFN_CHARS, 0xFFFF);
+    }
+    let parts = units.len() / LFN_CHARS;
+    (0..parts)
+        .rev()
+        .map(|i| {
+            let chars = &units[i * LFN_CHARS..(i + 1) * LFN_CHARS];
+            let mut raw = [0u8; DIR_ENTRY_SIZE];
+            raw[0] = (i + 1) as u8 | if i + 1 == parts { LFN_LAST } else { 0 };
+            raw[11] = ATTR_LONG_NAME;
+            raw[13] = checksum;
+            let offsets = (1..11)
+                .step_by(2)
+                .chain((14..26).step_by(2))
+                .chain((28..32).step_by(2));
+            for (offset, unit) in offsets.zip(chars) {
+                raw[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            raw
+        })
+        .collect()
+}
+
+fn parse_dir(data: &[u8]) -> Vec<DirEntry> {
+    let mut entries = Vec::new();
+    let mut long_parts: Vec<(u8, [u16; LFN_CHARS])> = Vec::new();
+    let mut long_start = 0;
+    let mut long_checksum = 0;
+    for (slot, raw) in data.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+        match raw[0] {
+            0 => break,
+            ENTRY_DELETED => {
+                long_parts.clear();
+                continue;
+            }
+            _ => {}
+        }
+        if raw[11] & 0x3F == ATTR_LONG_NAME {
+            if raw[0] & LFN_LAST != 0 {
+                long_parts.clear();
+                long_start = slot;
+                long_checksum = raw[13];
+            }
+            let mut chars = [0u16; LFN_CHARS];
+            let offsets = (1..11)
+                .step_by(2)
+                .chain((14..26).step_by(2))
+                .chain((28..32).step_by(2));
+            for (unit, offset) in chars.iter_mut().zip(offsets) {
+                *unit = u16::from_le_bytes([raw[offset], raw[offset + 1]]);
+            }
+            long_parts.push((raw[0] & !LFN_LAST, chars));
+            continue;
+        }
+        let mut short = [0u8; 11];
+        short.copy_from_slice(&raw[..11]);
+        let attr = raw[11];
+        let has_long = !long_parts.is_empty()
+            && long_checksum == short_name_checksum(&short)
+            && long_parts
+                .iter()
+                .rev()
+                .enumerate()
+                .all(|(i, (ord, _))| *ord as usize == i + 1);
+        let name = if has_long {
+            let units: Vec<u16> = long_parts
+                .iter()
+                .rev()
+                .flat_map(|(_, chars)| chars.iter().copied())
+                .take_while(|&u| u != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            short_name_display(&short, raw[12])
+        };
+        let first_slot = if has_long { long_start } else { slot };
+        long_parts.clear();
+        if attr & ATTR_VOLUME_ID != 0 || short[0] == b'.' {
+            continue;
+        }
+        entries.push(DirEntry {
+            name,
+            short_name: short,
+            attr,
+            cluster: (u16::from_le_bytes([raw[20], raw[21]]) as u32) << 16
+                | u16::from_le_bytes([raw[26], raw[27]]) as u32,
+            size: read_u32(raw, 28),
+            first_slot,
+            slot,
+        });
+    }
+    entries
+}
+
+fn find_free_slots(data: &[u8], count: usize) -> Option<usize> {
+    let mut run = 0;
+    for (slot, raw) in data.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+        if raw[0] == 0 || raw[0] == ENTRY_DELETED {
+            run += 1;
+            if run == count {
+                return Some(slot + 1 - count);
+            }
+        } else {
+            run = 0;
+        }
+    }
+    None
+}
+
+/// Current local time as FAT (date, time).
+fn dos_timestamp() -> (u16, u16) {
+    let now = Local::now();
+    let year = now.year().clamp(1980, 2107) as u16;
+    let date = ((year - 1980) << 9) | ((now.month() as u16) << 5) | now.day() as u16;
+    let time =
+        ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() as u16 / 2);
+    (date, time)
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn write_at<W: Write + Seek>(writer: &mut W, offset: u64, data: &[u8]) -> Result<()> {
+    writer.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+    writer.write_all(data).map_err(AppError::io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("wtg-fat32-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn formatted(size: u64, sector_size: u32, offset: u64) -> Fat32Volume<Cursor<Vec<u8>>> {
+        let mut disk = Cursor::new(vec![0xA5u8; (offset + size) as usize]);
+        format_fat32(
+            &mut disk,
+            offset,
+            size,
+            sector_size,
+            offset / sector_size as u64,
+            "efi",
+        )
+        .unwrap();
+        Fat32Volume::open(disk, offset).unwrap()
+    }
+
+    #[test]
+    fn picks_windows_cluster_sizes() {
+        let esp = fat32_params(300 * MIB, 512).unwrap();
+        assert_eq!(esp.cluster_size(), 4096);
+        assert_eq!(
+            (esp.reserved_sectors as u64 + 2 * esp.fat_sectors as u64) % 8,
+            0
+        );
+        assert!(esp.fat_sectors as u64 * 128 >= esp.cluster_count as u64 + 2);
+        assert_eq!(fat32_params(100 * MIB, 512).unwrap().cluster_size(), 1024);
+        assert_eq!(fat32_params(260 * MIB, 4096).unwrap().cluster_size(), 4096);
+        // 4Kn disks cannot hold a FAT32 ESP below roughly 260 MiB.
+        assert!(fat32_params(100 * MIB, 4096).is_err());
+        assert!(fat32_params(300 * MIB, 520).is_err());
+    }
+
+    #[test]
+    fn formats_an_empty_labelled_volume() {
+        let offset = MIB;
+        let volume = formatted(100 * MIB, 512, offset);
+        let params = volume.params();
+        assert_eq!(volume.free_clusters(), params.cluster_count - 1);
+        let raw = volume.into_inner().unwrap().into_inner();
+        let boot = &raw[offset as usize..offset as usize + 512];
+        assert_eq!(&boot[82..90], b"FAT32   ");
+        assert_eq!(&boot[71..82], b"EFI        ");
+        assert_eq!(read_u32(boot, 28), 2048);
+        assert_eq!(
+            boot,
+            &raw[offset as usize + 6 * 512..offset as usize + 7 * 512]
+        );
+        let fsinfo = &raw[offset as usize + 512..offset as usize + 1024];
+        assert_eq!(read_u32(fsinfo, 488), params.cluster_count - 1);
+        let root = offset as usize + params.data_start() as usize;
+        assert_eq!(&raw[root..root + 12], b"EFI        \x08");
+        // Bytes before the volume are left alone.
+        assert_eq!(raw[offset as usize - 1], 0xA5);
+    }
+
+    #[test]
+    fn writes_and_reads_back_the_boot_payload() {
+        for sector_size in [512, 4096] {
+            let path = temp_path(&format!("payload-{}", sector_size));
+            File::create(&path).unwrap().set_len(300 * MIB).unwrap();
+            format_fat32_file(&path, 0, 300 * MIB, sector_size, 2048, "EFI").unwrap();
+            let boot = fs::read(&path).unwrap();
+            assert_eq!(read_u32(&boot, 28), 2048);
+            let mut volume = Fat32Volume::open_file(&path, 0).unwrap();
+            let loader: Vec<u8> = (0..1_500_000u32).map(|i| (i * 7) as u8).collect();
+            volume
+                .write_file("EFI/Microsoft/Boot/bootmgfw.efi", &loader)
+                .unwrap();
+            volume.write_file("EFI/Boot/bootx64.efi", &loader).unwrap();
+            volume
+                .write_file("/EFI/Microsoft/Boot/BCD", b"regf")
+                .unwrap();
+            volume
+                .write_file("EFI\\Microsoft\\Boot\\en-US\\bootmgfw.efi.mui", b"mui")
+                .unwrap();
+            volume
+                .write_file("EFI/Microsoft/Recovery/empty", b"")
+                .unwrap();
+            let free = volume.free_clusters();
+
+            volume.flush().unwrap();
+            let mut volume = Fat32Volume::open_file(&path, 0).unwrap();
+            assert_eq!(volume.free_clusters(), free);
+            assert_eq!(
+                volume.read_file("efi/microsoft/boot/BOOTMGFW.EFI").unwrap(),
+                loader
+            );
+            assert_eq!(volume.read_file("EFI/Boot/bootx64.efi").unwrap(), loader);
+            assert_eq!(volume.read_file("EFI/Microsoft/Boot/BCD").unwrap(), b"regf");
+            assert_eq!(
+                volume
+                    .read_file("EFI/Microsoft/Boot/en-US/bootmgfw.efi.mui")
+                    .unwrap(),
+                b"mui"
+            );
+            assert!(volume
+                .read_file("EFI/Microsoft/Recovery/empty")
+                .unwrap()
+                .is_empty());
+            assert!(volume.exists("EFI/Microsoft").unwrap());
+            assert!(!volume.exists("EFI/Linux").unwrap());
+
+            // Replacing a file releases the clusters of the old content.
+            volume.write_file("EFI/Boot/bootx64.efi", b"small").unwrap();
+            let cluster_size = volume.params().cluster_size() as usize;
+            assert_eq!(
+                volume.free_clusters() as usize,
+                free as usize + loader.len().div_ceil(cluster_size) - 1
+            );
+            assert_eq!(volume.read_file("EFI/Boot/bootx64.efi").unwrap(), b"small");
+            drop(volume);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn names_and_directory_growth() {
+        let mut volume = formatted(64 * MIB, 512, 0);
+        // 512-byte clusters hold 16 entries, so these spill over several clusters.
+        for i in 0..40 {
+            volume
+                .write_file(&format!("Fonts/segoe_font_{:02}.ttf", i), &[i as u8])
+                .unwrap();
+        }
+        for i in 0..40 {
+            assert_eq!(
+                volume
+                    .read_file(&format!("FONTS/Segoe_Font_{:02}.TTF", i))
+                    .unwrap(),
+                [i as u8]
+            );
+        }
+        let fonts = volume.find("Fonts").unwrap().unwrap();
+        let listing = parse_dir(&volume.read_chain(fonts.cluster).unwrap());
+        assert_eq!(listing.len(), 40);
+        let mut shorts: Vec<[u8; 11]> = listing.iter().map(|e| e.short_name).collect();
+        shorts.sort();
+        shorts.dedup();
+        assert_eq!(shorts.len(), 40);
+        assert_eq!(&listing[0].short_name, b"SEGOE_~1TTF");
+
+        assert_eq!(exact_short_name("BCD"), Some((*b"BCD        ", 0)));
+        assert_eq!(
+            exact_short_name("bootx64.efi"),
+            Some((*b"BOOTX64 EFI", CASE_LOWER_BASE | CASE_LOWER_EXT))
+        );
+        assert_eq!(exact_short_name("Microsoft"), None);
+        assert_eq!(
+            exact_short_name("boot.STL"),
+            Some((*b"BOOT    STL", CASE_LOWER_BASE))
+        );
+        assert!(volume.write_file("EFI/bad:name", b"").is_err());
+        assert!(volume.write_file("Fonts", b"").is_err());
+    }
+}
@@ -0,0 +1,829 @@
+//! Read-only access to files inside ISO images (UDF and ISO9660)
+//!
+//! Windows installation media is mastered as a UDF/ISO9660 bridge. Files larger than
+//! 4 GB (install.wim on recent releases) only exist in the UDF tree, so UDF is preferred
+//! and ISO9660 is the fallback for plain images. Only the plain physical-partition UDF
+//! layout (1.02 - 2.01, what oscdimg writes) is supported; metadata and virtual
+//! partitions are rejected.
+
+use crate::models::ImageInfo;
+use crate::utils::wim::Wim;
+use crate::{AppError, Result};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const SECTOR_SIZE: u64 = 2048;
+
+const ISO9660_PVD_SECTOR: u64 = 16;
+const UDF_ANCHOR_SECTOR: u64 = 256;
+
+const TAG_ANCHOR: u16 = 2;
+const TAG_PARTITION: u16 = 5;
+const TAG_LOGICAL_VOLUME: u16 = 6;
+const TAG_TERMINATING: u16 = 8;
+const TAG_FILE_SET: u16 = 256;
+const TAG_FILE_IDENTIFIER: u16 = 257;
+const TAG_FILE_ENTRY: u16 = 261;
+const TAG_EXTENDED_FILE_ENTRY: u16 = 266;
+
+const ICB_FILE_TYPE_DIRECTORY: u8 = 4;
+const FID_DIRECTORY: u8 = 0x02;
+const FID_DELETED: u8 = 0x04;
+const FID_PARENT: u8 = 0x08;
+
+const ISO_FLAG_DIRECTORY: u8 = 0x02;
+const ISO_FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// Upper bound for a directory or descriptor sequence read into memory.
+const MAX_METADATA_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Candidate locations of the Windows install image, in preference order. For a split
+/// image only the first part is listed; the XML metadata lives in every part.
+pub const INSTALL_IMAGE_PATHS: [&str; 3] = [
+    "sources/install.wim",
+    "sources/install.esd",
+    "sources/install.swm",
+];
+
+/// A run of bytes in the ISO file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// A regular file located inside the ISO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsoFile {
+    pub path: String,
+    pub size: u64,
+    pub extents: Vec<Extent>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UdfVolume {
+    partition_start: u64,
+    block_size: u64,
+    root_icb: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Volume {
+    Udf(UdfVolume),
+    Iso9660 { root: Extent },
+}
+
+#[derive(Debug, Clone)]
+enum NodeLocation {
+    /// Logical block of the UDF (Extended) File Entry.
+    Udf(u32),
+    /// ISO9660 data extents and total size.
+    Iso(Vec<Extent>, u64),
+}
+
+#[derive(Debug, Clone)]
+struct DirEntry {
+    name: String,
+    is_dir: bool,
+    location: NodeLocation,
+}
+
+/// UDF file entry data: either recorded extents or data embedded in the entry itself.
+enum FileData {
+    Extents(Vec<Extent>),
+    Embedded(Vec<u8>),
+}
+
+pub struct IsoImage<R> {
+    reader: R,
+    volume: Volume,
+}
+
+impl IsoImage<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        IsoImage::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> IsoImage<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let volume = match read_udf_volume(&mut reader) {
+            Ok(Some(udf)) => Volume::Udf(udf),
+            Ok(None) => read_iso9660_root(&mut reader)?,
+            Err(e) => {
+                // A broken UDF tree on a bridge disc still leaves ISO9660 usable.
+                read_iso9660_root(&mut reader).map_err(|_| e)?
+            }
+        };
+        Ok(IsoImage { reader, volume })
+    }
+
+    /// Look up a regular file by '/' or '\' separated path, case-insensitively.
+    pub fn find_file(&mut self, path: &str) -> Result<Option<IsoFile>> {
+        let components: Vec<&str> = path.split(['/', '\\']).filter(|c| !c.is_empty()).collect();
+        let Some((file_name, dirs)) = components.split_last() else {
+            return Ok(None);
+        };
+
+        let mut dir = self.root_location();
+        for component in dirs {
+            let entries = self.read_dir(&dir)?;
+            let Some(next) = entries
+                .into_iter()
+                .find(|e| e.is_dir && e.name.eq_ignore_ascii_case(component))
+            else {
+                return Ok(None);
+            };
+            dir = next.location;
+        }
+
+        let entries = self.read_dir(&dir)?;
+        let Some(entry) = entries
+            .into_iter()
+            .find(|e| !e.is_dir && e.name.eq_ignore_ascii_case(file_name))
+        else {
+            return Ok(None);
+        };
+
+        let (size, extents) = match entry.location {
+            NodeLocation::Iso(extents, size) => (size, extents),
+            NodeLocation::Udf(icb) => {
+                let (_, size, data) = self.read_udf_file_entry(icb)?;
+                match data {
+                    FileData::Extents(extents) => (size, extents),
+                    FileData::Embedded(_) => {
+                        return Err(AppError::ImageError(format!(
+                            "{} is embedded in its UDF file entry",
+                            path
+                        )))
+                    }
+                }
+            }
+        };
+
+        Ok(Some(IsoFile {
+            path: components.join("/"),
+            size,
+            extents,
+        }))
+    }
+
+    /// `sources/install.wim`, `install.esd` or `install.swm`, whichever exists first.
+    pub fn find_install_image(&mut self) -> Result<Option<IsoFile>> {
+        for path in INSTALL_IMAGE_PATHS {
+            if let Some(file) = self.find_file(path)? {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Seekable reader over the bytes of `file`.
+    pub fn file_reader(self, file: &IsoFile) -> IsoFileReader<R> {
+        IsoFileReader {
+            inner: self.reader,
+            extents: file.extents.clone(),
+            size: file.size,
+            pos: 0,
+        }
+    }
+
+    fn root_location(&self) -> NodeLocation {
+        match self.volume {
+            Volume::Udf(udf) => NodeLocation::Udf(udf.root_icb),
+            Volume::Iso9660 { root } => NodeLocation::Iso(vec![root], root.length),
+        }
+    }
+
+    fn read_at(&mut self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        read_at(&mut self.reader, offset, len)
+    }
+
+    fn read_dir(&mut self, location: &NodeLocation) -> Result<Vec<DirEntry>> {
+        match location {
+            NodeLocation::Iso(extents, size) => {
+                let data = self.read_extents(extents, *size)?;
+                Ok(parse_iso9660_directory(&data))
+            }
+            NodeLocation::Udf(icb) => {
+                let (is_dir, size, data) = self.read_udf_file_entry(*icb)?;
+                if !is_dir {
+                    return Err(AppError::ImageError(
+                        "UDF path component is not a directory".to_string(),
+                    ));
+                }
+                let data = match data {
+                    FileData::Embedded(bytes) => bytes,
+                    FileData::Extents(extents) => self.read_extents(&extents, size)?,
+                };
+                parse_udf_directory(&data)
+            }
+        }
+    }
+
+    fn read_extents(&mut self, extents: &[Extent], size: u64) -> Result<Vec<u8>> {
+        if size > MAX_METADATA_SIZE {
+            return Err(AppError::ImageError(format!(
+                "ISO directory is implausibly large ({} bytes)",
+                size
+            )));
+        }
+        let mut out = Vec::with_capacity(size as usize);
+        for extent in extents {
+            let remaining = size - out.len() as u64;
+            if remaining == 0 {
+                break;
+            }
+            let chunk = self.read_at(extent.offset, extent.length.min(remaining))?;
+            out.extend_from_slice(&chunk);
+        }
+        Ok(out)
+    }
+
+    /// Parse the (Extended) File Entry at logical block `lbn` of the partition.
+    fn read_udf_file_entry(&mut self, lbn: u32) -> Result<(bool, u64, FileData)> {
+        let Volume::Udf(udf) = self.volume else {
+            return Err(AppError::ImageError("Not a UDF volume".to_string()));
+        };
+        let block = self.read_at(
+            udf.partition_start + lbn as u64 * udf.block_size,
+            udf.block_size,
+        )?;
+        let tag = check_udf_tag(&block, &[TAG_FILE_ENTRY, TAG_EXTENDED_FILE_ENTRY])?;
+
+        let file_type = block[16 + 11];
+        let icb_flags = read_u16(&block, 16 + 18);
+        let info_length = read_u64(&block, 56);
+        let (ea_len_at, ad_len_at, ea_start) = if tag == TAG_FILE_ENTRY {
+            (168, 172, 176)
+        } else {
+            (208, 212, 216)
+        };
+        let ea_len = read_u32(&block, ea_len_at) as usize;
+        let ad_len = read_u32(&block, ad_len_at) as usize;
+        let ad_start = ea_start + ea_len;
+        let descriptors = block.get(ad_start..ad_start + ad_len).ok_or_else(|| {
+            AppError::ImageError("UDF file entry allocation descriptors overflow".to_string())
+        })?;
+
+        let data = match icb_flags & 0x07 {
+            0 => FileData::Extents(parse_allocation_descriptors(descriptors, 8, &udf)?),
+            1 => FileData::Extents(parse_allocation_descriptors(descriptors, 16, &udf)?),
+            3 => FileData::Embedded(descriptors.to_vec()),
+            other => {
+                return Err(AppError::Unsupported(format!(
+                    "UDF allocation descriptor type {} is not supported",
+                    other
+                )))
+            }
+        };
+
+        Ok((file_type == ICB_FILE_TYPE_DIRECTORY, info_length, data))
+    }
+}
+
+/// Open the Windows install image inside an ISO and read its image list, without mounting.
+pub fn read_install_image_info(iso_path: &Path) -> Result<Vec<ImageInfo>> {
+    let mut iso = IsoImage::open(iso_path)?;
+    let install = iso.find_install_image()?.ok_or_else(|| {
+        AppError::ImageError(
+            "Cannot find sources/install.wim, install.esd or install.swm in ISO".to_string(),
+        )
+    })?;
+    let images = Wim::new(iso.file_reader(&install))?.images()?;
+    Ok(images.into_iter().map(ImageInfo::from).collect())
+}
+
+/// `Read + Seek` view of one file inside an ISO.
+pub struct IsoFileReader<R> {
+    inner: R,
+    extents: Vec<Extent>,
+    size: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Read for IsoFileReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let mut extent_start = 0u64;
+        for extent in &self.extents {
+            let extent_end = extent_start + extent.length;
+            if self.pos < extent_end {
+                let within = self.pos - extent_start;
+                let available = (extent_end - self.pos).min(self.size - self.pos);
+                let len = (buf.len() as u64).min(available) as usize;
+                self.inner.seek(SeekFrom::Start(extent.offset + within))?;
+                let n = self.inner.read(&mut buf[..len])?;
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            extent_start = extent_end;
+        }
+        Ok(0)
+    }
+}
+
+impl<R: Read + Seek> Seek for IsoFileReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => v as i128,
+            SeekFrom::End(v) => self.size as i128 + v as i128,
+            SeekFrom::Current(v) => self.pos as i128 + v as i128,
+        };
+        if target < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before start of ISO file",
+            ));
+        }
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; len as usize];
+    reader.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+    reader.read_exact(&mut buf).map_err(|e| {
+        AppError::ImageError(format!("ISO read at offset {} failed: {}", offset, e))
+    })?;
+    Ok(buf)
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Validate a UDF descriptor tag (identifier and header checksum) and return its identifier.
+fn check_udf_tag(buf: &[u8], expected: &[u16]) -> Result<u16> {
+    if buf.len() < 16 {
+        return Err(AppError::ImageError("Truncated UDF descriptor".to_string()));
+    }
+    let checksum = buf[..16]
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 4)
+        .fold(0u8, |acc, (_, b)| acc.wrapping_add(*b));
+    let id = read_u16(buf, 0);
+    if checksum != buf[4] || !expected.contains(&id) {
+        return Err(AppError::ImageError(format!(
+            "Unexpected UDF descriptor (tag {}, expected {:?})",
+            id, expected
+        )));
+    }
+    Ok(id)
+}
+
+/// Follow the anchor and the main volume descriptor sequence. `Ok(None)` means the image
+/// carries no UDF tree at all.
+fn read_udf_volume<R: Read + Seek>(reader: &mut R) -> Result<Option<UdfVolume>> {
+    let Ok(anchor) = read_at(reader, UDF_ANCHOR_SECTOR * SECTOR_SIZE, SECTOR_SIZE) else {
+        return Ok(None);
+    };
+    if check_udf_tag(&anchor, &[TAG_ANCHOR]).is_err() {
+        return Ok(None);
+    }
+
+    let vds_length = (read_u32(&anchor, 16) as u64).min(MAX_METADATA_SIZE);
+    let vds_start = read_u32(&anchor, 20) as u64 * SECTOR_SIZE;
+    let vds = read_at(reader, vds_start, vds_length)?;
+
+    let mut partition_start = None;
+    let mut logical_volume = None;
+    for sector in vds.chunks(SECTOR_SIZE as usize) {
+        if sector.len() < 16 {
+            break;
+        }
+        let Ok(tag) = check_udf_tag(
+            sector,
+            &[TAG_PARTITION, TAG_LOGICAL_VOLUME, TAG_TERMINATING],
+        ) else {
+            continue;
+        };
+        match tag {
+            TAG_PARTITION => partition_start = Some(read_u32(sector, 188) as u64),
+            TAG_LOGICAL_VOLUME => {
+                let block_size = read_u32(sector, 212) as u64;
+                let fsd_lbn = read_u32(sector, 252);
+                let map_table_len = read_u32(sector, 264) as usize;
+                let map_count = read_u32(sector, 268);
+                let maps = sector.get(440..440 + map_table_len).unwrap_or(&[]);
+                // Type 1 maps are plain physical partitions; type 2 (metadata, virtual,
+                // sparable) would need extra indirection.
+                if map_count != 1 || maps.first() != Some(&1) {
+                    return Err(AppError::Unsupported(
+                        "UDF volumes with metadata or virtual partitions are not supported"
+                            .to_string(),
+                    ));
+                }
+                logical_volume = Some((block_size, fsd_lbn));
+            }
+            _ => break,
+        }
+    }
+
+    let (Some(partition_sector), Some((block_size, fsd_lbn))) = (partition_start, logical_volume)
+    else {
+        return Err(AppError::ImageError(
+            "UDF volume descriptor sequence is incomplete".to_string(),
+        ));
+    };
+    if block_size != SECTOR_SIZE {
+        return Err(AppError::Unsupported(format!(
+            "UDF logical block size {} is not supported",
+            block_size
+        )));
+    }
+
+    let partition_start = partition_sector * SECTOR_SIZE;
+    let fsd = read_at(
+        reader,
+        partition_start + fsd_lbn as u64 * block_size,
+        block_size,
+    )?;
+    check_udf_tag(&fsd, &[TAG_FILE_SET])?;
+    let root_icb = read_u32(&fsd, 400 + 4);
+
+    Ok(Some(UdfVolume {
+        partition_start,
+        block_size,
+        root_icb,
+    }))
+}
+
+/// Short (8-byte) or long (16-byte) allocation descriptors into byte extents.
+fn parse_allocation_descriptors(data: &[u8], size: usize, udf: &UdfVolume) -> Result<Vec<Extent>> {
+    let mut extents = Vec::new();
+    for ad in data.chunks_exact(size) {
+        let raw_len = read_u32(ad, 0);
+        let length = (raw_len & 0x3FFF_FFFF) as u64;
+        if length == 0 {
+            break;
+        }
+        match raw_len >> 30 {
+            0 => {}
+            // Allocated but unrecorded extents read as zeros and never appear in ISOs.
+            1 | 2 => continue,
+            _ => {
+                return Err(AppError::Unsupported(
+                    "Chained UDF allocation descriptors are not supported".to_string(),
+                ))
+            }
+        }
+        let lbn = read_u32(ad, 4) as u64;
+        extents.push(Extent {
+            offset: udf.partition_start + lbn * udf.block_size,
+            length,
+        });
+    }
+    Ok(extents)
+}
+
+/// Decode an OSTA CS0 "d-string": compression ID 8 is Latin-1, 16 is UTF-16BE.
+fn decode_cs0(bytes: &[u8]) -> String {
+    match bytes.split_first() {
+        Some((8, rest)) => rest.iter().map(|b| *b as char).collect(),
+        Some((16, rest)) => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::new(),
+    }
+}
+
+fn parse_udf_directory(data: &[u8]) -> Result<Vec<DirEntry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0usize;
+
+    while offset + 38 <= data.len() {
+        let fid = &data[offset..];
+        check_udf_tag(fid, &[TAG_FILE_IDENTIFIER])?;
+        let characteristics = fid[18];
+        let name_len = fid[19] as usize;
+        let icb_lbn = read_u32(fid, 20 + 4);
+        let impl_use_len = read_u16(fid, 36) as usize;
+        let name_start = 38 + impl_use_len;
+        let total = (name_start + name_len + 3) & !3;
+        if offset + total > data.len() {
+            break;
+        }
+        offset += total;
+
+        if characteristics & (FID_PARENT | FID_DELETED) != 0 {
+            continue;
+        }
+        entries.push(DirEntry {
+            name: decode_cs0(&fid[name_start..name_start + name_len]),
+            is_dir: characteristics & FID_DIRECTORY != 0,
+            location: NodeLocation::Udf(icb_lbn),
+        });
+    }
+    Ok(entries)
+}
+
+fn read_iso9660_root<R: Read + Seek>(reader: &mut R) -> Result<Volume> {
+    let pvd = read_at(reader, ISO9660_PVD_SECTOR * SECTOR_SIZE, SECTOR_SIZE)?;
+    if pvd[0] != 1 || &pvd[1..6] != b"CD001" {
+        return Err(AppError::ImageError("Not an ISO9660/UDF image".to_string()));
+    }
+    let root = &pvd[156..156 + 34];
+    Ok(Volume::Iso9660 {
+        root: Extent {
+            offset: read_u32(root, 2) as u64 * SECTOR_SIZE,
+            length: read_u32(root, 10) as u64,
+        },
+    })
+}
+
+/// "INSTALL.WIM;1" -> "INSTALL.WIM"; directories never carry a version suffix.
+fn iso9660_name(raw: &[u8]) -> String {
+    let name: String = raw.iter().map(|b| *b as char).collect();
+    let name = name.split(';').next().unwrap_or_default();
+    name.strip_suffix('.').unwrap_or(name).to_string()
+}
+
+fn parse_iso9660_directory(data: &[u8]) -> Vec<DirEntry> {
+    let mut entries: Vec<DirEntry> = Vec::new();
+    // Pending multi-extent file: previous records of the same file had flag 0x80 set.
+    let mut continuing = false;
+    let mut offset = 0usize;
+
+    while offset < data.len() {
+        let record_len = data[offset] as usize;
+        if record_len == 0 {
+            // Records never straddle sectors; the rest of this one is padding.
+            offset = (offset / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+            continue;
+        }
+        if record_len < 34 || offset + record_len > data.len() {
+            break;
+        }
+        let record = &data[offset..offset + record_len];
+        offset += record_len;
+
+        let name_len = record[32] as usize;
+        let Some(raw_name) = record.get(33..33 + name_len) else {
+            continue;
+        };
+        // "\0" and "\1" are the self and parent entries.
+        if name_len == 1 && raw_name[0] <= 1 {
+            continue;
+        }
+        let flags = record[25];
+        let extent = Extent {
+            offset: read_u32(record, 2) as u64 * SECTOR_SIZE,
+            length: read_u32(record, 10) as u64,
+        };
+
+        if continuing {
+            if let Some(NodeLocation::Iso(extents, size)) =
+                entries.last_mut().map(|e| &mut e.location)
+            {
+                extents.push(extent);
+                *size += extent.length;
+            }
+        } else {
+            entries.push(DirEntry {
+                name: iso9660_name(raw_name),
+                is_dir: flags & ISO_FLAG_DIRECTORY != 0,
+                location: NodeLocation::Iso(vec![extent], extent.length),
+            });
+        }
+        continuing = flags & ISO_FLAG_MULTI_EXTENT != 0;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const FILE_LEN: usize = SECTOR_SIZE as usize + 100;
+
+    fn file_data() -> Vec<u8> {
+        (0..FILE_LEN).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn set_udf_tag(buf: &mut [u8], id: u16, location: u32) {
+        buf[0..2].copy_from_slice(&id.to_le_bytes());
+        buf[2..4].copy_from_slice(&2u16.to_le_bytes());
+        buf[12..16].copy_from_slice(&location.to_le_bytes());
+        buf[4] = buf[..16]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 4)
+            .fold(0u8, |acc, (_, b)| acc.wrapping_add(*b));
+    }
+
+    fn sector(image: &mut [u8], n: u64) -> &mut [u8] {
+        let start = (n * SECTOR_SIZE) as usize;
+        &mut image[start..start + SECTOR_SIZE as usize]
+    }
+
+    fn udf_fid(name: &str, icb: u32, characteristics: u8) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        if !name.is_empty() {
+            encoded.push(8u8);
+            encoded.extend_from_slice(name.as_bytes());
+        }
+        let total = (38 + encoded.len() + 3) & !3;
+        let mut fid = vec![0u8; total];
+        fid[18] = characteristics;
+        fid[19] = encoded.len() as u8;
+        fid[24..28].copy_from_slice(&icb.to_le_bytes());
+        fid[38..38 + encoded.len()].copy_from_slice(&encoded);
+        set_udf_tag(&mut fid, TAG_FILE_IDENTIFIER, 0);
+        fid
+    }
+
+    /// File entry with embedded data (directories) or short_ads (files).
+    fn udf_file_entry(is_dir: bool, info_len: u64, ads: &[u8], embedded: bool) -> Vec<u8> {
+        let mut fe = vec![0u8; SECTOR_SIZE as usize];
+        fe[16 + 11] = if is_dir { ICB_FILE_TYPE_DIRECTORY } else { 5 };
+        let flags: u16 = if embedded { 3 } else { 0 };
+        fe[16 + 18..16 + 20].copy_from_slice(&flags.to_le_bytes());
+        fe[56..64].copy_from_slice(&info_len.to_le_bytes());
+        fe[172..176].copy_from_slice(&(ads.len() as u32).to_le_bytes());
+        fe[176..176 + ads.len()].copy_from_slice(ads);
+        set_udf_tag(&mut fe, TAG_FILE_ENTRY, 0);
+        fe
+    }
+
+    fn short_ad(length: u32, lbn: u32) -> Vec<u8> {
+        let mut ad = length.to_le_bytes().to_vec();
+        ad.extend_from_slice(&lbn.to_le_bytes());
+        ad
+    }
+
+    /// UDF image: partition at sector 300, FSD at lbn 0, root FE at 1, SOURCES FE at 2,
+    /// install.wim FE at 3, data split into a full block at lbn 10 and a tail at lbn 11.
+    fn build_udf_image() -> Vec<u8> {
+        let mut image = vec![0u8; 320 * SECTOR_SIZE as usize];
+
+        let anchor = sector(&mut image, UDF_ANCHOR_SECTOR);
+        anchor[16..20].copy_from_slice(&(3 * SECTOR_SIZE as u32).to_le_bytes());
+        anchor[20..24].copy_from_slice(&32u32.to_le_bytes());
+        set_udf_tag(anchor, TAG_ANCHOR, UDF_ANCHOR_SECTOR as u32);
+
+        let pd = sector(&mut image, 32);
+        pd[188..192].copy_from_slice(&300u32.to_le_bytes());
+        set_udf_tag(pd, TAG_PARTITION, 32);
+
+        let lvd = sector(&mut image, 33);
+        lvd[212..216].copy_from_slice(&(SECTOR_SIZE as u32).to_le_bytes());
+        lvd[264..268].copy_from_slice(&6u32.to_le_bytes());
+        lvd[268..272].copy_from_slice(&1u32.to_le_bytes());
+        lvd[440] = 1;
+        lvd[441] = 6;
+        set_udf_tag(lvd, TAG_LOGICAL_VOLUME, 33);
+
+        set_udf_tag(sector(&mut image, 34), TAG_TERMINATING, 34);
+
+        let fsd = sector(&mut image, 300);
+        fsd[404..408].copy_from_slice(&1u32.to_le_bytes());
+        set_udf_tag(fsd, TAG_FILE_SET, 0);
+
+        let mut root_dir = udf_fid("", 1, FID_PARENT | FID_DIRECTORY);
+        root_dir.extend(udf_fid("sources", 2, FID_DIRECTORY));
+        let root = udf_file_entry(true, root_dir.len() as u64, &root_dir, true);
+        sector(&mut image, 301).copy_from_slice(&root);
+
+        let mut sources_dir = udf_fid("", 1, FID_PARENT | FID_DIRECTORY);
+        sources_dir.extend(udf_fid("boot.wim", 9, FID_DELETED));
+        sources_dir.extend(udf_fid("install.wim", 3, 0));
+        let sources = udf_file_entry(true, sources_dir.len() as u64, &sources_dir, true);
+        sector(&mut image, 302).copy_from_slice(&sources);
+
+        let mut ads = short_ad(SECTOR_SIZE as u32, 10);
+        ads.extend(short_ad((FILE_LEN - SECTOR_SIZE as usize) as u32, 11));
+        let fe = udf_file_entry(false, FILE_LEN as u64, &ads, false);
+        sector(&mut image, 303).copy_from_slice(&fe);
+
+        let data_start = 310 * SECTOR_SIZE as usize;
+        image[data_start..data_start + FILE_LEN].copy_from_slice(&file_data());
+        image
+    }
+
+    fn iso_record(name: &[u8], sector: u32, length: u32, flags: u8) -> Vec<u8> {
+        let len = 33 + name.len() + (name.len() + 1) % 2;
+        let mut rec = vec![0u8; len];
+        rec[0] = len as u8;
+        rec[2..6].copy_from_slice(&sector.to_le_bytes());
+        rec[10..14].copy_from_slice(&length.to_le_bytes());
+        rec[25] = flags;
+        rec[32] = name.len() as u8;
+        rec[33..33 + name.len()].copy_from_slice(name);
+        rec
+    }
+
+    /// ISO9660 image with SOURCES/INSTALL.ESD stored as a two-record multi-extent file.
+    fn build_iso9660_image() -> Vec<u8> {
+        let mut image = vec![0u8; 40 * SECTOR_SIZE as usize];
+        let pvd = sector(&mut image, ISO9660_PVD_SECTOR);
+        pvd[0] = 1;
+        pvd[1..6].copy_from_slice(b"CD001");
+        pvd[156..190].copy_from_slice(&iso_record(&[0], 20, SECTOR_SIZE as u32, 2)[..34]);
+
+        let mut root = iso_record(&[0], 20, SECTOR_SIZE as u32, 2);
+        root.extend(iso_record(&[1], 20, SECTOR_SIZE as u32, 2));
+        root.extend(iso_record(b"SOURCES", 21, SECTOR_SIZE as u32, 2));
+        sector(&mut image, 20)[..root.len()].copy_from_slice(&root);
+
+        let mut sources = iso_record(&[0], 21, SECTOR_SIZE as u32, 2);
+        sources.extend(iso_record(&[1], 20, SECTOR_SIZE as u32, 2));
+        sources.extend(iso_record(b"INSTALL.ESD;1", 30, SECTOR_SIZE as u32, 0x80));
+        sources.extend(iso_record(b"INSTALL.ESD;1", 31, 10, 0));
+        sector(&mut image, 21)[..sources.len()].copy_from_slice(&sources);
+
+        sector(&mut image, 30).fill(b'a');
+        sector(&mut image, 31)[..10].copy_from_slice(b"0123456789");
+        image
+    }
+
+    #[test]
+    fn finds_file_in_udf_tree() {
+        let mut iso = IsoImage::new(Cursor::new(build_udf_image())).unwrap();
+        assert!(matches!(iso.volume, Volume::Udf(_)));
+        assert!(iso.find_file("sources/boot.wim").unwrap().is_none());
+
+        let file = iso.find_install_image().unwrap().unwrap();
+        assert_eq!(file.path, "sources/install.wim");
+        assert_eq!(file.size, FILE_LEN as u64);
+        assert_eq!(file.extents.len(), 2);
+        assert_eq!(file.extents[0].offset, 310 * SECTOR_SIZE);
+
+        let mut bytes = Vec::new();
+        iso.file_reader(&file).read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, file_data());
+    }
+
+    #[test]
+    fn finds_multi_extent_file_in_iso9660() {
+        let mut iso = IsoImage::new(Cursor::new(build_iso9660_image())).unwrap();
+        assert!(matches!(iso.volume, Volume::Iso9660 { .. }));
+
+        let file = iso.find_file("\\Sources\\install.esd").unwrap().unwrap();
+        assert_eq!(file.size, SECTOR_SIZE + 10);
+        assert_eq!(file.extents[0].offset, 30 * SECTOR_SIZE);
+
+        let mut reader = iso.file_reader(&file);
+        reader.seek(SeekFrom::Start(SECTOR_SIZE - 2)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, b"aa0123456789");
+    }
+
+    #[test]
+    fn reader_crosses_non_adjacent_extents() {
+        let file = IsoFile {
+            path: "x".to_string(),
+            size: 6,
+            extents: vec![
+                Extent {
+                    offset: 4,
+                    length: 3,
+                },
+                Extent {
+                    offset: 12,
+                    length: 3,
+                },
+            ],
+        };
+        let data: Vec<u8> = (0u8..16).collect();
+        let iso = IsoImage {
+            reader: Cursor::new(data),
+            volume: Volume::Iso9660 {
+                root: Extent {
+                    offset: 0,
+                    length: 0,
+                },
+            },
+        };
+        let mut bytes = Vec::new();
+        iso.file_reader(&file).read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![4, 5, 6, 12, 13, 14]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Latency histograms for benchmark phases
+//!
+//! Log-linear buckets in microseconds: exact below 16 µs, then 8 sub-buckets per power
+//! of two, so any percentile is within 12.5% of the true value while a histogram stays a
+//! few hundred counters regardless of how many operations it records. Operations at or
+//! above the stall threshold are counted separately.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LINEAR_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Enough power-of-two groups to reach ~2^40 µs (12 days).
+const BUCKET_COUNT: usize = (LINEAR_BUCKETS + (40 - 4) * SUB_BUCKETS) as usize;
+
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum_us: u128,
+    max_us: u64,
+    stall_threshold: Duration,
+    stalls: u64,
+}
+
+/// One non-empty bucket: operations that took at most `upper_us` (and more than the
+/// previous bucket's bound).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencyBucket {
+    pub upper_us: u64,
+    pub count: u64,
+}
+
+/// Serialized form of a histogram.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+    pub stall_threshold_ms: u64,
+    /// Operations that took at least `stall_threshold_ms`
+    pub stalls: u64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+fn bucket_index(us: u64) -> usize {
+    if us < LINEAR_BUCKETS {
+        return us as usize;
+    }
+    let exp = 63 - us.leading_zeros();
+    let sub = (us >> (exp - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    let index = LINEAR_BUCKETS + (exp as u64 - 4) * SUB_BUCKETS + sub;
+    (index as usize).min(BUCKET_COUNT - 1)
+}
+
+/// Largest latency that lands in bucket `index`.
+fn bucket_upper_us(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_BUCKETS {
+        return index;
+    }
+    let exp = (index - LINEAR_BUCKETS) / SUB_BUCKETS + 4;
+    let sub = (index - LINEAR_BUCKETS) % SUB_BUCKETS;
+    let width = 1u64 << (exp - SUB_BUCKET_BITS as u64);
+    (1u64 << exp) + (sub + 1) * width - 1
+}
+
+impl LatencyHistogram {
+    pub fn new(stall_threshold: Duration) -> Self {
+        Self {
+            counts: vec![0; BUCKET_COUNT],
+            count: 0,
+            sum_us: 0,
+            max_us: 0,
+            stall_threshold,
+            stalls: 0,
+        }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        self.counts[bucket_index(us)] += 1;
+        self.count += 1;
+        self.sum_us += us as u128;
+        self.max_us = self.max_us.max(us);
+        if latency >= self.stall_threshold {
+            self.stalls += 1;
+        }
+    }
+
+    /// Add another histogram's operations (e.g. from a worker thread).
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.count += other.count;
+        self.sum_us += other.sum_us;
+        self.max_us = self.max_us.max(other.max_us);
+        self.stalls += other.stalls;
+    }
+
+    /// Latency at quantile `q` (0.0..=1.0), reported as its bucket's upper bound and
+    /// never above the recorded maximum.
+    pub fn percentile_us(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper_us(index).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            count: self.count,
+            mean_us: if self.count == 0 {
+                0.0
+            } else {
+                self.sum_us as f64 / self.count as f64
+            },
+            p50_us: self.percentile_us(0.50),
+            p90_us: self.percentile_us(0.90),
+            p99_us: self.percentile_us(0.99),
+            p999_us: self.percentile_us(0.999),
+            max_us: self.max_us,
+            stall_threshold_ms: self.stall_threshold.as_millis() as u64,
+            stalls: self.stalls,
+            buckets: self
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, count)| LatencyBucket {
+                    upper_us: bucket_upper_us(index),
+                    count: *count,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_cover_every_value_in_order() {
+        let mut previous_upper = None;
+        for index in 0..BUCKET_COUNT {
+            let upper = bucket_upper_us(index);
+            assert_eq!(bucket_index(upper), index);
+            if let Some(previous) = previous_upper {
+                assert_eq!(bucket_index(previous + 1), index);
+            }
+            previous_upper = Some(upper);
+        }
+        // Relative bucket width stays within 1/8 past the linear range.
+        for us in [17u64, 100, 4_095, 123_456, 2_000_000] {
+            let upper = bucket_upper_us(bucket_index(us));
+            assert!(
+                upper >= us && (upper - us) as f64 <= us as f64 / 8.0,
+                "{}",
+                us
+            );
+        }
+    }
+
+    #[test]
+    fn reports_percentiles_and_stalls() {
+        let mut fast = LatencyHistogram::new(Duration::from_millis(500));
+        for us in 1..=990u64 {
+            fast.record(Duration::from_micros(us));
+        }
+        let mut slow = LatencyHistogram::new(Duration::from_millis(500));
+        for ms in [100u64, 200, 300, 400, 499, 500, 900, 1_500, 2_000, 2_500] {
+            slow.record(Duration::from_millis(ms));
+        }
+        fast.merge(&slow);
+
+        let summary = fast.summary();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.stalls, 5);
+        assert_eq!(summary.stall_threshold_ms, 500);
+        assert_eq!(summary.max_us, 2_500_000);
+        assert!((500..=511).contains(&summary.p50_us), "{}", summary.p50_us);
+        assert!((900..=959).contains(&summary.p90_us), "{}", summary.p90_us);
+        assert!((990..=1023).contains(&summary.p99_us), "{}", summary.p99_us);
+        // The 999th operation is the 2 s stall.
+        assert!((2_000_000..=2_097_151).contains(&summary.p999_us));
+        assert_eq!(fast.percentile_us(1.0), 2_500_000);
+        assert_eq!(
+            summary.buckets.iter().map(|b| b.count).sum::<u64>(),
+            summary.count
+        );
+
+        let empty = LatencyHistogram::new(Duration::from_secs(1)).summary();
+        assert_eq!((empty.count, empty.p99_us, empty.mean_us), (0, 0, 0.0));
+    }
+}
@@ -1,9 +1,19 @@
+pub mod bcd;
 pub mod command;
+pub mod fat32;
+pub mod iso;
+pub mod latency;
 pub mod log;
 pub mod macos_admin;
 pub mod output_capture;
+pub mod partition_plan;
+pub mod partition_table;
 pub mod progress;
+pub mod regf;
 pub mod task_manager;
+pub mod vhd;
+pub mod vhdx;
+pub mod wim;
 
 use sysinfo::System;
 #[cfg(target_os = "macos")]
@@ -0,0 +1,719 @@
+//! Target disk partition layout planning
+//!
+//! Computes the exact layout a write creates on the target disk (partition numbers,
+//! byte offsets, sizes, partition types, filesystems) from the boot mode, the partition
+//! settings and the disk geometry. The diskpart and diskutil backends render their
+//! scripts from the same plan, and the Linux backend writes its partition table
+//! directly, instead of assuming partition numbers of their own.
+
+use crate::models::{BootMode, PartitionConfig, PartitionLayout, WtgConfig};
+use crate::utils::partition_table::{
+    DiskLayout, PartitionEntry, PartitionStyle, BASIC_DATA_TYPE_GUID, ESP_TYPE_GUID, MBR_TYPE_EFI,
+    MBR_TYPE_NTFS, MSR_TYPE_GUID,
+};
+use crate::{AppError, Result};
+use uuid::Uuid;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Partition starts and fixed sizes are multiples of 1 MiB, like diskpart and diskutil.
+pub const ALIGNMENT: u64 = MIB;
+
+pub const DEFAULT_EFI_SIZE_MB: u64 = 300;
+pub const MIN_EFI_SIZE_MB: u64 = 100;
+pub const MAX_EFI_SIZE_MB: u64 = 2048;
+/// FAT32 needs 65525 clusters, which with 4 KiB sectors means an ESP of about 260 MiB.
+pub const MIN_EFI_SIZE_4K_MB: u64 = 260;
+
+/// diskpart sizes the MSR of a fixed GPT disk by the disk size.
+const MSR_SIZE: u64 = 128 * MIB;
+const MSR_SIZE_SMALL_DISK: u64 = 32 * MIB;
+const MSR_SMALL_DISK_LIMIT: u64 = 16 * 1024 * MIB;
+
+/// Without an extended partition, which diskpart's scripts never create.
+const MBR_MAX_PARTITIONS: usize = 4;
+
+/// Backup GPT: 128 entries of 128 bytes plus the header sector.
+const GPT_ENTRY_ARRAY_BYTES: u64 = 128 * 128;
+
+pub const ESP_NAME: &str = "EFI system partition";
+pub const MSR_NAME: &str = "Microsoft reserved partition";
+pub const DATA_NAME: &str = "Basic data partition";
+pub const ESP_LABEL: &str = "EFI";
+pub const SYSTEM_LABEL: &str = "WTGA";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskGeometry {
+    /// Disk size in bytes
+    pub size: u64,
+    /// Logical sector size in bytes
+    pub sector_size: u32,
+    /// USB/SD target (`Disk::removable`); these get no MSR, as diskpart cannot create one
+    pub removable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRole {
+    Esp,
+    Msr,
+    /// The partition Windows is applied to
+    System,
+    /// Extra NTFS partition from `PartitionConfig::extra_partition_sizes`
+    Data,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystem {
+    Fat32,
+    Ntfs,
+    /// Left unformatted (MSR)
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPartition {
+    pub number: u32,
+    pub role: PartitionRole,
+    /// Byte offset of the first sector
+    pub start: u64,
+    pub size: u64,
+    /// GPT partition type
+    pub type_guid: Uuid,
+    /// MBR partition type
+    pub mbr_type: u8,
+    /// MBR active (boot) flag
+    pub active: bool,
+    pub filesystem: FileSystem,
+    /// GPT partition name
+    pub name: String,
+    /// Volume label given when formatting, empty for none
+    pub label: String,
+}
+
+#[cfg(test)]
+impl PlannedPartition {
+    fn end(&self) -> u64 {
+        self.start + self.size
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionPlan {
+    pub style: PartitionLayout,
+    pub geometry: DiskGeometry,
+    /// End of the space partitions may use: the backup GPT or the MBR 2 TiB limit
+    pub usable_end: u64,
+    pub partitions: Vec<PlannedPartition>,
+}
+
+impl PartitionPlan {
+    pub fn partition(&self, role: PartitionRole) -> Option<&PlannedPartition> {
+        self.partitions.iter().find(|p| p.role == role)
+    }
+
+    pub fn esp(&self) -> Option<&PlannedPartition> {
+        self.partition(PartitionRole::Esp)
+    }
+
+    pub fn system(&self) -> &PlannedPartition {
+        self.partition(PartitionRole::System)
+            .expect("every plan has a system partition")
+    }
+
+    // The Linux backend formats every planned partition in one pass.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub fn data_partitions(&self) -> impl Iterator<Item = &PlannedPartition> {
+        self.partitions
+            .iter()
+            .filter(|p| p.role == PartitionRole::Data)
+    }
+
+    /// The partition table to write for this plan, with a fresh disk GUID or MBR
+    /// signature and fresh partition GUIDs.
+    pub fn to_disk_layout(&self) -> DiskLayout {
+        let gpt = self.style == PartitionLayout::GPT;
+        let style = if gpt {
+            PartitionStyle::Gpt {
+                disk_guid: Uuid::new_v4(),
+            }
+        } else {
+            // Windows treats a zero signature as "no signature" and rewrites it.
+            PartitionStyle::Mbr {
+                signature: rand::random::<u32>().max(1),
+            }
+        };
+        let partitions = self
+            .partitions
+            .iter()
+            .map(|part| PartitionEntry {
+                number: part.number,
+                start: part.start,
+                size: part.size,
+                mbr_type: if gpt { 0 } else { part.mbr_type },
+                active: !gpt && part.active,
+                type_guid: gpt.then_some(part.type_guid),
+                unique_guid: gpt.then(Uuid::new_v4),
+                name: if gpt {
+                    part.name.clone()
+                } else {
+                    String::new()
+                },
+            })
+            .collect();
+        DiskLayout {
+            style,
+            sector_size: self.geometry.sector_size,
+            partitions,
+        }
+    }
+}
+
+/// ESP size in MiB: `efi_partition_size`, else the configured boot partition size,
+/// else the default. The result is clamped to 100–2048 MiB: 100 MiB is the smallest
+/// ESP Windows supports, and anything above 2 GiB is taken for a mistyped value.
+pub fn efi_size_mb(efi_partition_size: &str, partition_config: &PartitionConfig) -> u64 {
+    efi_partition_size
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&mb| mb > 0)
+        .or_else(|| Some(partition_config.boot_size as u64).filter(|&mb| mb > 0))
+        .unwrap_or(DEFAULT_EFI_SIZE_MB)
+        .clamp(MIN_EFI_SIZE_MB, MAX_EFI_SIZE_MB)
+}
+
+pub fn plan_for_config(config: &WtgConfig, geometry: DiskGeometry) -> Result<PartitionPlan> {
+    plan_partitions(
+        &config.boot_mode,
+        &config.partition_config,
+        &config.efi_partition_size,
+        geometry,
+    )
+}
+
+/// Lay out the target disk.
+///
+/// UEFI modes start with the ESP (active on MBR), fixed GPT disks get an MSR next, then
+/// follow the system partition and the extra data partitions. As with diskpart, the
+/// non-zero `extra_partition_sizes` (MiB) size every partition from the system partition
+/// on except the last, which takes the rest of the disk; with fewer than two sizes the
+/// system partition fills the disk. Legacy BIOS boots from an active system partition.
+/// MBR layouts are limited to four primary partitions.
+pub fn plan_partitions(
+    boot_mode: &BootMode,
+    partition_config: &PartitionConfig,
+    efi_partition_size: &str,
+    geometry: DiskGeometry,
+) -> Result<PartitionPlan> {
+    let sector_size = geometry.sector_size as u64;
+    if sector_size < 512 || !sector_size.is_power_of_two() || sector_size > ALIGNMENT {
+        return Err(AppError::InvalidParameter(format!(
+            "Unsupported sector size: {}",
+            geometry.sector_size
+        )));
+    }
+
+    let style = match boot_mode {
+        BootMode::UefiGpt => PartitionLayout::GPT,
+        BootMode::UefiMbr | BootMode::NonUefi => PartitionLayout::MBR,
+    };
+    let usable_end = usable_end(&style, geometry);
+
+    let mut roles: Vec<(PartitionRole, Option<u64>)> = Vec::new();
+    if *boot_mode != BootMode::NonUefi {
+        let mut efi_mb = efi_size_mb(efi_partition_size, partition_config);
+        if sector_size >= 4096 {
+            efi_mb = efi_mb.max(MIN_EFI_SIZE_4K_MB);
+        }
+        let efi_size = efi_mb * MIB;
+        roles.push((PartitionRole::Esp, Some(efi_size)));
+    }
+    if style == PartitionLayout::GPT && !geometry.removable {
+        let msr_size = if geometry.size < MSR_SMALL_DISK_LIMIT {
+            MSR_SIZE_SMALL_DISK
+        } else {
+            MSR_SIZE
+        };
+        roles.push((PartitionRole::Msr, Some(msr_size)));
+    }
+    let sizes: Vec<u64> = partition_config
+        .extra_partition_sizes
+        .iter()
+        .filter(|&&mb| mb > 0)
+        .map(|&mb| mb as u64 * MIB)
+        .collect();
+    let fixed = sizes.len().saturating_sub(1);
+    for (i, size) in sizes.iter().take(fixed).enumerate() {
+        let role = if i == 0 {
+            PartitionRole::System
+        } else {
+            PartitionRole::Data
+        };
+        roles.push((role, Some(*size)));
+    }
+    let last = if fixed == 0 {
+        PartitionRole::System
+    } else {
+        PartitionRole::Data
+    };
+    roles.push((last, None));
+    if style == PartitionLayout::MBR && roles.len() > MBR_MAX_PARTITIONS {
+        return Err(AppError::InvalidParameter(format!(
+            "An MBR disk holds at most {} partitions, the requested layout needs {}",
+            MBR_MAX_PARTITIONS,
+            roles.len()
+        )));
+    }
+
+    let mut partitions = Vec::with_capacity(roles.len());
+    let mut cursor = ALIGNMENT;
+    for (index, (role, size)) in roles.into_iter().enumerate() {
+        let size = size.unwrap_or_else(|| usable_end.saturating_sub(cursor));
+        if size == 0 || cursor + size > usable_end {
+            return Err(AppError::DiskError(format!(
+                "Target disk is too small for the requested layout ({} MiB usable)",
+                usable_end / MIB
+            )));
+        }
+        partitions.push(planned_partition(
+            index as u32 + 1,
+            role,
+            cursor,
+            size,
+            boot_mode,
+        ));
+        cursor += size;
+    }
+
+    Ok(PartitionPlan {
+        style,
+        geometry,
+        usable_end,
+        partitions,
+    })
+}
+
+/// Lay out a VHD/VHDX container the way Windows does: a single NTFS partition over
+/// the whole virtual disk, which is not active because it is never booted directly.
+pub fn plan_vhd_container(size: u64, gpt: bool) -> Result<PartitionPlan> {
+    let style = if gpt {
+        PartitionLayout::GPT
+    } else {
+        PartitionLayout::MBR
+    };
+    // Native VHD boot only handles 512-byte sectors.
+    let geometry = DiskGeometry {
+        size,
+        sector_size: 512,
+        removable: false,
+    };
+    let usable_end = usable_end(&style, geometry);
+    if usable_end <= ALIGNMENT {
+        return Err(AppError::DiskError(format!(
+            "VHD of {} bytes is too small for a partition",
+            size
+        )));
+    }
+    let system = planned_partition(
+        1,
+        PartitionRole::System,
+        ALIGNMENT,
+        usable_end - ALIGNMENT,
+        &BootMode::UefiGpt,
+    );
+    Ok(PartitionPlan {
+        style,
+        geometry,
+        usable_end,
+        partitions: vec![system],
+    })
+}
+
+/// End of the space partitions may use, aligned down: before the backup GPT, or
+/// within the 2^32 sectors an MBR can address.
+fn usable_end(style: &PartitionLayout, geometry: DiskGeometry) -> u64 {
+    let sector_size = geometry.sector_size as u64;
+    let disk_end = geometry.size / sector_size * sector_size;
+    let usable_end = match style {
+        PartitionLayout::GPT => disk_end.saturating_sub(GPT_ENTRY_ARRAY_BYTES + sector_size),
+        PartitionLayout::MBR => disk_end.min(u32::MAX as u64 * sector_size),
+    };
+    usable_end / ALIGNMENT * ALIGNMENT
+}
+
+fn planned_partition(
+    number: u32,
+    role: PartitionRole,
+    start: u64,
+    size: u64,
+    boot_mode: &BootMode,
+) -> PlannedPartition {
+    let (type_guid, mbr_type, filesystem, name, label) = match role {
+        PartitionRole::Esp => (
+            ESP_TYPE_GUID,
+            MBR_TYPE_EFI,
+            FileSystem::Fat32,
+            ESP_NAME,
+            ESP_LABEL,
+        ),
+        PartitionRole::Msr => (MSR_TYPE_GUID, 0, FileSystem::None, MSR_NAME, ""),
+        PartitionRole::System => (
+            BASIC_DATA_TYPE_GUID,
+            MBR_TYPE_NTFS,
+            FileSystem::Ntfs,
+            SYSTEM_LABEL,
+            SYSTEM_LABEL,
+        ),
+        PartitionRole::Data => (
+            BASIC_DATA_TYPE_GUID,
+            MBR_TYPE_NTFS,
+            FileSystem::Ntfs,
+            DATA_NAME,
+            "",
+        ),
+    };
+    let active = match boot_mode {
+        BootMode::UefiMbr => role == PartitionRole::Esp,
+        BootMode::NonUefi => role == PartitionRole::System,
+        BootMode::UefiGpt => false,
+    };
+    PlannedPartition {
+        number,
+        role,
+        start,
+        size,
+        type_guid,
+        mbr_type,
+        active,
+        filesystem,
+        name: name.to_string(),
+        label: label.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * MIB;
+    const MODES: [BootMode; 3] = [BootMode::UefiGpt, BootMode::UefiMbr, BootMode::NonUefi];
+
+    fn config(boot_size: u32, extra: &[u32]) -> PartitionConfig {
+        PartitionConfig {
+            boot_size,
+            partition_layout: PartitionLayout::GPT,
+            extra_partition_sizes: extra.to_vec(),
+        }
+    }
+
+    fn geometry(size: u64, sector_size: u32, removable: bool) -> DiskGeometry {
+        DiskGeometry {
+            size,
+            sector_size,
+            removable,
+        }
+    }
+
+    fn roles(plan: &PartitionPlan) -> Vec<PartitionRole> {
+        plan.partitions.iter().map(|p| p.role).collect()
+    }
+
+    #[test]
+    fn picks_efi_size() {
+        let none = config(0, &[]);
+        assert_eq!(efi_size_mb("300", &none), 300);
+        assert_eq!(efi_size_mb(" 512 ", &none), 512);
+        assert_eq!(efi_size_mb("", &none), DEFAULT_EFI_SIZE_MB);
+        assert_eq!(efi_size_mb("abc", &config(260, &[])), 260);
+        assert_eq!(efi_size_mb("0", &config(0, &[])), DEFAULT_EFI_SIZE_MB);
+        assert_eq!(efi_size_mb("10", &none), MIN_EFI_SIZE_MB);
+        assert_eq!(efi_size_mb("99999", &none), MAX_EFI_SIZE_MB);
+    }
+
+    #[test]
+    fn every_combination_is_consistent() {
+        let extras: [&[u32]; 4] = [&[], &[0, 40960], &[40960, 0, 8192], &[40960, 4096, 8192]];
+        for mode in &MODES {
+            for removable in [false, true] {
+                for sector_size in [512u32, 4096] {
+                    for extra in extras {
+                        let disk = geometry(64 * GIB + 12345, sector_size, removable);
+                        let plan = plan_partitions(mode, &config(0, extra), "300", disk)
+                            .unwrap_or_else(|e| {
+                                panic!("{:?} removable={} {:?}: {}", mode, removable, extra, e)
+                            });
+                        let what = format!("{:?} removable={} {:?}", mode, removable, extra);
+
+                        // Numbered from 1, aligned, ordered and inside the usable area.
+                        let mut cursor = ALIGNMENT;
+                        for (i, p) in plan.partitions.iter().enumerate() {
+                            assert_eq!(p.number, i as u32 + 1, "{}", what);
+                            assert_eq!(p.start, cursor, "{}", what);
+                            assert!(p.start.is_multiple_of(ALIGNMENT), "{}", what);
+                            assert!(p.size.is_multiple_of(sector_size as u64), "{}", what);
+                            cursor = p.end();
+                        }
+                        assert!(cursor <= plan.usable_end, "{}", what);
+                        assert!(plan.usable_end <= disk.size, "{}", what);
+                        assert_eq!(cursor, plan.usable_end, "last partition fills the disk");
+
+                        let is_gpt = *mode == BootMode::UefiGpt;
+                        assert_eq!(plan.style == PartitionLayout::GPT, is_gpt, "{}", what);
+                        assert_eq!(plan.esp().is_some(), *mode != BootMode::NonUefi, "{}", what);
+                        assert_eq!(
+                            plan.partition(PartitionRole::Msr).is_some(),
+                            is_gpt && !removable,
+                            "{}",
+                            what
+                        );
+
+                        let data = extra.iter().filter(|&&mb| mb > 0).count().saturating_sub(1);
+                        assert_eq!(plan.data_partitions().count(), data, "{}", what);
+                        if data > 0 {
+                            assert_eq!(plan.system().size, 40960 * MIB, "{}", what);
+                        }
+
+                        let active: Vec<PartitionRole> = plan
+                            .partitions
+                            .iter()
+                            .filter(|p| p.active)
+                            .map(|p| p.role)
+                            .collect();
+                        let expected = match mode {
+                            BootMode::UefiGpt => vec![],
+                            BootMode::UefiMbr => vec![PartitionRole::Esp],
+                            BootMode::NonUefi => vec![PartitionRole::System],
+                        };
+                        assert_eq!(active, expected, "{}", what);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_gpt_disk_layout() {
+        let plan = plan_partitions(
+            &BootMode::UefiGpt,
+            &config(0, &[]),
+            "260",
+            geometry(64 * GIB, 512, false),
+        )
+        .unwrap();
+        assert_eq!(
+            roles(&plan),
+            vec![
+                PartitionRole::Esp,
+                PartitionRole::Msr,
+                PartitionRole::System
+            ]
+        );
+        let esp = plan.esp().unwrap();
+        assert_eq!((esp.start, esp.size), (MIB, 260 * MIB));
+        assert_eq!(esp.type_guid, ESP_TYPE_GUID);
+        assert_eq!(esp.filesystem, FileSystem::Fat32);
+        let msr = &plan.partitions[1];
+        assert_eq!((msr.start, msr.size), (261 * MIB, 128 * MIB));
+        assert_eq!(msr.filesystem, FileSystem::None);
+        let system = plan.system();
+        assert_eq!(system.number, 3);
+        assert_eq!(system.start, 389 * MIB);
+        // 64 GiB minus the backup GPT, rounded down to the alignment.
+        assert_eq!(system.end(), 64 * GIB - MIB);
+        assert_eq!(system.label, "WTGA");
+    }
+
+    #[test]
+    fn removable_gpt_disk_has_no_msr() {
+        let plan = plan_partitions(
+            &BootMode::UefiGpt,
+            &config(0, &[20480, 4096]),
+            "300",
+            geometry(32 * GIB, 512, true),
+        )
+        .unwrap();
+        assert_eq!(
+            roles(&plan),
+            vec![
+                PartitionRole::Esp,
+                PartitionRole::System,
+                PartitionRole::Data
+            ]
+        );
+        assert_eq!(plan.system().number, 2);
+        assert_eq!(plan.system().size, 20480 * MIB);
+        assert!(plan_partitions(
+            &BootMode::UefiGpt,
+            &config(0, &[20480, 4096]),
+            "300",
+            geometry(16 * GIB, 512, true),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn small_fixed_disk_gets_small_msr() {
+        let plan = plan_partitions(
+            &BootMode::UefiGpt,
+            &config(0, &[]),
+            "300",
+            geometry(8 * GIB, 512, false),
+        )
+        .unwrap();
+        assert_eq!(plan.partitions[1].size, 32 * MIB);
+    }
+
+    #[test]
+    fn mbr_layouts() {
+        let uefi = plan_partitions(
+            &BootMode::UefiMbr,
+            &config(0, &[]),
+            "300",
+            geometry(32 * GIB, 512, false),
+        )
+        .unwrap();
+        assert_eq!(
+            roles(&uefi),
+            vec![PartitionRole::Esp, PartitionRole::System]
+        );
+        assert_eq!(uefi.esp().unwrap().mbr_type, MBR_TYPE_EFI);
+        assert_eq!(uefi.system().mbr_type, MBR_TYPE_NTFS);
+        assert_eq!(uefi.system().end(), 32 * GIB);
+
+        let legacy = plan_partitions(
+            &BootMode::NonUefi,
+            &config(0, &[16384, 1024]),
+            "300",
+            geometry(32 * GIB, 512, true),
+        )
+        .unwrap();
+        assert_eq!(
+            roles(&legacy),
+            vec![PartitionRole::System, PartitionRole::Data]
+        );
+        assert_eq!(legacy.system().start, MIB);
+        assert!(legacy.system().active);
+    }
+
+    #[test]
+    fn mbr_stops_at_two_tib() {
+        let plan = plan_partitions(
+            &BootMode::UefiMbr,
+            &config(0, &[]),
+            "300",
+            geometry(4 * 1024 * GIB, 512, false),
+        )
+        .unwrap();
+        assert!(plan.system().end() <= u32::MAX as u64 * 512);
+
+        let native_4k = plan_partitions(
+            &BootMode::UefiMbr,
+            &config(0, &[]),
+            "300",
+            geometry(4 * 1024 * GIB, 4096, false),
+        )
+        .unwrap();
+        assert_eq!(native_4k.system().end(), 4 * 1024 * GIB);
+    }
+
+    #[test]
+    fn grows_small_esps_on_4k_disks() {
+        for (sector_size, expected) in [(512, 100), (4096, MIN_EFI_SIZE_4K_MB)] {
+            let plan = plan_partitions(
+                &BootMode::UefiGpt,
+                &config(0, &[]),
+                "100",
+                geometry(64 * GIB, sector_size, false),
+            )
+            .unwrap();
+            assert_eq!(plan.esp().unwrap().size, expected * MIB);
+        }
+    }
+
+    #[test]
+    fn vhd_container_has_one_ntfs_partition() {
+        for gpt in [false, true] {
+            let plan = plan_vhd_container(40 * GIB, gpt).unwrap();
+            assert_eq!(roles(&plan), [PartitionRole::System]);
+            let system = plan.system();
+            assert_eq!((system.number, system.start), (1, MIB));
+            assert_eq!(system.end(), plan.usable_end);
+            assert_eq!(system.filesystem, FileSystem::Ntfs);
+            assert!(!system.active);
+            assert_eq!(plan.geometry.sector_size, 512);
+        }
+        assert_eq!(
+            plan_vhd_container(40 * GIB, false).unwrap().usable_end,
+            40 * GIB
+        );
+        assert!(plan_vhd_container(40 * GIB, true).unwrap().usable_end < 40 * GIB);
+        assert!(plan_vhd_container(MIB, false).is_err());
+    }
+
+    #[test]
+    fn limits_mbr_layouts_to_four_partitions() {
+        let disk = geometry(256 * GIB, 512, false);
+        let three_extra = config(0, &[40960, 4096, 8192, 8192]);
+        for mode in [BootMode::UefiMbr, BootMode::NonUefi] {
+            assert!(plan_partitions(&mode, &config(0, &[40960, 4096, 8192]), "300", disk).is_ok());
+        }
+        assert!(matches!(
+            plan_partitions(&BootMode::UefiMbr, &three_extra, "300", disk),
+            Err(AppError::InvalidParameter(_))
+        ));
+        assert!(plan_partitions(&BootMode::NonUefi, &three_extra, "300", disk).is_ok());
+        assert!(plan_partitions(
+            &BootMode::NonUefi,
+            &config(0, &[1, 1, 1, 1, 1]),
+            "300",
+            disk
+        )
+        .is_err());
+        assert_eq!(
+            plan_partitions(&BootMode::UefiGpt, &three_extra, "300", disk)
+                .unwrap()
+                .partitions
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn rejects_bad_geometry() {
+        let cfg = config(0, &[]);
+        assert!(
+            plan_partitions(&BootMode::UefiGpt, &cfg, "300", geometry(GIB, 520, false)).is_err()
+        );
+        assert!(plan_partitions(
+            &BootMode::UefiGpt,
+            &cfg,
+            "300",
+            geometry(200 * MIB, 512, true)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn written_plans_read_back_as_planned() {
+        use crate::utils::partition_table::{read_layout, write_layout};
+        use std::io::Cursor;
+
+        for mode in MODES {
+            let plan = plan_partitions(
+                &mode,
+                &config(0, &[64, 1]),
+                "100",
+                geometry(256 * MIB, 512, true),
+            )
+            .unwrap();
+            let layout = plan.to_disk_layout();
+            let mut disk = Cursor::new(vec![0u8; 256 * MIB as usize]);
+            write_layout(&mut disk, &layout, 256 * MIB).unwrap();
+            let read = read_layout(&mut disk).unwrap();
+            assert_eq!(read, layout);
+            for (part, planned) in read.partitions.iter().zip(&plan.partitions) {
+                assert_eq!((part.start, part.size), (planned.start, planned.size));
+            }
+        }
+    }
+}
@@ -0,0 +1,755 @@
+//! MBR and GPT partition table parsing and writing
+//!
+//! Reads the partition layout of a raw disk device or disk image without going through
+//! the OS, and writes a protective MBR with primary and backup GPT, or a classic MBR,
+//! so every host produces the same table. All I/O is in whole, sector-aligned sectors
+//! so that raw device handles (`\\.\PhysicalDriveN`, `/dev/rdiskN`) accept it.
+
+use crate::{AppError, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+pub const ESP_TYPE_GUID: Uuid = uuid::uuid!("c12a7328-f81f-11d2-ba4b-00a0c93ec93b");
+pub const MSR_TYPE_GUID: Uuid = uuid::uuid!("e3c9e316-0b5c-4db8-817d-f92df00215ae");
+pub const BASIC_DATA_TYPE_GUID: Uuid = uuid::uuid!("ebd0a0a2-b9e5-4433-87c0-68b6b72699c7");
+
+pub const MBR_TYPE_EFI: u8 = 0xEF;
+pub const MBR_TYPE_NTFS: u8 = 0x07;
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xEE;
+const MBR_TYPE_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_REVISION: u32 = 0x0001_0000;
+const GPT_HEADER_SIZE: usize = 92;
+const GPT_ENTRY_COUNT: usize = 128;
+const GPT_ENTRY_SIZE: usize = 128;
+const GPT_NAME_UNITS: usize = 36;
+const MAX_GPT_ENTRIES: u32 = 1024;
+const MAX_LOGICAL_PARTITIONS: u32 = 128;
+const MBR_BOOT_CODE_LEN: usize = 440;
+const MBR_PRIMARY_ENTRIES: usize = 4;
+/// Bytes cleared at each end of the disk before a new table is written, wiping stale
+/// partition tables and filesystem signatures outside the partitions.
+const WIPE_LEN: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionStyle {
+    Mbr { signature: u32 },
+    Gpt { disk_guid: Uuid },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// 1-based number as Windows and Linux count it (logical MBR partitions start at 5)
+    pub number: u32,
+    /// Byte offset of the first sector
+    pub start: u64,
+    pub size: u64,
+    /// MBR partition type, 0 on GPT disks
+    pub mbr_type: u8,
+    /// Active (boot) flag of MBR partitions
+    pub active: bool,
+    pub type_guid: Option<Uuid>,
+    pub unique_guid: Option<Uuid>,
+    /// GPT partition name
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskLayout {
+    pub style: PartitionStyle,
+    pub sector_size: u32,
+    pub partitions: Vec<PartitionEntry>,
+}
+
+impl DiskLayout {
+    pub fn partition_at(&self, start: u64) -> Option<&PartitionEntry> {
+        self.partitions.iter().find(|p| p.start == start)
+    }
+
+    pub fn partition_by_guid(&self, guid: &Uuid) -> Option<&PartitionEntry> {
+        self.partitions
+            .iter()
+            .find(|p| p.unique_guid.as_ref() == Some(guid))
+    }
+}
+
+impl PartitionEntry {
+    pub fn is_efi_system(&self) -> bool {
+        self.type_guid == Some(ESP_TYPE_GUID) || self.mbr_type == MBR_TYPE_EFI
+    }
+}
+
+/// Read the partition table of a disk device or image file.
+pub fn read_disk_layout(path: &Path) -> Result<DiskLayout> {
+    let mut file = File::open(path).map_err(AppError::io)?;
+    read_layout(&mut file)
+}
+
+pub fn read_layout<R: Read + Seek>(reader: &mut R) -> Result<DiskLayout> {
+    let mbr = read_sectors(reader, 0, 512)?;
+    if mbr[510] != 0x55 || mbr[511] != 0xAA {
+        return Err(AppError::DiskError(
+            "No partition table found (missing 0x55AA boot signature)".to_string(),
+        ));
+    }
+
+    let primary: Vec<(usize, MbrEntry)> = (0..4)
+        .map(|i| (i, MbrEntry::parse(&mbr[446 + i * 16..462 + i * 16])))
+        .filter(|(_, e)| e.partition_type != 0)
+        .collect();
+    if primary
+        .iter()
+        .any(|(_, e)| e.partition_type == MBR_TYPE_GPT_PROTECTIVE)
+    {
+        for sector_size in [512u32, 4096] {
+            if let Some(layout) = read_gpt(reader, sector_size)? {
+                return Ok(layout);
+            }
+        }
+        return Err(AppError::DiskError(
+            "Protective MBR found but the GPT header is missing or damaged".to_string(),
+        ));
+    }
+
+    let mut partitions = Vec::new();
+    for (index, entry) in &primary {
+        if MBR_TYPE_EXTENDED.contains(&entry.partition_type) {
+            read_logical_partitions(reader, entry.first_lba as u64, &mut partitions)?;
+            continue;
+        }
+        partitions.push(entry.to_partition(*index as u32 + 1, 0));
+    }
+    partitions.sort_by_key(|p| p.number);
+
+    Ok(DiskLayout {
+        style: PartitionStyle::Mbr {
+            signature: u32::from_le_bytes([mbr[440], mbr[441], mbr[442], mbr[443]]),
+        },
+        sector_size: 512,
+        partitions,
+    })
+}
+
+struct MbrEntry {
+    active: bool,
+    partition_type: u8,
+    first_lba: u32,
+    sectors: u32,
+}
+
+impl MbrEntry {
+    fn parse(raw: &[u8]) -> Self {
+        MbrEntry {
+            active: raw[0] == 0x80,
+            partition_type: raw[4],
+            first_lba: u32::from_le_bytes([raw[8], raw[9], raw[10], raw[11]]),
+            sectors: u32::from_le_bytes([raw[12], raw[13], raw[14], raw[15]]),
+        }
+    }
+
+    fn to_partition(&self, number: u32, base_lba: u64) -> PartitionEntry {
+        PartitionEntry {
+            number,
+            start: (base_lba + self.first_lba as u64) * 512,
+            size: self.sectors as u64 * 512,
+            mbr_type: self.partition_type,
+            active: self.active,
+            type_guid: None,
+            unique_guid: None,
+            name: String::new(),
+        }
+    }
+}
+
+/// Walk the EBR chain of an extended partition. Each EBR describes one logical
+/// partition (relative to the EBR) and links to the next EBR (relative to the
+/// extended partition start).
+fn read_logical_partitions<R: Read + Seek>(
+    reader: &mut R,
+    extended_lba: u64,
+    out: &mut Vec<PartitionEntry>,
+) -> Result<()> {
+    let mut ebr_lba = extended_lba;
+    for number in 5..5 + MAX_LOGICAL_PARTITIONS {
+        let ebr = read_sectors(reader, ebr_lba * 512, 512)?;
+        if ebr[510] != 0x55 || ebr[511] != 0xAA {
+            break;
+        }
+        let logical = MbrEntry::parse(&ebr[446..462]);
+        if logical.partition_type != 0 {
+            out.push(logical.to_partition(number, ebr_lba));
+        }
+        let next = MbrEntry::parse(&ebr[462..478]);
+        if next.partition_type == 0 || next.first_lba == 0 {
+            break;
+        }
+        ebr_lba = extended_lba + next.first_lba as u64;
+    }
+    Ok(())
+}
+
+/// `Ok(None)` when no valid GPT header is found for this sector size.
+fn read_gpt<R: Read + Seek>(reader: &mut R, sector_size: u32) -> Result<Option<DiskLayout>> {
+    let sector = sector_size as u64;
+    let header = read_sectors(reader, sector, sector_size as usize)?;
+    if &header[..8] != GPT_SIGNATURE {
+        return Ok(None);
+    }
+    let header_size = read_u32(&header, 12) as usize;
+    if !(92..=sector_size as usize).contains(&header_size)
+        || read_u32(&header, 16) != crc32_with_hole(&header[..header_size], 16)
+    {
+        return Ok(None);
+    }
+
+    let entries_lba = read_u64(&header, 72);
+    let entry_count = read_u32(&header, 80);
+    let entry_size = read_u32(&header, 84) as usize;
+    if entry_count > MAX_GPT_ENTRIES || entry_size < 128 || !entry_size.is_multiple_of(8) {
+        return Err(AppError::DiskError(format!(
+            "Unsupported GPT entry array ({} entries of {} bytes)",
+            entry_count, entry_size
+        )));
+    }
+    let array_len = entry_count as usize * entry_size;
+    let padded = array_len.div_ceil(sector_size as usize) * sector_size as usize;
+    let array = read_sectors(reader, entries_lba * sector, padded)?;
+    if read_u32(&header, 88) != crc32_with_hole(&array[..array_len], array_len) {
+        return Err(AppError::DiskError(
+            "GPT partition entry array checksum mismatch".to_string(),
+        ));
+    }
+
+    let partitions = array[..array_len]
+        .chunks_exact(entry_size)
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let type_guid = read_guid(raw, 0);
+            if type_guid.is_nil() {
+                return None;
+            }
+            let first_lba = read_u64(raw, 32);
+            let last_lba = read_u64(raw, 40);
+            let units: Vec<u16> = raw[56..128]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&u| u != 0)
+                .collect();
+            Some(PartitionEntry {
+                number: i as u32 + 1,
+                start: first_lba * sector,
+                size: (last_lba + 1).saturating_sub(first_lba) * sector,
+                mbr_type: 0,
+                active: false,
+                type_guid: Some(type_guid),
+                unique_guid: Some(read_guid(raw, 16)),
+                name: String::from_utf16_lossy(&units),
+            })
+        })
+        .collect();
+
+    Ok(Some(DiskLayout {
+        style: PartitionStyle::Gpt {
+            disk_guid: read_guid(&header, 56),
+        },
+        sector_size,
+        partitions,
+    }))
+}
+
+/// Write `layout` to a disk device or image file. The disk size comes from the file
+/// itself, so block devices and pre-sized images work alike.
+pub fn write_disk_layout(path: &Path, layout: &DiskLayout) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(AppError::io)?;
+    let disk_size = file.seek(SeekFrom::End(0)).map_err(AppError::io)?;
+    if disk_size == 0 {
+        return Err(AppError::DiskError(format!(
+            "Cannot determine the size of {}",
+            path.display()
+        )));
+    }
+    write_layout(&mut file, layout, disk_size)?;
+    file.sync_all().map_err(AppError::io)
+}
+
+/// Write a protective MBR plus primary and backup GPT, or a classic MBR (keeping any
+/// boot code already in sector 0), for a disk of `disk_size` bytes.
+pub fn write_layout<W: Read + Write + Seek>(
+    writer: &mut W,
+    layout: &DiskLayout,
+    disk_size: u64,
+) -> Result<()> {
+    let sector = layout.sector_size as u64;
+    if !matches!(layout.sector_size, 512 | 4096) {
+        return Err(AppError::InvalidParameter(format!(
+            "Unsupported sector size: {}",
+            layout.sector_size
+        )));
+    }
+    let total_sectors = disk_size / sector;
+    // MBR (and GPT header plus entry array at both ends), then one partition sector.
+    let min_sectors = match layout.style {
+        PartitionStyle::Gpt { .. } => 2 * (2 + gpt_array_sectors(sector)) + 1,
+        PartitionStyle::Mbr { .. } => 2,
+    };
+    if total_sectors < min_sectors {
+        return Err(AppError::DiskError(format!(
+            "The {} byte disk is too small for a partition table",
+            disk_size
+        )));
+    }
+    let (first_usable, last_usable) = match layout.style {
+        PartitionStyle::Gpt { .. } => {
+            let array_sectors = gpt_array_sectors(sector);
+            (2 + array_sectors, total_sectors - 2 - array_sectors)
+        }
+        PartitionStyle::Mbr { .. } => (1, total_sectors.min(u32::MAX as u64) - 1),
+    };
+    for part in &layout.partitions {
+        let first = part.start / sector;
+        let sectors = part.size / sector;
+        if part.start % sector != 0
+            || part.size % sector != 0
+            || sectors == 0
+            || first < first_usable
+            || first + sectors - 1 > last_usable
+        {
+            return Err(AppError::DiskError(format!(
+                "Partition {} ({} bytes at {}) does not fit the {} byte disk",
+                part.number, part.size, part.start, disk_size
+            )));
+        }
+    }
+
+    let boot_code = match layout.style {
+        PartitionStyle::Mbr { .. } => {
+            let mbr = read_sectors(writer, 0, sector as usize)?;
+            mbr[..MBR_BOOT_CODE_LEN].to_vec()
+        }
+        PartitionStyle::Gpt { .. } => vec![0u8; MBR_BOOT_CODE_LEN],
+    };
+    wipe(writer, 0, WIPE_LEN.min(disk_size))?;
+    let tail = disk_size.saturating_sub(WIPE_LEN) / sector * sector;
+    wipe(writer, tail, total_sectors * sector - tail)?;
+
+    let mut mbr = vec![0u8; sector as usize];
+    mbr[..MBR_BOOT_CODE_LEN].copy_from_slice(&boot_code);
+    match layout.style {
+        PartitionStyle::Gpt { disk_guid } => {
+            let protective_sectors = (total_sectors - 1).min(u32::MAX as u64) as u32;
+            mbr[446..462].copy_from_slice(&mbr_entry(
+                false,
+                MBR_TYPE_GPT_PROTECTIVE,
+                1,
+                protective_sectors,
+            ));
+            write_gpt(writer, layout, disk_guid, total_sectors)?;
+        }
+        PartitionStyle::Mbr { signature } => {
+            if layout.partitions.len() > MBR_PRIMARY_ENTRIES {
+                return Err(AppError::DiskError(format!(
+                    "An MBR holds at most {} primary partitions, {} requested",
+                    MBR_PRIMARY_ENTRIES,
+                    layout.partitions.len()
+                )));
+            }
+            mbr[440..444].copy_from_slice(&signature.to_le_bytes());
+            for (i, part) in layout.partitions.iter().enumerate() {
+                mbr[446 + i * 16..462 + i * 16].copy_from_slice(&mbr_entry(
+                    part.active,
+                    part.mbr_type,
+                    (part.start / sector) as u32,
+                    (part.size / sector) as u32,
+                ));
+            }
+        }
+    }
+    mbr[510] = 0x55;
+    mbr[511] = 0xAA;
+    write_at(writer, 0, &mbr)?;
+    writer.flush().map_err(AppError::io)
+}
+
+fn write_gpt<W: Write + Seek>(
+    writer: &mut W,
+    layout: &DiskLayout,
+    disk_guid: Uuid,
+    total_sectors: u64,
+) -> Result<()> {
+    let sector = layout.sector_size as u64;
+    let array_sectors = gpt_array_sectors(sector);
+    let mut array = vec![0u8; (array_sectors * sector) as usize];
+    for (i, part) in layout.partitions.iter().enumerate() {
+        if i >= GPT_ENTRY_COUNT {
+            return Err(AppError::DiskError(format!(
+                "A GPT holds at most {} partitions",
+                GPT_ENTRY_COUNT
+            )));
+        }
+        let raw = &mut array[i * GPT_ENTRY_SIZE..(i + 1) * GPT_ENTRY_SIZE];
+        let type_guid = part.type_guid.ok_or_else(|| {
+            AppError::InvalidParameter(format!("GPT partition {} has no type GUID", part.number))
+        })?;
+        let unique_guid = part.unique_guid.unwrap_or_else(Uuid::new_v4);
+        raw[0..16].copy_from_slice(&type_guid.to_bytes_le());
+        raw[16..32].copy_from_slice(&unique_guid.to_bytes_le());
+        raw[32..40].copy_from_slice(&(part.start / sector).to_le_bytes());
+        raw[40..48].copy_from_slice(&((part.start + part.size) / sector - 1).to_le_bytes());
+        for (j, unit) in part.name.encode_utf16().take(GPT_NAME_UNITS).enumerate() {
+            raw[56 + j * 2..58 + j * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+    let array_crc = crc32_with_hole(
+        &array[..GPT_ENTRY_COUNT * GPT_ENTRY_SIZE],
+        GPT_ENTRY_COUNT * GPT_ENTRY_SIZE,
+    );
+
+    let backup_lba = total_sectors - 1;
+    let backup_array_lba = backup_lba - array_sectors;
+    let header = |current: u64, other: u64, entries: u64| {
+        let mut header = vec![0u8; sector as usize];
+        header[..8].copy_from_slice(GPT_SIGNATURE);
+        header[8..12].copy_from_slice(&GPT_REVISION.to_le_bytes());
+        header[12..16].copy_from_slice(&(GPT_HEADER_SIZE as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&current.to_le_bytes());
+        header[32..40].copy_from_slice(&other.to_le_bytes());
+        header[40..48].copy_from_slice(&(2 + array_sectors).to_le_bytes());
+        header[48..56].copy_from_slice(&(backup_array_lba - 1).to_le_bytes());
+        header[56..72].copy_from_slice(&disk_guid.to_bytes_le());
+        header[72..80].copy_from_slice(&entries.to_le_bytes());
+        header[80..84].copy_from_slice(&(GPT_ENTRY_COUNT as u32).to_le_bytes());
+        header[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+        header[88..92].copy_from_slice(&array_crc.to_le_bytes());
+        let crc = crc32_with_hole(&header[..GPT_HEADER_SIZE], 16);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+        header
+    };
+
+    // Backup first, so a half-written table never has a valid primary alone.
+    write_at(writer, backup_array_lba * sector, &array)?;
+    write_at(
+        writer,
+        backup_lba * sector,
+        &header(backup_lba, 1, backup_array_lba),
+    )?;
+    write_at(writer, 2 * sector, &array)?;
+    write_at(writer, sector, &header(1, backup_lba, 2))
+}
+
+fn gpt_array_sectors(sector: u64) -> u64 {
+    ((GPT_ENTRY_COUNT * GPT_ENTRY_SIZE) as u64).div_ceil(sector)
+}
+
+fn mbr_entry(active: bool, partition_type: u8, first_lba: u32, sectors: u32) -> [u8; 16] {
+    let mut raw = [0u8; 16];
+    raw[0] = if active { 0x80 } else { 0 };
+    raw[1..4].copy_from_slice(&chs(first_lba));
+    raw[4] = partition_type;
+    raw[5..8].copy_from_slice(&chs(first_lba.saturating_add(sectors.saturating_sub(1))));
+    raw[8..12].copy_from_slice(&first_lba.to_le_bytes());
+    raw[12..16].copy_from_slice(&sectors.to_le_bytes());
+    raw
+}
+
+/// CHS address for 255 heads and 63 sectors per track, saturating past cylinder 1023
+/// as every modern tool does.
+fn chs(lba: u32) -> [u8; 3] {
+    const HEADS: u32 = 255;
+    const SECTORS: u32 = 63;
+    let cylinder = lba / (HEADS * SECTORS);
+    if cylinder > 1023 {
+        return [0xFE, 0xFF, 0xFF];
+    }
+    let head = (lba / SECTORS) % HEADS;
+    let sector = lba % SECTORS + 1;
+    [
+        head as u8,
+        (sector as u8) | (((cylinder >> 2) & 0xC0) as u8),
+        (cylinder & 0xFF) as u8,
+    ]
+}
+
+fn wipe<W: Write + Seek>(writer: &mut W, offset: u64, len: u64) -> Result<()> {
+    let zeros = vec![0u8; len.min(WIPE_LEN) as usize];
+    let mut done = 0;
+    while done < len {
+        let chunk = (len - done).min(zeros.len() as u64) as usize;
+        write_at(writer, offset + done, &zeros[..chunk])?;
+        done += chunk as u64;
+    }
+    Ok(())
+}
+
+fn write_at<W: Write + Seek>(writer: &mut W, offset: u64, data: &[u8]) -> Result<()> {
+    writer.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+    writer.write_all(data).map_err(AppError::io)
+}
+
+fn read_sectors<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+    reader.read_exact(&mut buf).map_err(AppError::io)?;
+    Ok(buf)
+}
+
+/// CRC-32 (IEEE 802.3, as used by GPT) of `buf` with the 4-byte field at `hole` zeroed.
+pub(crate) fn crc32_with_hole(buf: &[u8], hole: usize) -> u32 {
+    let mut crc = !0u32;
+    for (i, &byte) in buf.iter().enumerate() {
+        let byte = if (hole..hole + 4).contains(&i) {
+            0
+        } else {
+            byte
+        };
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn read_guid(buf: &[u8], offset: usize) -> Uuid {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&buf[offset..offset + 16]);
+    Uuid::from_bytes_le(bytes)
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn entry(number: u32, start: u64, size: u64) -> PartitionEntry {
+        PartitionEntry {
+            number,
+            start,
+            size,
+            mbr_type: 0,
+            active: false,
+            type_guid: None,
+            unique_guid: None,
+            name: String::new(),
+        }
+    }
+
+    #[test]
+    fn crc32_matches_reference_vector() {
+        assert_eq!(crc32_with_hole(b"123456789", 9), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn reads_mbr_with_logical_partitions() {
+        let mut disk = vec![0u8; 8 * 1024 * 1024];
+        disk[440..444].copy_from_slice(&0x4F2B_AEB0u32.to_le_bytes());
+        disk[446..462].copy_from_slice(&mbr_entry(true, MBR_TYPE_NTFS, 2048, 4096));
+        disk[462..478].copy_from_slice(&mbr_entry(false, 0x0F, 8192, 6144));
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+        // First EBR at LBA 8192: logical partition 63 sectors in, link to the next EBR.
+        let ebr = 8192 * 512;
+        disk[ebr + 446..ebr + 462].copy_from_slice(&mbr_entry(false, 0x0C, 63, 1000));
+        disk[ebr + 462..ebr + 478].copy_from_slice(&mbr_entry(false, 0x05, 2048, 2048));
+        disk[ebr + 510] = 0x55;
+        disk[ebr + 511] = 0xAA;
+        let ebr = (8192 + 2048) * 512;
+        disk[ebr + 446..ebr + 462].copy_from_slice(&mbr_entry(false, 0x07, 63, 1500));
+        disk[ebr + 510] = 0x55;
+        disk[ebr + 511] = 0xAA;
+
+        let layout = read_layout(&mut Cursor::new(disk)).unwrap();
+        assert_eq!(
+            layout.style,
+            PartitionStyle::Mbr {
+                signature: 0x4F2B_AEB0
+            }
+        );
+        let summary: Vec<(u32, u64, u8, bool)> = layout
+            .partitions
+            .iter()
+            .map(|p| (p.number, p.start, p.mbr_type, p.active))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 2048 * 512, MBR_TYPE_NTFS, true),
+                (5, (8192 + 63) * 512, 0x0C, false),
+                (6, (8192 + 2048 + 63) * 512, 0x07, false),
+            ]
+        );
+        assert!(layout.partition_at(2048 * 512).is_some());
+    }
+
+    #[test]
+    fn reads_gpt_and_rejects_damaged_entry_array() {
+        let disk_guid = Uuid::new_v4();
+        let esp_guid = Uuid::new_v4();
+        let mut disk = vec![0u8; 64 * 1024];
+        disk[446..462].copy_from_slice(&mbr_entry(false, MBR_TYPE_GPT_PROTECTIVE, 1, 127));
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+
+        let mut entries = vec![0u8; 128 * 128];
+        entries[..16].copy_from_slice(&ESP_TYPE_GUID.to_bytes_le());
+        entries[16..32].copy_from_slice(&esp_guid.to_bytes_le());
+        entries[32..40].copy_from_slice(&40u64.to_le_bytes());
+        entries[40..48].copy_from_slice(&79u64.to_le_bytes());
+        for (i, unit) in "EFI system partition".encode_utf16().enumerate() {
+            entries[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        disk[1024..1024 + entries.len()].copy_from_slice(&entries);
+
+        let mut header = vec![0u8; 92];
+        header[..8].copy_from_slice(GPT_SIGNATURE);
+        header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        header[12..16].copy_from_slice(&92u32.to_le_bytes());
+        header[56..72].copy_from_slice(&disk_guid.to_bytes_le());
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&128u32.to_le_bytes());
+        header[84..88].copy_from_slice(&128u32.to_le_bytes());
+        let array_crc = crc32_with_hole(&entries, entries.len());
+        header[88..92].copy_from_slice(&array_crc.to_le_bytes());
+        let header_crc = crc32_with_hole(&header, 16);
+        header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+        disk[512..604].copy_from_slice(&header);
+
+        let layout = read_layout(&mut Cursor::new(disk.clone())).unwrap();
+        assert_eq!(layout.style, PartitionStyle::Gpt { disk_guid });
+        assert_eq!(layout.partitions.len(), 1);
+        let esp = layout.partition_by_guid(&esp_guid).unwrap();
+        assert!(esp.is_efi_system());
+        assert_eq!((esp.number, esp.start, esp.size), (1, 40 * 512, 40 * 512));
+        assert_eq!(esp.name, "EFI system partition");
+
+        disk[1024 + 40] ^= 0xFF;
+        assert!(read_layout(&mut Cursor::new(disk)).is_err());
+    }
+
+    #[test]
+    fn written_gpt_reads_back_from_both_headers() {
+        for sector_size in [512u32, 4096] {
+            let disk_size = 64 * 1024 * 1024;
+            let mut esp = entry(1, 1024 * 1024, 16 * 1024 * 1024);
+            esp.type_guid = Some(ESP_TYPE_GUID);
+            esp.unique_guid = Some(Uuid::new_v4());
+            esp.name = "EFI system partition".to_string();
+            let mut data = entry(2, 17 * 1024 * 1024, 46 * 1024 * 1024);
+            data.type_guid = Some(BASIC_DATA_TYPE_GUID);
+            data.unique_guid = Some(Uuid::new_v4());
+            data.name = "Basic data partition".to_string();
+            let layout = DiskLayout {
+                style: PartitionStyle::Gpt {
+                    disk_guid: Uuid::new_v4(),
+                },
+                sector_size,
+                partitions: vec![esp, data],
+            };
+
+            // Stale bytes everywhere must not survive outside the partitions.
+            let mut disk = Cursor::new(vec![0xA5u8; disk_size as usize]);
+            write_layout(&mut disk, &layout, disk_size).unwrap();
+            assert_eq!(read_layout(&mut disk).unwrap(), layout);
+
+            let raw = disk.into_inner();
+            let sector = sector_size as usize;
+            let total_sectors = disk_size / sector_size as u64;
+            let backup = &raw[raw.len() - sector..];
+            assert_eq!(&backup[..8], GPT_SIGNATURE);
+            assert_eq!(read_u64(backup, 24), total_sectors - 1);
+            assert_eq!(read_u64(backup, 32), 1);
+            assert_eq!(read_u32(backup, 16), crc32_with_hole(&backup[..92], 16));
+            let array_lba = read_u64(backup, 72) as usize;
+            let array = &raw[array_lba * sector..array_lba * sector + 128 * 128];
+            assert_eq!(read_u32(backup, 88), crc32_with_hole(array, array.len()));
+            assert_eq!(raw[disk_size as usize - 2 * 1024 * 1024], 0xA5);
+            assert_eq!(raw[disk_size as usize - 1024 * 1024], 0);
+        }
+    }
+
+    #[test]
+    fn written_mbr_keeps_boot_code_and_reads_back() {
+        let disk_size = 32 * 1024 * 1024;
+        let mut disk = vec![0u8; disk_size as usize];
+        disk[..MBR_BOOT_CODE_LEN].fill(0x90);
+        let mut esp = entry(1, 1024 * 1024, 8 * 1024 * 1024);
+        esp.mbr_type = MBR_TYPE_EFI;
+        esp.active = true;
+        let mut system = entry(2, 9 * 1024 * 1024, 23 * 1024 * 1024);
+        system.mbr_type = MBR_TYPE_NTFS;
+        let layout = DiskLayout {
+            style: PartitionStyle::Mbr {
+                signature: 0x1234_5678,
+            },
+            sector_size: 512,
+            partitions: vec![esp, system],
+        };
+
+        let mut disk = Cursor::new(disk);
+        write_layout(&mut disk, &layout, disk_size).unwrap();
+        assert_eq!(read_layout(&mut disk).unwrap(), layout);
+        let raw = disk.into_inner();
+        assert!(raw[..MBR_BOOT_CODE_LEN].iter().all(|&b| b == 0x90));
+        // LBA 2048 is cylinder 0, head 32, sector 33.
+        assert_eq!(&raw[447..450], &[32, 33, 0]);
+    }
+
+    #[test]
+    fn rejects_partitions_outside_the_usable_area() {
+        let mut data = entry(1, 1024 * 1024, 16 * 1024 * 1024);
+        data.type_guid = Some(BASIC_DATA_TYPE_GUID);
+        let layout = DiskLayout {
+            style: PartitionStyle::Gpt {
+                disk_guid: Uuid::new_v4(),
+            },
+            sector_size: 512,
+            partitions: vec![data],
+        };
+        let disk_size = 17 * 1024 * 1024;
+        let mut disk = Cursor::new(vec![0u8; disk_size as usize]);
+        assert!(write_layout(&mut disk, &layout, disk_size).is_err());
+        assert!(write_layout(&mut disk, &layout, disk_size + 1024 * 1024).is_ok());
+
+        // Too small for the partition table itself: rejected rather than underflowing.
+        for (style, size) in [
+            (layout.style, 33 * 512),
+            (PartitionStyle::Mbr { signature: 1 }, 512),
+            (PartitionStyle::Mbr { signature: 1 }, 0),
+        ] {
+            let tiny = DiskLayout {
+                style,
+                sector_size: 512,
+                partitions: Vec::new(),
+            };
+            let err = write_layout(&mut Cursor::new(vec![0u8; size]), &tiny, size as u64)
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("too small"), "{}", err);
+        }
+    }
+}
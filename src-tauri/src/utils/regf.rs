@@ -0,0 +1,1023 @@
+//! Access to offline Windows registry hive files (regf format)
+//!
+//! Used where `reg.exe load` is not available, e.g. reading the SOFTWARE hive of a
+//! mounted Windows volume on Linux. Cells are read on demand, so multi-hundred-MB
+//! hives are never loaded into memory as a whole.
+//!
+//! Small hives (BCD stores) can also be loaded into a [`KeyTree`], edited and written
+//! back as a freshly laid out hive with [`HiveFile`].
+
+use crate::{AppError, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const BASE_BLOCK_SIZE: u64 = 4096;
+const NO_CELL: u32 = 0xFFFF_FFFF;
+const KEY_HIVE_ENTRY: u16 = 0x0004;
+const KEY_NO_DELETE: u16 = 0x0008;
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_INLINE: u32 = 0x8000_0000;
+const BIG_DATA_SEGMENT_SIZE: usize = 16344;
+const HBIN_SIZE: usize = 4096;
+const HBIN_HEADER_SIZE: usize = 32;
+/// Subkeys per "lh" list before the writer switches to an "ri" index of lists.
+const MAX_LEAF_ENTRIES: usize = 512;
+const MAX_KEY_DEPTH: u32 = 512;
+/// 100ns intervals between 1601-01-01 and 1970-01-01.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+/// Upper bound for a single cell; real hives never come close.
+const MAX_CELL_SIZE: usize = 16 * 1024 * 1024;
+
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+/// A key node ("nk" cell) of an offline hive.
+#[derive(Debug, Clone)]
+pub struct KeyNode {
+    pub name: String,
+    pub subkey_count: u32,
+    pub value_count: u32,
+    subkey_list: u32,
+    value_list: u32,
+    flags: u16,
+    last_written: u64,
+    security: u32,
+    class_offset: u32,
+    class_len: u16,
+}
+
+/// A value ("vk" cell) with its data already resolved.
+#[derive(Debug, Clone)]
+pub struct RegValue {
+    pub name: String,
+    pub data_type: u32,
+    pub data: Vec<u8>,
+}
+
+impl RegValue {
+    /// REG_SZ / REG_EXPAND_SZ data, decoded from UTF-16LE up to the first NUL.
+    pub fn as_string(&self) -> Option<String> {
+        match self.data_type {
+            REG_SZ | REG_EXPAND_SZ => Some(decode_utf16_z(&self.data)),
+            _ => None,
+        }
+    }
+
+    pub fn as_multi_string(&self) -> Option<Vec<String>> {
+        if self.data_type != REG_MULTI_SZ {
+            return None;
+        }
+        let units: Vec<u16> = self
+            .data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        Some(
+            units
+                .split(|u| *u == 0)
+                .filter(|s| !s.is_empty())
+                .map(String::from_utf16_lossy)
+                .collect(),
+        )
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.data_type {
+            REG_DWORD if self.data.len() >= 4 => Some(u32::from_le_bytes([
+                self.data[0],
+                self.data[1],
+                self.data[2],
+                self.data[3],
+            ])),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.data_type {
+            REG_QWORD if self.data.len() >= 8 => {
+                let mut raw = [0u8; 8];
+                raw.copy_from_slice(&self.data[..8]);
+                Some(u64::from_le_bytes(raw))
+            }
+            _ => self.as_u32().map(u64::from),
+        }
+    }
+}
+
+pub struct Hive<R> {
+    reader: R,
+    root_offset: u32,
+}
+
+impl Hive<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        Hive::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> Hive<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut base = [0u8; 512];
+        reader.seek(SeekFrom::Start(0)).map_err(AppError::io)?;
+        reader.read_exact(&mut base).map_err(AppError::io)?;
+        if &base[0..4] != b"regf" {
+            return Err(invalid("missing regf signature"));
+        }
+
+        let root_offset = le_u32(&base, 0x24);
+        Ok(Hive {
+            reader,
+            root_offset,
+        })
+    }
+
+    pub fn root(&mut self) -> Result<KeyNode> {
+        self.read_key(self.root_offset)
+    }
+
+    /// Resolve a backslash separated path below the root key, case-insensitively.
+    pub fn open_key(&mut self, path: &str) -> Result<Option<KeyNode>> {
+        let mut key = self.root()?;
+        for part in path.split('\\').filter(|p| !p.is_empty()) {
+            match self.subkey(&key, part)? {
+                Some(next) => key = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    pub fn subkey(&mut self, key: &KeyNode, name: &str) -> Result<Option<KeyNode>> {
+        Ok(self
+            .subkeys(key)?
+            .into_iter()
+            .find(|k| k.name.eq_ignore_ascii_case(name)))
+    }
+
+    pub fn subkeys(&mut self, key: &KeyNode) -> Result<Vec<KeyNode>> {
+        if key.subkey_count == 0 || key.subkey_list == NO_CELL {
+            return Ok(Vec::new());
+        }
+        let mut offsets = Vec::new();
+        self.collect_subkey_offsets(key.subkey_list, &mut offsets, 0)?;
+        offsets.into_iter().map(|o| self.read_key(o)).collect()
+    }
+
+    pub fn values(&mut self, key: &KeyNode) -> Result<Vec<RegValue>> {
+        if key.value_count == 0 || key.value_list == NO_CELL {
+            return Ok(Vec::new());
+        }
+        let list = self.read_cell(key.value_list)?;
+        let count = (key.value_count as usize).min(list.len() / 4);
+        (0..count)
+            .map(|i| le_u32(&list, i * 4))
+            .map(|offset| self.read_value(offset))
+            .collect()
+    }
+
+    /// Look up a value by name; an empty name selects the key's default value.
+    pub fn value(&mut self, key: &KeyNode, name: &str) -> Result<Option<RegValue>> {
+        Ok(self
+            .values(key)?
+            .into_iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Load `key` and everything below it into an owned, editable tree.
+    pub fn load_tree(&mut self, key: &KeyNode) -> Result<KeyTree> {
+        self.load_tree_at(key, 0)
+    }
+
+    fn load_tree_at(&mut self, key: &KeyNode, depth: u32) -> Result<KeyTree> {
+        if depth > MAX_KEY_DEPTH {
+            return Err(invalid("key nesting too deep"));
+        }
+        let security = if key.security == NO_CELL {
+            None
+        } else {
+            let cell = self.read_cell(key.security)?;
+            if cell.len() < 0x14 || &cell[0..2] != b"sk" {
+                return Err(invalid("expected security descriptor"));
+            }
+            let size = le_u32(&cell, 0x10) as usize;
+            Some(
+                cell.get(0x14..0x14 + size)
+                    .ok_or_else(|| invalid("security descriptor out of bounds"))?
+                    .to_vec(),
+            )
+        };
+        let class = if key.class_offset == NO_CELL || key.class_len == 0 {
+            None
+        } else {
+            let mut cell = self.read_cell(key.class_offset)?;
+            cell.truncate(key.class_len as usize);
+            Some(cell)
+        };
+
+        let values = self.values(key)?;
+        let mut subkeys = Vec::new();
+        for subkey in self.subkeys(key)? {
+            subkeys.push(self.load_tree_at(&subkey, depth + 1)?);
+        }
+        Ok(KeyTree {
+            name: key.name.clone(),
+            values,
+            subkeys,
+            flags: key.flags & !KEY_COMP_NAME,
+            last_written: key.last_written,
+            security,
+            class,
+        })
+    }
+
+    fn collect_subkey_offsets(
+        &mut self,
+        list_offset: u32,
+        out: &mut Vec<u32>,
+        depth: u8,
+    ) -> Result<()> {
+        if depth > 4 {
+            return Err(invalid("subkey index nesting too deep"));
+        }
+        let cell = self.read_cell(list_offset)?;
+        if cell.len() < 4 {
+            return Err(invalid("subkey list too short"));
+        }
+        let count = le_u16(&cell, 2) as usize;
+        match &cell[0..2] {
+            // lf / lh: (offset, hash) pairs
+            b"lf" | b"lh" => {
+                for i in 0..count {
+                    let at = 4 + i * 8;
+                    if at + 4 > cell.len() {
+                        break;
+                    }
+                    out.push(le_u32(&cell, at));
+                }
+            }
+            // li: plain offsets; ri: offsets of further lists
+            b"li" | b"ri" => {
+                let nested = &cell[0..2] == b"ri";
+                for i in 0..count {
+                    let at = 4 + i * 4;
+                    if at + 4 > cell.len() {
+                        break;
+                    }
+                    let offset = le_u32(&cell, at);
+                    if nested {
+                        self.collect_subkey_offsets(offset, out, depth + 1)?;
+                    } else {
+                        out.push(offset);
+                    }
+                }
+            }
+            _ => return Err(invalid("unknown subkey list signature")),
+        }
+        Ok(())
+    }
+
+    fn read_key(&mut self, offset: u32) -> Result<KeyNode> {
+        let cell = self.read_cell(offset)?;
+        if cell.len() < 0x4C || &cell[0..2] != b"nk" {
+            return Err(invalid("expected key node"));
+        }
+        let flags = le_u16(&cell, 0x02);
+        let name_len = le_u16(&cell, 0x48) as usize;
+        let name_bytes = cell
+            .get(0x4C..0x4C + name_len)
+            .ok_or_else(|| invalid("key name out of bounds"))?;
+        Ok(KeyNode {
+            name: decode_name(name_bytes, flags & KEY_COMP_NAME != 0),
+            subkey_count: le_u32(&cell, 0x14),
+            value_count: le_u32(&cell, 0x24),
+            subkey_list: le_u32(&cell, 0x1C),
+            value_list: le_u32(&cell, 0x28),
+            flags,
+            last_written: le_u64(&cell, 0x04),
+            security: le_u32(&cell, 0x2C),
+            class_offset: le_u32(&cell, 0x30),
+            class_len: le_u16(&cell, 0x4A),
+        })
+    }
+
+    fn read_value(&mut self, offset: u32) -> Result<RegValue> {
+        let cell = self.read_cell(offset)?;
+        if cell.len() < 0x14 || &cell[0..2] != b"vk" {
+            return Err(invalid("expected value key"));
+        }
+        let name_len = le_u16(&cell, 0x02) as usize;
+        let raw_size = le_u32(&cell, 0x04);
+        let data_offset = le_u32(&cell, 0x08);
+        let data_type = le_u32(&cell, 0x0C);
+        let flags = le_u16(&cell, 0x10);
+        let name_bytes = cell
+            .get(0x14..0x14 + name_len)
+            .ok_or_else(|| invalid("value name out of bounds"))?;
+
+        let size = (raw_size & !DATA_INLINE) as usize;
+        let data = if raw_size & DATA_INLINE != 0 {
+            // Up to four bytes live directly in the data offset field.
+            cell[0x08..0x08 + size.min(4)].to_vec()
+        } else if size == 0 {
+            Vec::new()
+        } else {
+            self.read_value_data(data_offset, size)?
+        };
+
+        Ok(RegValue {
+            name: decode_name(name_bytes, flags & VALUE_COMP_NAME != 0),
+            data_type,
+            data,
+        })
+    }
+
+    fn read_value_data(&mut self, offset: u32, size: usize) -> Result<Vec<u8>> {
+        let cell = self.read_cell(offset)?;
+        if size > BIG_DATA_SEGMENT_SIZE && cell.len() >= 8 && &cell[0..2] == b"db" {
+            let segments = le_u16(&cell, 2) as usize;
+            let list = self.read_cell(le_u32(&cell, 4))?;
+            let mut data = Vec::with_capacity(size);
+            for i in 0..segments.min(list.len() / 4) {
+                let segment = self.read_cell(le_u32(&list, i * 4))?;
+                let take = (size - data.len()).min(BIG_DATA_SEGMENT_SIZE);
+                data.extend_from_slice(&segment[..take.min(segment.len())]);
+                if data.len() >= size {
+                    break;
+                }
+            }
+            return Ok(data);
+        }
+        Ok(cell[..size.min(cell.len())].to_vec())
+    }
+
+    /// Read the payload of the cell at `offset` (relative to the first hive bin).
+    fn read_cell(&mut self, offset: u32) -> Result<Vec<u8>> {
+        if offset == NO_CELL {
+            return Err(invalid("null cell reference"));
+        }
+        let position = BASE_BLOCK_SIZE + offset as u64;
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .map_err(AppError::io)?;
+        let mut size_raw = [0u8; 4];
+        self.reader
+            .read_exact(&mut size_raw)
+            .map_err(AppError::io)?;
+        // Allocated cells store their size negated.
+        let size = i32::from_le_bytes(size_raw).unsigned_abs() as usize;
+        if !(4..=MAX_CELL_SIZE).contains(&size) {
+            return Err(invalid("cell size out of range"));
+        }
+        let mut data = vec![0u8; size - 4];
+        self.reader.read_exact(&mut data).map_err(AppError::io)?;
+        Ok(data)
+    }
+}
+
+/// An owned key with its values and subkeys, as loaded by [`Hive::load_tree`].
+#[derive(Debug, Clone, Default)]
+pub struct KeyTree {
+    pub name: String,
+    pub values: Vec<RegValue>,
+    pub subkeys: Vec<KeyTree>,
+    /// Key node flags other than the name encoding, e.g. the hive root markers.
+    flags: u16,
+    last_written: u64,
+    /// Self-relative security descriptor; new keys inherit their parent's.
+    security: Option<Vec<u8>>,
+    class: Option<Vec<u8>>,
+}
+
+impl KeyTree {
+    pub fn new(name: &str) -> Self {
+        KeyTree {
+            name: name.to_string(),
+            last_written: filetime_now(),
+            ..Default::default()
+        }
+    }
+
+    pub fn subkey(&self, name: &str) -> Option<&KeyTree> {
+        self.subkeys
+            .iter()
+            .find(|k| k.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Resolve a backslash separated path below this key, case-insensitively.
+    pub fn open(&self, path: &str) -> Option<&KeyTree> {
+        path.split('\\')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |key, part| key.subkey(part))
+    }
+
+    /// Like [`KeyTree::open`], creating missing keys along the way.
+    pub fn create(&mut self, path: &str) -> &mut KeyTree {
+        path.split('\\')
+            .filter(|p| !p.is_empty())
+            .fold(self, |key, part| key.create_subkey(part))
+    }
+
+    pub fn create_subkey(&mut self, name: &str) -> &mut KeyTree {
+        let index = match self
+            .subkeys
+            .iter()
+            .position(|k| k.name.eq_ignore_ascii_case(name))
+        {
+            Some(index) => index,
+            None => {
+                let mut key = KeyTree::new(name);
+                key.security = self.security.clone();
+                self.subkeys.push(key);
+                self.last_written = filetime_now();
+                self.subkeys.len() - 1
+            }
+        };
+        &mut self.subkeys[index]
+    }
+
+    #[cfg(test)]
+    pub fn remove_subkey(&mut self, name: &str) -> bool {
+        let before = self.subkeys.len();
+        self.subkeys.retain(|k| !k.name.eq_ignore_ascii_case(name));
+        self.subkeys.len() != before
+    }
+
+    pub fn value(&self, name: &str) -> Option<&RegValue> {
+        self.values
+            .iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set_value(&mut self, name: &str, data_type: u32, data: Vec<u8>) {
+        let value = RegValue {
+            name: name.to_string(),
+            data_type,
+            data,
+        };
+        match self
+            .values
+            .iter_mut()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+        {
+            Some(existing) => *existing = value,
+            None => self.values.push(value),
+        }
+        self.last_written = filetime_now();
+    }
+
+    fn for_each_security<'a>(&'a self, f: &mut impl FnMut(&'a [u8])) {
+        if let Some(security) = &self.security {
+            f(security);
+        }
+        for subkey in &self.subkeys {
+            subkey.for_each_security(f);
+        }
+    }
+}
+
+/// A whole hive held in memory for editing. Writing lays the cells out anew, so
+/// free space and fragmentation of the original file are not carried over.
+#[derive(Debug, Clone)]
+pub struct HiveFile {
+    base_block: Vec<u8>,
+    pub root: KeyTree,
+}
+
+impl HiveFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        Self::read(BufReader::new(file))
+    }
+
+    pub fn read<R: Read + Seek>(mut reader: R) -> Result<Self> {
+        let mut base_block = vec![0u8; BASE_BLOCK_SIZE as usize];
+        reader.seek(SeekFrom::Start(0)).map_err(AppError::io)?;
+        reader.read_exact(&mut base_block).map_err(AppError::io)?;
+        let mut hive = Hive::new(reader)?;
+        let root_node = hive.root()?;
+        let root = hive.load_tree(&root_node)?;
+        Ok(HiveFile { base_block, root })
+    }
+
+    /// Serialize into a complete hive file image.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = CellWriter::default();
+
+        // Identical descriptors share one reference-counted "sk" cell.
+        let mut descriptors: Vec<(&[u8], u32)> = Vec::new();
+        self.root.for_each_security(&mut |sd| match descriptors
+            .iter_mut()
+            .find(|(known, _)| *known == sd)
+        {
+            Some((_, refs)) => *refs += 1,
+            None => descriptors.push((sd, 1)),
+        });
+        let sk_offsets: Vec<u32> = descriptors
+            .iter()
+            .map(|(sd, refs)| {
+                let mut cell = vec![0u8; 0x14];
+                cell[0..2].copy_from_slice(b"sk");
+                cell[0x0C..0x10].copy_from_slice(&refs.to_le_bytes());
+                cell[0x10..0x14].copy_from_slice(&(sd.len() as u32).to_le_bytes());
+                cell.extend_from_slice(sd);
+                writer.alloc(&cell)
+            })
+            .collect();
+        // The security cells form a circular doubly linked list.
+        for (i, &offset) in sk_offsets.iter().enumerate() {
+            let count = sk_offsets.len();
+            writer.patch_u32(offset, 0x04, sk_offsets[(i + 1) % count]);
+            writer.patch_u32(offset, 0x08, sk_offsets[(i + count - 1) % count]);
+        }
+        let security: HashMap<&[u8], u32> = descriptors
+            .iter()
+            .zip(&sk_offsets)
+            .map(|((sd, _), &offset)| (*sd, offset))
+            .collect();
+
+        let root_offset = writer.write_key(&self.root, NO_CELL, true, &security);
+        let bins = writer.finish();
+
+        let mut base = self.base_block.clone();
+        let sequence = le_u32(&base, 0x04).max(le_u32(&base, 0x08)).wrapping_add(1);
+        base[0x04..0x08].copy_from_slice(&sequence.to_le_bytes());
+        base[0x08..0x0C].copy_from_slice(&sequence.to_le_bytes());
+        base[0x0C..0x14].copy_from_slice(&filetime_now().to_le_bytes());
+        base[0x24..0x28].copy_from_slice(&root_offset.to_le_bytes());
+        base[0x28..0x2C].copy_from_slice(&(bins.len() as u32).to_le_bytes());
+        let checksum = match (0..0x1FC)
+            .step_by(4)
+            .fold(0u32, |acc, at| acc ^ le_u32(&base, at))
+        {
+            0 => 1,
+            0xFFFF_FFFF => 0xFFFF_FFFE,
+            sum => sum,
+        };
+        base[0x1FC..0x200].copy_from_slice(&checksum.to_le_bytes());
+
+        base.extend_from_slice(&bins);
+        base
+    }
+
+    /// Write the hive to `path`, replacing it. Transaction logs of the previous contents
+    /// are removed, since Windows would otherwise replay them over the new file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // Write a temp file next to the store and rename it over, so a crash or a full
+        // disk never leaves a truncated store behind.
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp = path.with_file_name(tmp_name);
+        let written = File::create(&tmp)
+            .and_then(|mut file| {
+                file.write_all(&self.to_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&tmp, path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(AppError::io(e));
+        }
+
+        if let Some(name) = path.file_name() {
+            for suffix in [".LOG", ".LOG1", ".LOG2"] {
+                let mut log_name = name.to_os_string();
+                log_name.push(suffix);
+                let _ = std::fs::remove_file(path.with_file_name(log_name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lays out cells in hive bins. Offsets are relative to the first bin, as in the file.
+#[derive(Default)]
+struct CellWriter {
+    data: Vec<u8>,
+    bin_end: usize,
+}
+
+impl CellWriter {
+    fn alloc(&mut self, payload: &[u8]) -> u32 {
+        let size = (payload.len() + 4).div_ceil(8) * 8;
+        if self.data.len() + size > self.bin_end {
+            self.close_bin();
+            self.open_bin(size);
+        }
+        let offset = self.data.len();
+        self.data.extend_from_slice(&(-(size as i32)).to_le_bytes());
+        self.data.extend_from_slice(payload);
+        self.data.resize(offset + size, 0);
+        offset as u32
+    }
+
+    fn patch_u32(&mut self, cell: u32, at: usize, value: u32) {
+        let pos = cell as usize + 4 + at;
+        self.data[pos..pos + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn open_bin(&mut self, cell_size: usize) {
+        let start = self.data.len();
+        let size = (HBIN_HEADER_SIZE + cell_size).div_ceil(HBIN_SIZE) * HBIN_SIZE;
+        self.data.extend_from_slice(b"hbin");
+        self.data.extend_from_slice(&(start as u32).to_le_bytes());
+        self.data.extend_from_slice(&(size as u32).to_le_bytes());
+        self.data.resize(start + HBIN_HEADER_SIZE, 0);
+        self.bin_end = start + size;
+    }
+
+    /// Turn the unused tail of the current bin into one free cell.
+    fn close_bin(&mut self) {
+        let free = self.bin_end - self.data.len();
+        if free > 0 {
+            let start = self.data.len();
+            self.data.extend_from_slice(&(free as i32).to_le_bytes());
+            self.data.resize(start + free, 0);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.data.is_empty() {
+            self.open_bin(0);
+        }
+        self.close_bin();
+        self.data
+    }
+
+    fn write_key(
+        &mut self,
+        key: &KeyTree,
+        parent: u32,
+        root: bool,
+        security: &HashMap<&[u8], u32>,
+    ) -> u32 {
+        let (name, compressed) = encode_name(&key.name);
+        let mut flags = key.flags;
+        if compressed {
+            flags |= KEY_COMP_NAME;
+        }
+        if root {
+            flags |= KEY_HIVE_ENTRY | KEY_NO_DELETE;
+        }
+        let utf16_len = |s: &str| s.encode_utf16().count() as u32 * 2;
+
+        let mut nk = vec![0u8; 0x4C];
+        nk[0..2].copy_from_slice(b"nk");
+        nk[0x02..0x04].copy_from_slice(&flags.to_le_bytes());
+        nk[0x04..0x0C].copy_from_slice(&key.last_written.to_le_bytes());
+        nk[0x10..0x14].copy_from_slice(&parent.to_le_bytes());
+        nk[0x14..0x18].copy_from_slice(&(key.subkeys.len() as u32).to_le_bytes());
+        for field in [0x1C, 0x20, 0x28, 0x2C, 0x30] {
+            nk[field..field + 4].copy_from_slice(&NO_CELL.to_le_bytes());
+        }
+        nk[0x24..0x28].copy_from_slice(&(key.values.len() as u32).to_le_bytes());
+        let max_subkey_name = key.subkeys.iter().map(|k| utf16_len(&k.name)).max();
+        let max_value_name = key.values.iter().map(|v| utf16_len(&v.name)).max();
+        let max_value_data = key.values.iter().map(|v| v.data.len() as u32).max();
+        let max_class = key
+            .subkeys
+            .iter()
+            .filter_map(|k| k.class.as_ref().map(|c| c.len() as u32))
+            .max();
+        nk[0x34..0x38].copy_from_slice(&max_subkey_name.unwrap_or(0).to_le_bytes());
+        nk[0x38..0x3C].copy_from_slice(&max_class.unwrap_or(0).to_le_bytes());
+        nk[0x3C..0x40].copy_from_slice(&max_value_name.unwrap_or(0).to_le_bytes());
+        nk[0x40..0x44].copy_from_slice(&max_value_data.unwrap_or(0).to_le_bytes());
+        nk[0x48..0x4A].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        nk.extend_from_slice(&name);
+        let offset = self.alloc(&nk);
+
+        if let Some(sk) = key.security.as_deref().and_then(|sd| security.get(sd)) {
+            self.patch_u32(offset, 0x2C, *sk);
+        }
+        if let Some(class) = &key.class {
+            let class_offset = self.alloc(class);
+            self.patch_u32(offset, 0x30, class_offset);
+            let pos = offset as usize + 4 + 0x4A;
+            self.data[pos..pos + 2].copy_from_slice(&(class.len() as u16).to_le_bytes());
+        }
+
+        if !key.values.is_empty() {
+            let value_offsets: Vec<u8> = key
+                .values
+                .iter()
+                .flat_map(|v| self.write_value(v).to_le_bytes())
+                .collect();
+            let list = self.alloc(&value_offsets);
+            self.patch_u32(offset, 0x28, list);
+        }
+
+        if !key.subkeys.is_empty() {
+            // Lookups binary-search the list, so it must be sorted by upper-cased name.
+            let mut sorted: Vec<&KeyTree> = key.subkeys.iter().collect();
+            sorted.sort_by_cached_key(|k| k.name.to_uppercase().encode_utf16().collect::<Vec<_>>());
+            let children: Vec<(u32, u32)> = sorted
+                .iter()
+                .map(|k| {
+                    let child = self.write_key(k, offset, false, security);
+                    (child, name_hash(&k.name))
+                })
+                .collect();
+            let list = self.write_subkey_list(&children);
+            self.patch_u32(offset, 0x1C, list);
+        }
+        offset
+    }
+
+    fn write_subkey_list(&mut self, children: &[(u32, u32)]) -> u32 {
+        let leaf = |writer: &mut CellWriter, chunk: &[(u32, u32)]| {
+            let mut cell = Vec::with_capacity(4 + chunk.len() * 8);
+            cell.extend_from_slice(b"lh");
+            cell.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            for (child, hash) in chunk {
+                cell.extend_from_slice(&child.to_le_bytes());
+                cell.extend_from_slice(&hash.to_le_bytes());
+            }
+            writer.alloc(&cell)
+        };
+        if children.len() <= MAX_LEAF_ENTRIES {
+            return leaf(self, children);
+        }
+        let leaves: Vec<u32> = children
+            .chunks(MAX_LEAF_ENTRIES)
+            .map(|chunk| leaf(self, chunk))
+            .collect();
+        let mut cell = Vec::with_capacity(4 + leaves.len() * 4);
+        cell.extend_from_slice(b"ri");
+        cell.extend_from_slice(&(leaves.len() as u16).to_le_bytes());
+        for offset in leaves {
+            cell.extend_from_slice(&offset.to_le_bytes());
+        }
+        self.alloc(&cell)
+    }
+
+    fn write_value(&mut self, value: &RegValue) -> u32 {
+        let (name, compressed) = encode_name(&value.name);
+        let size = value.data.len();
+        let mut vk = vec![0u8; 0x14];
+        vk[0..2].copy_from_slice(b"vk");
+        vk[0x02..0x04].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        vk[0x0C..0x10].copy_from_slice(&value.data_type.to_le_bytes());
+        vk[0x10..0x12]
+            .copy_from_slice(&(if compressed { VALUE_COMP_NAME } else { 0 }).to_le_bytes());
+        vk.extend_from_slice(&name);
+
+        if size <= 4 {
+            vk[0x04..0x08].copy_from_slice(&(size as u32 | DATA_INLINE).to_le_bytes());
+            vk[0x08..0x08 + size].copy_from_slice(&value.data);
+        } else {
+            let data_offset = if size > BIG_DATA_SEGMENT_SIZE {
+                let segments: Vec<u8> = value
+                    .data
+                    .chunks(BIG_DATA_SEGMENT_SIZE)
+                    .flat_map(|segment| self.alloc(segment).to_le_bytes())
+                    .collect();
+                let list = self.alloc(&segments);
+                let mut db = Vec::with_capacity(8);
+                db.extend_from_slice(b"db");
+                db.extend_from_slice(&((segments.len() / 4) as u16).to_le_bytes());
+                db.extend_from_slice(&list.to_le_bytes());
+                self.alloc(&db)
+            } else {
+                self.alloc(&value.data)
+            };
+            vk[0x04..0x08].copy_from_slice(&(size as u32).to_le_bytes());
+            vk[0x08..0x0C].copy_from_slice(&data_offset.to_le_bytes());
+        }
+        self.alloc(&vk)
+    }
+}
+
+/// Names that fit in Latin-1 are stored one byte per character ("compressed").
+fn encode_name(name: &str) -> (Vec<u8>, bool) {
+    if name.chars().all(|c| (c as u32) < 0x100) {
+        (name.chars().map(|c| c as u8).collect(), true)
+    } else {
+        (
+            name.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            false,
+        )
+    }
+}
+
+/// Hash stored next to each entry of an "lh" subkey list.
+fn name_hash(name: &str) -> u32 {
+    name.to_uppercase().encode_utf16().fold(0u32, |hash, unit| {
+        hash.wrapping_mul(37).wrapping_add(unit as u32)
+    })
+}
+
+fn filetime_now() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    FILETIME_UNIX_EPOCH + since_epoch.as_nanos() as u64 / 100
+}
+
+fn invalid(detail: &str) -> AppError {
+    AppError::SystemError(format!("Invalid registry hive: {}", detail))
+}
+
+fn le_u16(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn le_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn le_u64(buf: &[u8], at: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(raw)
+}
+
+fn decode_name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        // "Compressed" names are Latin-1, one byte per character.
+        bytes.iter().map(|b| *b as char).collect()
+    } else {
+        decode_utf16_z(bytes)
+    }
+}
+
+fn decode_utf16_z(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|u| *u != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SOFTWARE: &[u8] = include_bytes!("../../tests/fixtures/regf/software_min.hiv");
+
+    fn hive() -> Hive<Cursor<&'static [u8]>> {
+        Hive::new(Cursor::new(SOFTWARE)).unwrap()
+    }
+
+    #[test]
+    fn rejects_non_hive_data() {
+        assert!(Hive::new(Cursor::new(vec![0u8; 4096])).is_err());
+    }
+
+    #[test]
+    fn opens_nested_key_case_insensitively() {
+        let mut hive = hive();
+        let key = hive
+            .open_key("microsoft\\WINDOWS NT\\CurrentVersion")
+            .unwrap()
+            .unwrap();
+        assert_eq!(key.name, "CurrentVersion");
+        assert!(hive.open_key("Microsoft\\Missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_typed_values() {
+        let mut hive = hive();
+        let key = hive
+            .open_key("Microsoft\\Windows NT\\CurrentVersion")
+            .unwrap()
+            .unwrap();
+
+        let product = hive.value(&key, "ProductName").unwrap().unwrap();
+        assert_eq!(product.as_string().as_deref(), Some("Windows 10 Pro"));
+
+        let build = hive.value(&key, "CurrentBuildNumber").unwrap().unwrap();
+        assert_eq!(build.as_string().as_deref(), Some("22631"));
+
+        let major = hive
+            .value(&key, "CurrentMajorVersionNumber")
+            .unwrap()
+            .unwrap();
+        assert_eq!(major.as_u32(), Some(10));
+
+        let install_time = hive.value(&key, "InstallTime").unwrap().unwrap();
+        assert_eq!(install_time.as_u64(), Some(133_476_000_000_000_000));
+
+        let langs = hive.value(&key, "Languages").unwrap().unwrap();
+        assert_eq!(
+            langs.as_multi_string().unwrap(),
+            vec!["en-US".to_string(), "zh-CN".to_string()]
+        );
+    }
+
+    #[test]
+    fn rewritten_hive_keeps_keys_and_values() {
+        let mut file = HiveFile::read(Cursor::new(SOFTWARE)).unwrap();
+        let bytes = file.to_bytes();
+        assert_eq!((bytes.len() - BASE_BLOCK_SIZE as usize) % HBIN_SIZE, 0);
+        let checksum = (0..0x1FC)
+            .step_by(4)
+            .fold(0u32, |acc, at| acc ^ le_u32(&bytes, at));
+        assert_eq!(le_u32(&bytes, 0x1FC), checksum);
+
+        let mut hive = Hive::new(Cursor::new(bytes)).unwrap();
+        let key = hive
+            .open_key("Microsoft\\Windows NT\\CurrentVersion")
+            .unwrap()
+            .unwrap();
+        let product = hive.value(&key, "ProductName").unwrap().unwrap();
+        assert_eq!(product.as_string().as_deref(), Some("Windows 10 Pro"));
+        assert_eq!(hive.values(&key).unwrap().len(), key.value_count as usize);
+
+        // Edits: a new nested key, a replaced value and data large enough for "db" cells.
+        let big: Vec<u8> = (0..40_000u32).map(|i| i as u8).collect();
+        let created = file.root.create("Microsoft\\Zeta\\Alpha");
+        created.set_value("Blob", REG_BINARY, big.clone());
+        created.set_value(
+            "Name",
+            REG_SZ,
+            "ab\0".encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        );
+        file.root
+            .create("Microsoft\\Windows NT\\CurrentVersion")
+            .set_value(
+                "CurrentMajorVersionNumber",
+                REG_DWORD,
+                11u32.to_le_bytes().to_vec(),
+            );
+
+        let mut hive = Hive::new(Cursor::new(file.to_bytes())).unwrap();
+        let alpha = hive.open_key("MICROSOFT\\zeta\\alpha").unwrap().unwrap();
+        assert_eq!(hive.value(&alpha, "Blob").unwrap().unwrap().data, big);
+        assert_eq!(
+            hive.value(&alpha, "Name")
+                .unwrap()
+                .unwrap()
+                .as_string()
+                .as_deref(),
+            Some("ab")
+        );
+        let key = hive
+            .open_key("Microsoft\\Windows NT\\CurrentVersion")
+            .unwrap()
+            .unwrap();
+        let major = hive
+            .value(&key, "CurrentMajorVersionNumber")
+            .unwrap()
+            .unwrap();
+        assert_eq!(major.as_u32(), Some(11));
+
+        let microsoft = hive.open_key("Microsoft").unwrap().unwrap();
+        let names: Vec<String> = hive
+            .subkeys(&microsoft)
+            .unwrap()
+            .into_iter()
+            .map(|k| k.name)
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort_by_key(|n| n.to_uppercase());
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn save_replaces_the_store_and_its_logs() {
+        let dir = std::env::temp_dir().join(format!("wtg-regf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BCD");
+        std::fs::write(&path, b"old").unwrap();
+        std::fs::write(dir.join("BCD.LOG1"), b"log").unwrap();
+
+        let file = HiveFile::read(Cursor::new(SOFTWARE)).unwrap();
+        file.save(&path).unwrap();
+        let mut saved = Hive::new(Cursor::new(std::fs::read(&path).unwrap())).unwrap();
+        assert!(saved.open_key("Microsoft").unwrap().is_some());
+        assert!(!dir.join("BCD.LOG1").exists());
+        assert!(!dir.join("BCD.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lists_subkeys_and_values() {
+        let mut hive = hive();
+        let root = hive.root().unwrap();
+        let names: Vec<String> = hive
+            .subkeys(&root)
+            .unwrap()
+            .into_iter()
+            .map(|k| k.name)
+            .collect();
+        assert_eq!(names, vec!["Classes".to_string(), "Microsoft".to_string()]);
+
+        let key = hive
+            .open_key("Microsoft\\Windows NT\\CurrentVersion")
+            .unwrap()
+            .unwrap();
+        assert_eq!(hive.values(&key).unwrap().len(), key.value_count as usize);
+    }
+}
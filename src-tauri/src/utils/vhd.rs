@@ -0,0 +1,874 @@
+//! VHD (Virtual Hard Disk v1) container creation and inspection
+//!
+//! Implements the parts of the VHD specification needed to create fixed and dynamic
+//! disks and to inspect existing ones: the 512-byte footer, the dynamic disk header,
+//! the block allocation table (BAT) and parent locators of differencing disks. Block
+//! contents are never touched; the disk is partitioned and formatted after attaching.
+
+use crate::models::VirtualDiskInfo;
+use crate::utils::vhdx;
+use crate::{AppError, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub const SECTOR_SIZE: u64 = 512;
+pub const FOOTER_SIZE: u64 = 512;
+/// Largest virtual size the format supports (2040 GiB, limited by the CHS geometry).
+pub const MAX_VIRTUAL_SIZE: u64 = 2040 * 1024 * 1024 * 1024;
+pub const DEFAULT_BLOCK_SIZE: u32 = 2 * 1024 * 1024;
+
+const FOOTER_COOKIE: &[u8; 8] = b"conectix";
+const DYNAMIC_COOKIE: &[u8; 8] = b"cxsparse";
+const DYNAMIC_HEADER_SIZE: usize = 1024;
+const FEATURES_RESERVED: u32 = 0x0000_0002;
+const FORMAT_VERSION: u32 = 0x0001_0000;
+const CREATOR_APP: &[u8; 4] = b"wtga";
+const CREATOR_VERSION: u32 = 0x0001_0000;
+const NO_OFFSET: u64 = u64::MAX;
+const UNUSED_BAT_ENTRY: u32 = u32::MAX;
+/// Seconds between the Unix epoch and the VHD epoch (2000-01-01 00:00:00 UTC).
+const VHD_EPOCH_OFFSET: u64 = 946_684_800;
+/// Upper bound for a BAT read into memory (covers 2040 GiB at 512 KiB blocks).
+const MAX_BAT_ENTRIES: u32 = 4 * 1024 * 1024;
+const MAX_LOCATOR_SIZE: u32 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VhdDiskType {
+    Fixed,
+    Dynamic,
+    Differencing,
+}
+
+impl VhdDiskType {
+    fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            2 => Some(VhdDiskType::Fixed),
+            3 => Some(VhdDiskType::Dynamic),
+            4 => Some(VhdDiskType::Differencing),
+            _ => None,
+        }
+    }
+
+    fn raw(self) -> u32 {
+        match self {
+            VhdDiskType::Fixed => 2,
+            VhdDiskType::Dynamic => 3,
+            VhdDiskType::Differencing => 4,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VhdDiskType::Fixed => "fixed",
+            VhdDiskType::Dynamic => "dynamic",
+            VhdDiskType::Differencing => "differencing",
+        }
+    }
+}
+
+/// CHS geometry stored in the footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskGeometry {
+    pub cylinders: u16,
+    pub heads: u8,
+    pub sectors_per_track: u8,
+}
+
+impl DiskGeometry {
+    /// Geometry for `size` bytes, following the algorithm in the VHD specification.
+    pub fn for_size(size: u64) -> Self {
+        let mut total_sectors = size / SECTOR_SIZE;
+        if total_sectors > 65535 * 16 * 255 {
+            total_sectors = 65535 * 16 * 255;
+        }
+
+        let (sectors_per_track, heads, cylinder_times_heads) = if total_sectors >= 65535 * 16 * 63 {
+            (255, 16, total_sectors / 255)
+        } else {
+            let mut spt = 17;
+            let mut cth = total_sectors / spt;
+            let mut heads = cth.div_ceil(1024).max(4);
+            if cth >= heads * 1024 || heads > 16 {
+                spt = 31;
+                heads = 16;
+                cth = total_sectors / spt;
+            }
+            if cth >= heads * 1024 {
+                spt = 63;
+                heads = 16;
+                cth = total_sectors / spt;
+            }
+            (spt, heads, cth)
+        };
+
+        DiskGeometry {
+            cylinders: (cylinder_times_heads / heads) as u16,
+            heads: heads as u8,
+            sectors_per_track: sectors_per_track as u8,
+        }
+    }
+}
+
+/// The 512-byte footer at the end of every VHD (and copied to offset 0 of dynamic ones).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdFooter {
+    pub features: u32,
+    pub format_version: u32,
+    /// Offset of the dynamic disk header, `u64::MAX` for fixed disks
+    pub data_offset: u64,
+    /// Seconds since 2000-01-01 00:00:00 UTC
+    pub timestamp: u32,
+    pub creator_app: [u8; 4],
+    pub creator_version: u32,
+    pub creator_host_os: [u8; 4],
+    pub original_size: u64,
+    pub current_size: u64,
+    pub geometry: DiskGeometry,
+    pub disk_type: VhdDiskType,
+    pub unique_id: [u8; 16],
+    pub saved_state: bool,
+}
+
+impl VhdFooter {
+    fn new(size: u64, disk_type: VhdDiskType) -> Self {
+        VhdFooter {
+            features: FEATURES_RESERVED,
+            format_version: FORMAT_VERSION,
+            data_offset: if disk_type == VhdDiskType::Fixed {
+                NO_OFFSET
+            } else {
+                FOOTER_SIZE
+            },
+            timestamp: vhd_timestamp_now(),
+            creator_app: *CREATOR_APP,
+            creator_version: CREATOR_VERSION,
+            creator_host_os: creator_host_os(),
+            original_size: size,
+            current_size: size,
+            geometry: DiskGeometry::for_size(size),
+            disk_type,
+            unique_id: *uuid::Uuid::new_v4().as_bytes(),
+            saved_state: false,
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        if buf.len() < FOOTER_SIZE as usize || &buf[..8] != FOOTER_COOKIE {
+            return Err(AppError::ImageError(
+                "Not a VHD file: footer cookie missing".to_string(),
+            ));
+        }
+        let stored = read_u32(buf, 64);
+        let computed = checksum(&buf[..FOOTER_SIZE as usize], 64);
+        if stored != computed {
+            return Err(AppError::ImageError(format!(
+                "VHD footer checksum mismatch (stored {:#010x}, computed {:#010x})",
+                stored, computed
+            )));
+        }
+        let raw_type = read_u32(buf, 60);
+        let disk_type = VhdDiskType::from_raw(raw_type).ok_or_else(|| {
+            AppError::ImageError(format!("Unsupported VHD disk type {}", raw_type))
+        })?;
+
+        let mut unique_id = [0u8; 16];
+        unique_id.copy_from_slice(&buf[68..84]);
+        Ok(VhdFooter {
+            features: read_u32(buf, 8),
+            format_version: read_u32(buf, 12),
+            data_offset: read_u64(buf, 16),
+            timestamp: read_u32(buf, 24),
+            creator_app: [buf[28], buf[29], buf[30], buf[31]],
+            creator_version: read_u32(buf, 32),
+            creator_host_os: [buf[36], buf[37], buf[38], buf[39]],
+            original_size: read_u64(buf, 40),
+            current_size: read_u64(buf, 48),
+            geometry: DiskGeometry {
+                cylinders: u16::from_be_bytes([buf[56], buf[57]]),
+                heads: buf[58],
+                sectors_per_track: buf[59],
+            },
+            disk_type,
+            unique_id,
+            saved_state: buf[84] != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; FOOTER_SIZE as usize] {
+        let mut buf = [0u8; FOOTER_SIZE as usize];
+        buf[..8].copy_from_slice(FOOTER_COOKIE);
+        buf[8..12].copy_from_slice(&self.features.to_be_bytes());
+        buf[12..16].copy_from_slice(&self.format_version.to_be_bytes());
+        buf[16..24].copy_from_slice(&self.data_offset.to_be_bytes());
+        buf[24..28].copy_from_slice(&self.timestamp.to_be_bytes());
+        buf[28..32].copy_from_slice(&self.creator_app);
+        buf[32..36].copy_from_slice(&self.creator_version.to_be_bytes());
+        buf[36..40].copy_from_slice(&self.creator_host_os);
+        buf[40..48].copy_from_slice(&self.original_size.to_be_bytes());
+        buf[48..56].copy_from_slice(&self.current_size.to_be_bytes());
+        buf[56..58].copy_from_slice(&self.geometry.cylinders.to_be_bytes());
+        buf[58] = self.geometry.heads;
+        buf[59] = self.geometry.sectors_per_track;
+        buf[60..64].copy_from_slice(&self.disk_type.raw().to_be_bytes());
+        buf[68..84].copy_from_slice(&self.unique_id);
+        buf[84] = u8::from(self.saved_state);
+        let sum = checksum(&buf, 64);
+        buf[64..68].copy_from_slice(&sum.to_be_bytes());
+        buf
+    }
+}
+
+/// Where a differencing disk finds its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParentLocator {
+    /// "W2ku" (absolute path), "W2ru" (relative path), "Wi2k", "Wi2r", "Mac ", "MacX"
+    pub platform_code: [u8; 4],
+    pub data_space: u32,
+    pub data_length: u32,
+    pub data_offset: u64,
+}
+
+/// Header of dynamic and differencing disks, pointed to by the footer's data offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicHeader {
+    pub table_offset: u64,
+    pub header_version: u32,
+    pub max_table_entries: u32,
+    pub block_size: u32,
+    pub parent_unique_id: [u8; 16],
+    pub parent_timestamp: u32,
+    pub parent_name: String,
+    pub parent_locators: Vec<ParentLocator>,
+}
+
+impl DynamicHeader {
+    fn new(virtual_size: u64, block_size: u32) -> Self {
+        DynamicHeader {
+            table_offset: FOOTER_SIZE + DYNAMIC_HEADER_SIZE as u64,
+            header_version: FORMAT_VERSION,
+            max_table_entries: virtual_size.div_ceil(block_size as u64) as u32,
+            block_size,
+            parent_unique_id: [0; 16],
+            parent_timestamp: 0,
+            parent_name: String::new(),
+            parent_locators: Vec::new(),
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        if buf.len() < DYNAMIC_HEADER_SIZE || &buf[..8] != DYNAMIC_COOKIE {
+            return Err(AppError::ImageError(
+                "VHD dynamic disk header is missing or damaged".to_string(),
+            ));
+        }
+        let stored = read_u32(buf, 36);
+        let computed = checksum(&buf[..DYNAMIC_HEADER_SIZE], 36);
+        if stored != computed {
+            return Err(AppError::ImageError(format!(
+                "VHD dynamic header checksum mismatch (stored {:#010x}, computed {:#010x})",
+                stored, computed
+            )));
+        }
+
+        let block_size = read_u32(buf, 32);
+        if block_size < SECTOR_SIZE as u32 || !block_size.is_power_of_two() {
+            return Err(AppError::ImageError(format!(
+                "Invalid VHD block size {}",
+                block_size
+            )));
+        }
+
+        let mut parent_unique_id = [0u8; 16];
+        parent_unique_id.copy_from_slice(&buf[40..56]);
+        let parent_name = decode_utf16(&buf[64..576], false);
+
+        let parent_locators = (0..8)
+            .map(|i| 576 + i * 24)
+            .filter(|&at| buf[at..at + 4] != [0; 4])
+            .map(|at| ParentLocator {
+                platform_code: [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]],
+                data_space: read_u32(buf, at + 4),
+                data_length: read_u32(buf, at + 8),
+                data_offset: read_u64(buf, at + 16),
+            })
+            .collect();
+
+        Ok(DynamicHeader {
+            table_offset: read_u64(buf, 16),
+            header_version: read_u32(buf, 24),
+            max_table_entries: read_u32(buf, 28),
+            block_size,
+            parent_unique_id,
+            parent_timestamp: read_u32(buf, 56),
+            parent_name,
+            parent_locators,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; DYNAMIC_HEADER_SIZE] {
+        let mut buf = [0u8; DYNAMIC_HEADER_SIZE];
+        buf[..8].copy_from_slice(DYNAMIC_COOKIE);
+        buf[8..16].copy_from_slice(&NO_OFFSET.to_be_bytes());
+        buf[16..24].copy_from_slice(&self.table_offset.to_be_bytes());
+        buf[24..28].copy_from_slice(&self.header_version.to_be_bytes());
+        buf[28..32].copy_from_slice(&self.max_table_entries.to_be_bytes());
+        buf[32..36].copy_from_slice(&self.block_size.to_be_bytes());
+        buf[40..56].copy_from_slice(&self.parent_unique_id);
+        buf[56..60].copy_from_slice(&self.parent_timestamp.to_be_bytes());
+        for (i, unit) in self.parent_name.encode_utf16().take(256).enumerate() {
+            buf[64 + i * 2..66 + i * 2].copy_from_slice(&unit.to_be_bytes());
+        }
+        for (i, locator) in self.parent_locators.iter().take(8).enumerate() {
+            let at = 576 + i * 24;
+            buf[at..at + 4].copy_from_slice(&locator.platform_code);
+            buf[at + 4..at + 8].copy_from_slice(&locator.data_space.to_be_bytes());
+            buf[at + 8..at + 12].copy_from_slice(&locator.data_length.to_be_bytes());
+            buf[at + 16..at + 24].copy_from_slice(&locator.data_offset.to_be_bytes());
+        }
+        let sum = checksum(&buf, 36);
+        buf[36..40].copy_from_slice(&sum.to_be_bytes());
+        buf
+    }
+
+    /// Bytes occupied by the BAT, padded to a sector boundary.
+    fn table_size(&self) -> u64 {
+        (self.max_table_entries as u64 * 4).div_ceil(SECTOR_SIZE) * SECTOR_SIZE
+    }
+
+    /// File offset just past the BAT; a table offset near `u64::MAX` is rejected.
+    fn table_end(&self) -> Result<u64> {
+        self.table_offset
+            .checked_add(self.table_size())
+            .ok_or_else(|| {
+                AppError::InvalidParameter(format!(
+                    "VHD block table offset {} overflows",
+                    self.table_offset
+                ))
+            })
+    }
+
+    /// Size of the per-block sector bitmap that precedes every data block.
+    fn bitmap_size(&self) -> u64 {
+        (self.block_size as u64 / SECTOR_SIZE)
+            .div_ceil(8)
+            .div_ceil(SECTOR_SIZE)
+            * SECTOR_SIZE
+    }
+}
+
+/// An opened VHD with validated footer and (for dynamic disks) header.
+pub struct Vhd<R> {
+    reader: R,
+    footer: VhdFooter,
+    dynamic: Option<DynamicHeader>,
+    file_size: u64,
+}
+
+impl Vhd<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> Vhd<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let file_size = reader.seek(SeekFrom::End(0)).map_err(AppError::io)?;
+        if file_size < FOOTER_SIZE {
+            return Err(AppError::ImageError(
+                "File is too small to be a VHD".to_string(),
+            ));
+        }
+
+        let mut buf = [0u8; FOOTER_SIZE as usize];
+        reader
+            .seek(SeekFrom::Start(file_size - FOOTER_SIZE))
+            .map_err(AppError::io)?;
+        reader.read_exact(&mut buf).map_err(AppError::io)?;
+        let footer = match VhdFooter::parse(&buf) {
+            Ok(footer) => footer,
+            Err(err) => {
+                // A damaged trailing footer with an intact copy at offset 0 means the
+                // file was truncated or partially overwritten, not that it isn't a VHD.
+                reader.seek(SeekFrom::Start(0)).map_err(AppError::io)?;
+                reader.read_exact(&mut buf).map_err(AppError::io)?;
+                if VhdFooter::parse(&buf).is_ok() {
+                    return Err(AppError::ImageError(
+                        "VHD footer at the end of the file is damaged or the file is truncated"
+                            .to_string(),
+                    ));
+                }
+                return Err(err);
+            }
+        };
+
+        if footer.current_size == 0 || footer.current_size % SECTOR_SIZE != 0 {
+            return Err(AppError::ImageError(format!(
+                "Invalid VHD virtual size {}",
+                footer.current_size
+            )));
+        }
+
+        let dynamic = if footer.disk_type == VhdDiskType::Fixed {
+            if file_size - FOOTER_SIZE < footer.current_size {
+                return Err(AppError::ImageError(format!(
+                    "Fixed VHD is truncated: {} bytes of data for a {} byte disk",
+                    file_size - FOOTER_SIZE,
+                    footer.current_size
+                )));
+            }
+            None
+        } else {
+            if footer
+                .data_offset
+                .checked_add(DYNAMIC_HEADER_SIZE as u64)
+                .is_none_or(|end| end > file_size)
+            {
+                return Err(AppError::ImageError(
+                    "VHD dynamic header offset is outside the file".to_string(),
+                ));
+            }
+            let mut header = [0u8; DYNAMIC_HEADER_SIZE];
+            reader
+                .seek(SeekFrom::Start(footer.data_offset))
+                .map_err(AppError::io)?;
+            reader.read_exact(&mut header).map_err(AppError::io)?;
+            let header = DynamicHeader::parse(&header)?;
+
+            let needed = footer.current_size.div_ceil(header.block_size as u64);
+            if (header.max_table_entries as u64) < needed
+                || header.max_table_entries > MAX_BAT_ENTRIES
+            {
+                return Err(AppError::ImageError(format!(
+                    "VHD block table has {} entries, {} needed",
+                    header.max_table_entries, needed
+                )));
+            }
+            if header.table_end()? > file_size {
+                return Err(AppError::ImageError(
+                    "VHD block table extends past the end of the file".to_string(),
+                ));
+            }
+            Some(header)
+        };
+
+        Ok(Vhd {
+            reader,
+            footer,
+            dynamic,
+            file_size,
+        })
+    }
+
+    /// Block allocation table of a dynamic/differencing disk, as sector offsets
+    /// (`u32::MAX` for unallocated blocks). Empty for fixed disks.
+    pub fn block_table(&mut self) -> Result<Vec<u32>> {
+        let Some(header) = &self.dynamic else {
+            return Ok(Vec::new());
+        };
+        let mut raw = vec![0u8; header.max_table_entries as usize * 4];
+        self.reader
+            .seek(SeekFrom::Start(header.table_offset))
+            .map_err(AppError::io)?;
+        self.reader.read_exact(&mut raw).map_err(AppError::io)?;
+        Ok(raw
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect())
+    }
+
+    /// Bytes of guest data backed by the file. Every allocated block is checked to lie
+    /// inside the file so truncated copies are caught before they are used.
+    pub fn allocated_size(&mut self) -> Result<u64> {
+        let Some(header) = self.dynamic.clone() else {
+            return Ok(self.footer.current_size);
+        };
+        let data_end = self.file_size - FOOTER_SIZE;
+        let table_end = header.table_end()?;
+        let block_span = header.bitmap_size() + header.block_size as u64;
+        let mut allocated = 0u64;
+        for (index, entry) in self.block_table()?.into_iter().enumerate() {
+            if entry == UNUSED_BAT_ENTRY {
+                continue;
+            }
+            let start = entry as u64 * SECTOR_SIZE;
+            if start < table_end || start + block_span > data_end {
+                return Err(AppError::ImageError(format!(
+                    "VHD block {} points outside the data area (sector {})",
+                    index, entry
+                )));
+            }
+            allocated += header.block_size as u64;
+        }
+        Ok(allocated.min(self.footer.current_size))
+    }
+
+    /// Parent path of a differencing disk. Absolute Windows paths are preferred over
+    /// relative ones; the bare parent file name from the header is the last resort.
+    pub fn parent_path(&mut self) -> Result<Option<String>> {
+        let Some(header) = self.dynamic.clone() else {
+            return Ok(None);
+        };
+        if self.footer.disk_type != VhdDiskType::Differencing {
+            return Ok(None);
+        }
+
+        for code in [b"W2ku", b"W2ru", b"Wi2k", b"Wi2r", b"MacX", b"Mac "] {
+            let Some(locator) = header
+                .parent_locators
+                .iter()
+                .find(|l| &l.platform_code == code)
+            else {
+                continue;
+            };
+            let locator_end = locator
+                .data_offset
+                .checked_add(locator.data_length as u64)
+                .ok_or_else(|| {
+                    AppError::InvalidParameter(format!(
+                        "VHD parent locator offset {} overflows",
+                        locator.data_offset
+                    ))
+                })?;
+            if locator.data_length == 0
+                || locator.data_length > MAX_LOCATOR_SIZE
+                || locator_end > self.file_size
+            {
+                continue;
+            }
+            let mut raw = vec![0u8; locator.data_length as usize];
+            self.reader
+                .seek(SeekFrom::Start(locator.data_offset))
+                .map_err(AppError::io)?;
+            self.reader.read_exact(&mut raw).map_err(AppError::io)?;
+            let path = match code {
+                b"W2ku" | b"W2ru" => decode_utf16(&raw, true),
+                _ => String::from_utf8_lossy(&raw)
+                    .trim_end_matches('\0')
+                    .trim_start_matches("file://")
+                    .to_string(),
+            };
+            if !path.is_empty() {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok((!header.parent_name.is_empty()).then_some(header.parent_name))
+    }
+
+    pub fn info(&mut self, path: &Path) -> Result<VirtualDiskInfo> {
+        Ok(VirtualDiskInfo {
+            path: path.to_string_lossy().to_string(),
+            format: "vhd".to_string(),
+            disk_type: self.footer.disk_type.as_str().to_string(),
+            virtual_size: self.footer.current_size,
+            allocated_size: self.allocated_size()?,
+            file_size: self.file_size,
+            block_size: self.dynamic.as_ref().map(|h| h.block_size).unwrap_or(0),
+            logical_sector_size: SECTOR_SIZE as u32,
+            physical_sector_size: SECTOR_SIZE as u32,
+            parent_path: self.parent_path()?,
+        })
+    }
+}
+
+/// Read the metadata of a VHD file and check its structures for consistency.
+pub fn inspect_vhd(path: &Path) -> Result<VirtualDiskInfo> {
+    Vhd::open(path)?.info(path)
+}
+
+/// Inspect a virtual disk file, picking the format from its extension.
+pub fn inspect_virtual_disk(path: &Path) -> Result<VirtualDiskInfo> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match ext.as_str() {
+        "vhd" => inspect_vhd(path),
+        "vhdx" => vhdx::inspect_vhdx(path),
+        _ => Err(AppError::Unsupported(format!(
+            "Cannot inspect {}: only .vhd and .vhdx files are supported",
+            path.display()
+        ))),
+    }
+}
+
+/// Create an empty virtual disk, picking VHD or VHDX from the extension of `path`.
+pub fn create_virtual_disk(path: &Path, virtual_size: u64, disk_type: VhdDiskType) -> Result<()> {
+    let is_vhdx = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("vhdx"));
+    if is_vhdx {
+        vhdx::create_vhdx(path, virtual_size, disk_type, 512)
+    } else {
+        create_vhd(path, virtual_size, disk_type)
+    }
+}
+
+/// Inspect a virtual disk that is about to be copied onto a drive and booted natively.
+/// Differencing disks are rejected because their parent is not copied along, and 4K
+/// native (4Kn) disks because native VHD boot only handles 512-byte logical sectors.
+pub fn validate_import(path: &Path) -> Result<VirtualDiskInfo> {
+    let info = inspect_virtual_disk(path)?;
+    if info.logical_sector_size != SECTOR_SIZE as u32 {
+        return Err(AppError::ImageError(format!(
+            "{} uses {}-byte logical sectors (4Kn). Native boot needs 512-byte sectors",
+            path.display(),
+            info.logical_sector_size
+        )));
+    }
+    if info.disk_type == VhdDiskType::Differencing.as_str() {
+        return Err(AppError::ImageError(format!(
+            "{} is a differencing disk (parent: {}). Merge it into a fixed or dynamic disk first",
+            path.display(),
+            info.parent_path.as_deref().unwrap_or("unknown")
+        )));
+    }
+    Ok(info)
+}
+
+/// Create an empty fixed or dynamic VHD of `virtual_size` bytes (rounded up to a whole
+/// sector). An existing file at `path` is not overwritten.
+pub fn create_vhd(path: &Path, virtual_size: u64, disk_type: VhdDiskType) -> Result<()> {
+    let size = virtual_size.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+    if size == 0 || size > MAX_VIRTUAL_SIZE {
+        return Err(AppError::InvalidParameter(format!(
+            "VHD size must be between 1 byte and {} bytes, got {}",
+            MAX_VIRTUAL_SIZE, virtual_size
+        )));
+    }
+    if disk_type == VhdDiskType::Differencing {
+        return Err(AppError::Unsupported(
+            "Creating differencing VHDs is not supported".to_string(),
+        ));
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(AppError::io)?;
+    let result = write_new_vhd(file, size, disk_type);
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+fn write_new_vhd(file: File, size: u64, disk_type: VhdDiskType) -> Result<()> {
+    let footer = VhdFooter::new(size, disk_type).to_bytes();
+    let mut writer = BufWriter::new(file);
+
+    if disk_type == VhdDiskType::Fixed {
+        // The data area is left as a hole/zero-filled extent by set_len.
+        writer.get_ref().set_len(size).map_err(AppError::io)?;
+        writer.seek(SeekFrom::Start(size)).map_err(AppError::io)?;
+        writer.write_all(&footer).map_err(AppError::io)?;
+    } else {
+        let header = DynamicHeader::new(size, DEFAULT_BLOCK_SIZE);
+        writer.write_all(&footer).map_err(AppError::io)?;
+        writer.write_all(&header.to_bytes()).map_err(AppError::io)?;
+        let mut table = vec![0u8; header.table_size() as usize];
+        for entry in table.chunks_exact_mut(4) {
+            entry.copy_from_slice(&UNUSED_BAT_ENTRY.to_be_bytes());
+        }
+        writer.write_all(&table).map_err(AppError::io)?;
+        writer.write_all(&footer).map_err(AppError::io)?;
+    }
+
+    let file = writer
+        .into_inner()
+        .map_err(|e| AppError::io(e.into_error()))?;
+    file.sync_all().map_err(AppError::io)
+}
+
+/// One's complement of the byte sum, skipping the 4-byte checksum field at `skip`.
+fn checksum(buf: &[u8], skip: usize) -> u32 {
+    let sum = buf
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !(skip..skip + 4).contains(i))
+        .fold(0u32, |acc, (_, &b)| acc.wrapping_add(b as u32));
+    !sum
+}
+
+fn vhd_timestamp_now() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().saturating_sub(VHD_EPOCH_OFFSET) as u32)
+        .unwrap_or(0)
+}
+
+fn creator_host_os() -> [u8; 4] {
+    if cfg!(target_os = "macos") {
+        *b"Mac "
+    } else {
+        *b"Wi2k"
+    }
+}
+
+fn decode_utf16(raw: &[u8], little_endian: bool) -> String {
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|c| {
+            if little_endian {
+                u16::from_le_bytes([c[0], c[1]])
+            } else {
+                u16::from_be_bytes([c[0], c[1]])
+            }
+        })
+        .take_while(|&u| u != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("wtg-vhd-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn geometry_matches_specification_examples() {
+        assert_eq!(
+            DiskGeometry::for_size(8 * 1024 * 1024),
+            DiskGeometry {
+                cylinders: 240,
+                heads: 4,
+                sectors_per_track: 17
+            }
+        );
+        assert_eq!(
+            DiskGeometry::for_size(127 * 1024 * 1024),
+            DiskGeometry {
+                cylinders: 1019,
+                heads: 15,
+                sectors_per_track: 17
+            }
+        );
+        assert_eq!(
+            DiskGeometry::for_size(40 * 1024 * 1024 * 1024),
+            DiskGeometry {
+                cylinders: 20560,
+                heads: 16,
+                sectors_per_track: 255
+            }
+        );
+    }
+
+    #[test]
+    fn creates_and_inspects_fixed_and_dynamic_disks() {
+        let fixed = temp_path("fixed.vhd");
+        create_vhd(&fixed, 8 * 1024 * 1024, VhdDiskType::Fixed).unwrap();
+        assert_eq!(
+            std::fs::metadata(&fixed).unwrap().len(),
+            8 * 1024 * 1024 + FOOTER_SIZE
+        );
+        let info = inspect_vhd(&fixed).unwrap();
+        assert_eq!(info.format, "vhd");
+        assert_eq!(info.disk_type, "fixed");
+        assert_eq!(info.virtual_size, 8 * 1024 * 1024);
+        assert_eq!(info.allocated_size, 8 * 1024 * 1024);
+        assert_eq!(info.parent_path, None);
+        assert!(create_vhd(&fixed, 1024 * 1024, VhdDiskType::Fixed).is_err());
+
+        let dynamic = temp_path("dynamic.vhd");
+        create_vhd(&dynamic, 100 * 1024 * 1024 + 1, VhdDiskType::Dynamic).unwrap();
+        let mut vhd = Vhd::open(&dynamic).unwrap();
+        let header = vhd.dynamic.clone().unwrap();
+        assert_eq!(header.block_size, DEFAULT_BLOCK_SIZE);
+        assert_eq!(header.max_table_entries, 51);
+        assert_eq!(
+            vhd.file_size,
+            FOOTER_SIZE * 2 + DYNAMIC_HEADER_SIZE as u64 + SECTOR_SIZE
+        );
+        let info = vhd.info(&dynamic).unwrap();
+        assert_eq!(info.disk_type, "dynamic");
+        assert_eq!(info.virtual_size, 100 * 1024 * 1024 + 512);
+        assert_eq!(info.allocated_size, 0);
+
+        let _ = std::fs::remove_file(&fixed);
+        let _ = std::fs::remove_file(&dynamic);
+    }
+
+    #[test]
+    fn reads_parent_locator_of_differencing_disk() {
+        let size = 16 * 1024 * 1024;
+        let parent = r"D:\VMs\base.vhd";
+        let locator_data: Vec<u8> = parent
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+
+        let mut footer = VhdFooter::new(size, VhdDiskType::Differencing);
+        footer.data_offset = FOOTER_SIZE;
+        let mut header = DynamicHeader::new(size, DEFAULT_BLOCK_SIZE);
+        header.parent_name = "base.vhd".to_string();
+        let locator_offset = header.table_offset + header.table_size();
+        header.parent_locators.push(ParentLocator {
+            platform_code: *b"W2ku",
+            data_space: SECTOR_SIZE as u32,
+            data_length: locator_data.len() as u32,
+            data_offset: locator_offset,
+        });
+
+        let mut image = footer.to_bytes().to_vec();
+        image.extend_from_slice(&header.to_bytes());
+        image.resize(image.len() + header.table_size() as usize, 0xFF);
+        let mut sector = locator_data.clone();
+        sector.resize(SECTOR_SIZE as usize, 0);
+        image.extend_from_slice(&sector);
+        image.extend_from_slice(&footer.to_bytes());
+
+        let mut vhd = Vhd::new(Cursor::new(image)).unwrap();
+        let info = vhd.info(Path::new("child.vhd")).unwrap();
+        assert_eq!(info.disk_type, "differencing");
+        assert_eq!(info.parent_path.as_deref(), Some(parent));
+    }
+
+    #[test]
+    fn rejects_corrupt_footer_and_block_table() {
+        let size = 4 * 1024 * 1024;
+        let footer = VhdFooter::new(size, VhdDiskType::Dynamic);
+        let header = DynamicHeader::new(size, DEFAULT_BLOCK_SIZE);
+        let mut image = footer.to_bytes().to_vec();
+        image.extend_from_slice(&header.to_bytes());
+        let mut table = vec![0xFF; header.table_size() as usize];
+        // Block 0 claims to live far beyond the end of the file.
+        table[..4].copy_from_slice(&0x0010_0000u32.to_be_bytes());
+        image.extend_from_slice(&table);
+        image.extend_from_slice(&footer.to_bytes());
+
+        let mut vhd = Vhd::new(Cursor::new(image.clone())).unwrap();
+        let err = vhd.allocated_size().unwrap_err().to_string();
+        assert!(err.contains("outside the data area"), "{}", err);
+
+        let len = image.len();
+        image[len - 400] ^= 0xFF;
+        let err = Vhd::new(Cursor::new(image)).err().unwrap().to_string();
+        assert!(err.contains("damaged or the file is truncated"), "{}", err);
+    }
+}
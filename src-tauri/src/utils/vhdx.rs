@@ -0,0 +1,972 @@
+//! VHDX container creation, parsing and validation
+//!
+//! Covers the structures needed to create fixed/dynamic disks and to check images before
+//! they are copied to a drive: the file identifier, the two CRC32C-protected headers,
+//! the region table, the metadata region (file parameters, virtual size, sector sizes,
+//! parent locator) and the block allocation table. Log entries are never replayed; an
+//! image with a pending log is reported instead.
+
+use crate::models::VirtualDiskInfo;
+use crate::utils::vhd::VhdDiskType;
+use crate::{AppError, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+
+pub const DEFAULT_BLOCK_SIZE: u32 = 32 * MB as u32;
+pub const MAX_VIRTUAL_SIZE: u64 = 64 * 1024 * 1024 * MB;
+
+const FILE_SIGNATURE: &[u8; 8] = b"vhdxfile";
+const HEADER_SIGNATURE: &[u8; 4] = b"head";
+const REGION_SIGNATURE: &[u8; 4] = b"regi";
+const METADATA_SIGNATURE: &[u8; 8] = b"metadata";
+
+const HEADER_OFFSETS: [u64; 2] = [64 * KB, 128 * KB];
+const HEADER_SIZE: usize = 4 * KB as usize;
+const REGION_TABLE_OFFSETS: [u64; 2] = [192 * KB, 256 * KB];
+const REGION_TABLE_SIZE: usize = 64 * KB as usize;
+const METADATA_TABLE_SIZE: usize = 64 * KB as usize;
+const LOG_OFFSET: u64 = MB;
+const LOG_LENGTH: u32 = MB as u32;
+const METADATA_OFFSET: u64 = 2 * MB;
+const METADATA_LENGTH: u32 = MB as u32;
+const BAT_OFFSET: u64 = 3 * MB;
+
+const REGION_BAT: Uuid = uuid::uuid!("2dc27766-f623-4200-9d64-115e9bfd4a08");
+const REGION_METADATA: Uuid = uuid::uuid!("8b7ca206-4790-4b9a-b8fe-575f050f886e");
+
+const META_FILE_PARAMETERS: Uuid = uuid::uuid!("caa16737-fa36-4d43-b3b6-33f0aa44e76b");
+const META_VIRTUAL_DISK_SIZE: Uuid = uuid::uuid!("2fa54224-cd1b-4876-b211-5dbed83bf4b8");
+const META_VIRTUAL_DISK_ID: Uuid = uuid::uuid!("beca12ab-b2e6-4523-93ef-c309e000c746");
+const META_LOGICAL_SECTOR_SIZE: Uuid = uuid::uuid!("8141bf1d-a96f-4709-ba47-f233a8faab5f");
+const META_PHYSICAL_SECTOR_SIZE: Uuid = uuid::uuid!("cda348c7-445d-4471-9cc9-e9885251c556");
+const META_PARENT_LOCATOR: Uuid = uuid::uuid!("a8d35f2d-b30b-454d-abf7-d3d84834ab0c");
+
+const META_FLAG_VIRTUAL_DISK: u32 = 0x2;
+const META_FLAG_REQUIRED: u32 = 0x4;
+const FILE_PARAM_LEAVE_ALLOCATED: u32 = 0x1;
+const FILE_PARAM_HAS_PARENT: u32 = 0x2;
+
+const PAYLOAD_FULLY_PRESENT: u64 = 6;
+const PAYLOAD_PARTIALLY_PRESENT: u64 = 7;
+const BAT_STATE_MASK: u64 = 0x7;
+
+/// Entry of the region table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionEntry {
+    pub guid: Uuid,
+    pub file_offset: u64,
+    pub length: u32,
+    pub required: bool,
+}
+
+/// The current (highest sequence number, valid checksum) VHDX header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdxHeader {
+    pub sequence_number: u64,
+    pub file_write_guid: Uuid,
+    pub data_write_guid: Uuid,
+    /// Non-nil when the log holds entries that must be replayed before the file is used
+    pub log_guid: Uuid,
+    pub log_version: u16,
+    pub version: u16,
+    pub log_length: u32,
+    pub log_offset: u64,
+}
+
+impl VhdxHeader {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        if &buf[..4] != HEADER_SIGNATURE || read_u32(buf, 4) != crc32c_with_hole(buf, 4) {
+            return None;
+        }
+        Some(VhdxHeader {
+            sequence_number: read_u64(buf, 8),
+            file_write_guid: read_guid(buf, 16),
+            data_write_guid: read_guid(buf, 32),
+            log_guid: read_guid(buf, 48),
+            log_version: read_u16(buf, 64),
+            version: read_u16(buf, 66),
+            log_length: read_u32(buf, 68),
+            log_offset: read_u64(buf, 72),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; HEADER_SIZE];
+        buf[..4].copy_from_slice(HEADER_SIGNATURE);
+        buf[8..16].copy_from_slice(&self.sequence_number.to_le_bytes());
+        buf[16..32].copy_from_slice(&self.file_write_guid.to_bytes_le());
+        buf[32..48].copy_from_slice(&self.data_write_guid.to_bytes_le());
+        buf[48..64].copy_from_slice(&self.log_guid.to_bytes_le());
+        buf[64..66].copy_from_slice(&self.log_version.to_le_bytes());
+        buf[66..68].copy_from_slice(&self.version.to_le_bytes());
+        buf[68..72].copy_from_slice(&self.log_length.to_le_bytes());
+        buf[72..80].copy_from_slice(&self.log_offset.to_le_bytes());
+        let crc = crc32c_with_hole(&buf, 4);
+        buf[4..8].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+}
+
+/// Contents of the metadata region that matter for creating and checking disks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdxMetadata {
+    pub block_size: u32,
+    pub leave_blocks_allocated: bool,
+    pub has_parent: bool,
+    pub virtual_size: u64,
+    pub virtual_disk_id: Uuid,
+    pub logical_sector_size: u32,
+    pub physical_sector_size: u32,
+    /// Key/value pairs of the parent locator (`parent_linkage`, `absolute_win32_path`, ...)
+    pub parent_locator: Vec<(String, String)>,
+}
+
+impl VhdxMetadata {
+    pub fn disk_type(&self) -> VhdDiskType {
+        if self.has_parent {
+            VhdDiskType::Differencing
+        } else if self.leave_blocks_allocated {
+            VhdDiskType::Fixed
+        } else {
+            VhdDiskType::Dynamic
+        }
+    }
+
+    /// Payload blocks per sector bitmap block.
+    fn chunk_ratio(&self) -> u64 {
+        (1u64 << 23) * self.logical_sector_size as u64 / self.block_size as u64
+    }
+
+    fn data_blocks(&self) -> u64 {
+        self.virtual_size.div_ceil(self.block_size as u64)
+    }
+
+    /// Number of BAT entries, payload and sector bitmap entries interleaved.
+    fn bat_entries(&self) -> u64 {
+        let chunk_ratio = self.chunk_ratio();
+        let data_blocks = self.data_blocks();
+        if self.has_parent {
+            data_blocks.div_ceil(chunk_ratio) * (chunk_ratio + 1)
+        } else {
+            data_blocks + data_blocks.saturating_sub(1) / chunk_ratio
+        }
+    }
+}
+
+/// An opened VHDX with validated headers, region table and metadata.
+pub struct Vhdx<R> {
+    reader: R,
+    bat: RegionEntry,
+    metadata: VhdxMetadata,
+    file_size: u64,
+}
+
+impl Vhdx<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> Vhdx<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let file_size = reader.seek(SeekFrom::End(0)).map_err(AppError::io)?;
+        if file_size < BAT_OFFSET {
+            return Err(AppError::ImageError(
+                "File is too small to be a VHDX".to_string(),
+            ));
+        }
+
+        let identifier = read_at(&mut reader, 0, 8)?;
+        if identifier != FILE_SIGNATURE {
+            return Err(AppError::ImageError(
+                "Not a VHDX file: file identifier missing".to_string(),
+            ));
+        }
+
+        current_header(&mut reader)?;
+
+        let mut regions = None;
+        for offset in REGION_TABLE_OFFSETS {
+            let buf = read_at(&mut reader, offset, REGION_TABLE_SIZE)?;
+            if let Some(parsed) = parse_region_table(&buf)? {
+                regions = Some(parsed);
+                break;
+            }
+        }
+        let regions = regions.ok_or_else(|| {
+            AppError::ImageError("Both VHDX region tables are damaged".to_string())
+        })?;
+        for region in &regions {
+            if region.required && region.guid != REGION_BAT && region.guid != REGION_METADATA {
+                return Err(AppError::ImageError(format!(
+                    "VHDX requires unknown region {}",
+                    region.guid
+                )));
+            }
+            if !region.file_offset.is_multiple_of(MB)
+                || region.file_offset + region.length as u64 > file_size
+            {
+                return Err(AppError::ImageError(format!(
+                    "VHDX region {} lies outside the file",
+                    region.guid
+                )));
+            }
+        }
+        let find_region = |guid: Uuid, name: &str| {
+            regions
+                .iter()
+                .find(|r| r.guid == guid)
+                .copied()
+                .ok_or_else(|| AppError::ImageError(format!("VHDX has no {} region", name)))
+        };
+        let bat = find_region(REGION_BAT, "BAT")?;
+        let metadata_region = find_region(REGION_METADATA, "metadata")?;
+
+        let metadata_buf = read_at(
+            &mut reader,
+            metadata_region.file_offset,
+            metadata_region.length as usize,
+        )?;
+        let metadata = parse_metadata(&metadata_buf)?;
+        if metadata.bat_entries() * 8 > bat.length as u64 {
+            return Err(AppError::ImageError(format!(
+                "VHDX BAT region holds {} bytes, {} entries needed",
+                bat.length,
+                metadata.bat_entries()
+            )));
+        }
+
+        Ok(Vhdx {
+            reader,
+            bat,
+            metadata,
+            file_size,
+        })
+    }
+
+    /// Payload block entries of the BAT (sector bitmap entries are skipped).
+    pub fn payload_entries(&mut self) -> Result<Vec<u64>> {
+        let count = self.metadata.bat_entries() as usize;
+        let raw = read_at(&mut self.reader, self.bat.file_offset, count * 8)?;
+        let chunk_ratio = self.metadata.chunk_ratio() as usize;
+        let data_blocks = self.metadata.data_blocks() as usize;
+        Ok(raw
+            .chunks_exact(8)
+            .enumerate()
+            .filter(|(i, _)| (i + 1) % (chunk_ratio + 1) != 0)
+            .map(|(_, c)| read_u64(c, 0))
+            .take(data_blocks)
+            .collect())
+    }
+
+    /// File offset of the guest data when every block is present and stored back to
+    /// back in order, as in fixed disks; the disk can then be used as a raw byte range.
+    pub fn contiguous_data_offset(&mut self) -> Result<Option<u64>> {
+        let block_size = self.metadata.block_size as u64;
+        let entries = self.payload_entries()?;
+        let Some(first) = entries.first() else {
+            return Ok(None);
+        };
+        let start = (first >> 20) * MB;
+        let contiguous = entries.iter().enumerate().all(|(index, entry)| {
+            entry & BAT_STATE_MASK == PAYLOAD_FULLY_PRESENT
+                && (entry >> 20) * MB == start + index as u64 * block_size
+        });
+        Ok(contiguous.then_some(start))
+    }
+
+    /// Bytes of guest data backed by the file. Present blocks must lie inside the file.
+    pub fn allocated_size(&mut self) -> Result<u64> {
+        let block_size = self.metadata.block_size as u64;
+        let mut allocated = 0u64;
+        for (index, entry) in self.payload_entries()?.into_iter().enumerate() {
+            let state = entry & BAT_STATE_MASK;
+            if state != PAYLOAD_FULLY_PRESENT && state != PAYLOAD_PARTIALLY_PRESENT {
+                continue;
+            }
+            let offset = (entry >> 20) * MB;
+            if offset < BAT_OFFSET || offset + block_size > self.file_size {
+                return Err(AppError::ImageError(format!(
+                    "VHDX block {} points outside the file (offset {})",
+                    index, offset
+                )));
+            }
+            allocated += block_size;
+        }
+        Ok(allocated.min(self.metadata.virtual_size))
+    }
+
+    /// Parent path of a differencing disk, preferring the absolute Win32 path.
+    pub fn parent_path(&self) -> Option<String> {
+        if !self.metadata.has_parent {
+            return None;
+        }
+        ["absolute_win32_path", "relative_path", "volume_path"]
+            .iter()
+            .find_map(|key| {
+                self.metadata
+                    .parent_locator
+                    .iter()
+                    .find(|(k, v)| k == key && !v.is_empty())
+                    .map(|(_, v)| v.clone())
+            })
+    }
+
+    pub fn info(&mut self, path: &Path) -> Result<VirtualDiskInfo> {
+        Ok(VirtualDiskInfo {
+            path: path.to_string_lossy().to_string(),
+            format: "vhdx".to_string(),
+            disk_type: self.metadata.disk_type().as_str().to_string(),
+            virtual_size: self.metadata.virtual_size,
+            allocated_size: self.allocated_size()?,
+            file_size: self.file_size,
+            block_size: self.metadata.block_size,
+            logical_sector_size: self.metadata.logical_sector_size,
+            physical_sector_size: self.metadata.physical_sector_size,
+            parent_path: self.parent_path(),
+        })
+    }
+}
+
+/// Read the metadata of a VHDX file and check its structures for consistency.
+pub fn inspect_vhdx(path: &Path) -> Result<VirtualDiskInfo> {
+    Vhdx::open(path)?.info(path)
+}
+
+/// Create an empty fixed or dynamic VHDX of `virtual_size` bytes (rounded up to a whole
+/// logical sector). An existing file at `path` is not overwritten.
+pub fn create_vhdx(
+    path: &Path,
+    virtual_size: u64,
+    disk_type: VhdDiskType,
+    logical_sector_size: u32,
+) -> Result<()> {
+    if logical_sector_size != 512 && logical_sector_size != 4096 {
+        return Err(AppError::InvalidParameter(format!(
+            "VHDX logical sector size must be 512 or 4096, got {}",
+            logical_sector_size
+        )));
+    }
+    let size = virtual_size.div_ceil(logical_sector_size as u64) * logical_sector_size as u64;
+    if size == 0 || size > MAX_VIRTUAL_SIZE {
+        return Err(AppError::InvalidParameter(format!(
+            "VHDX size must be between 1 byte and {} bytes, got {}",
+            MAX_VIRTUAL_SIZE, virtual_size
+        )));
+    }
+    if disk_type == VhdDiskType::Differencing {
+        return Err(AppError::Unsupported(
+            "Creating differencing VHDX files is not supported".to_string(),
+        ));
+    }
+
+    let metadata = VhdxMetadata {
+        block_size: DEFAULT_BLOCK_SIZE,
+        leave_blocks_allocated: disk_type == VhdDiskType::Fixed,
+        has_parent: false,
+        virtual_size: size,
+        virtual_disk_id: Uuid::new_v4(),
+        logical_sector_size,
+        physical_sector_size: 4096,
+        parent_locator: Vec::new(),
+    };
+    let (prefix, file_size) = build_image_prefix(&metadata);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(AppError::io)?;
+    let result = file
+        .write_all(&prefix)
+        .and_then(|_| file.set_len(file_size))
+        .and_then(|_| file.sync_all())
+        .map_err(AppError::io);
+    if result.is_err() {
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+/// Everything up to the first payload block (identifier, headers, region tables, log,
+/// metadata and BAT) plus the final file size. Fixed disks get every block allocated
+/// back to back after the BAT.
+/// The header with the highest sequence number and a valid checksum. A VHDX whose log
+/// still has to be replayed is rejected, as its data may be stale.
+fn current_header<R: Read + Seek>(reader: &mut R) -> Result<VhdxHeader> {
+    let mut header: Option<VhdxHeader> = None;
+    for offset in HEADER_OFFSETS {
+        let buf = read_at(reader, offset, HEADER_SIZE)?;
+        if let Some(candidate) = VhdxHeader::parse(&buf) {
+            if header
+                .as_ref()
+                .is_none_or(|h| candidate.sequence_number > h.sequence_number)
+            {
+                header = Some(candidate);
+            }
+        }
+    }
+    let header = header.ok_or_else(|| {
+        AppError::ImageError("Both VHDX headers are damaged (checksum mismatch)".to_string())
+    })?;
+    if header.version != 1 {
+        return Err(AppError::ImageError(format!(
+            "Unsupported VHDX version {}",
+            header.version
+        )));
+    }
+    if !header.log_guid.is_nil() {
+        return Err(AppError::ImageError(
+            "VHDX log has pending entries (the file was not closed cleanly). Attach it once \
+             in Windows or Hyper-V to replay the log"
+                .to_string(),
+        ));
+    }
+    Ok(header)
+}
+
+fn build_image_prefix(metadata: &VhdxMetadata) -> (Vec<u8>, u64) {
+    let bat_length = (metadata.bat_entries() * 8).div_ceil(MB) * MB;
+    let data_start = BAT_OFFSET + bat_length;
+    let mut image = vec![0u8; data_start as usize];
+
+    image[..8].copy_from_slice(FILE_SIGNATURE);
+    let creator: Vec<u8> = "WTG Assistant"
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes())
+        .collect();
+    image[8..8 + creator.len()].copy_from_slice(&creator);
+
+    let file_write_guid = Uuid::new_v4();
+    for (i, offset) in HEADER_OFFSETS.iter().enumerate() {
+        let header = VhdxHeader {
+            sequence_number: i as u64,
+            file_write_guid,
+            data_write_guid: Uuid::new_v4(),
+            log_guid: Uuid::nil(),
+            log_version: 0,
+            version: 1,
+            log_length: LOG_LENGTH,
+            log_offset: LOG_OFFSET,
+        };
+        let at = *offset as usize;
+        image[at..at + HEADER_SIZE].copy_from_slice(&header.to_bytes());
+    }
+
+    let regions = [
+        RegionEntry {
+            guid: REGION_BAT,
+            file_offset: BAT_OFFSET,
+            length: bat_length as u32,
+            required: true,
+        },
+        RegionEntry {
+            guid: REGION_METADATA,
+            file_offset: METADATA_OFFSET,
+            length: METADATA_LENGTH,
+            required: true,
+        },
+    ];
+    let region_table = build_region_table(&regions);
+    for offset in REGION_TABLE_OFFSETS {
+        let at = offset as usize;
+        image[at..at + REGION_TABLE_SIZE].copy_from_slice(&region_table);
+    }
+
+    let metadata_region = build_metadata(metadata);
+    let at = METADATA_OFFSET as usize;
+    image[at..at + metadata_region.len()].copy_from_slice(&metadata_region);
+
+    // Dynamic and differencing disks keep the all-zero (not present) BAT
+    let mut next_block = data_start;
+    if metadata.leave_blocks_allocated {
+        let chunk_ratio = metadata.chunk_ratio();
+        for block in 0..metadata.data_blocks() {
+            let entry = ((next_block / MB) << 20) | PAYLOAD_FULLY_PRESENT;
+            let at = (BAT_OFFSET + (block + block / chunk_ratio) * 8) as usize;
+            image[at..at + 8].copy_from_slice(&entry.to_le_bytes());
+            next_block += metadata.block_size as u64;
+        }
+    }
+
+    (image, next_block)
+}
+
+fn build_region_table(regions: &[RegionEntry]) -> Vec<u8> {
+    let mut buf = vec![0u8; REGION_TABLE_SIZE];
+    buf[..4].copy_from_slice(REGION_SIGNATURE);
+    buf[8..12].copy_from_slice(&(regions.len() as u32).to_le_bytes());
+    for (i, region) in regions.iter().enumerate() {
+        let at = 16 + i * 32;
+        buf[at..at + 16].copy_from_slice(&region.guid.to_bytes_le());
+        buf[at + 16..at + 24].copy_from_slice(&region.file_offset.to_le_bytes());
+        buf[at + 24..at + 28].copy_from_slice(&region.length.to_le_bytes());
+        buf[at + 28..at + 32].copy_from_slice(&u32::from(region.required).to_le_bytes());
+    }
+    let crc = crc32c_with_hole(&buf, 4);
+    buf[4..8].copy_from_slice(&crc.to_le_bytes());
+    buf
+}
+
+/// `Ok(None)` when the table's signature or checksum is bad (the other copy is tried).
+fn parse_region_table(buf: &[u8]) -> Result<Option<Vec<RegionEntry>>> {
+    if &buf[..4] != REGION_SIGNATURE || read_u32(buf, 4) != crc32c_with_hole(buf, 4) {
+        return Ok(None);
+    }
+    let count = read_u32(buf, 8) as usize;
+    if count > 2047 {
+        return Err(AppError::ImageError(format!(
+            "VHDX region table has {} entries",
+            count
+        )));
+    }
+    Ok(Some(
+        (0..count)
+            .map(|i| 16 + i * 32)
+            .map(|at| RegionEntry {
+                guid: read_guid(buf, at),
+                file_offset: read_u64(buf, at + 16),
+                length: read_u32(buf, at + 24),
+                required: read_u32(buf, at + 28) & 1 != 0,
+            })
+            .collect(),
+    ))
+}
+
+fn build_metadata(metadata: &VhdxMetadata) -> Vec<u8> {
+    let mut file_parameters = metadata.block_size.to_le_bytes().to_vec();
+    let mut flags = 0u32;
+    if metadata.leave_blocks_allocated {
+        flags |= FILE_PARAM_LEAVE_ALLOCATED;
+    }
+    if metadata.has_parent {
+        flags |= FILE_PARAM_HAS_PARENT;
+    }
+    file_parameters.extend_from_slice(&flags.to_le_bytes());
+
+    let virtual_disk = META_FLAG_VIRTUAL_DISK | META_FLAG_REQUIRED;
+    let mut items = vec![
+        (META_FILE_PARAMETERS, META_FLAG_REQUIRED, file_parameters),
+        (
+            META_VIRTUAL_DISK_SIZE,
+            virtual_disk,
+            metadata.virtual_size.to_le_bytes().to_vec(),
+        ),
+        (
+            META_VIRTUAL_DISK_ID,
+            virtual_disk,
+            metadata.virtual_disk_id.to_bytes_le().to_vec(),
+        ),
+        (
+            META_LOGICAL_SECTOR_SIZE,
+            virtual_disk,
+            metadata.logical_sector_size.to_le_bytes().to_vec(),
+        ),
+        (
+            META_PHYSICAL_SECTOR_SIZE,
+            virtual_disk,
+            metadata.physical_sector_size.to_le_bytes().to_vec(),
+        ),
+    ];
+    if metadata.has_parent {
+        items.push((
+            META_PARENT_LOCATOR,
+            META_FLAG_REQUIRED,
+            build_parent_locator(&metadata.parent_locator),
+        ));
+    }
+
+    let mut buf = vec![0u8; METADATA_TABLE_SIZE];
+    buf[..8].copy_from_slice(METADATA_SIGNATURE);
+    buf[10..12].copy_from_slice(&(items.len() as u16).to_le_bytes());
+    for (i, (guid, flags, data)) in items.into_iter().enumerate() {
+        let at = 32 + i * 32;
+        let offset = buf.len() as u32;
+        buf[at..at + 16].copy_from_slice(&guid.to_bytes_le());
+        buf[at + 16..at + 20].copy_from_slice(&offset.to_le_bytes());
+        buf[at + 20..at + 24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        buf[at + 24..at + 28].copy_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&data);
+    }
+    buf
+}
+
+fn parse_metadata(region: &[u8]) -> Result<VhdxMetadata> {
+    if region.len() < METADATA_TABLE_SIZE || &region[..8] != METADATA_SIGNATURE {
+        return Err(AppError::ImageError(
+            "VHDX metadata table is missing or damaged".to_string(),
+        ));
+    }
+    let count = read_u16(region, 10) as usize;
+    if count > 2047 {
+        return Err(AppError::ImageError(format!(
+            "VHDX metadata table has {} entries",
+            count
+        )));
+    }
+
+    let mut metadata = VhdxMetadata {
+        block_size: 0,
+        leave_blocks_allocated: false,
+        has_parent: false,
+        virtual_size: 0,
+        virtual_disk_id: Uuid::nil(),
+        logical_sector_size: 0,
+        physical_sector_size: 0,
+        parent_locator: Vec::new(),
+    };
+    let mut seen_parameters = false;
+    for i in 0..count {
+        let at = 32 + i * 32;
+        let guid = read_guid(region, at);
+        let offset = read_u32(region, at + 16) as usize;
+        let length = read_u32(region, at + 20) as usize;
+        let flags = read_u32(region, at + 24);
+        if length == 0 {
+            continue;
+        }
+        if offset < METADATA_TABLE_SIZE || offset + length > region.len() {
+            return Err(AppError::ImageError(format!(
+                "VHDX metadata item {} lies outside the metadata region",
+                guid
+            )));
+        }
+        let data = &region[offset..offset + length];
+        let need = |n: usize| {
+            if length < n {
+                Err(AppError::ImageError(format!(
+                    "VHDX metadata item {} is truncated",
+                    guid
+                )))
+            } else {
+                Ok(())
+            }
+        };
+        match guid {
+            g if g == META_FILE_PARAMETERS => {
+                need(8)?;
+                metadata.block_size = read_u32(data, 0);
+                let file_flags = read_u32(data, 4);
+                metadata.leave_blocks_allocated = file_flags & FILE_PARAM_LEAVE_ALLOCATED != 0;
+                metadata.has_parent = file_flags & FILE_PARAM_HAS_PARENT != 0;
+                seen_parameters = true;
+            }
+            g if g == META_VIRTUAL_DISK_SIZE => {
+                need(8)?;
+                metadata.virtual_size = read_u64(data, 0);
+            }
+            g if g == META_VIRTUAL_DISK_ID => {
+                need(16)?;
+                metadata.virtual_disk_id = read_guid(data, 0);
+            }
+            g if g == META_LOGICAL_SECTOR_SIZE => {
+                need(4)?;
+                metadata.logical_sector_size = read_u32(data, 0);
+            }
+            g if g == META_PHYSICAL_SECTOR_SIZE => {
+                need(4)?;
+                metadata.physical_sector_size = read_u32(data, 0);
+            }
+            g if g == META_PARENT_LOCATOR => {
+                metadata.parent_locator = parse_parent_locator(data)?;
+            }
+            _ if flags & META_FLAG_REQUIRED != 0 => {
+                return Err(AppError::ImageError(format!(
+                    "VHDX requires unknown metadata item {}",
+                    guid
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    if !seen_parameters
+        || !(MB as u32..=256 * MB as u32).contains(&metadata.block_size)
+        || !metadata.block_size.is_power_of_two()
+    {
+        return Err(AppError::ImageError(format!(
+            "Invalid VHDX block size {}",
+            metadata.block_size
+        )));
+    }
+    if metadata.logical_sector_size != 512 && metadata.logical_sector_size != 4096 {
+        return Err(AppError::ImageError(format!(
+            "Invalid VHDX logical sector size {}",
+            metadata.logical_sector_size
+        )));
+    }
+    if metadata.physical_sector_size != 512 && metadata.physical_sector_size != 4096 {
+        return Err(AppError::ImageError(format!(
+            "Invalid VHDX physical sector size {}",
+            metadata.physical_sector_size
+        )));
+    }
+    if metadata.virtual_size == 0
+        || metadata.virtual_size > MAX_VIRTUAL_SIZE
+        || !metadata
+            .virtual_size
+            .is_multiple_of(metadata.logical_sector_size as u64)
+    {
+        return Err(AppError::ImageError(format!(
+            "Invalid VHDX virtual size {}",
+            metadata.virtual_size
+        )));
+    }
+    Ok(metadata)
+}
+
+fn build_parent_locator(entries: &[(String, String)]) -> Vec<u8> {
+    let header_len = 20 + entries.len() * 12;
+    let mut buf = vec![0u8; header_len];
+    // Locator type of VHDX parents: B04AEFB7-D19E-4A81-B789-25B8E9445913
+    buf[..16].copy_from_slice(&uuid::uuid!("b04aefb7-d19e-4a81-b789-25b8e9445913").to_bytes_le());
+    buf[18..20].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (i, (key, value)) in entries.iter().enumerate() {
+        let key = encode_utf16le(key);
+        let value = encode_utf16le(value);
+        let at = 20 + i * 12;
+        let key_offset = buf.len() as u32;
+        buf.extend_from_slice(&key);
+        let value_offset = buf.len() as u32;
+        buf.extend_from_slice(&value);
+        buf[at..at + 4].copy_from_slice(&key_offset.to_le_bytes());
+        buf[at + 4..at + 8].copy_from_slice(&value_offset.to_le_bytes());
+        buf[at + 8..at + 10].copy_from_slice(&(key.len() as u16).to_le_bytes());
+        buf[at + 10..at + 12].copy_from_slice(&(value.len() as u16).to_le_bytes());
+    }
+    buf
+}
+
+fn parse_parent_locator(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let truncated = || AppError::ImageError("VHDX parent locator is truncated".to_string());
+    if data.len() < 20 {
+        return Err(truncated());
+    }
+    let count = read_u16(data, 18) as usize;
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let at = 20 + i * 12;
+        if at + 12 > data.len() {
+            return Err(truncated());
+        }
+        let key_offset = read_u32(data, at) as usize;
+        let value_offset = read_u32(data, at + 4) as usize;
+        let key_len = read_u16(data, at + 8) as usize;
+        let value_len = read_u16(data, at + 10) as usize;
+        let key = data
+            .get(key_offset..key_offset + key_len)
+            .ok_or_else(truncated)?;
+        let value = data
+            .get(value_offset..value_offset + value_len)
+            .ok_or_else(truncated)?;
+        entries.push((decode_utf16le(key), decode_utf16le(value)));
+    }
+    Ok(entries)
+}
+
+/// CRC-32C (Castagnoli) of `buf` with the 4-byte checksum field at `hole` taken as zero.
+fn crc32c_with_hole(buf: &[u8], hole: usize) -> u32 {
+    let mut crc = !0u32;
+    for (i, &byte) in buf.iter().enumerate() {
+        let byte = if (hole..hole + 4).contains(&i) {
+            0
+        } else {
+            byte
+        };
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader.seek(SeekFrom::Start(offset)).map_err(AppError::io)?;
+    reader.read_exact(&mut buf).map_err(AppError::io)?;
+    Ok(buf)
+}
+
+fn encode_utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+}
+
+fn decode_utf16le(raw: &[u8]) -> String {
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn read_guid(buf: &[u8], offset: usize) -> Uuid {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&buf[offset..offset + 16]);
+    Uuid::from_bytes_le(bytes)
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("wtg-vhdx-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn dynamic_metadata(virtual_size: u64) -> VhdxMetadata {
+        VhdxMetadata {
+            block_size: DEFAULT_BLOCK_SIZE,
+            leave_blocks_allocated: false,
+            has_parent: false,
+            virtual_size,
+            virtual_disk_id: Uuid::new_v4(),
+            logical_sector_size: 512,
+            physical_sector_size: 4096,
+            parent_locator: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn crc32c_matches_reference_vector() {
+        // RFC 3720 test vector: 32 bytes of zeros.
+        assert_eq!(crc32c_with_hole(&[0u8; 32], 32), 0x8A91_36AA);
+        assert_eq!(crc32c_with_hole(b"123456789", 9), 0xE306_9283);
+    }
+
+    #[test]
+    fn creates_and_inspects_fixed_and_dynamic_disks() {
+        let dynamic = temp_path("dynamic.vhdx");
+        create_vhdx(&dynamic, 100 * MB, VhdDiskType::Dynamic, 512).unwrap();
+        assert_eq!(std::fs::metadata(&dynamic).unwrap().len(), 4 * MB);
+        let mut file = File::open(&dynamic).unwrap();
+        assert_eq!(current_header(&mut file).unwrap().sequence_number, 1);
+        let mut vhdx = Vhdx::open(&dynamic).unwrap();
+        assert_eq!(vhdx.payload_entries().unwrap().len(), 4);
+        assert_eq!(vhdx.contiguous_data_offset().unwrap(), None);
+        let info = vhdx.info(&dynamic).unwrap();
+        assert_eq!(info.format, "vhdx");
+        assert_eq!(info.disk_type, "dynamic");
+        assert_eq!(info.virtual_size, 100 * MB);
+        assert_eq!(info.allocated_size, 0);
+        assert_eq!(info.block_size, DEFAULT_BLOCK_SIZE);
+        assert_eq!(info.logical_sector_size, 512);
+        assert_eq!(info.physical_sector_size, 4096);
+
+        let fixed = temp_path("fixed.vhdx");
+        create_vhdx(&fixed, 64 * MB, VhdDiskType::Fixed, 4096).unwrap();
+        assert_eq!(std::fs::metadata(&fixed).unwrap().len(), 4 * MB + 64 * MB);
+        let info = inspect_vhdx(&fixed).unwrap();
+        assert_eq!(info.disk_type, "fixed");
+        assert_eq!(info.allocated_size, 64 * MB);
+        assert_eq!(info.logical_sector_size, 4096);
+        let mut vhdx = Vhdx::open(&fixed).unwrap();
+        assert_eq!(vhdx.contiguous_data_offset().unwrap(), Some(4 * MB));
+        assert!(create_vhdx(&fixed, MB, VhdDiskType::Fixed, 512).is_err());
+
+        let _ = std::fs::remove_file(&dynamic);
+        let _ = std::fs::remove_file(&fixed);
+    }
+
+    #[test]
+    fn falls_back_to_second_header_and_detects_pending_log() {
+        let (mut image, _) = build_image_prefix(&dynamic_metadata(64 * MB));
+
+        // A torn update of the newer header leaves the older one in charge.
+        image[HEADER_OFFSETS[1] as usize + 100] ^= 0xFF;
+        let header = current_header(&mut Cursor::new(image.clone())).unwrap();
+        assert_eq!(header.sequence_number, 0);
+
+        image[HEADER_OFFSETS[0] as usize + 100] ^= 0xFF;
+        let err = Vhdx::new(Cursor::new(image.clone())).err().unwrap();
+        assert!(err.to_string().contains("Both VHDX headers"), "{}", err);
+
+        let header = VhdxHeader {
+            sequence_number: 5,
+            file_write_guid: Uuid::new_v4(),
+            data_write_guid: Uuid::new_v4(),
+            log_guid: Uuid::new_v4(),
+            log_version: 0,
+            version: 1,
+            log_length: LOG_LENGTH,
+            log_offset: LOG_OFFSET,
+        };
+        let at = HEADER_OFFSETS[0] as usize;
+        image[at..at + HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        let err = Vhdx::new(Cursor::new(image)).err().unwrap();
+        assert!(err.to_string().contains("replay the log"), "{}", err);
+    }
+
+    #[test]
+    fn reads_differencing_disk_parent_and_block_states() {
+        let mut metadata = dynamic_metadata(128 * MB);
+        metadata.has_parent = true;
+        metadata.parent_locator = vec![
+            (
+                "parent_linkage".to_string(),
+                format!("{{{}}}", Uuid::new_v4()),
+            ),
+            ("relative_path".to_string(), r".\base.vhdx".to_string()),
+            (
+                "absolute_win32_path".to_string(),
+                r"D:\VMs\base.vhdx".to_string(),
+            ),
+        ];
+        let (mut image, data_start) = build_image_prefix(&metadata);
+        let chunk_ratio = metadata.chunk_ratio();
+        // Differencing BATs always cover whole chunks.
+        assert_eq!(metadata.bat_entries(), chunk_ratio + 1);
+
+        // Block 1 is present right after the BAT.
+        let entry = ((data_start / MB) << 20) | PAYLOAD_PARTIALLY_PRESENT;
+        let at = (BAT_OFFSET + 8) as usize;
+        image[at..at + 8].copy_from_slice(&entry.to_le_bytes());
+        image.resize((data_start + DEFAULT_BLOCK_SIZE as u64) as usize, 0);
+
+        let mut vhdx = Vhdx::new(Cursor::new(image.clone())).unwrap();
+        let info = vhdx.info(Path::new("child.vhdx")).unwrap();
+        assert_eq!(info.disk_type, "differencing");
+        assert_eq!(info.parent_path.as_deref(), Some(r"D:\VMs\base.vhdx"));
+        assert_eq!(info.allocated_size, DEFAULT_BLOCK_SIZE as u64);
+
+        // Truncated copy: the block now points past the end of the file.
+        image.truncate(data_start as usize);
+        let mut vhdx = Vhdx::new(Cursor::new(image)).unwrap();
+        let err = vhdx.allocated_size().unwrap_err();
+        assert!(err.to_string().contains("outside the file"), "{}", err);
+    }
+}
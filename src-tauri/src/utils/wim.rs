@@ -0,0 +1,635 @@
+//! Read-only access to WIM/ESD image metadata
+//!
+//! Parses the fixed WIM header and the UTF-16 XML document embedded in the archive,
+//! which is where DISM and wimlib take image names, sizes and Windows version details
+//! from. ESD files use the same container (solid LZMS resources), and the XML resource is
+//! stored uncompressed in both, so no decompressor is needed.
+
+use crate::models::ImageInfo;
+use crate::{AppError, Result};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub const WIM_MAGIC: &[u8; 8] = b"MSWIM\0\0\0";
+/// Pipable WIMs (`wimlib-imagex capture --pipable`) swap the magic but keep the layout.
+pub const PWM_MAGIC: &[u8; 8] = b"WLPWM\0\0\0";
+pub const WIM_HEADER_SIZE: usize = 208;
+
+const RESHDR_FLAG_COMPRESSED: u8 = 0x04;
+/// Largest XML document accepted; real install images stay well below 1 MB.
+const MAX_XML_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A resource header ("reshdr") entry of the WIM header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceHeader {
+    pub stored_size: u64,
+    pub flags: u8,
+    pub offset: u64,
+    pub original_size: u64,
+}
+
+impl ResourceHeader {
+    fn parse(buf: &[u8]) -> Self {
+        let mut size = [0u8; 8];
+        size[..7].copy_from_slice(&buf[..7]);
+        ResourceHeader {
+            stored_size: u64::from_le_bytes(size),
+            flags: buf[7],
+            offset: read_u64(buf, 8),
+            original_size: read_u64(buf, 16),
+        }
+    }
+}
+
+/// The parts of the fixed-size WIM/ESD/SWM header needed to locate the XML document
+/// and to group split images.
+#[derive(Debug, Clone)]
+pub struct WimHeader {
+    pub guid: [u8; 16],
+    /// 1-based part number within a split set; 1 for ordinary images.
+    pub part_number: u16,
+    pub total_parts: u16,
+    pub xml_data: ResourceHeader,
+}
+
+/// One `<IMAGE>` element of the WIM XML document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WimImage {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub display_name: String,
+    pub total_bytes: u64,
+    /// Normalized architecture name ("x86", "x64", "arm64", ...), empty when absent.
+    pub architecture: String,
+    pub build: u32,
+    pub edition_id: String,
+    pub languages: Vec<String>,
+}
+
+impl From<WimImage> for ImageInfo {
+    fn from(image: WimImage) -> Self {
+        ImageInfo {
+            index: image.index,
+            name: image.name,
+            description: image.description,
+            size: image.total_bytes,
+            architecture: image.architecture,
+            build: image.build,
+            edition_id: image.edition_id,
+            languages: image.languages,
+        }
+    }
+}
+
+pub struct Wim<R> {
+    reader: R,
+    header: WimHeader,
+}
+
+impl Wim<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(AppError::io)?;
+        Wim::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> Wim<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut buf = [0u8; WIM_HEADER_SIZE];
+        reader.seek(SeekFrom::Start(0)).map_err(AppError::io)?;
+        reader
+            .read_exact(&mut buf)
+            .map_err(|e| AppError::ImageError(format!("File too small for a WIM header: {}", e)))?;
+        let header = parse_header(&buf)?;
+        Ok(Wim { reader, header })
+    }
+
+    pub fn header(&self) -> &WimHeader {
+        &self.header
+    }
+
+    /// The embedded XML document, decoded from UTF-16LE.
+    pub fn xml(&mut self) -> Result<String> {
+        let res = self.header.xml_data;
+        if res.offset == 0 || res.original_size == 0 {
+            return Err(AppError::ImageError(
+                "WIM has no XML metadata resource".to_string(),
+            ));
+        }
+        if res.flags & RESHDR_FLAG_COMPRESSED != 0 {
+            return Err(AppError::ImageError(
+                "Compressed WIM XML metadata is not supported".to_string(),
+            ));
+        }
+        if res.original_size > MAX_XML_SIZE {
+            return Err(AppError::ImageError(format!(
+                "WIM XML metadata is implausibly large ({} bytes)",
+                res.original_size
+            )));
+        }
+
+        let mut data = vec![0u8; res.original_size as usize];
+        self.reader
+            .seek(SeekFrom::Start(res.offset))
+            .map_err(AppError::io)?;
+        self.reader
+            .read_exact(&mut data)
+            .map_err(|e| AppError::ImageError(format!("WIM XML metadata is truncated: {}", e)))?;
+        Ok(decode_utf16_xml(&data))
+    }
+
+    pub fn images(&mut self) -> Result<Vec<WimImage>> {
+        let xml = self.xml()?;
+        Ok(parse_images_xml(&xml))
+    }
+}
+
+/// Read the image list of a WIM/ESD/SWM file without external tools. For a split WIM
+/// the whole set is validated and the list comes from the first part.
+pub fn read_image_info(path: &Path) -> Result<Vec<ImageInfo>> {
+    let images = if is_split_wim(path) {
+        let set = resolve_split_wim(path)?;
+        Wim::open(set.first_part())?.images()?
+    } else {
+        Wim::open(path)?.images()?
+    };
+    Ok(images.into_iter().map(ImageInfo::from).collect())
+}
+
+/// Parts of a split WIM (`install.swm`, `install2.swm`, ...) in part-number order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitWimSet {
+    pub parts: Vec<PathBuf>,
+}
+
+impl SplitWimSet {
+    pub fn first_part(&self) -> &Path {
+        &self.parts[0]
+    }
+
+    /// Wildcard matching every part (`<dir>/install*.swm`), the form DISM `/SWMFile:`
+    /// and wimlib `--ref` expect.
+    pub fn ref_pattern(&self) -> String {
+        let first = self.first_part();
+        let stem = first
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = first
+            .extension()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "swm".to_string());
+        first
+            .with_file_name(format!("{}*.{}", stem, ext))
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+pub fn is_split_wim(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("swm"))
+        .unwrap_or(false)
+}
+
+/// Collect the complete split WIM set that `any_part` belongs to. Siblings are matched by
+/// name (`<base>.swm`, `<base>N.swm`) and confirmed through the header GUID and part number.
+pub fn resolve_split_wim(any_part: &Path) -> Result<SplitWimSet> {
+    let header = Wim::open(any_part)?.header().clone();
+    let total = header.total_parts.max(1) as usize;
+    if total == 1 {
+        return Ok(SplitWimSet {
+            parts: vec![any_part.to_path_buf()],
+        });
+    }
+
+    let stem = any_part
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let base = if header.part_number > 1 {
+        stem.strip_suffix(&header.part_number.to_string())
+            .unwrap_or(&stem)
+            .to_ascii_lowercase()
+    } else {
+        stem.to_ascii_lowercase()
+    };
+    let dir = match any_part.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut slots: Vec<Option<PathBuf>> = vec![None; total];
+    for entry in std::fs::read_dir(&dir).map_err(AppError::io)?.flatten() {
+        let path = entry.path();
+        if !is_split_wim(&path) {
+            continue;
+        }
+        let entry_stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let Some(suffix) = entry_stem.strip_prefix(&base) else {
+            continue;
+        };
+        if !suffix.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let Ok(part) = Wim::open(&path).map(|w| w.header().clone()) else {
+            continue;
+        };
+        if part.guid != header.guid {
+            continue;
+        }
+        if let Some(slot) = (part.part_number as usize)
+            .checked_sub(1)
+            .and_then(|i| slots.get_mut(i))
+        {
+            slot.get_or_insert(path);
+        }
+    }
+
+    let missing: Vec<String> = slots
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.is_none())
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::ImageError(format!(
+            "Split WIM set {} is incomplete: missing part(s) {} of {}",
+            any_part.display(),
+            missing.join(", "),
+            total
+        )));
+    }
+
+    Ok(SplitWimSet {
+        parts: slots.into_iter().flatten().collect(),
+    })
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+pub fn parse_header(buf: &[u8]) -> Result<WimHeader> {
+    if buf.len() < WIM_HEADER_SIZE {
+        return Err(AppError::ImageError(
+            "File too small for a WIM header".to_string(),
+        ));
+    }
+    if &buf[..8] != WIM_MAGIC && &buf[..8] != PWM_MAGIC {
+        return Err(AppError::ImageError(
+            "Not a WIM/ESD file (bad magic)".to_string(),
+        ));
+    }
+    let header_size = read_u32(buf, 8) as usize;
+    if header_size < WIM_HEADER_SIZE {
+        return Err(AppError::ImageError(format!(
+            "Unexpected WIM header size {}",
+            header_size
+        )));
+    }
+
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(&buf[24..40]);
+
+    Ok(WimHeader {
+        guid,
+        part_number: read_u16(buf, 40),
+        total_parts: read_u16(buf, 42),
+        xml_data: ResourceHeader::parse(&buf[72..96]),
+    })
+}
+
+fn decode_utf16_xml(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    let text = String::from_utf16_lossy(&units);
+    text.trim_start_matches('\u{FEFF}')
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// Text of the first `<TAG>...</TAG>` inside `xml`, entity-decoded and trimmed.
+fn element_text(xml: &str, tag: &str) -> Option<String> {
+    element_inner(xml, tag).map(|inner| decode_entities(inner.trim()))
+}
+
+/// Raw content of the first `<TAG>...</TAG>` inside `xml`. Elements of the WIM XML
+/// schema other than IMAGE carry no attributes, so an exact open tag match suffices.
+fn element_inner<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(&xml[start..end])
+}
+
+fn all_element_texts(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut out = Vec::new();
+    let mut rest = xml;
+    while let Some(pos) = rest.find(&open) {
+        let start = pos + open.len();
+        let Some(len) = rest[start..].find(&close) else {
+            break;
+        };
+        let text = decode_entities(rest[start..start + len].trim());
+        if !text.is_empty() {
+            out.push(text);
+        }
+        rest = &rest[start + len + close.len()..];
+    }
+    out
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp..];
+        let Some(semi) = after.find(';').filter(|s| *s <= 10) else {
+            out.push('&');
+            rest = &after[1..];
+            continue;
+        };
+        let entity = &after[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Numbers in WIM XML are decimal, but some tools write `0x` hex.
+fn parse_xml_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// PROCESSOR_ARCHITECTURE_* value stored in `<WINDOWS><ARCH>`.
+fn architecture_name(code: u64) -> String {
+    match code {
+        0 => "x86",
+        5 => "arm",
+        6 => "ia64",
+        9 => "x64",
+        12 => "arm64",
+        _ => return format!("unknown ({})", code),
+    }
+    .to_string()
+}
+
+fn image_index_attribute(open_tag: &str) -> Option<u32> {
+    let pos = open_tag.find("INDEX=")?;
+    let value = &open_tag[pos + "INDEX=".len()..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    let end = value.find(quote)?;
+    value[..end].trim().parse().ok()
+}
+
+/// Parse every `<IMAGE INDEX="n">` element of a WIM XML document.
+pub fn parse_images_xml(xml: &str) -> Vec<WimImage> {
+    let mut images = Vec::new();
+    let mut rest = xml;
+
+    while let Some(pos) = rest.find("<IMAGE") {
+        let after = &rest[pos..];
+        // Skip longer tag names such as <IMAGECOUNT>.
+        if !after[6..].starts_with([' ', '>', '\t', '\r', '\n']) {
+            rest = &after[6..];
+            continue;
+        }
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let open_tag = &after[..tag_end];
+        let body_start = tag_end + 1;
+        let body_len = after[body_start..]
+            .find("</IMAGE>")
+            .unwrap_or(after.len() - body_start);
+        let body = &after[body_start..body_start + body_len];
+        rest = &after[body_start + body_len..];
+
+        let windows = element_inner(body, "WINDOWS").unwrap_or("");
+        // NAME/DESCRIPTION must not be picked up from inside <WINDOWS>.
+        let own = match (body.find("<WINDOWS>"), body.find("</WINDOWS>")) {
+            (Some(s), Some(e)) if e > s => format!("{}{}", &body[..s], &body[e..]),
+            _ => body.to_string(),
+        };
+
+        let index = image_index_attribute(open_tag).unwrap_or(images.len() as u32 + 1);
+        let name = element_text(&own, "NAME").unwrap_or_default();
+        images.push(WimImage {
+            index,
+            name: if name.is_empty() {
+                format!("Image {}", index)
+            } else {
+                name
+            },
+            description: element_text(&own, "DESCRIPTION").unwrap_or_default(),
+            display_name: element_text(&own, "DISPLAYNAME").unwrap_or_default(),
+            total_bytes: element_text(&own, "TOTALBYTES")
+                .and_then(|v| parse_xml_number(&v))
+                .unwrap_or(0),
+            architecture: element_text(windows, "ARCH")
+                .and_then(|v| parse_xml_number(&v))
+                .map(architecture_name)
+                .unwrap_or_default(),
+            build: element_inner(windows, "VERSION")
+                .and_then(|v| element_text(v, "BUILD"))
+                .and_then(|v| parse_xml_number(&v))
+                .unwrap_or(0) as u32,
+            edition_id: element_text(windows, "EDITIONID").unwrap_or_default(),
+            languages: element_inner(windows, "LANGUAGES")
+                .map(|v| all_element_texts(v, "LANGUAGE"))
+                .unwrap_or_default(),
+        });
+    }
+
+    images.retain(|i| i.index > 0);
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SAMPLE_XML: &str = r#"<WIM><TOTALBYTES>4982342135</TOTALBYTES><IMAGE INDEX="1"><DIRCOUNT>24012</DIRCOUNT><TOTALBYTES>17214337182</TOTALBYTES><WINDOWS><ARCH>9</ARCH><PRODUCTNAME>Microsoft® Windows® Operating System</PRODUCTNAME><EDITIONID>Core</EDITIONID><LANGUAGES><LANGUAGE>en-US</LANGUAGE><LANGUAGE>zh-CN</LANGUAGE><DEFAULT>en-US</DEFAULT></LANGUAGES><VERSION><MAJOR>10</MAJOR><MINOR>0</MINOR><BUILD>22631</BUILD><SPBUILD>2428</SPBUILD></VERSION></WINDOWS><NAME>Windows 11 Home</NAME><DESCRIPTION>Windows 11 Home</DESCRIPTION><DISPLAYNAME>Windows 11 Home</DISPLAYNAME></IMAGE><IMAGE INDEX="2"><TOTALBYTES>0x40DA4C83E</TOTALBYTES><WINDOWS><ARCH>12</ARCH><EDITIONID>Professional</EDITIONID></WINDOWS><NAME>Windows 11 Pro &amp; Workstations</NAME></IMAGE></WIM>"#;
+
+    /// Minimal WIM: a header followed directly by the UTF-16LE XML resource.
+    fn build_wim(xml: &str) -> Vec<u8> {
+        build_wim_part(xml, [0x11; 16], 1, 1)
+    }
+
+    fn build_wim_part(xml: &str, guid: [u8; 16], part: u16, total: u16) -> Vec<u8> {
+        let mut xml_bytes = vec![0xFF, 0xFE];
+        for unit in xml.encode_utf16() {
+            xml_bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let mut buf = vec![0u8; WIM_HEADER_SIZE];
+        buf[..8].copy_from_slice(WIM_MAGIC);
+        buf[8..12].copy_from_slice(&(WIM_HEADER_SIZE as u32).to_le_bytes());
+        buf[12..16].copy_from_slice(&0x10d00u32.to_le_bytes());
+        buf[24..40].copy_from_slice(&guid);
+        buf[40..42].copy_from_slice(&part.to_le_bytes());
+        buf[42..44].copy_from_slice(&total.to_le_bytes());
+        buf[44..48].copy_from_slice(&2u32.to_le_bytes());
+        let size = xml_bytes.len() as u64;
+        buf[72..79].copy_from_slice(&size.to_le_bytes()[..7]);
+        buf[80..88].copy_from_slice(&(WIM_HEADER_SIZE as u64).to_le_bytes());
+        buf[88..96].copy_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&xml_bytes);
+        buf
+    }
+
+    #[test]
+    fn reads_header_and_images() {
+        let mut wim = Wim::new(Cursor::new(build_wim(SAMPLE_XML))).unwrap();
+        assert_eq!(wim.header().part_number, 1);
+        assert_eq!(wim.header().total_parts, 1);
+
+        let images = wim.images().unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(
+            images[0],
+            WimImage {
+                index: 1,
+                name: "Windows 11 Home".to_string(),
+                description: "Windows 11 Home".to_string(),
+                display_name: "Windows 11 Home".to_string(),
+                total_bytes: 17_214_337_182,
+                architecture: "x64".to_string(),
+                build: 22631,
+                edition_id: "Core".to_string(),
+                languages: vec!["en-US".to_string(), "zh-CN".to_string()],
+            }
+        );
+        assert_eq!(images[1].name, "Windows 11 Pro & Workstations");
+        assert_eq!(images[1].total_bytes, 0x40DA4C83E);
+        assert_eq!(images[1].architecture, "arm64");
+        assert_eq!(images[1].build, 0);
+        assert!(images[1].languages.is_empty());
+    }
+
+    #[test]
+    fn rejects_non_wim_and_compressed_xml() {
+        assert!(Wim::new(Cursor::new(vec![0u8; WIM_HEADER_SIZE])).is_err());
+
+        let mut data = build_wim(SAMPLE_XML);
+        data[79] = RESHDR_FLAG_COMPRESSED;
+        let mut wim = Wim::new(Cursor::new(data)).unwrap();
+        assert!(wim.xml().is_err());
+    }
+
+    #[test]
+    fn nameless_images_get_a_placeholder() {
+        let images =
+            parse_images_xml("<WIM><IMAGE INDEX='3'><TOTALBYTES>10</TOTALBYTES></IMAGE></WIM>");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].index, 3);
+        assert_eq!(images[0].name, "Image 3");
+    }
+
+    #[test]
+    fn resolves_split_wim_set_from_any_part() {
+        let dir = std::env::temp_dir().join(format!("wtg-swm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let guid = [0x42; 16];
+        std::fs::write(
+            dir.join("install.swm"),
+            build_wim_part(SAMPLE_XML, guid, 1, 3),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("install2.swm"),
+            build_wim_part(SAMPLE_XML, guid, 2, 3),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("INSTALL3.SWM"),
+            build_wim_part(SAMPLE_XML, guid, 3, 3),
+        )
+        .unwrap();
+        // Same naming, different set: must be ignored.
+        std::fs::write(
+            dir.join("install4.swm"),
+            build_wim_part(SAMPLE_XML, [0x99; 16], 1, 1),
+        )
+        .unwrap();
+
+        let set = resolve_split_wim(&dir.join("install2.swm")).unwrap();
+        assert_eq!(
+            set.parts,
+            vec![
+                dir.join("install.swm"),
+                dir.join("install2.swm"),
+                dir.join("INSTALL3.SWM")
+            ]
+        );
+        assert_eq!(
+            set.ref_pattern(),
+            dir.join("install*.swm").to_string_lossy()
+        );
+        assert_eq!(read_image_info(&dir.join("install2.swm")).unwrap().len(), 2);
+
+        std::fs::remove_file(dir.join("install2.swm")).unwrap();
+        let err = resolve_split_wim(&dir.join("install.swm")).unwrap_err();
+        assert!(err.to_string().contains("missing part(s) 2 of 3"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,51 @@
+import { useEffect, useRef } from 'react'
+import { listen } from '@tauri-apps/api/event'
+import { usbApi } from '@/services/api'
+import type { UsbEvent } from '@/types'
+
+/**
+ * Run `onEvent` whenever a USB disk (or a card reader's medium) is attached or removed.
+ * Monitoring runs while the calling component is mounted.
+ */
+export function useUsbEvents(onEvent: (event: UsbEvent) => void) {
+  const handlerRef = useRef(onEvent)
+  handlerRef.current = onEvent
+
+  useEffect(() => {
+    let disposed = false
+    let unlisten: (() => void) | null = null
+    let monitorId: string | null = null
+
+    const setup = async () => {
+      unlisten = await listen<UsbEvent>('usb-event', (event) => {
+        handlerRef.current(event.payload)
+      })
+      try {
+        monitorId = await usbApi.startMonitoring(null)
+      } catch {
+        // Hotplug is a convenience; the refresh button still works without it.
+      }
+      if (disposed) {
+        cleanup()
+      }
+    }
+
+    const cleanup = () => {
+      if (unlisten) {
+        unlisten()
+        unlisten = null
+      }
+      if (monitorId) {
+        void usbApi.stopMonitoring(monitorId).catch(() => undefined)
+        monitorId = null
+      }
+    }
+
+    void setup()
+
+    return () => {
+      disposed = true
+      cleanup()
+    }
+  }, [])
+}
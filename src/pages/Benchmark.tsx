@@ -2,8 +2,9 @@ import { useCallback, useEffect, useMemo, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 import { benchmarkApi, diskApi } from '../services/api'
 import { useAppStore } from '../services/store'
+import { useUsbEvents } from '../hooks/useUsbEvents'
 import { SpinnerIcon, RefreshIcon } from '../components/Icons'
-import type { BenchmarkResult as BenchResult, DiskInfo } from '../types'
+import type { BenchmarkResult as BenchResult, DiskInfo, LatencySummary } from '../types'
 import './Benchmark.css'
 
 type PrimaryBenchmarkMode = 'quick' | 'multithread' | 'full'
@@ -123,6 +124,25 @@ function buildTrendChart(samples?: { x: number; y: number }[]) {
   return { points, linePath, areaPath, xTicks, yTicks, xMax, yMax, plotW, plotH }
 }
 
+function formatLatency(us: number): string {
+  if (us >= 1_000_000) return `${(us / 1_000_000).toFixed(2)} s`
+  if (us >= 1_000) return `${(us / 1_000).toFixed(1)} ms`
+  return `${us} µs`
+}
+
+function buildLatencyRows(result: BenchResult | undefined, t: (key: string) => string) {
+  if (!result) return []
+  const phases: [string, LatencySummary | undefined][] = [
+    [t('benchmark.random4K') || 'Random 4K', result.write_4k_latency],
+    [t('benchmark.random4KRead') || 'Random 4K Read', result.read_4k_latency],
+    [t('benchmark.scenarioRead') || 'Scenario read', result.scenario_read_latency],
+    [t('benchmark.scenarioWrite') || 'Scenario write', result.scenario_write_latency],
+  ]
+  return phases
+    .filter((entry): entry is [string, LatencySummary] => !!entry[1]?.count)
+    .map(([label, stats]) => ({ label, stats }))
+}
+
 function isModeCompleted(mode: BenchmarkMode, result?: BenchResult): boolean {
   if (!result) return false
   if (mode === 'scenario') return !!(result.scenario_samples?.length || result.scenario_total_io)
@@ -200,6 +220,10 @@ function BenchmarkPage() {
     void loadDisks()
   }, [loadDisks])
 
+  useUsbEvents(() => {
+    void loadDisks()
+  })
+
   const visibleDisks = useMemo(() => {
     const withPath = disks.filter((d) => getBenchmarkTargetPath(d).length > 0)
     const withoutPath = disks.filter((d) => getBenchmarkTargetPath(d).length === 0)
@@ -258,13 +282,13 @@ function BenchmarkPage() {
     return Math.max(1, Math.min(80, raw))
   }, [running, selectedModes, estimateModeSeconds, results, currentMode, currentModeStartedAt, progressNowMs])
 
-  const runModesSequential = async (targetPath: string) => {
+  const runModesSequential = async (targetPath: string, diskId: string) => {
     const queue = [...selectedModes]
     setResults({})
     for (const m of queue) {
       setCurrentMode(m)
       setCurrentModeStartedAt(Date.now())
-      const r = await benchmarkApi.run(targetPath, m)
+      const r = await benchmarkApi.run(targetPath, m, undefined, diskId)
       setResults((prev) => ({ ...prev, [m]: r }))
     }
     setCurrentMode(null)
@@ -289,7 +313,7 @@ function BenchmarkPage() {
       setBenchError(null)
       setCurrentMode(null)
       setCurrentModeStartedAt(null)
-      await runModesSequential(targetPath)
+      await runModesSequential(targetPath, fallbackDisk.id)
     } catch (err: unknown) {
       const message = err instanceof Error ? err.message : String(err)
       if (/cancel/i.test(message)) {
@@ -435,6 +459,7 @@ function BenchmarkPage() {
           const done = isModeCompleted(mode, result)
           const isRunningMode = running && currentMode === mode && !done
           const threadChart = buildThreadChart(result)
+          const latencyRows = buildLatencyRows(result, t)
           const seqChart = buildSeqChart(result?.full_seq_samples)
           const random4kChart = buildTrendChart(result?.write_4k_samples)
           const scenarioChart = buildTrendChart(result?.scenario_samples)
@@ -474,6 +499,20 @@ function BenchmarkPage() {
                       </div>
                     ) : null}
 
+                    {result.read_seq > 0 ? (
+                      <div className="result-card">
+                        <div className="label">{t('benchmark.sequentialRead') || 'Sequential Read'}</div>
+                        <div className="value highlight">{result.read_seq.toFixed(1)} MB/s</div>
+                      </div>
+                    ) : null}
+
+                    {result.read_4k > 0 ? (
+                      <div className="result-card">
+                        <div className="label">{t('benchmark.random4KRead') || '4K Random Read'}</div>
+                        <div className="value highlight">{result.read_4k.toFixed(1)} MB/s</div>
+                      </div>
+                    ) : null}
+
                     {typeof result.write_4k_raw === 'number' ? (
                       <div className="result-card muted">
                         <div className="label">{t('benchmark.raw4k') || '4K Raw'}</div>
@@ -516,6 +555,15 @@ function BenchmarkPage() {
                       </div>
                     ) : null}
 
+                    {typeof result.unbuffered_io === 'boolean' ? (
+                      <div className="result-card muted" title={result.io_fallback_reason || undefined}>
+                        <div className="label">{t('benchmark.unbufferedIo') || 'Unbuffered I/O'}</div>
+                        <div className="value">
+                          {result.unbuffered_io ? t('benchmark.yes') || 'Yes' : t('benchmark.no') || 'No'}
+                        </div>
+                      </div>
+                    ) : null}
+
                     <div className="result-card muted">
                       <div className="label">{t('benchmark.duration') || 'Duration'}</div>
                       <div className="value">{(result.duration_ms / 1000).toFixed(1)} s</div>
@@ -648,6 +696,71 @@ function BenchmarkPage() {
                     </div>
                   ) : null}
 
+                  {latencyRows.length ? (
+                    <div className="chart-panel">
+                      <div className="chart-header">{t('benchmark.latency') || 'Latency percentiles'}</div>
+                      <table className="qd-table">
+                        <thead>
+                          <tr>
+                            <th>{t('benchmark.phase') || 'Phase'}</th>
+                            <th>p50</th>
+                            <th>p90</th>
+                            <th>p99</th>
+                            <th>p99.9</th>
+                            <th>{t('benchmark.max') || 'Max'}</th>
+                            <th>{t('benchmark.stalls') || 'Stalls'}</th>
+                          </tr>
+                        </thead>
+                        <tbody>
+                          {latencyRows.map(({ label, stats }) => (
+                            <tr key={label}>
+                              <td>{label}</td>
+                              <td>{formatLatency(stats.p50_us)}</td>
+                              <td>{formatLatency(stats.p90_us)}</td>
+                              <td>{formatLatency(stats.p99_us)}</td>
+                              <td>{formatLatency(stats.p999_us)}</td>
+                              <td>{formatLatency(stats.max_us)}</td>
+                              <td title={`≥ ${stats.stall_threshold_ms} ms`}>{stats.stalls}</td>
+                            </tr>
+                          ))}
+                        </tbody>
+                      </table>
+                    </div>
+                  ) : null}
+
+                  {result.queue_depth_results?.length ? (
+                    <div className="chart-panel">
+                      <div className="chart-header">{t('benchmark.queueDepth') || '4K random I/O by queue depth'}</div>
+                      <table className="qd-table">
+                        <thead>
+                          <tr>
+                            <th>QD</th>
+                            <th>{t('benchmark.readIops') || 'Read IOPS'}</th>
+                            <th>{t('benchmark.readLatency') || 'Read latency'}</th>
+                            <th>{t('benchmark.writeIops') || 'Write IOPS'}</th>
+                            <th>{t('benchmark.writeLatency') || 'Write latency'}</th>
+                          </tr>
+                        </thead>
+                        <tbody>
+                          {result.queue_depth_results.map((row) => (
+                            <tr key={row.queue_depth}>
+                              <td>{row.queue_depth}</td>
+                              <td>{Math.round(row.read_iops)}</td>
+                              <td>{row.read_latency_us.toFixed(1)} µs</td>
+                              <td>{Math.round(row.write_iops)}</td>
+                              <td>{row.write_latency_us.toFixed(1)} µs</td>
+                            </tr>
+                          ))}
+                        </tbody>
+                      </table>
+                    </div>
+                  ) : result.queue_depth_skip_reason ? (
+                    <div className="chart-panel">
+                      <div className="chart-header">{t('benchmark.queueDepth') || '4K random I/O by queue depth'}</div>
+                      <div className="empty-state">{result.queue_depth_skip_reason}</div>
+                    </div>
+                  ) : null}
+
                   {seqChart ? (
                     <div className="chart-panel">
                       <div className="chart-header">{t('benchmark.seqTrend') || 'Sequential speed over written data'}</div>
@@ -2,9 +2,10 @@ import { useCallback, useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { useTranslation } from 'react-i18next'
 import { useAppStore } from '../services/store'
+import { useUsbEvents } from '../hooks/useUsbEvents'
 import { imageApi } from '../services/api'
 import { SpinnerIcon, RefreshIcon, FolderIcon } from '../components/Icons'
-import type { DiskInfo, BootMode, ApplyMode, ExtraFeatures } from '../types'
+import type { DiskInfo, BootMode, ApplyMode, ExtraFeatures, VirtualDiskInfo } from '../types'
 import './Configure.css'
 
 type ToggleFeatureKey = Exclude<keyof ExtraFeatures, 'driver_path'>
@@ -57,6 +58,7 @@ function ConfigurePage() {
   const [error, setError] = useState<string | null>(null)
   const [imageLoading, setImageLoading] = useState(false)
   const [imageError, setImageError] = useState<string | null>(null)
+  const [virtualDiskInfo, setVirtualDiskInfo] = useState<VirtualDiskInfo | null>(null)
   const [showAllDisks, setShowAllDisks] = useState(false)
 
   const loadDisks = useCallback(async () => {
@@ -76,6 +78,10 @@ function ConfigurePage() {
     void loadDisks()
   }, [loadDisks])
 
+  useUsbEvents(() => {
+    void loadDisks()
+  })
+
   const visibleDisks = showAllDisks ? disks : disks.filter((d) => d.removable)
 
   const getMediaLabel = (disk: DiskInfo) => {
@@ -115,8 +121,8 @@ function ConfigurePage() {
     try {
       const { open } = await import('@tauri-apps/plugin-dialog')
       const imageExtensions = isMacHost
-        ? ['iso', 'wim', 'esd']
-        : ['iso', 'wim', 'esd', 'vhd', 'vhdx']
+        ? ['iso', 'wim', 'esd', 'swm']
+        : ['iso', 'wim', 'esd', 'swm', 'vhd', 'vhdx']
       const selected = await open({
         multiple: false,
         filters: [
@@ -135,10 +141,18 @@ function ConfigurePage() {
         setImageInfoList([])
         setSelectedWimIndex('0')
         setImageError(null)
+        setVirtualDiskInfo(null)
 
         // VHD/VHDX files are used directly, no index selection needed
         if (ext === 'vhd' || ext === 'vhdx') {
           setApplyMode(ext as ApplyMode)
+          try {
+            setVirtualDiskInfo(await imageApi.inspectVirtualDisk(path))
+          } catch (err: unknown) {
+            setImageError(typeof err === 'string' ? err
+              : err instanceof Error ? err.message
+              : JSON.stringify(err))
+          }
           return
         }
 
@@ -309,6 +323,17 @@ function ConfigurePage() {
         {/* Error */}
         {imageError && <div className="error-msg" style={{ marginTop: 12 }}>{imageError}</div>}
 
+        {/* VHD/VHDX summary */}
+        {virtualDiskInfo && !imageError && (
+          <div className="virtual-disk-info">
+            {[
+              `${virtualDiskInfo.format.toUpperCase()} (${virtualDiskInfo.disk_type})`,
+              `${t('configure.vhdVirtualSize') || 'Virtual size'}: ${formatBytes(virtualDiskInfo.virtual_size)}`,
+              `${t('configure.vhdAllocated') || 'Allocated'}: ${formatBytes(virtualDiskInfo.allocated_size)}`,
+            ].join(' · ')}
+          </div>
+        )}
+
         {/* Edition / WIM Index Selection */}
         {imageInfoList.length > 0 && (
           <div className="edition-selector">
@@ -333,9 +358,17 @@ function ConfigurePage() {
                     {img.description && img.description !== img.name && (
                       <div className="edition-desc">{img.description}</div>
                     )}
-                    {(img.size ?? 0) > 0 && (
-                      <div className="edition-size">{formatBytes(img.size ?? 0)}</div>
-                    )}
+                    {(() => {
+                      const details = [
+                        (img.size ?? 0) > 0 ? formatBytes(img.size ?? 0) : '',
+                        img.architecture ?? '',
+                        img.build ? (t('configure.imageBuild') || 'Build {{build}}').replace('{{build}}', String(img.build)) : '',
+                        (img.languages ?? []).join(', '),
+                      ].filter(Boolean)
+                      return details.length > 0 && (
+                        <div className="edition-size">{details.join(' · ')}</div>
+                      )
+                    })()}
                   </div>
                   <div className="edition-index">#{img.index}</div>
                 </div>
@@ -5,6 +5,8 @@ import { RefreshIcon, SpinnerIcon } from '../components/Icons'
 import { diskApi, toolsApi } from '../services/api'
 import { useAppStore } from '../services/store'
 import type {
+  BcdDeviceInfo,
+  BcdStoreInfo,
   BootRepairFirmware,
   DiskDiagnostics,
   HardwareOverview,
@@ -324,6 +326,8 @@ function ToolsPage() {
   const [bootRunning, setBootRunning] = useState(false)
   const [bootMessage, setBootMessage] = useState<string | null>(null)
   const [bootError, setBootError] = useState<string | null>(null)
+  const [bcdStores, setBcdStores] = useState<BcdStoreInfo[] | null>(null)
+  const [bcdInspecting, setBcdInspecting] = useState(false)
 
   const [capacityInput, setCapacityInput] = useState('64')
   const [capacityFrom, setCapacityFrom] = useState<CapacityUnitKey>('GB')
@@ -590,6 +594,34 @@ function ToolsPage() {
     }
   }
 
+  const handleInspectBoot = async () => {
+    if (!bootTarget) {
+      setBootError(tr('tools.bootRepairTargetRequired', '请选择目标盘符后再执行引导修复。'))
+      return
+    }
+
+    try {
+      setBcdInspecting(true)
+      setBootError(null)
+      setBootMessage(null)
+      const stores = await toolsApi.inspectBootConfiguration(bootTarget)
+      setBcdStores(stores)
+    } catch (err) {
+      setBcdStores(null)
+      setBootError(err instanceof Error ? err.message : String(err))
+    } finally {
+      setBcdInspecting(false)
+    }
+  }
+
+  const bcdDeviceLabel = (device?: BcdDeviceInfo | null) => {
+    if (!device) return '-'
+    if (device.partition_number) {
+      return `${device.display} (${tr('tools.bcdPartitionNumber', '分区')} ${device.partition_number})`
+    }
+    return device.display
+  }
+
   const appendInstallLog = (line: string) => {
     setMacosInstallLogs((prev) => {
       const next = [...prev, line]
@@ -1060,7 +1092,14 @@ function ToolsPage() {
               <div className="tool-form-grid">
                 <label className="form-field">
                   <span>{tr('tools.bootRepairTarget', '目标盘符')}</span>
-                  <select value={bootTarget} onChange={(event) => setBootTarget(event.target.value)} disabled={bootRunning}>
+                  <select
+                    value={bootTarget}
+                    onChange={(event) => {
+                      setBootTarget(event.target.value)
+                      setBcdStores(null)
+                    }}
+                    disabled={bootRunning}
+                  >
                     {partitions.map((partition) => (
                       <option key={partition.drive_letter} value={partition.drive_letter}>
                         {partitionOptionLabel(partition)}
@@ -1105,7 +1144,73 @@ function ToolsPage() {
                 <button className="btn-primary" onClick={() => void handleRepairBoot()} disabled={bootRunning} type="button">
                   {bootRunning ? tr('tools.bootRepairRunning', '修复中...') : tr('tools.bootRepairStart', '开始修复')}
                 </button>
+                <button
+                  className="btn-secondary"
+                  onClick={() => void handleInspectBoot()}
+                  disabled={bootRunning || bcdInspecting}
+                  type="button"
+                >
+                  {bcdInspecting ? tr('tools.bcdInspecting', '读取中...') : tr('tools.bcdInspect', '检查引导配置')}
+                </button>
               </div>
+
+              {bcdStores && !bcdStores.length ? (
+                <div className="empty-state">{tr('tools.bcdNoStore', '目标磁盘上未找到 BCD 引导配置文件。')}</div>
+              ) : null}
+
+              {bcdStores?.map((store) => (
+                <div className="bcd-store" key={store.path}>
+                  <div className="bcd-store-header">
+                    <strong>{store.path}</strong>
+                    <span>
+                      {store.partition_style ? store.partition_style.toUpperCase() : '-'}
+                      {store.timeout != null ? ` · ${tr('tools.bcdTimeout', '超时')} ${store.timeout}s` : ''}
+                    </span>
+                  </div>
+                  {store.issues.map((issue) => (
+                    <div className="bcd-issue" key={issue}>
+                      {issue}
+                    </div>
+                  ))}
+                  {store.entries.map((entry) => (
+                    <div className={`bcd-entry ${entry.issues.length ? 'has-issues' : ''}`} key={entry.id}>
+                      <div className="bcd-entry-title">
+                        <span>{entry.description || entry.alias || entry.id}</span>
+                        {entry.is_default ? <em>{tr('tools.bcdDefault', '默认')}</em> : null}
+                      </div>
+                      <dl>
+                        <dt>{tr('tools.bcdIdentifier', '标识符')}</dt>
+                        <dd>{entry.alias || entry.id}</dd>
+                        <dt>device</dt>
+                        <dd>{bcdDeviceLabel(entry.device)}</dd>
+                        {entry.kind === 'osloader' ? (
+                          <>
+                            <dt>osdevice</dt>
+                            <dd>{bcdDeviceLabel(entry.os_device)}</dd>
+                          </>
+                        ) : null}
+                        {entry.path ? (
+                          <>
+                            <dt>path</dt>
+                            <dd>{entry.path}</dd>
+                          </>
+                        ) : null}
+                        {entry.locale ? (
+                          <>
+                            <dt>locale</dt>
+                            <dd>{entry.locale}</dd>
+                          </>
+                        ) : null}
+                      </dl>
+                      {entry.issues.map((issue) => (
+                        <div className="bcd-issue" key={issue}>
+                          {issue}
+                        </div>
+                      ))}
+                    </div>
+                  ))}
+                </div>
+              ))}
             </div>
           ) : null}
         </section>
@@ -13,6 +13,8 @@ function getImageType(path: string): ImageType {
       return 'wim'
     case 'esd':
       return 'esd'
+    case 'swm':
+      return 'swm'
     case 'iso':
       return 'iso'
     case 'vhd':
@@ -6,10 +6,15 @@ import type {
   WtgConfig,
   WriteProgress,
   ImageInfo,
+  VirtualDiskInfo,
   BenchmarkResult,
+  BenchmarkRecord,
+  BenchmarkRunSummary,
+  BenchmarkComparison,
   DiskDiagnostics,
   PartitionInfo,
   BootRepairFirmware,
+  BcdStoreInfo,
   HardwareOverview,
   MacosAdminSessionStatus,
   MacosPluginItem,
@@ -157,6 +162,17 @@ export const imageApi = {
       throw error
     }
   },
+
+  inspectVirtualDisk: async (imagePath: string): Promise<VirtualDiskInfo> => {
+    try {
+      return await invoke<VirtualDiskInfo>('inspect_virtual_disk', {
+        imagePath,
+      })
+    } catch (error) {
+      console.error('Failed to inspect virtual disk:', error)
+      throw error
+    }
+  },
 }
 
 /**
@@ -226,9 +242,11 @@ export const benchmarkApi = {
   run: async (
     targetPath: string,
     mode: 'quick' | 'multithread' | 'fullwrite' | 'full' | 'scenario' = 'quick',
+    stallThresholdMs?: number,
+    diskId?: string,
   ): Promise<BenchmarkResult> => {
     try {
-      const result = await invoke<BenchmarkResult>('run_benchmark', { targetPath, mode })
+      const result = await invoke<BenchmarkResult>('run_benchmark', { targetPath, mode, stallThresholdMs, diskId })
       return result
     } catch (error) {
       console.error('Failed to run benchmark:', error)
@@ -243,6 +261,38 @@ export const benchmarkApi = {
       throw error
     }
   },
+  listHistory: async (): Promise<BenchmarkRunSummary[]> => {
+    try {
+      return await invoke<BenchmarkRunSummary[]>('list_benchmark_history')
+    } catch (error) {
+      console.error('Failed to list benchmark history:', error)
+      throw error
+    }
+  },
+  getRun: async (id: string): Promise<BenchmarkRecord> => {
+    try {
+      return await invoke<BenchmarkRecord>('get_benchmark_run', { id })
+    } catch (error) {
+      console.error('Failed to get benchmark run:', error)
+      throw error
+    }
+  },
+  deleteRun: async (id: string): Promise<void> => {
+    try {
+      await invoke('delete_benchmark_run', { id })
+    } catch (error) {
+      console.error('Failed to delete benchmark run:', error)
+      throw error
+    }
+  },
+  compareRuns: async (baseId: string, otherId: string): Promise<BenchmarkComparison> => {
+    try {
+      return await invoke<BenchmarkComparison>('compare_benchmark_runs', { baseId, otherId })
+    } catch (error) {
+      console.error('Failed to compare benchmark runs:', error)
+      throw error
+    }
+  },
 }
 
 /**
@@ -269,6 +319,16 @@ export const toolsApi = {
     }
   },
 
+  inspectBootConfiguration: async (targetDisk: string): Promise<BcdStoreInfo[]> => {
+    try {
+      const result = await invoke<BcdStoreInfo[]>('inspect_boot_configuration', { targetDisk })
+      return result
+    } catch (error) {
+      console.error('Failed to inspect boot configuration:', error)
+      throw error
+    }
+  },
+
   getHardwareOverview: async (): Promise<HardwareOverview> => {
     try {
       const result = await invoke<HardwareOverview>('get_hardware_overview')
@@ -40,7 +40,8 @@ export type PartitionLayout = 'mbr' | 'gpt'
 export type ApplyMode = 'legacy' | 'vhd' | 'vhdx'
 export type BootMode = 'uefi_gpt' | 'uefi_mbr' | 'non_uefi'
 export type VhdType = 'fixed' | 'expandable'
-export type ImageType = 'wim' | 'esd' | 'iso' | 'vhd' | 'vhdx'
+export type TargetKind = 'disk' | 'image_file'
+export type ImageType = 'wim' | 'esd' | 'swm' | 'iso' | 'vhd' | 'vhdx'
 export type FirmwareType = 'bios' | 'uefi' | 'all'
 export type BootRepairFirmware = 'bios' | 'uefi' | 'all'
 export type WriteStatus =
@@ -71,6 +72,11 @@ export interface VhdConfig {
   partition_type?: number
 }
 
+export interface ImageTargetConfig {
+  vhd_type?: VhdType
+  overwrite?: boolean
+}
+
 export interface ExtraFeatures {
   install_dotnet35: boolean
   block_local_disk: boolean
@@ -93,6 +99,8 @@ export interface WtgConfig {
   image_type: ImageType
   wim_index?: string
   target_disk: Disk
+  target_kind?: TargetKind
+  image_target?: ImageTargetConfig
   boot_mode: BootMode
   apply_mode: ApplyMode
   partition_config: PartitionConfig
@@ -117,6 +125,23 @@ export interface ImageInfo {
   name: string
   description?: string
   size?: number
+  architecture?: string
+  build?: number
+  edition_id?: string
+  languages?: string[]
+}
+
+export interface VirtualDiskInfo {
+  path: string
+  format: 'vhd' | 'vhdx'
+  disk_type: 'fixed' | 'dynamic' | 'differencing'
+  virtual_size: number
+  allocated_size: number
+  file_size: number
+  block_size?: number
+  logical_sector_size?: number
+  physical_sector_size?: number
+  parent_path?: string | null
 }
 
 export interface WriteConfig {
@@ -156,6 +181,19 @@ export interface MacosTargetWritableCheck {
   reason?: string | null
 }
 
+export interface LatencySummary {
+  count: number
+  mean_us: number
+  p50_us: number
+  p90_us: number
+  p99_us: number
+  p999_us: number
+  max_us: number
+  stall_threshold_ms: number
+  stalls: number
+  buckets: { upper_us: number; count: number }[]
+}
+
 export interface BenchmarkResult {
   write_seq: number
   write_4k: number
@@ -165,6 +203,25 @@ export interface BenchmarkResult {
   duration_ms: number
   mode: string
   thread_results: { threads: number; mb_s: number }[]
+  read_seq: number
+  read_4k: number
+  read_seq_samples: { t_ms: number; value: number; x_gb: number }[]
+  read_4k_samples: { x: number; y: number }[]
+  read_thread_results: { threads: number; mb_s: number }[]
+  write_4k_latency?: LatencySummary
+  read_4k_latency?: LatencySummary
+  scenario_read_latency?: LatencySummary
+  scenario_write_latency?: LatencySummary
+  queue_depth_results?: {
+    queue_depth: number
+    read_iops: number
+    read_latency_us: number
+    write_iops: number
+    write_latency_us: number
+  }[]
+  queue_depth_skip_reason?: string
+  unbuffered_io?: boolean
+  io_fallback_reason?: string
   full_seq_samples: { t_ms: number; value: number; x_gb: number }[]
   scenario_samples: { x: number; y: number }[]
   scenario_total_io?: number
@@ -174,6 +231,71 @@ export interface BenchmarkResult {
   full_written_gb: number
 }
 
+export interface BenchmarkDisk {
+  id: string
+  model: string
+  serial_number: string
+  firmware_version: string
+  size_bytes: number
+  bus_type: string
+  transport_type: string
+}
+
+export interface BenchmarkHost {
+  os: string
+  arch: string
+  os_version: string
+  cpu_model: string
+  total_memory: number
+  app_version: string
+}
+
+export interface BenchmarkRecord {
+  id: string
+  created_at: string
+  target_path: string
+  mode: string
+  disk?: BenchmarkDisk | null
+  host: BenchmarkHost
+  result: BenchmarkResult
+}
+
+export interface BenchmarkRunSummary {
+  id: string
+  created_at: string
+  target_path: string
+  mode: string
+  disk?: BenchmarkDisk | null
+  host_os: string
+  write_seq: number
+  write_4k: number
+  read_seq: number
+  read_4k: number
+  score?: number
+  grade?: string
+  scenario_score?: number
+  duration_ms: number
+}
+
+export interface BenchmarkMetricDelta {
+  metric: string
+  base?: number | null
+  other?: number | null
+  delta?: number | null
+  delta_pct?: number | null
+  higher_is_better: boolean
+  regressed: boolean
+}
+
+export interface BenchmarkComparison {
+  base: BenchmarkRunSummary
+  other: BenchmarkRunSummary
+  same_mode: boolean
+  same_disk: boolean
+  firmware_changed: boolean
+  metrics: BenchmarkMetricDelta[]
+}
+
 export interface SmartAttribute {
   id: number
   name: string
@@ -232,6 +354,41 @@ export interface HardwareOverview {
   network_adapters: string[]
 }
 
+export interface BcdDeviceInfo {
+  kind: 'boot' | 'locate' | 'partition' | 'vhd' | 'unknown'
+  partition_style?: 'mbr' | 'gpt' | null
+  disk_id?: string | null
+  partition_id?: string | null
+  partition_offset?: number | null
+  partition_number?: number | null
+  vhd_path?: string | null
+  display: string
+}
+
+export interface BcdEntryInfo {
+  id: string
+  alias?: string | null
+  kind: 'bootmgr' | 'fwbootmgr' | 'osloader' | 'resume' | 'memdiag' | 'application'
+  description: string
+  device?: BcdDeviceInfo | null
+  os_device?: BcdDeviceInfo | null
+  path?: string | null
+  system_root?: string | null
+  locale?: string | null
+  is_default: boolean
+  issues: string[]
+}
+
+export interface BcdStoreInfo {
+  path: string
+  partition_style: string
+  default_entry?: string | null
+  display_order: string[]
+  timeout?: number | null
+  entries: BcdEntryInfo[]
+  issues: string[]
+}
+
 export interface MacosPluginItem {
   id: string
   name: string